@@ -0,0 +1,141 @@
+//! Rasterizer timings; run with `cargo bench`, which compares every case with the last
+//! run.
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use rusterizer::color::Color;
+use rusterizer::drawable::{Drawable, Image, Point3f};
+use rusterizer::geometry;
+use rusterizer::math::Mat4;
+use rusterizer::raster::{Precision, SliceBuffer, Traversal};
+use rusterizer::render::draw_mesh;
+use rusterizer::DrawStyle;
+
+const WHITE: Color = Color(255, 255, 255);
+
+fn primitives(c: &mut Criterion) {
+    let mut image = Image::new(512, 512);
+    c.bench_function("line", |b| {
+        b.iter(|| image.line(black_box(10), 20, 500, 300, WHITE))
+    });
+
+    let (p1, p2, p3) = (
+        Point3f::new(10., 10., 0.),
+        Point3f::new(500., 60., 0.),
+        Point3f::new(200., 490., 0.),
+    );
+    for (name, precision) in [
+        ("triangle fill", Precision::Float),
+        ("triangle fill fixed", Precision::Fixed),
+    ] {
+        c.bench_function(name, |b| {
+            b.iter(|| {
+                let mut image = Image::new(512, 512);
+                image.set_precision(precision);
+                image.triangle(&p1, &p2, black_box(&p3), &DrawStyle::Filled(WHITE), 1.0);
+            })
+        });
+    }
+
+    // the allocation-free core alone, as driving an RGB565 display
+    let mut pixels = vec![0u16; 512 * 512];
+    let mut depth = vec![0.0f32; 512 * 512];
+    let corners = [[10., 10., 0.], [500., 60., 0.5], [200., 490., 1.]];
+    for (name, precision) in [
+        ("slice triangle fill", Precision::Float),
+        ("slice triangle fill fixed", Precision::Fixed),
+    ] {
+        c.bench_function(name, |b| {
+            b.iter(|| {
+                let mut buffer = SliceBuffer::new(&mut pixels, 512, 512)
+                    .unwrap()
+                    .with_depth(&mut depth)
+                    .unwrap()
+                    .with_precision(precision);
+                let [p1, p2, p3] = corners;
+                buffer.triangle(p1, p2, black_box(p3), 0xffff);
+            })
+        });
+    }
+
+    // a sliver across the image, mostly empty bounding box
+    let (p1, p2, p3) = (
+        Point3f::new(5., 5., 0.),
+        Point3f::new(505., 500., 0.),
+        Point3f::new(9., 5., 0.),
+    );
+    for (name, traversal) in [
+        ("thin triangle bbox", Traversal::BoundingBox),
+        ("thin triangle scanline", Traversal::Scanline),
+    ] {
+        c.bench_function(name, |b| {
+            b.iter(|| {
+                let mut image = Image::new(512, 512);
+                image.set_traversal(traversal);
+                image.triangle(&p1, &p2, black_box(&p3), &DrawStyle::Filled(WHITE), 1.0);
+            })
+        });
+    }
+}
+
+fn render(image: &mut Image, mesh: &rusterizer::mesh::Mesh) {
+    draw_mesh(
+        image,
+        black_box(mesh),
+        &DrawStyle::Filled(WHITE),
+        &Mat4::identity(),
+    );
+}
+
+fn meshes(c: &mut Criterion) {
+    let sphere = geometry::sphere(0.8, 64, 32);
+    c.bench_function("sphere render", |b| {
+        b.iter(|| render(&mut Image::new(512, 512), &sphere))
+    });
+
+    // a wall in front of dense geometry, most of which fails the depth test
+    let mut wall = geometry::plane(1.8, 1.8, 1);
+    wall.transform(&Mat4::rotation_x(std::f64::consts::FRAC_PI_2));
+    wall.transform(&Mat4::new([
+        [1., 0., 0., 0.],
+        [0., 1., 0., 0.],
+        [0., 0., 1., 0.9],
+        [0., 0., 0., 1.],
+    ]));
+    c.bench_function("occluded sphere render", |b| {
+        b.iter(|| {
+            let mut image = Image::new(512, 512);
+            render(&mut image, &wall);
+            render(&mut image, &sphere);
+        })
+    });
+
+    // faces and vertices stored in no particular order, as scans often are
+    let mut shuffled = geometry::sphere(0.8, 256, 128);
+    let count = shuffled.indices.len();
+    shuffled.indices = (0..count)
+        .map(|i| shuffled.indices[i * 7919 % count])
+        .collect();
+    let mut optimized = shuffled.clone();
+    optimized.optimize_vertex_cache();
+    optimized.optimize_vertex_fetch();
+    for (name, mesh) in [
+        ("shuffled sphere render", &shuffled),
+        ("optimized sphere render", &optimized),
+    ] {
+        c.bench_function(name, |b| b.iter(|| render(&mut Image::new(512, 512), mesh)));
+    }
+
+    let torus = geometry::torus(0.6, 0.25, 96, 48);
+    c.bench_function("torus render + export", |b| {
+        b.iter(|| {
+            let mut image = Image::new(512, 512);
+            render(&mut image, &torus);
+            black_box(image.to_rgb_image());
+        })
+    });
+}
+
+criterion_group!(benches, primitives, meshes);
+criterion_main!(benches);
@@ -0,0 +1,104 @@
+#ifndef RUSTERIZER_H
+#define RUSTERIZER_H
+
+/* Generated with cbindgen from src/ffi.rs, do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Byte layout of the pixels of a [`SliceTarget`].
+typedef enum RusterizerPixelLayout {
+  RUSTERIZER_PIXEL_LAYOUT_RGB,
+  // RGB followed by an alpha byte, set to opaque for every drawn pixel.
+  RUSTERIZER_PIXEL_LAYOUT_RGBA,
+  // Blue, green, red and an opaque alpha byte, as used by many window surfaces.
+  RUSTERIZER_PIXEL_LAYOUT_BGRA,
+} RusterizerPixelLayout;
+
+// Model formats accepted by [`rusterizer_load_mesh`].
+typedef enum RusterizerFormat {
+  RUSTERIZER_FORMAT_OBJ,
+  RUSTERIZER_FORMAT_PLY,
+  RUSTERIZER_FORMAT_STL,
+} RusterizerFormat;
+
+// Result of the fallible functions of the C API.
+typedef enum RusterizerStatus {
+  RUSTERIZER_STATUS_OK = 0,
+  RUSTERIZER_STATUS_NULL_POINTER = -1,
+  RUSTERIZER_STATUS_PARSE_ERROR = -2,
+  RUSTERIZER_STATUS_BUFFER_TOO_SMALL = -3,
+  // An internal error stopped the call; the renderer may have been left with part of
+  // what it was doing done.
+  RUSTERIZER_STATUS_PANICKED = -4,
+} RusterizerStatus;
+
+// Meshes and view settings shared by every render, opaque to C.
+typedef struct RusterizerRenderer RusterizerRenderer;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Creates a renderer producing `width` x `height` images, to be freed with
+// [`rusterizer_renderer_free`].
+RusterizerRenderer *rusterizer_renderer_new(uint32_t width, uint32_t height);
+
+// Frees a renderer; passing null does nothing.
+//
+// # Safety
+//
+// `renderer` must be null or come from [`rusterizer_renderer_new`] and not be used again.
+void rusterizer_renderer_free(RusterizerRenderer *renderer);
+
+// Parses a model from `len` bytes at `data` and adds its meshes to the scene.
+//
+// # Safety
+//
+// `renderer` must be a live renderer and `data` must point to `len` readable bytes.
+RusterizerStatus rusterizer_load_mesh(RusterizerRenderer *renderer,
+                                      const uint8_t *data,
+                                      size_t len,
+                                      RusterizerFormat format);
+
+// Removes every mesh from the scene.
+//
+// # Safety
+//
+// `renderer` must be null or a live renderer.
+void rusterizer_clear_meshes(RusterizerRenderer *renderer);
+
+// Sets the transform from model space to the view, given as 16 row-major values.
+// The view shows -1 to 1 on x and y and looks along -z.
+//
+// # Safety
+//
+// `renderer` must be a live renderer and `matrix` must point to 16 readable doubles.
+RusterizerStatus rusterizer_set_camera(RusterizerRenderer *renderer, const double *matrix);
+
+// Sets the color of the pixels not covered by any mesh.
+//
+// # Safety
+//
+// `renderer` must be null or a live renderer.
+void rusterizer_set_background(RusterizerRenderer *renderer, uint8_t r, uint8_t g, uint8_t b);
+
+// Renders the scene into `pixels`, which holds top-down rows of `stride` bytes in the
+// given layout.
+//
+// # Safety
+//
+// `renderer` must be a live renderer and `pixels` must point to `len` writable bytes.
+RusterizerStatus rusterizer_render(const RusterizerRenderer *renderer,
+                                   uint8_t *pixels,
+                                   size_t len,
+                                   size_t stride,
+                                   RusterizerPixelLayout layout);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* RUSTERIZER_H */
@@ -0,0 +1,57 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::error::{EncodingError, ImageFormatHint};
+use image::{Delay, DynamicImage, Frame, ImageError, ImageFormat, ImageResult, RgbImage};
+
+/// Saves `frames` as a looping animation, choosing GIF or APNG by the extension of `path`.
+pub fn save_animation<Q: AsRef<Path>>(
+    frames: &[RgbImage],
+    delay_ms: u16,
+    path: Q,
+) -> ImageResult<()> {
+    let path = path.as_ref();
+    match ImageFormat::from_path(path)? {
+        ImageFormat::Gif => save_gif(frames, delay_ms, path),
+        ImageFormat::Png => save_apng(frames, delay_ms, path),
+        format => Err(ImageError::Unsupported(
+            ImageFormatHint::Exact(format).into(),
+        )),
+    }
+}
+
+fn save_gif(frames: &[RgbImage], delay_ms: u16, path: &Path) -> ImageResult<()> {
+    let mut encoder = GifEncoder::new(BufWriter::new(File::create(path)?));
+    encoder.set_repeat(Repeat::Infinite)?;
+    let delay = Delay::from_numer_denom_ms(delay_ms as u32, 1);
+    encoder.encode_frames(frames.iter().map(|frame| {
+        let rgba = DynamicImage::from(frame.clone()).to_rgba8();
+        Frame::from_parts(rgba, 0, 0, delay)
+    }))
+}
+
+fn save_apng(frames: &[RgbImage], delay_ms: u16, path: &Path) -> ImageResult<()> {
+    let (width, height) = frames.first().map_or((0, 0), |f| f.dimensions());
+    let to_image_error = |e: png::EncodingError| {
+        ImageError::Encoding(EncodingError::new(ImageFormat::Png.into(), e))
+    };
+
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), width, height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .set_animated(frames.len() as u32, 0)
+        .map_err(to_image_error)?;
+    encoder
+        .set_frame_delay(delay_ms, 1000)
+        .map_err(to_image_error)?;
+    let mut writer = encoder.write_header().map_err(to_image_error)?;
+    for frame in frames {
+        writer
+            .write_image_data(frame.as_raw())
+            .map_err(to_image_error)?;
+    }
+    writer.finish().map_err(to_image_error)
+}
@@ -0,0 +1,178 @@
+use std::sync::Arc;
+
+use image::RgbImage;
+
+use crate::mesh::Mesh;
+
+/// Gap around every texture in the atlas, filled with the texture's edge pixels so that
+/// UVs of 1 and neighbouring texels do not pick up colors of the next texture.
+const PADDING: u32 = 1;
+
+/// Where a texture ended up in the atlas, in pixels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Region {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Packs the distinct base color textures of `meshes` into a single texture and remaps
+/// the UVs of the textured meshes into their part of it, so that all of them are drawn
+/// with the same texture. UVs are clamped to the texture first, as the sampler would.
+///
+/// Returns the atlas, or `None` if fewer than two textures are used and nothing changed.
+pub fn pack_textures(meshes: &mut [Mesh]) -> Option<Arc<RgbImage>> {
+    let mut textures: Vec<Arc<RgbImage>> = Vec::new();
+    for mesh in meshes.iter() {
+        if let Some(texture) = &mesh.material.base_color_texture {
+            let empty = texture.width() == 0 || texture.height() == 0;
+            if mesh.has_uvs() && !empty && !textures.iter().any(|t| Arc::ptr_eq(t, texture)) {
+                textures.push(texture.clone());
+            }
+        }
+    }
+    if textures.len() < 2 {
+        return None;
+    }
+
+    let sizes: Vec<(u32, u32)> = textures.iter().map(|t| t.dimensions()).collect();
+    let (regions, width, height) = pack(&sizes);
+    let mut atlas = RgbImage::new(width, height);
+    for (texture, region) in textures.iter().zip(&regions) {
+        blit_padded(&mut atlas, texture, region);
+    }
+    let atlas = Arc::new(atlas);
+
+    let (width, height) = (width as f64, height as f64);
+    for mesh in meshes.iter_mut() {
+        let Some(texture) = &mesh.material.base_color_texture else {
+            continue;
+        };
+        let Some(index) = textures.iter().position(|t| Arc::ptr_eq(t, texture)) else {
+            continue;
+        };
+        let region = regions[index];
+        for [u, v] in &mut mesh.uvs {
+            *u = (region.x as f64 + u.clamp(0.0, 1.0) * region.width as f64) / width;
+            *v = (region.y as f64 + v.clamp(0.0, 1.0) * region.height as f64) / height;
+        }
+        mesh.material.base_color_texture = Some(atlas.clone());
+    }
+    Some(atlas)
+}
+
+/// Places rectangles of the given sizes on shelves, tallest first, in an atlas about as
+/// wide as it is tall. Returns the region of every rectangle in the order given and the
+/// size of the atlas.
+pub fn pack(sizes: &[(u32, u32)]) -> (Vec<Region>, u32, u32) {
+    let padded = |(width, height): (u32, u32)| (width + 2 * PADDING, height + 2 * PADDING);
+    let area: u64 = sizes
+        .iter()
+        .map(|&size| {
+            let (width, height) = padded(size);
+            width as u64 * height as u64
+        })
+        .sum();
+    let widest = sizes.iter().map(|&size| padded(size).0).max().unwrap_or(0);
+    let atlas_width = ((area as f64).sqrt().ceil() as u32).max(widest);
+
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(sizes[i].1));
+    let mut regions = vec![
+        Region {
+            x: 0,
+            y: 0,
+            width: 0,
+            height: 0,
+        };
+        sizes.len()
+    ];
+    let (mut x, mut shelf_y, mut shelf_height, mut used_width) = (0, 0, 0, 0);
+    for i in order {
+        let (width, height) = padded(sizes[i]);
+        if x + width > atlas_width {
+            shelf_y += shelf_height;
+            x = 0;
+            shelf_height = 0;
+        }
+        regions[i] = Region {
+            x: x + PADDING,
+            y: shelf_y + PADDING,
+            width: sizes[i].0,
+            height: sizes[i].1,
+        };
+        x += width;
+        used_width = used_width.max(x);
+        shelf_height = shelf_height.max(height);
+    }
+    (regions, used_width, shelf_y + shelf_height)
+}
+
+/// Copies `texture` into its region and repeats its edges into the padding.
+fn blit_padded(atlas: &mut RgbImage, texture: &RgbImage, region: &Region) {
+    let (x0, y0) = (region.x - PADDING, region.y - PADDING);
+    for y in 0..region.height + 2 * PADDING {
+        for x in 0..region.width + 2 * PADDING {
+            let tx = x.saturating_sub(PADDING).min(region.width - 1);
+            let ty = y.saturating_sub(PADDING).min(region.height - 1);
+            atlas.put_pixel(x0 + x, y0 + y, *texture.get_pixel(tx, ty));
+        }
+    }
+}
+
+#[test]
+fn test_pack_textures() {
+    use crate::drawable::sample_texture;
+    use image::Rgb;
+
+    let solid = |width, height, color| Arc::new(RgbImage::from_pixel(width, height, Rgb(color)));
+    let quad = |texture: &Arc<RgbImage>| {
+        let mut mesh = Mesh {
+            uvs: vec![[0., 0.], [1., 0.], [1., 1.], [0.25, 0.75]],
+            ..Mesh::default()
+        };
+        mesh.material.base_color_texture = Some(texture.clone());
+        mesh
+    };
+    let (red, green) = (solid(4, 8, [255, 0, 0]), solid(16, 2, [0, 255, 0]));
+    let mut meshes = vec![quad(&red), quad(&green), quad(&red), Mesh::default()];
+    let atlas = pack_textures(&mut meshes).unwrap();
+
+    for (mesh, texture) in meshes.iter().zip([&red, &green, &red]) {
+        assert!(Arc::ptr_eq(
+            mesh.material.base_color_texture.as_ref().unwrap(),
+            &atlas
+        ));
+        // every corner, including those at 1, samples the same color as before
+        for &[u, v] in &mesh.uvs {
+            assert_eq!(
+                sample_texture(&atlas, u, v),
+                sample_texture(texture, 0.5, 0.5)
+            );
+        }
+    }
+    assert!(meshes[3].material.base_color_texture.is_none());
+
+    // a single texture is left alone
+    let mut meshes = vec![quad(&red), quad(&red)];
+    assert!(pack_textures(&mut meshes).is_none());
+    assert_eq!(meshes[0].uvs[1], [1., 0.]);
+}
+
+#[test]
+fn test_pack_does_not_overlap() {
+    let sizes = [(8, 8), (3, 5), (10, 1), (4, 4), (1, 9)];
+    let (regions, width, height) = pack(&sizes);
+    for (i, a) in regions.iter().enumerate() {
+        assert_eq!((a.width, a.height), sizes[i]);
+        assert!(a.x + a.width + PADDING <= width && a.y + a.height + PADDING <= height);
+        for b in &regions[i + 1..] {
+            let apart = a.x + a.width + PADDING <= b.x - PADDING
+                || b.x + b.width + PADDING <= a.x - PADDING
+                || a.y + a.height + PADDING <= b.y - PADDING
+                || b.y + b.height + PADDING <= a.y - PADDING;
+            assert!(apart, "{:?} overlaps {:?}", a, b);
+        }
+    }
+}
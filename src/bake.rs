@@ -0,0 +1,371 @@
+use std::f64::consts::PI;
+use std::ops::{Add, Mul};
+use std::sync::Arc;
+
+use image::{GrayImage, RgbImage};
+
+use crate::color::{Color, HdrColor};
+use crate::drawable::{rasterize, Point3f};
+use crate::math::{self, Aabb, Mat4, Vec3f};
+use crate::mesh::Mesh;
+use crate::ray::Ray;
+use crate::spatial::Bvh;
+use crate::texture::{ColorSpace, Texels, Texture};
+
+/// Texels around the UV islands filled in from their neighbours, so that sampling near
+/// the edge of an island does not pick up the empty space around it.
+const PADDING: u32 = 2;
+
+/// What [`bake_ambient_occlusion`] bakes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AoSettings {
+    pub width: u32,
+    pub height: u32,
+    /// Rays cast over the hemisphere above every texel.
+    pub samples: u32,
+    /// Occluders farther away than this do not darken a texel.
+    pub max_distance: f64,
+}
+
+impl Default for AoSettings {
+    fn default() -> Self {
+        AoSettings {
+            width: 256,
+            height: 256,
+            samples: 64,
+            max_distance: f64::INFINITY,
+        }
+    }
+}
+
+/// Bakes the ambient occlusion of `mesh` into a texture laid out by its UVs: every texel
+/// holds the fraction of the hemisphere above its point of the surface from which light
+/// reaches it past `occluders`, 1 where nothing is in the way.
+///
+/// The occluders are in the mesh's own coordinates and usually include the mesh itself.
+/// Rays are spread by cosine so that the result is the occlusion of diffuse light, with
+/// the same pattern rotated differently in every texel, which turns banding into noise.
+/// Meshes without UVs have nothing to bake into.
+pub fn bake_ambient_occlusion(
+    mesh: &Mesh,
+    occluders: &[Bvh],
+    settings: &AoSettings,
+) -> Option<Texture> {
+    if !mesh.has_uvs() || settings.width == 0 || settings.height == 0 {
+        return None;
+    }
+    let (width, height) = (settings.width, settings.height);
+    // keeps rays from hitting the face they leave, relative to the size of the mesh
+    let bias = mesh
+        .bounds()
+        .map_or(0.0, |bounds| (bounds.max - bounds.min).length() * 1e-4);
+    let mut occlusion: Vec<Option<f64>> = vec![None; (width * height) as usize];
+    rasterize_uv_layout(
+        mesh,
+        width,
+        height,
+        |texel, position, normal, face_normal| {
+            // off the side of the face the normals are on
+            let side = if math::dot(&face_normal, &normal) < 0.0 {
+                -bias
+            } else {
+                bias
+            };
+            let origin = position + face_normal * side;
+            let visible = hemisphere_visibility(&origin, &normal, occluders, settings, texel);
+            occlusion[texel as usize] = Some(visible);
+        },
+    );
+
+    for _ in 0..PADDING {
+        occlusion = dilate(&occlusion, width, height);
+    }
+    let texels = GrayImage::from_fn(width, height, |x, y| {
+        let value = occlusion[(y * width + x) as usize].unwrap_or(1.0);
+        image::Luma([(value * 255.0).round() as u8])
+    });
+    Some(Texture {
+        texels: Texels::Gray(Arc::new(texels)),
+        color_space: ColorSpace::Linear,
+    })
+}
+
+/// Bakes the normals of `mesh` in its own coordinates into a `width` by `height`
+/// texture laid out by its UVs, each axis mapped from -1..1 to the 0..255 of a channel
+/// as normal maps store them. Faces of meshes without normals bake their face normal;
+/// meshes without UVs have nothing to bake into.
+pub fn bake_normals(mesh: &Mesh, width: u32, height: u32) -> Option<Texture> {
+    if !mesh.has_uvs() || width == 0 || height == 0 {
+        return None;
+    }
+    let mut normals: Vec<Option<Vec3f>> = vec![None; (width * height) as usize];
+    rasterize_uv_layout(mesh, width, height, |texel, _, normal, _| {
+        normals[texel as usize] = Some(normal);
+    });
+    for _ in 0..PADDING {
+        normals = dilate(&normals, width, height);
+    }
+    let texels = RgbImage::from_fn(width, height, |x, y| {
+        let normal = normals[(y * width + x) as usize]
+            .map_or(Vec3f::new(0., 0., 1.), |normal| normal.normalized());
+        let channel = |c: f64| ((c * 0.5 + 0.5) * 255.0).round().clamp(0.0, 255.0) as u8;
+        image::Rgb([channel(normal.x), channel(normal.y), channel(normal.z)])
+    });
+    Some(Texture {
+        texels: Texels::Rgb(Arc::new(texels)),
+        color_space: ColorSpace::Linear,
+    })
+}
+
+/// Calls `shade` with the index, position, interpolated unit normal and face normal of
+/// every texel the faces of `mesh` cover in a `width` by `height` texture laid out by its
+/// UVs. Faces without vertex normals use their face normal.
+fn rasterize_uv_layout<F: FnMut(u32, Vec3f, Vec3f, Vec3f)>(
+    mesh: &Mesh,
+    width: u32,
+    height: u32,
+    mut shade: F,
+) {
+    for &[idx1, idx2, idx3] in &mesh.indices {
+        let corners = [idx1, idx2, idx3].map(|idx| mesh.positions[idx]);
+        let face_normal =
+            math::cross(&(corners[1] - corners[0]), &(corners[2] - corners[0])).normalized();
+        let normals = [idx1, idx2, idx3].map(|idx| {
+            if mesh.has_normals() {
+                mesh.normals[idx]
+            } else {
+                face_normal
+            }
+        });
+        let [p1, p2, p3] = [idx1, idx2, idx3].map(|idx| {
+            let [u, v] = mesh.uvs[idx];
+            Point3f::new(u * width as f64, v * height as f64, 0.)
+        });
+        rasterize(width, height, &p1, &p2, &p3, |x, y, (a, b, c), _| {
+            let mix = |x: [Vec3f; 3]| x[0] * a + x[1] * b + x[2] * c;
+            shade(
+                y * width + x,
+                mix(corners),
+                mix(normals).normalized(),
+                face_normal,
+            );
+        });
+    }
+}
+
+/// Square floor under `meshes`, touching the bottom of their bounds and `scale` times as
+/// wide as the larger side of their footprint, so that they do not float in a void.
+///
+/// Its base color texture is `color` darkened by the ambient occlusion of the meshes
+/// within that side's length, baked as [`bake_ambient_occlusion`] would, which gives them
+/// a soft contact shadow whatever the lights; lights with shadows add their own. The
+/// texture is as large as `settings` says.
+pub fn ground_plane(
+    meshes: &[Mesh],
+    scale: f64,
+    color: Color,
+    settings: &AoSettings,
+) -> Option<Mesh> {
+    let bounds = Aabb::from_points(meshes.iter().flat_map(|mesh| &mesh.positions))?;
+    let side = (bounds.max.x - bounds.min.x).max(bounds.max.z - bounds.min.z);
+    let mut ground = crate::geometry::plane(side * scale, side * scale, 1);
+    let center = (bounds.min + bounds.max) * 0.5;
+    for position in &mut ground.positions {
+        *position = *position + Vec3f::new(center.x, bounds.min.y, center.z);
+    }
+    ground.name = Some("ground".to_string());
+
+    let occluders: Vec<Bvh> = meshes
+        .iter()
+        .map(|mesh| Bvh::build(mesh, &Mat4::identity()))
+        .collect();
+    let settings = AoSettings {
+        max_distance: settings.max_distance.min(side),
+        ..*settings
+    };
+    let occlusion = bake_ambient_occlusion(&ground, &occluders, &settings)?.to_gray();
+    let linear = HdrColor::from(color);
+    let texels = RgbImage::from_fn(settings.width, settings.height, |x, y| {
+        let visible = occlusion.get_pixel(x, y)[0] as f64 / 255.0;
+        linear.scale(visible).to_srgb().into()
+    });
+    ground.material.base_color = color;
+    ground.material.base_color_texture = Some(Arc::new(texels));
+    Some(ground)
+}
+
+/// Fraction of `settings.samples` cosine-distributed rays from `origin` over the
+/// hemisphere around `normal` that miss all `occluders`.
+fn hemisphere_visibility(
+    origin: &Vec3f,
+    normal: &Vec3f,
+    occluders: &[Bvh],
+    settings: &AoSettings,
+    texel: u32,
+) -> f64 {
+    let samples = settings.samples.max(1);
+    let (tangent, bitangent) = orthonormal_basis(normal);
+    let rotation = hash(texel as u64);
+    let mut visible = 0;
+    for i in 0..samples {
+        // Hammersley points, rotated per texel
+        let u1 = (i as f64 + 0.5) / samples as f64;
+        let u2 = (radical_inverse(i) + rotation).fract();
+        let (r, phi) = (u1.sqrt(), 2.0 * PI * u2);
+        let direction = tangent * (r * phi.cos())
+            + bitangent * (r * phi.sin())
+            + *normal * (1.0 - u1).max(0.0).sqrt();
+        let mut ray = Ray::new(*origin, direction);
+        ray.far = settings.max_distance;
+        if !occluders.iter().any(|bvh| bvh.occludes(&ray)) {
+            visible += 1;
+        }
+    }
+    visible as f64 / samples as f64
+}
+
+/// Two unit vectors perpendicular to `normal` and each other.
+fn orthonormal_basis(normal: &Vec3f) -> (Vec3f, Vec3f) {
+    let helper = if normal.x.abs() < 0.9 {
+        Vec3f::new(1., 0., 0.)
+    } else {
+        Vec3f::new(0., 1., 0.)
+    };
+    let tangent = math::cross(&helper, normal).normalized();
+    (tangent, math::cross(normal, &tangent))
+}
+
+/// Van der Corput sequence in base 2: the bits of `i` mirrored behind the binary point.
+fn radical_inverse(i: u32) -> f64 {
+    i.reverse_bits() as f64 / (1u64 << 32) as f64
+}
+
+/// Pseudo-random number in `0..1` derived from `seed` with the SplitMix64 mixer.
+fn hash(seed: u64) -> f64 {
+    let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+    (z >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Fills every empty texel next to baked ones with their average.
+fn dilate<T>(texels: &[Option<T>], width: u32, height: u32) -> Vec<Option<T>>
+where
+    T: Copy + Add<Output = T> + Mul<f64, Output = T>,
+{
+    let mut dilated = texels.to_vec();
+    for y in 0..height {
+        for x in 0..width {
+            if texels[(y * width + x) as usize].is_some() {
+                continue;
+            }
+            let (mut sum, mut count) = (None, 0);
+            for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+                let (nx, ny) = (x as i64 + dx, y as i64 + dy);
+                if nx < 0 || ny < 0 || nx >= width as i64 || ny >= height as i64 {
+                    continue;
+                }
+                if let Some(value) = texels[(ny as u32 * width + nx as u32) as usize] {
+                    sum = Some(sum.map_or(value, |sum| sum + value));
+                    count += 1;
+                }
+            }
+            dilated[(y * width + x) as usize] = sum.map(|sum| sum * (1.0 / count as f64));
+        }
+    }
+    dilated
+}
+
+#[test]
+fn test_bake_ambient_occlusion() {
+    use crate::math::Mat4;
+
+    // a floor with a box standing on its middle
+    let floor = crate::geometry::plane(4.0, 4.0, 4);
+    let mut block = crate::geometry::cuboid(Vec3f::new(1., 1., 1.));
+    for position in &mut block.positions {
+        position.y += 0.5;
+    }
+    let occluders = [
+        Bvh::build(&floor, &Mat4::identity()),
+        Bvh::build(&block, &Mat4::identity()),
+    ];
+    let settings = AoSettings {
+        width: 32,
+        height: 32,
+        ..Default::default()
+    };
+    let baked = bake_ambient_occlusion(&floor, &occluders, &settings).unwrap();
+    let at = |u: f64, v: f64| baked.sample(u, v)[0];
+
+    // the corners see nearly the whole sky, the floor beside the box about half of it
+    // and under it none
+    assert!(at(0.02, 0.02) > 0.95);
+    assert!(at(0.5, 0.5) < 0.01);
+    let beside = at(0.5 + 0.55 / 4.0, 0.5);
+    assert!((0.3..0.7).contains(&beside), "{}", beside);
+    assert!(at(0.5 + 1.5 / 4.0, 0.5) > beside);
+
+    // without the box nothing is occluded
+    let open = bake_ambient_occlusion(&floor, &occluders[..1], &settings).unwrap();
+    assert!(open.to_gray().pixels().all(|p| p[0] == 255));
+    block.uvs.clear();
+    assert!(bake_ambient_occlusion(&block, &occluders, &settings).is_none());
+}
+
+#[test]
+fn test_ground_plane() {
+    let mut sphere = crate::geometry::sphere(0.5, 16, 8);
+    for position in &mut sphere.positions {
+        position.y += 2.0;
+    }
+    let settings = AoSettings {
+        width: 32,
+        height: 32,
+        samples: 32,
+        ..Default::default()
+    };
+    let color = Color(200, 200, 200);
+    let ground = ground_plane(&[sphere], 3.0, color, &settings).unwrap();
+
+    // three times the sphere's width, just under it
+    let bounds = ground.bounds().unwrap();
+    assert!((bounds.max.x - bounds.min.x - 3.0).abs() < 1e-9);
+    assert!((bounds.min.y - 1.5).abs() < 1e-9 && bounds.max.y == bounds.min.y);
+    assert!((bounds.min.z + 1.5).abs() < 1e-9);
+
+    // darkest right under the sphere, and untouched at the corners
+    let texture = ground.material.base_color_texture.as_ref().unwrap();
+    let under = texture.get_pixel(16, 16)[0];
+    assert!(under < 100, "{}", under);
+    assert!(texture.get_pixel(17, 20)[0] > under);
+    assert_eq!(texture.get_pixel(0, 0)[0], 200);
+    assert!(ground_plane(&[], 3.0, color, &settings).is_none());
+}
+
+#[test]
+fn test_bake_normals() {
+    let cube = crate::geometry::cuboid(Vec3f::new(1., 1., 1.));
+    let baked = bake_normals(&cube, 64, 64).unwrap().to_image().to_rgb8();
+    // the texels of every face hold its axis
+    let axes = [
+        [255, 128, 128],
+        [0, 128, 128],
+        [128, 255, 128],
+        [128, 0, 128],
+        [128, 128, 255],
+        [128, 128, 0],
+    ];
+    let covered = baked.pixels().filter(|p| axes.contains(&p.0)).count();
+    assert!(covered > 64 * 64 / 4, "{}", covered);
+
+    // without vertex normals the faces bake theirs, the way they wind
+    let mut plane = crate::geometry::plane(1.0, 1.0, 1);
+    let up = plane.normals[0];
+    plane.normals.clear();
+    let baked = bake_normals(&plane, 4, 4).unwrap();
+    let [x, y, z, _] = baked.sample(0.5, 0.5).map(|c| c as f64 * 2.0 - 1.0);
+    assert!((Vec3f::new(x, y, z) - up).length() < 0.02);
+    assert!(bake_normals(&Mesh::default(), 4, 4).is_none());
+}
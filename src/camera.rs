@@ -0,0 +1,254 @@
+use std::f64::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+use crate::drawable::Point3f;
+use crate::math::{Mat4, Vec3f};
+
+/// Standard directions to look at a model from, each turning the named side towards the
+/// viewer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ViewPreset {
+    Front,
+    Back,
+    Left,
+    Right,
+    Top,
+    Bottom,
+    /// Front, right and top sides foreshortened equally.
+    Isometric,
+}
+
+impl ViewPreset {
+    pub const ALL: [ViewPreset; 7] = [
+        ViewPreset::Front,
+        ViewPreset::Back,
+        ViewPreset::Left,
+        ViewPreset::Right,
+        ViewPreset::Top,
+        ViewPreset::Bottom,
+        ViewPreset::Isometric,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ViewPreset::Front => "front",
+            ViewPreset::Back => "back",
+            ViewPreset::Left => "left",
+            ViewPreset::Right => "right",
+            ViewPreset::Top => "top",
+            ViewPreset::Bottom => "bottom",
+            ViewPreset::Isometric => "iso",
+        }
+    }
+
+    /// Rotation applied to the model before projecting it.
+    pub fn rotation(&self) -> Mat4 {
+        match self {
+            ViewPreset::Front => Mat4::identity(),
+            ViewPreset::Back => Mat4::rotation_y(PI),
+            ViewPreset::Left => Mat4::rotation_y(FRAC_PI_2),
+            ViewPreset::Right => Mat4::rotation_y(-FRAC_PI_2),
+            ViewPreset::Top => Mat4::rotation_x(FRAC_PI_2),
+            ViewPreset::Bottom => Mat4::rotation_x(-FRAC_PI_2),
+            ViewPreset::Isometric => {
+                let elevation = (1.0 / 3f64.sqrt()).asin();
+                Mat4::rotation_x(elevation) * Mat4::rotation_y(-FRAC_PI_4)
+            }
+        }
+    }
+
+    /// Direction from the model towards the viewer, in model space.
+    pub fn direction(&self) -> Vec3f {
+        let to_viewer = Vec3f::new(0., 0., 1.);
+        self.rotation()
+            .inverse()
+            .map_or(to_viewer, |inverse| inverse.transform_vector(&to_viewer))
+    }
+}
+
+/// Parallel projection of the box `left..right` by `bottom..top` between the `near` and
+/// `far` planes, in a view space looking along -z; parallel lines stay parallel.
+///
+/// The default volume is the [-1, 1] cube drawn when no projection is set.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Orthographic {
+    pub left: f64,
+    pub right: f64,
+    pub bottom: f64,
+    pub top: f64,
+    /// Distance of the near plane in front of the viewer, negative when behind.
+    pub near: f64,
+    pub far: f64,
+}
+
+impl Default for Orthographic {
+    fn default() -> Self {
+        Orthographic {
+            left: -1.0,
+            right: 1.0,
+            bottom: -1.0,
+            top: 1.0,
+            near: -1.0,
+            far: 1.0,
+        }
+    }
+}
+
+impl Orthographic {
+    /// A volume `height` units tall around the view axis, as wide as the `aspect` ratio
+    /// (width over height) of the image requires so nothing is stretched.
+    pub fn from_height(height: f64, aspect: f64, near: f64, far: f64) -> Self {
+        let (half_width, half_height) = (height * aspect / 2.0, height / 2.0);
+        Orthographic {
+            left: -half_width,
+            right: half_width,
+            bottom: -half_height,
+            top: half_height,
+            near,
+            far,
+        }
+    }
+
+    /// Maps the volume onto the [-1, 1] cube, the near plane going to a depth of 1 as
+    /// the z-buffer keeps larger depths.
+    pub fn projection(&self) -> Mat4 {
+        let (width, height, depth) = (
+            self.right - self.left,
+            self.top - self.bottom,
+            self.far - self.near,
+        );
+        Mat4::new([
+            [2. / width, 0., 0., -(self.right + self.left) / width],
+            [0., 2. / height, 0., -(self.top + self.bottom) / height],
+            [0., 0., 2. / depth, (self.far + self.near) / depth],
+            [0., 0., 0., 1.],
+        ])
+    }
+}
+
+/// Rectangle of pixels that normalized device coordinates are mapped onto, `(x, y)` being
+/// its bottom-left corner with rows counted from the bottom.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Viewport {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Viewport {
+    /// The whole `width` x `height` image.
+    pub fn full(width: u32, height: u32) -> Self {
+        Viewport {
+            x: 0,
+            y: 0,
+            width,
+            height,
+        }
+    }
+
+    /// The largest rectangle with the `aspect` ratio (width over height) centered in a
+    /// `width` x `height` image, leaving bars on two sides when the ratios differ.
+    pub fn letterboxed(width: u32, height: u32, aspect: f64) -> Self {
+        let fitted_width = ((height as f64 * aspect).round() as u32).min(width);
+        let fitted_height = ((width as f64 / aspect).round() as u32).min(height);
+        Viewport {
+            x: (width - fitted_width) / 2,
+            y: (height - fitted_height) / 2,
+            width: fitted_width,
+            height: fitted_height,
+        }
+    }
+
+    pub fn contains(&self, x: u32, y: u32) -> bool {
+        (self.x..self.x + self.width).contains(&x) && (self.y..self.y + self.height).contains(&y)
+    }
+
+    /// Maps normalized device coordinates in [-1, 1] to pixel coordinates, passing the
+    /// depth through.
+    pub fn to_screen(&self, v: &Vec3f) -> Point3f {
+        Point3f::new(
+            self.x as f64 + (v.x + 1.0) * self.width as f64 / 2.0,
+            self.y as f64 + (v.y + 1.0) * self.height as f64 / 2.0,
+            v.z,
+        )
+    }
+
+    /// Inverse of [`Viewport::to_screen`] on x and y.
+    pub fn to_ndc(&self, x: f64, y: f64) -> (f64, f64) {
+        (
+            (x - self.x as f64) * 2.0 / self.width as f64 - 1.0,
+            (y - self.y as f64) * 2.0 / self.height as f64 - 1.0,
+        )
+    }
+}
+
+impl std::str::FromStr for ViewPreset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ViewPreset::ALL
+            .into_iter()
+            .find(|view| view.name() == s)
+            .ok_or_else(|| format!("unknown view '{}'", s))
+    }
+}
+
+#[test]
+fn test_view_directions() {
+    let expected = [
+        (ViewPreset::Front, Vec3f::new(0., 0., 1.)),
+        (ViewPreset::Back, Vec3f::new(0., 0., -1.)),
+        (ViewPreset::Left, Vec3f::new(-1., 0., 0.)),
+        (ViewPreset::Right, Vec3f::new(1., 0., 0.)),
+        (ViewPreset::Top, Vec3f::new(0., 1., 0.)),
+        (ViewPreset::Bottom, Vec3f::new(0., -1., 0.)),
+    ];
+    for (view, direction) in expected {
+        assert!((view.direction() - direction).length() < 1e-9, "{:?}", view);
+        assert_eq!(view.name().parse(), Ok(view));
+    }
+    let iso = ViewPreset::Isometric.direction();
+    assert!((iso.x - iso.y).abs() < 1e-9 && (iso.y - iso.z).abs() < 1e-9);
+}
+
+#[test]
+fn test_viewport() {
+    let wide = Viewport::letterboxed(200, 100, 1.0);
+    assert_eq!(
+        wide,
+        Viewport {
+            x: 50,
+            y: 0,
+            width: 100,
+            height: 100
+        }
+    );
+    let tall = Viewport::letterboxed(100, 200, 1.0);
+    assert_eq!(
+        tall,
+        Viewport {
+            x: 0,
+            y: 50,
+            width: 100,
+            height: 100
+        }
+    );
+    assert_eq!(Viewport::letterboxed(64, 64, 1.0), Viewport::full(64, 64));
+
+    let corner = wide.to_screen(&Vec3f::new(1., -1., 0.5));
+    assert_eq!((corner.x, corner.y, corner.z), (150., 0., 0.5));
+    assert_eq!(wide.to_ndc(150., 0.), (1., -1.));
+    assert!(wide.contains(50, 99) && !wide.contains(49, 0) && !wide.contains(150, 0));
+}
+
+#[test]
+fn test_orthographic_projection() {
+    assert_eq!(Orthographic::default().projection(), Mat4::identity());
+
+    let volume = Orthographic::from_height(4.0, 2.0, 1.0, 11.0);
+    let projection = volume.projection();
+    let corner = projection.transform_point(&Vec3f::new(4., 2., -1.));
+    assert!((corner - Vec3f::new(1., 1., 1.)).length() < 1e-9);
+    let corner = projection.transform_point(&Vec3f::new(-4., -2., -11.));
+    assert!((corner - Vec3f::new(-1., -1., -1.)).length() < 1e-9);
+}
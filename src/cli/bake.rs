@@ -0,0 +1,98 @@
+//! `rusterizer bake ao|normals MODEL OUTPUT`: textures baked from the meshes.
+
+use rusterizer::bake::{bake_ambient_occlusion, bake_normals, AoSettings};
+use rusterizer::math::Mat4;
+use rusterizer::mesh::Mesh;
+use rusterizer::spatial::Bvh;
+use rusterizer::stats::{RenderStats, StageTimings};
+
+use super::options::Args;
+use super::output::{in_out_dir, suffixed_path};
+use super::{
+    create_out_dir, fail_saving, fail_usage, finish, load_meshes, no_more_arguments,
+    reject_render_options,
+};
+
+/// The arguments, the map to bake and the path of the texture to write.
+pub fn parse(mut args: Args, positional: Vec<String>) -> Result<(Args, BakeMap, String), String> {
+    let mut positional = positional.into_iter();
+    let usage = "bake expects ao or normals, a model and an output texture path";
+    let map = positional.next().ok_or(usage)?.parse()?;
+    args.obj_path = Some(positional.next().ok_or(usage)?);
+    let path = positional.next().ok_or(usage)?;
+    no_more_arguments(positional)?;
+    reject_render_options("bake", &args)?;
+    Ok((args, map, path))
+}
+
+pub fn run(args: Args, map: BakeMap, path: String) {
+    let mut timings = StageTimings::new();
+    let meshes = load_meshes(&args, &mut timings);
+    create_out_dir(&args);
+    let path = in_out_dir(&args, path);
+    let outputs = timings.time("bake", || bake_maps(&meshes, map, &path, &args));
+    finish(&args, RenderStats::default(), timings, &outputs);
+}
+
+/// Texture made by `rusterizer bake`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BakeMap {
+    /// Ambient occlusion, see [`bake_ambient_occlusion`].
+    Occlusion,
+    /// Normals in model coordinates, see [`bake_normals`].
+    Normals,
+}
+
+impl std::str::FromStr for BakeMap {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ao" => Ok(BakeMap::Occlusion),
+            "normals" => Ok(BakeMap::Normals),
+            _ => Err(format!("unknown map '{}', expected ao or normals", s)),
+        }
+    }
+}
+
+/// Bakes `map` of every mesh with UVs into a texture of the output size, the ambient
+/// occlusion with all meshes as occluders. Several meshes get their index appended to
+/// `path`.
+fn bake_maps(meshes: &[Mesh], map: BakeMap, path: &str, args: &Args) -> Vec<String> {
+    let occluders: Vec<Bvh> = match map {
+        BakeMap::Occlusion => meshes
+            .iter()
+            .map(|mesh| Bvh::build(mesh, &Mat4::identity()))
+            .collect(),
+        BakeMap::Normals => Vec::new(),
+    };
+    let settings = AoSettings {
+        width: args.size.0,
+        height: args.size.1,
+        samples: args.ao_samples,
+        ..Default::default()
+    };
+    let mut baked = Vec::new();
+    for (index, mesh) in meshes.iter().enumerate() {
+        let texture = match map {
+            BakeMap::Occlusion => bake_ambient_occlusion(mesh, &occluders, &settings),
+            BakeMap::Normals => bake_normals(mesh, settings.width, settings.height),
+        };
+        let Some(texture) = texture else {
+            log::warn!("mesh {} has no UVs to bake into", index);
+            continue;
+        };
+        let output_path = match meshes.len() {
+            1 => path.to_string(),
+            _ => suffixed_path(path, &index.to_string()),
+        };
+        if let Err(e) = texture.to_image().save(&output_path) {
+            fail_saving(&output_path, e);
+        }
+        baked.push(output_path);
+    }
+    if baked.is_empty() {
+        fail_usage("bake needs a mesh with UVs".to_string());
+    }
+    baked
+}
@@ -0,0 +1,136 @@
+//! `rusterizer batch DIR [TEXTURE] --out DIR`: every model in a directory rendered into
+//! an image of its own.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use rusterizer::environment::EnvironmentMap;
+use rusterizer::error::RusterizerError;
+use rusterizer::loader;
+use rusterizer::math::Mat4;
+use rusterizer::stats::{RenderStats, StageTimings};
+
+use super::options::Args;
+use super::output::{metadata, output_path};
+use super::progress::ProgressBar;
+use super::render::{self, render};
+use super::{apply_overrides, fail, fail_partly, finish, load_texture, no_more_arguments};
+
+pub fn parse(mut args: Args, positional: Vec<String>) -> Result<Args, String> {
+    let mut positional = positional.into_iter();
+    args.batch_dir = Some(positional.next().ok_or("batch expects a model directory")?);
+    if args.out_dir.is_none() {
+        return Err("batch expects an output directory given with --out".to_string());
+    }
+    args.tex_path = positional.next();
+    no_more_arguments(positional)?;
+    render::check(&args)?;
+    Ok(args)
+}
+
+pub fn run(mut args: Args) {
+    let mut timings = StageTimings::new();
+    let texture = load_texture(&args, &mut timings);
+    let environment = render::load_resources(&mut args, &mut timings);
+    let dir = args
+        .batch_dir
+        .as_deref()
+        .expect("set when parsing arguments");
+    let result = timings.time("render", || {
+        render_batch(dir, texture.as_deref(), environment.as_ref(), &args)
+    });
+    match result {
+        Ok((stats, outputs, 0)) => finish(&args, stats, timings, &outputs),
+        Ok((stats, outputs, failed)) => {
+            let error = format!("{} file(s) could not be rendered", failed);
+            fail_partly(error, stats, timings, &outputs);
+        }
+        Err(e) => fail(e),
+    }
+}
+
+/// Returns `true` for the model formats rendered from a batch directory.
+fn is_mesh_path(path: &str) -> bool {
+    let path = path.to_ascii_lowercase();
+    [".gltf", ".glb", ".obj", ".ply", ".stl"]
+        .iter()
+        .any(|extension| path.ends_with(extension))
+}
+
+/// Renders every model in `dir` into a PNG of the same name in the `--out` directory,
+/// `--jobs` files at a time, and returns the summed stats, the paths written in order and
+/// the number of failed files.
+fn render_batch(
+    dir: &str,
+    texture: Option<&image::RgbImage>,
+    environment: Option<&EnvironmentMap>,
+    args: &Args,
+) -> Result<(RenderStats, Vec<String>, usize), RusterizerError> {
+    let out_dir = Path::new(
+        args.out_dir
+            .as_deref()
+            .expect("checked when parsing arguments"),
+    );
+    std::fs::create_dir_all(out_dir).map_err(|e| RusterizerError::in_file(out_dir, e))?;
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map_err(|e| RusterizerError::in_file(Path::new(dir), e))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.to_str().is_some_and(is_mesh_path) && path.is_file())
+        .collect();
+    paths.sort();
+
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new((RenderStats::default(), Vec::new(), 0));
+    let bar = args
+        .progress
+        .then(|| ProgressBar::new("files", paths.len() as u64, 1));
+    let completed = AtomicUsize::new(0);
+    let render_file = |path: &Path| -> Result<(RenderStats, PathBuf), RusterizerError> {
+        let mut meshes = loader::load(path)?;
+        apply_overrides(&mut meshes, args)?;
+        let mut image = render(&meshes, texture, environment, &Mat4::identity(), args);
+        image.set_text(metadata(args, "front"));
+        let stats = image.stats();
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        // the output path names the files if it has any fields
+        let output = if args.output_path.contains('{') {
+            PathBuf::from(output_path(args, &stem, "front", 1))
+        } else {
+            out_dir.join(format!("{}.png", stem))
+        };
+        image.save(&output)?;
+        log::info!("rendered {} to {}", path.display(), output.display());
+        Ok((stats, output))
+    };
+    std::thread::scope(|scope| {
+        for _ in 0..args.jobs.min(paths.len()) {
+            scope.spawn(|| {
+                while let Some(path) = paths.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    let result = render_file(path);
+                    if let Some(bar) = &bar {
+                        bar.set(completed.fetch_add(1, Ordering::Relaxed) as u64 + 1);
+                    }
+                    let mut results = results.lock().unwrap();
+                    match result {
+                        Ok((stats, output)) => {
+                            results.0 += stats;
+                            results.1.push(output.to_string_lossy().into_owned());
+                        }
+                        Err(e) => {
+                            log::error!("{}", e);
+                            results.2 += 1;
+                        }
+                    }
+                }
+            });
+        }
+    });
+    if let Some(bar) = &bar {
+        bar.finish();
+    }
+    let mut results = results.into_inner().unwrap();
+    // in the order of the files rather than of the threads finishing them
+    results.1.sort();
+    Ok(results)
+}
@@ -0,0 +1,35 @@
+//! `rusterizer convert MODEL OUTPUT.ply`: the meshes merged into a PLY file.
+
+use rusterizer::loader;
+use rusterizer::mesh::Mesh;
+use rusterizer::stats::{RenderStats, StageTimings};
+
+use super::options::Args;
+use super::output::in_out_dir;
+use super::{create_out_dir, fail, finish, load_meshes, no_more_arguments, reject_render_options};
+
+/// The arguments and the path of the PLY file to write.
+pub fn parse(mut args: Args, positional: Vec<String>) -> Result<(Args, String), String> {
+    let mut positional = positional.into_iter();
+    let usage = "convert expects a model and an output .ply path";
+    args.obj_path = Some(positional.next().ok_or(usage)?);
+    let path = positional.next().ok_or(usage)?;
+    if !path.to_ascii_lowercase().ends_with(".ply") {
+        return Err(usage.to_string());
+    }
+    no_more_arguments(positional)?;
+    reject_render_options("convert", &args)?;
+    Ok((args, path))
+}
+
+pub fn run(args: Args, path: String) {
+    let mut timings = StageTimings::new();
+    let meshes = load_meshes(&args, &mut timings);
+    create_out_dir(&args);
+    let path = in_out_dir(&args, path);
+    let merged = Mesh::merged(&meshes);
+    if let Err(e) = timings.time("save", || loader::ply::save(&merged, &path)) {
+        fail(e);
+    }
+    finish(&args, RenderStats::default(), timings, &[path]);
+}
@@ -0,0 +1,83 @@
+//! `rusterizer info MODEL`: what the meshes hold and whether they can be drawn.
+
+use rusterizer::math::Vec3f;
+use rusterizer::mesh::Mesh;
+use rusterizer::stats::{RenderStats, StageTimings};
+
+use super::options::Args;
+use super::{fail_partly, finish, load_meshes, no_more_arguments, reject_render_options};
+
+pub fn parse(mut args: Args, positional: Vec<String>) -> Result<Args, String> {
+    let mut positional = positional.into_iter();
+    args.obj_path = positional.next();
+    if args.obj_path.is_none() && args.primitive.is_none() {
+        return Err("info expects a model".to_string());
+    }
+    no_more_arguments(positional)?;
+    reject_render_options("info", &args)?;
+    Ok(args)
+}
+
+pub fn run(args: Args) {
+    let mut timings = StageTimings::new();
+    let meshes = load_meshes(&args, &mut timings);
+    if !print_info(&meshes) {
+        let error = "some meshes cannot be drawn".to_string();
+        fail_partly(error, RenderStats::default(), timings, &[]);
+    }
+    finish(&args, RenderStats::default(), timings, &[]);
+}
+
+/// Prints the size, bounds, attributes and issues of every mesh, and the size and bounds
+/// of all of them together if there are several. Returns whether all can be drawn.
+fn print_info(meshes: &[Mesh]) -> bool {
+    let print_mesh = |mesh: &Mesh| {
+        println!("  vertices:  {}", mesh.positions.len());
+        println!("  triangles: {}", mesh.indices.len());
+        if !mesh.lines.is_empty() || !mesh.points.is_empty() {
+            println!("  lines:     {}", mesh.lines.len());
+            println!("  points:    {}", mesh.points.len());
+        }
+        if let Some(bounds) = mesh.bounds() {
+            let point = |p: Vec3f| format!("({:.4}, {:.4}, {:.4})", p.x, p.y, p.z);
+            let size = bounds.max - bounds.min;
+            println!(
+                "  bounds:    {} to {}",
+                point(bounds.min),
+                point(bounds.max)
+            );
+            println!("  size:      {:.4} x {:.4} x {:.4}", size.x, size.y, size.z);
+        }
+        let has = |present: bool| if present { "yes" } else { "no" };
+        println!(
+            "  normals: {}, UVs: {}, colors: {}",
+            has(mesh.has_normals()),
+            has(mesh.has_uvs()),
+            has(mesh.has_colors())
+        );
+    };
+    let mut drawable = true;
+    for (index, mesh) in meshes.iter().enumerate() {
+        println!(
+            "object {}: {}",
+            index,
+            mesh.name.as_deref().unwrap_or("(unnamed)")
+        );
+        print_mesh(mesh);
+        let issues = mesh.validate();
+        if issues.is_empty() {
+            println!("  no issues");
+        } else {
+            println!("  issues:");
+            for issue in issues.to_string().lines() {
+                println!("    {}", issue);
+            }
+        }
+        drawable &= !issues.is_broken();
+    }
+    if meshes.len() > 1 {
+        println!("total:");
+        print_mesh(&Mesh::merged(meshes));
+    }
+    drawable
+}
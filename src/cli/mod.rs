@@ -0,0 +1,502 @@
+//! The `rusterizer` command line: the options every command takes in [`options`], and a
+//! module for each command with its own parser and the function running it.
+
+mod bake;
+mod batch;
+mod convert;
+mod info;
+mod options;
+mod output;
+mod progress;
+mod render;
+mod uv_layout;
+
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+use std::time::Instant;
+
+use rusterizer::atlas;
+use rusterizer::bake::{ground_plane, AoSettings};
+use rusterizer::color::Color;
+use rusterizer::error::RusterizerError;
+use rusterizer::loader;
+use rusterizer::mesh::Mesh;
+use rusterizer::report::{OutputFile, Report};
+use rusterizer::stats::{RenderStats, StageTimings};
+use rusterizer::texture::{Texture, TextureFormat};
+
+use options::{primitive_mesh, Args, ObjectStyle};
+
+const USAGE: &str = "\
+Usage: rusterizer [render] [OPTIONS] [MODEL [TEXTURE]]
+       rusterizer batch [OPTIONS] DIR [TEXTURE] --out DIR
+       rusterizer info [OPTIONS] MODEL
+       rusterizer convert [OPTIONS] MODEL OUTPUT.ply
+       rusterizer bake ao|normals [OPTIONS] MODEL OUTPUT
+       rusterizer uv-layout [OPTIONS] MODEL [TEXTURE]
+
+Commands:
+  render      Render the model into images, the default
+  batch       Render every model in a directory into the --out directory
+  info        Print the size, bounds, attributes and issues of every mesh
+  convert     Write the meshes merged into a PLY file
+  bake        Bake ambient occlusion or normals into a texture of every mesh
+  uv-layout   Draw the faces in UV space over the texture
+
+General:
+  -h, --help                  Print this message
+  -v, --verbose               Show what is done, twice for debugging details
+  -q, --quiet                 Show errors only
+  --config PATH               Read the settings from PATH instead of rusterizer.toml
+  --no-config                 Leave out the settings file
+  --report PATH               Write counters, stage timings and output hashes as JSON
+  --stats                     Print render counters and stage timings
+  --progress                  Show a progress bar
+  --time-budget SECONDS       Stop drawing once the run has taken this long
+  --jobs N                    Files rendered at once by batch, else threads drawing
+
+Output:
+  -o, --output PATH           Image path, with {model}, {style}, {view}, {width},
+                              {height} and {frame} fields; output.png by default
+  --out DIR                   Directory relative output paths are in
+  --size WIDTHxHEIGHT         Output size in pixels, 512x512 by default
+  --view LIST                 front, back, left, right, top, bottom, iso or all
+  --ortho HEIGHT[:NEAR:FAR]   Orthographic view volume keeping the aspect ratio
+  --sheet COLUMNS             Contact sheet of the views and modes, 0 for automatic
+  --sheet-modes LIST          Output modes on the contact sheet
+  --band-height ROWS          Render in bands streamed to the output PNG
+  --turntable FRAMES          Frames of a full turn around the model
+  --clip NAME[:FPS]           Animation clip of a glTF model to render frame by frame
+  --morph PATH                Morph target weights of a glTF model for every frame
+  --animation PATH            GIF or APNG to write the frames into
+  --delay MS                  Delay between animation frames
+  --metadata                  Write the camera, lights and style into PNG outputs
+  --label TEXT                Text drawn in the top-left corner
+
+Meshes:
+  --primitive NAME            Add a sphere, cube, plane or torus
+  --groups LIST               Only the objects or groups with these names
+  --weld DISTANCE             Join vertices closer than DISTANCE
+  --decimate FRACTION         Keep this fraction of the triangles
+  --normals area|angle[:DEG]  Generate normals for meshes without them
+  --subdivide LEVELS[:loop]   Split every triangle in four, LEVELS times
+  --displace PATH[:SCALE]     Move vertices along their normals by a height map
+  --optimize                  Reorder faces and vertices for locality
+  --ground                    Add a floor with the contact shadow baked in
+  --atlas                     Pack the textures of all meshes into one atlas
+
+Materials:
+  --style NAME=STYLE          wireframe[:COLOR], filled[:COLOR], random:SEED or
+                              textured:PATH for the meshes of that name
+  --checker SQUARES           Checkerboard replacing the textures
+  --texture-space srgb|linear How base color textures are stored
+  --matcap PATH               Material capture image shading the meshes
+  --ao-texture PATH           Ambient occlusion texture of every mesh
+  --random-fill SEED          Flat random colors per face
+  --unlit-vertex-colors       Vertex colors as they are, without shading
+  --metallic VALUE            Metalness of every mesh, 0 to 1
+  --roughness VALUE           Roughness of every mesh, 0 to 1
+  --metal REFLECTIVITY        Mirror the environment
+
+Shading:
+  --pbr                       Metallic-roughness shading
+  --deferred                  Light through a G-buffer after rasterizing
+  --raytrace                  Ray cast instead of rasterizing, as a reference
+  --light SPEC                X,Y,Z towards a light, @X,Y,Z for a point light or
+                              @X,Y,Z:DX,DY,DZ:INNER,OUTER for a spot light; point and
+                              spot lights take :C,L,Q falloff, any light :shadows
+  --ambient SKY:GROUND[:WRAP] Hemisphere light, or none
+  --environment PATH          Equirectangular image around the scene
+  --cube-map PATHS            Cross image or six comma-separated faces around the scene
+  --ibl                       Also light the model with the environment
+  --fog SPEC                  linear:START:END, exp:DENSITY or exp2:DENSITY
+  --fog-color COLOR           Fog color, the background's by default
+  --output-mode MODE          shaded, normal, depth or uv
+  --scalars SOURCE            curvature, height or a CSV file of per-vertex values
+  --colormap NAME             viridis, magma, plasma, turbo, grayscale or jet
+  --colormap-scale linear|log How scalars are spread over the colormap
+  --scalar-range MIN:MAX      Values at the ends of the colormap
+  --points RADIUS             Draw the vertices as splats of RADIUS pixels
+  --point-color normal|depth  Color of the splats
+
+Drawing:
+  --background SPEC           Color, gradient:TOP:BOTTOM, checker or transparent
+  --backdrop PATH             Photo shown behind the model
+  --line-width PIXELS         Width of wireframe lines
+  --line-aa                   Antialiased lines
+  --depth-bias C[:SLOPE]      Depth bias of wireframes over coplanar surfaces
+  --rasterizer bbox|scanline  How filled triangles find their pixels
+  --precision float|fixed     How the corners of filled triangles are held
+  --overlay LIST              axes, grid, lights and frustum drawn over the render
+  --outline PIXELS            Toon outlines along silhouettes and creases
+  --exposure STOPS            Exposure before tone mapping
+  --tonemap NAME              none, reinhard or aces
+  --post PASS[:VALUE]         blur, bloom, vignette or fxaa, in the given order
+  --dof DISTANCE[:APERTURE]   Depth of field
+
+Inspection:
+  --pick X,Y                  Print the mesh and triangle at a pixel
+  --overdraw                  Save heatmaps of the fragments covering every pixel
+
+Baking:
+  --ao-samples N              Rays cast per texel when baking ambient occlusion
+  --bake-ao PATH              Short for bake ao MODEL PATH
+";
+
+/// Commands named by the first argument that is not an option, `render` if none is.
+const COMMANDS: [&str; 6] = ["render", "batch", "info", "convert", "bake", "uv-layout"];
+
+/// Runs the command given by `command_line`, the arguments after the program name.
+pub fn run(command_line: Vec<String>) {
+    if command_line
+        .iter()
+        .any(|arg| arg == "-h" || arg == "--help")
+    {
+        print!("{}", USAGE);
+        return;
+    }
+    let arguments = options::with_config(command_line).unwrap_or_else(|e| fail(e));
+    let (mut args, mut positional) = parsed(options::parse(arguments));
+    if let Some(level) = args.log_level {
+        log::set_max_level(level);
+    }
+    if let Some(path) = &args.report_path {
+        let _ = REPORT_PATH.set(path.clone());
+    }
+    args.deadline = args.time_budget.map(|budget| Instant::now() + budget);
+
+    let mut command = match positional.first() {
+        Some(name) if COMMANDS.contains(&name.as_str()) => positional.remove(0),
+        _ => "render".to_string(),
+    };
+    if let Some(path) = args.bake_ao.take() {
+        if command != "render" {
+            fail_usage(format!("{} cannot be combined with --bake-ao", command));
+        }
+        command = "bake".to_string();
+        positional.insert(0, "ao".to_string());
+        positional.push(path);
+    }
+    match command.as_str() {
+        "render" => render::run(parsed(render::parse(args, positional))),
+        "batch" => batch::run(parsed(batch::parse(args, positional))),
+        "info" => info::run(parsed(info::parse(args, positional))),
+        "convert" => {
+            let (args, path) = parsed(convert::parse(args, positional));
+            convert::run(args, path);
+        }
+        "bake" => {
+            let (args, map, path) = parsed(bake::parse(args, positional));
+            bake::run(args, map, path);
+        }
+        "uv-layout" => uv_layout::run(parsed(uv_layout::parse(args, positional))),
+        _ => unreachable!("not one of the commands"),
+    }
+}
+
+/// The parsed arguments, or else a usage error.
+fn parsed<T>(result: Result<T, String>) -> T {
+    result.unwrap_or_else(|e| fail_usage(e))
+}
+
+/// Fails if any of `positional` is left over once a command has taken its arguments.
+fn no_more_arguments(mut positional: impl Iterator<Item = String>) -> Result<(), String> {
+    match positional.next() {
+        Some(arg) => Err(format!("unexpected argument '{}'", arg)),
+        None => Ok(()),
+    }
+}
+
+/// Rejects the options making several renders, for the commands other than `render`.
+fn reject_render_options(command: &str, args: &Args) -> Result<(), String> {
+    let unsupported = [
+        ("--turntable", args.turntable_frames.is_some()),
+        ("--clip", args.clip.is_some()),
+        ("--morph", args.morph_path.is_some()),
+        ("--band-height", args.band_height.is_some()),
+        ("--view", !args.views.is_empty()),
+        ("--sheet", args.contact_sheet.is_some()),
+        ("--pick", args.pick.is_some()),
+    ];
+    match unsupported.iter().find(|(_, used)| *used) {
+        Some((flag, _)) => Err(format!("{} cannot be combined with {}", command, flag)),
+        None => Ok(()),
+    }
+}
+
+/// Loads the model, if one is given, and applies the options changing the meshes.
+fn load_meshes(args: &Args, timings: &mut StageTimings) -> Vec<Mesh> {
+    let mut meshes = match &args.obj_path {
+        Some(path) => {
+            let meshes = timings
+                .time("load", || loader::load(path))
+                .unwrap_or_else(|e| fail(e));
+            log::info!(
+                "loaded {} object(s) with {} triangles from {}",
+                meshes.len(),
+                meshes.iter().map(|mesh| mesh.indices.len()).sum::<usize>(),
+                path
+            );
+            meshes
+        }
+        None => Vec::new(),
+    };
+    if let Err(e) = timings.time("load", || apply_overrides(&mut meshes, args)) {
+        fail(e);
+    }
+    for mesh in &meshes {
+        log::debug!(
+            "object {}: {} vertices, {} triangles",
+            mesh.name.as_deref().unwrap_or("(unnamed)"),
+            mesh.positions.len(),
+            mesh.indices.len()
+        );
+    }
+    meshes
+}
+
+/// The texture given on the command line, or the `--checker` board overriding it and
+/// the model's textures.
+fn load_texture(args: &Args, timings: &mut StageTimings) -> Option<Arc<image::RgbImage>> {
+    if let Some(squares) = args.checker {
+        return Some(Texture::checker(squares).to_srgb_rgb());
+    }
+    let path = args.tex_path.as_ref()?;
+    Some(
+        timings
+            .time("load", || load_color_texture(path, args))
+            .unwrap_or_else(|e| fail(e)),
+    )
+}
+
+/// Creates the `--out` directory, if one is given.
+fn create_out_dir(args: &Args) {
+    if let Some(dir) = &args.out_dir {
+        if let Err(e) = std::fs::create_dir_all(dir) {
+            fail(RusterizerError::in_file(Path::new(dir), e));
+        }
+    }
+}
+
+/// Logs what was drawn and how long it took, and writes the report of a run that wrote
+/// `outputs`.
+fn finish(args: &Args, stats: RenderStats, timings: StageTimings, outputs: &[String]) {
+    if let Some(bar) = &args.progress_bar {
+        bar.finish();
+    }
+    log::info!(
+        "drew {} of {} triangles ({} culled, {} occluded, {} degenerate) in {:.1} ms",
+        stats.triangles_submitted
+            - stats.triangles_culled
+            - stats.triangles_occluded
+            - stats.triangles_degenerate,
+        stats.triangles_submitted,
+        stats.triangles_culled,
+        stats.triangles_occluded,
+        stats.triangles_degenerate,
+        timings.total().as_secs_f64() * 1000.0
+    );
+    if args.stats {
+        eprintln!("{}\n{}", stats, timings);
+    } else {
+        for (stage, duration) in timings.stages() {
+            log::debug!("{}: {:.3} ms", stage, duration.as_secs_f64() * 1000.0);
+        }
+    }
+    write_report(&report(0, None, stats, timings, outputs));
+}
+
+/// Logs `error` about part of the work and exits with 1, after writing the report.
+fn fail_partly(error: String, stats: RenderStats, timings: StageTimings, outputs: &[String]) -> ! {
+    log::error!("{}", error);
+    write_report(&report(1, Some(error), stats, timings, outputs));
+    std::process::exit(1);
+}
+
+/// Cache size the `--optimize` statistics are given for.
+const OPTIMIZE_CACHE_SIZE: usize = 16;
+
+/// Width of the `--ground` floor relative to the footprint of the meshes, and its color.
+const GROUND_SCALE: f64 = 3.0;
+const GROUND_COLOR: Color = Color(160, 160, 160);
+
+/// The `--report` path, set once the arguments are parsed so that failures are reported.
+static REPORT_PATH: OnceLock<String> = OnceLock::new();
+
+/// Prints the error and exits with a status telling the kinds of errors apart: 2 for
+/// invalid arguments, 3 for I/O errors, 4 for malformed models and 5 for images that
+/// could not be decoded or encoded. A batch with files that failed, or meshes that
+/// `info` finds cannot be drawn, exit with 1. The status and error go into the
+/// `--report` too.
+fn fail(error: RusterizerError) -> ! {
+    log::error!("{}", error);
+    let code = match error {
+        RusterizerError::InvalidArgument(_) => 2,
+        RusterizerError::Io(_) => 3,
+        RusterizerError::Parse(_) => 4,
+        RusterizerError::Texture(_) => 5,
+    };
+    write_report(&Report {
+        exit_code: code,
+        error: Some(error.to_string()),
+        ..Default::default()
+    });
+    std::process::exit(code);
+}
+
+/// Report of a run that ended with `exit_code`, hashing the `outputs` if it is written.
+fn report(
+    exit_code: i32,
+    error: Option<String>,
+    stats: RenderStats,
+    timings: StageTimings,
+    outputs: &[String],
+) -> Report {
+    if REPORT_PATH.get().is_none() {
+        return Report::default();
+    }
+    let outputs = outputs
+        .iter()
+        .map(|path| OutputFile::read(path).unwrap_or_else(|e| fail(e)))
+        .collect();
+    Report {
+        exit_code,
+        error,
+        stats,
+        timings,
+        outputs,
+    }
+}
+
+/// Writes `report` to the `--report` path, if one was given.
+fn write_report(report: &Report) {
+    if let Some(path) = REPORT_PATH.get() {
+        if let Err(e) = std::fs::write(path, report.to_json() + "\n") {
+            log::error!("{}", RusterizerError::in_file(Path::new(path), e));
+        }
+    }
+}
+
+/// Fails with an invalid argument error.
+fn fail_usage(message: String) -> ! {
+    fail(RusterizerError::InvalidArgument(message))
+}
+
+/// Fails with an error from writing the output at `path`.
+fn fail_saving<P: AsRef<Path>>(path: P, error: image::ImageError) -> ! {
+    match error {
+        image::ImageError::IoError(e) => fail(RusterizerError::in_file(path.as_ref(), e)),
+        e => fail(e.into()),
+    }
+}
+
+/// Adds the requested primitive and applies the material overrides given on the command
+/// line, loading the textures of textured object styles.
+fn apply_overrides(meshes: &mut Vec<Mesh>, args: &Args) -> Result<(), RusterizerError> {
+    if !args.selected_groups.is_empty() {
+        meshes.retain(|mesh| args.selected_groups.iter().any(|name| is_named(mesh, name)));
+    }
+    if let Some(name) = &args.primitive {
+        meshes.extend(primitive_mesh(name));
+    }
+    for mesh in meshes.iter_mut() {
+        let material = &mut mesh.material;
+        material.reflectivity = args.reflectivity.unwrap_or(material.reflectivity);
+        material.metallic = args.metallic.unwrap_or(material.metallic);
+        material.roughness = args.roughness.unwrap_or(material.roughness);
+    }
+    if let Some(tolerance) = args.weld_tolerance {
+        for mesh in meshes.iter_mut() {
+            let welded = mesh.weld_vertices(tolerance);
+            log::debug!("welded {} duplicate vertices", welded);
+        }
+    }
+    if let Some(ratio) = args.decimation {
+        for mesh in meshes.iter_mut() {
+            let target = (mesh.indices.len() as f64 * ratio).round() as usize;
+            mesh.decimate(target);
+        }
+    }
+    if let Some((weighting, crease_angle)) = args.generate_normals {
+        for mesh in meshes.iter_mut().filter(|mesh| !mesh.has_normals()) {
+            mesh.generate_normals(weighting, crease_angle);
+        }
+    }
+    for mesh in meshes.iter_mut() {
+        if args.loop_subdivision {
+            mesh.subdivide_loop(args.subdivisions);
+        } else {
+            mesh.subdivide(args.subdivisions);
+        }
+    }
+    if let Some((path, scale)) = &args.displacement {
+        let height_map = args.textures.get(path, TextureFormat::SCALAR)?.to_gray();
+        for mesh in meshes.iter_mut() {
+            mesh.displace(&height_map, *scale);
+        }
+    }
+    if args.optimize {
+        for mesh in meshes.iter_mut() {
+            let before = mesh.average_cache_miss_ratio(OPTIMIZE_CACHE_SIZE);
+            mesh.optimize_vertex_cache();
+            mesh.optimize_vertex_fetch();
+            log::debug!(
+                "reordered faces, cache misses per face {:.2} -> {:.2}",
+                before,
+                mesh.average_cache_miss_ratio(OPTIMIZE_CACHE_SIZE)
+            );
+        }
+    }
+    if let Some(path) = &args.occlusion_path {
+        let occlusion = args.textures.get(path, TextureFormat::SCALAR)?;
+        for mesh in meshes.iter_mut() {
+            mesh.material.occlusion_texture = Some(occlusion.clone());
+        }
+    }
+    for mesh in meshes.iter_mut() {
+        if let Some(ObjectStyle::Textured(path)) = object_style(mesh, args) {
+            mesh.material.base_color_texture = Some(load_color_texture(path, args)?);
+        }
+    }
+    if args.ground {
+        let settings = AoSettings {
+            samples: args.ao_samples,
+            ..Default::default()
+        };
+        let ground = ground_plane(meshes, GROUND_SCALE, GROUND_COLOR, &settings);
+        meshes.extend(ground);
+    }
+    if args.atlas {
+        if let Some(atlas) = atlas::pack_textures(meshes) {
+            log::debug!(
+                "packed the textures into a {}x{} atlas",
+                atlas.width(),
+                atlas.height()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Loads a base color texture in the `--texture-space` it was stored in.
+fn load_color_texture(path: &str, args: &Args) -> Result<Arc<image::RgbImage>, RusterizerError> {
+    let format = TextureFormat {
+        color_space: args.texture_space,
+        ..TextureFormat::COLOR
+    };
+    Ok(args.textures.get(path, format)?.to_srgb_rgb())
+}
+
+/// Whether `name` is the mesh's name or one of its groups.
+fn is_named(mesh: &Mesh, name: &str) -> bool {
+    mesh.name.as_deref() == Some(name) || mesh.groups.iter().any(|group| group == name)
+}
+
+/// The style given to the mesh's name or one of its groups with `--style`, if any.
+fn object_style<'a>(mesh: &Mesh, args: &'a Args) -> Option<&'a ObjectStyle> {
+    args.object_styles
+        .iter()
+        .rev()
+        .find(|(name, _)| is_named(mesh, name))
+        .map(|(_, style)| style)
+}
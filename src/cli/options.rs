@@ -0,0 +1,1169 @@
+//! Options every command takes, and the settings file standing in for some of them.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use log::LevelFilter;
+use rusterizer::bake::AoSettings;
+use rusterizer::camera::ViewPreset;
+use rusterizer::color::{self, Color, HdrColor};
+use rusterizer::colormap::{Colormap, Scale};
+use rusterizer::config::Config;
+use rusterizer::drawable::{Background, DepthBias, LineStyle};
+use rusterizer::error::RusterizerError;
+use rusterizer::fog::FogFalloff;
+use rusterizer::geometry;
+use rusterizer::light::{Falloff, HemisphereLight, Light};
+use rusterizer::loader::TextureCache;
+use rusterizer::math::{Mat4, Vec3f};
+use rusterizer::mesh::{Mesh, NormalWeighting};
+use rusterizer::postprocess::{Bloom, Focus, Fxaa, GaussianBlur, PostProcess, Vignette};
+use rusterizer::raster::{Precision, Traversal};
+use rusterizer::render::{DebugView, SplatColoring};
+use rusterizer::texture::ColorSpace;
+use rusterizer::tonemap::ToneMapping;
+
+use super::output::{expand_output_path, OutputFields};
+use super::progress::ProgressBar;
+
+/// Draw style given to the meshes of one name with `--style`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ObjectStyle {
+    Wireframe(Color),
+    /// Flat color, the material color if not given.
+    Filled(Option<Color>),
+    Random(u64),
+    /// Image replacing the material texture, loaded with the meshes.
+    Textured(String),
+}
+
+impl std::str::FromStr for ObjectStyle {
+    type Err = String;
+
+    /// Parses `wireframe[:COLOR]`, `filled[:COLOR]`, `random:SEED` or `textured:PATH`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, parameter) = match s.split_once(':') {
+            Some((name, parameter)) => (name, Some(parameter)),
+            None => (s, None),
+        };
+        let color = |color: &str| {
+            color
+                .parse::<Color>()
+                .map_err(|e| format!("invalid {} color: {}", name, e))
+        };
+        match (name, parameter) {
+            ("wireframe", None) => Ok(ObjectStyle::Wireframe(color::WHITE)),
+            ("wireframe", Some(c)) => Ok(ObjectStyle::Wireframe(color(c)?)),
+            ("filled", None) => Ok(ObjectStyle::Filled(None)),
+            ("filled", Some(c)) => Ok(ObjectStyle::Filled(Some(color(c)?))),
+            ("random", Some(seed)) => seed
+                .parse()
+                .map(ObjectStyle::Random)
+                .map_err(|e| format!("invalid seed: {}", e)),
+            ("textured", Some(path)) => Ok(ObjectStyle::Textured(path.to_string())),
+            _ => Err(format!("unknown object style '{}'", s)),
+        }
+    }
+}
+
+/// Debug geometry drawn over the render with `--overlay`, in the order listed here.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Overlay {
+    /// A grid on the `y = 0` plane, first so that the axes lying on it show.
+    Grid,
+    /// The X, Y and Z axes in red, green and blue.
+    Axes,
+    /// Where the lights, or the headlight, shine from.
+    Lights,
+    /// The view volume of the front view.
+    Frustum,
+}
+
+impl std::str::FromStr for Overlay {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "grid" => Ok(Overlay::Grid),
+            "axes" => Ok(Overlay::Axes),
+            "lights" => Ok(Overlay::Lights),
+            "frustum" => Ok(Overlay::Frustum),
+            _ => Err(format!("unknown overlay '{}'", s)),
+        }
+    }
+}
+
+/// Per-vertex values drawn with `--scalars`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ScalarSource {
+    /// Mean curvature of the surface.
+    Curvature,
+    /// Model-space height along Y.
+    Height,
+    /// Values from a CSV file, running through the vertices of every mesh in turn.
+    File(String),
+}
+
+impl ScalarSource {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ScalarSource::Curvature => "curvature",
+            ScalarSource::Height => "height",
+            ScalarSource::File(_) => "scalars",
+        }
+    }
+}
+
+/// Images surrounding the scene.
+#[derive(Clone)]
+pub enum EnvironmentSource {
+    /// An equirectangular image.
+    LatLong(String),
+    /// A cross image or the six faces of a cube map.
+    CubeMap(Vec<String>),
+}
+
+#[derive(Clone)]
+pub struct Args {
+    pub obj_path: Option<String>,
+    /// Where `--bake-ao` writes the ambient occlusion of the model, standing for
+    /// `bake ao MODEL PATH`.
+    pub bake_ao: Option<String>,
+    pub tex_path: Option<String>,
+    /// Output image path, which may hold fields filled in by [`expand_output_path`]; the
+    /// extension selects the image format.
+    pub output_path: String,
+    /// Number of frames for a full turntable rotation, if requested.
+    pub turntable_frames: Option<u32>,
+    /// Animation clip of a glTF model to render frame by frame, and its frame rate.
+    pub clip: Option<(String, f64)>,
+    /// Morph target weights of a glTF model for every frame to render.
+    pub morph_path: Option<String>,
+    /// Path of an animated GIF/APNG to write the turntable, clip or morph frames into.
+    pub animation_path: Option<String>,
+    /// Delay between animation frames in milliseconds.
+    pub frame_delay_ms: u16,
+    pub tone_mapping: ToneMapping,
+    /// Light through a G-buffer in a separate pass instead of while rasterizing.
+    pub deferred: bool,
+    /// Pack the textures of all meshes into one atlas.
+    pub atlas: bool,
+    /// Material capture image shading meshes without a style of their own.
+    pub matcap_path: Option<String>,
+    /// The `--matcap` image, once loaded.
+    pub matcap: Option<Arc<image::RgbImage>>,
+    /// Replace the textures with a checkerboard of this many squares along each side.
+    pub checker: Option<u32>,
+    /// How base color textures are stored.
+    pub texture_space: ColorSpace,
+    /// Textures loaded so far, shared by all frames and batch files.
+    pub textures: Arc<TextureCache>,
+    /// Thickness of toon outlines drawn along silhouettes and creases.
+    pub outline: Option<u32>,
+    /// Mesh attribute to visualize instead of the shaded render.
+    pub debug_view: Option<DebugView>,
+    /// Per-vertex values to draw through the colormap instead of the shaded render.
+    pub scalars: Option<ScalarSource>,
+    /// The `--scalars` file's values, once loaded.
+    pub scalar_values: Option<Arc<Vec<f64>>>,
+    /// Palette of scalars and depth-colored splats, instead of their own defaults.
+    pub colormap: Option<Colormap>,
+    /// How scalars are spread over the colormap.
+    pub colormap_scale: Scale,
+    /// Values at the ends of the colormap, the smallest and largest value by default.
+    pub scalar_range: Option<(f64, f64)>,
+    /// Pixel, counted from the top left, whose mesh and triangle are printed after
+    /// rendering.
+    pub pick: Option<(u32, u32)>,
+    /// Save heatmaps of the fragments covering and written to every pixel in place of
+    /// the render.
+    pub overdraw: bool,
+    /// Render by ray casting instead of rasterizing, as a reference to compare with.
+    pub raytrace: bool,
+    /// Splat radius in pixels when rendering vertices as a point cloud.
+    pub point_radius: Option<f64>,
+    pub point_coloring: SplatColoring,
+    pub line_style: LineStyle,
+    /// How filled triangles find their pixels.
+    pub traversal: Traversal,
+    /// How the corners of filled triangles are held.
+    pub precision: Precision,
+    pub background: Background,
+    /// Photo as large as the output shown behind the model in place of the background.
+    pub backdrop_path: Option<String>,
+    /// The `--backdrop` image, once loaded.
+    pub backdrop: Option<Arc<image::RgbaImage>>,
+    /// Sky and ground light filling in the sides of the model the lights miss.
+    pub ambient: Option<HemisphereLight>,
+    pub fog: Option<FogFalloff>,
+    /// Fog color, the background color if not given.
+    pub fog_color: Option<Color>,
+    pub focus: Option<Focus>,
+    /// Print render counters and stage timings to stderr.
+    pub stats: bool,
+    /// Most detailed messages shown, if set on the command line.
+    pub log_level: Option<LevelFilter>,
+    /// Show a progress bar on stderr.
+    pub progress: bool,
+    /// Bar advanced by the triangles of every render, once the amount of work is known.
+    pub progress_bar: Option<Arc<ProgressBar>>,
+    /// Time all renders may take together, after which drawing stops.
+    pub time_budget: Option<Duration>,
+    /// When the time budget runs out, once rendering has started.
+    pub deadline: Option<Instant>,
+    /// Output size in pixels.
+    pub size: (u32, u32),
+    /// Render in bands of this many rows streamed to the output PNG.
+    pub band_height: Option<u32>,
+    /// Environment shown behind the model instead of the background color.
+    pub environment: Option<EnvironmentSource>,
+    /// Also light the model with the environment, which uses deferred shading.
+    pub image_based_lighting: bool,
+    /// Reflectivity given to every mesh, mirroring the environment.
+    pub reflectivity: Option<f64>,
+    /// Shade with the metallic-roughness model, which uses deferred shading.
+    pub physically_based: bool,
+    /// Directional, point and spot lights replacing the one from the viewer, which use
+    /// deferred shading except in bands.
+    pub lights: Vec<Light>,
+    /// Metalness given to every mesh.
+    pub metallic: Option<f64>,
+    /// Roughness given to every mesh.
+    pub roughness: Option<f64>,
+    /// Seed for flat random per-face colors instead of the material color.
+    pub random_fill: Option<u64>,
+    /// Show vertex colors as they are instead of shading them.
+    pub unlit_vertex_colors: bool,
+    /// Post-processing passes, run in the given order.
+    pub post_processing: Vec<String>,
+    /// Text drawn in the top-left corner of the output.
+    pub label: Option<String>,
+    /// Debug geometry drawn after the scene, depth tested against it.
+    pub overlays: Vec<Overlay>,
+    /// Name of a procedural primitive to render in addition to any loaded model.
+    pub primitive: Option<String>,
+    /// Directory whose models are each rendered into `out_dir` by `rusterizer batch`.
+    pub batch_dir: Option<String>,
+    /// Directory relative output paths are in.
+    pub out_dir: Option<String>,
+    /// Number of files rendered at once in batch mode, otherwise of threads transforming
+    /// the geometry.
+    pub jobs: usize,
+    /// Views rendered one after the other from the loaded geometry, each into its own
+    /// image.
+    pub views: Vec<ViewPreset>,
+    /// Tiles per row of a contact sheet combining every view and sheet mode into the
+    /// output image, 0 choosing the layout automatically.
+    pub contact_sheet: Option<u32>,
+    /// Output modes rendered side by side on the contact sheet.
+    pub sheet_modes: Vec<String>,
+    /// Height, near and far plane of an orthographic view volume keeping the aspect ratio
+    /// of the output.
+    pub orthographic: Option<(f64, f64, f64)>,
+    /// Styles of the meshes with the given object or group names, the last one given for
+    /// a name winning.
+    pub object_styles: Vec<(String, ObjectStyle)>,
+    /// Object or group names of the only meshes to render.
+    pub selected_groups: Vec<String>,
+    /// Weighting and crease angle in radians of the normals generated for meshes that
+    /// have none.
+    pub generate_normals: Option<(NormalWeighting, f64)>,
+    /// Depth bias of objects drawn as wireframes, so they show over coplanar surfaces.
+    pub depth_bias: DepthBias,
+    /// Fraction of the triangles of every mesh kept by decimation.
+    pub decimation: Option<f64>,
+    /// Distance within which duplicate vertices are joined.
+    pub weld_tolerance: Option<f64>,
+    /// Reorder faces and vertices for locality once the meshes are final.
+    pub optimize: bool,
+    /// Number of times every triangle is split into four before displacement.
+    pub subdivisions: u32,
+    /// Whether subdivision smooths the mesh with Loop's rules instead of only splitting.
+    pub loop_subdivision: bool,
+    /// Height map moving the vertices along their normals, and the height of white.
+    pub displacement: Option<(String, f64)>,
+    /// Rays cast per texel when baking ambient occlusion.
+    pub ao_samples: u32,
+    /// Ambient occlusion texture given to every mesh.
+    pub occlusion_path: Option<String>,
+    /// Add a floor under the meshes with their contact shadow baked in.
+    pub ground: bool,
+    /// Write the camera, lights and style into PNG outputs.
+    pub metadata: bool,
+    /// JSON file to write the counters, stage timings and output hashes of the run to.
+    pub report_path: Option<String>,
+}
+
+/// Parses the options into [`Args`], returning the other arguments in order for the
+/// command to take.
+pub fn parse(arguments: Vec<String>) -> Result<(Args, Vec<String>), String> {
+    let mut args = Args {
+        obj_path: None,
+        bake_ao: None,
+        tex_path: None,
+        output_path: "output.png".to_string(),
+        turntable_frames: None,
+        clip: None,
+        morph_path: None,
+        animation_path: None,
+        frame_delay_ms: 40,
+        tone_mapping: ToneMapping::default(),
+        deferred: false,
+        atlas: false,
+        matcap_path: None,
+        checker: None,
+        matcap: None,
+        texture_space: ColorSpace::Srgb,
+        textures: Arc::default(),
+        outline: None,
+        debug_view: None,
+        scalars: None,
+        scalar_values: None,
+        colormap: None,
+        colormap_scale: Scale::Linear,
+        scalar_range: None,
+        pick: None,
+        overdraw: false,
+        raytrace: false,
+        line_style: LineStyle::default(),
+        traversal: Traversal::default(),
+        precision: Precision::default(),
+        background: Background::Solid(color::DARK_GRAY),
+        backdrop_path: None,
+        backdrop: None,
+        ambient: Some(HemisphereLight {
+            sky: HdrColor::from(Color(80, 88, 104)),
+            ground: HdrColor::from(Color(40, 36, 32)),
+            wrap: 0.0,
+        }),
+        fog: None,
+        fog_color: None,
+        focus: None,
+        stats: false,
+        log_level: None,
+        progress: false,
+        progress_bar: None,
+        time_budget: None,
+        deadline: None,
+        size: (512, 512),
+        band_height: None,
+        environment: None,
+        image_based_lighting: false,
+        reflectivity: None,
+        physically_based: false,
+        lights: Vec::new(),
+        metallic: None,
+        roughness: None,
+        random_fill: None,
+        unlit_vertex_colors: false,
+        post_processing: Vec::new(),
+        label: None,
+        overlays: Vec::new(),
+        primitive: None,
+        point_radius: None,
+        point_coloring: SplatColoring::Normal,
+        batch_dir: None,
+        out_dir: None,
+        jobs: 1,
+        views: Vec::new(),
+        contact_sheet: None,
+        sheet_modes: Vec::new(),
+        orthographic: None,
+        object_styles: Vec::new(),
+        selected_groups: Vec::new(),
+        generate_normals: None,
+        depth_bias: DepthBias::default(),
+        decimation: None,
+        weld_tolerance: None,
+        optimize: false,
+        subdivisions: 0,
+        loop_subdivision: false,
+        displacement: None,
+        ao_samples: AoSettings::default().samples,
+        occlusion_path: None,
+        ground: false,
+        metadata: false,
+        report_path: None,
+    };
+    let mut positional = Vec::new();
+    let mut iter = arguments.into_iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--turntable" => {
+                let frames = iter
+                    .next()
+                    .ok_or("--turntable expects a frame count")?
+                    .parse::<u32>()
+                    .map_err(|e| format!("invalid frame count: {}", e))?;
+                if frames == 0 {
+                    return Err("frame count must be positive".to_string());
+                }
+                args.turntable_frames = Some(frames);
+            }
+            "--clip" => {
+                let spec = iter
+                    .next()
+                    .ok_or("--clip expects a clip name or index, optionally with :FPS")?;
+                let (name, fps) = match spec.rsplit_once(':') {
+                    Some((name, fps)) => {
+                        let fps = fps
+                            .parse::<f64>()
+                            .map_err(|e| format!("invalid frame rate: {}", e))?;
+                        (name.to_string(), fps)
+                    }
+                    None => (spec, 24.0),
+                };
+                if fps <= 0.0 {
+                    return Err("frame rate must be positive".to_string());
+                }
+                args.clip = Some((name, fps));
+            }
+            "--morph" => {
+                let path = iter.next().ok_or("--morph expects a weights file")?;
+                args.morph_path = Some(path);
+            }
+            "-o" | "--output" => {
+                let path = iter.next().ok_or("--output expects a path")?;
+                // checks the fields with stand-in values
+                let fields = OutputFields {
+                    model: "",
+                    style: "",
+                    view: "",
+                    size: (0, 0),
+                    frame: 0,
+                };
+                expand_output_path(&path, &fields)?;
+                args.output_path = path;
+            }
+            "--animation" => {
+                let path = iter.next().ok_or("--animation expects an output path")?;
+                args.animation_path = Some(path);
+            }
+            "--delay" => {
+                args.frame_delay_ms = iter
+                    .next()
+                    .ok_or("--delay expects a value in milliseconds")?
+                    .parse::<u16>()
+                    .map_err(|e| format!("invalid frame delay: {}", e))?;
+            }
+            "--exposure" => {
+                args.tone_mapping.exposure = iter
+                    .next()
+                    .ok_or("--exposure expects a value")?
+                    .parse::<f32>()
+                    .map_err(|e| format!("invalid exposure: {}", e))?;
+            }
+            "--tonemap" => {
+                args.tone_mapping.operator = iter
+                    .next()
+                    .ok_or("--tonemap expects one of none, reinhard, aces")?
+                    .parse()?;
+            }
+            "--output-mode" => {
+                let mode = iter
+                    .next()
+                    .ok_or("--output-mode expects one of shaded, normal, depth, uv")?;
+                args.debug_view = output_mode(&mode)?;
+            }
+            "--sheet" => {
+                let columns = iter
+                    .next()
+                    .ok_or("--sheet expects a number of columns, 0 for automatic")?
+                    .parse::<u32>()
+                    .map_err(|e| format!("invalid column count: {}", e))?;
+                args.contact_sheet = Some(columns);
+            }
+            "--sheet-modes" => {
+                let modes = iter
+                    .next()
+                    .ok_or("--sheet-modes expects a list of shaded, normal, depth, uv")?;
+                for mode in modes.split(',') {
+                    output_mode(mode)?;
+                    args.sheet_modes.push(mode.to_string());
+                }
+            }
+            "--points" => {
+                let radius = iter
+                    .next()
+                    .ok_or("--points expects a splat radius in pixels")?
+                    .parse::<f64>()
+                    .map_err(|e| format!("invalid splat radius: {}", e))?;
+                args.point_radius = Some(radius);
+            }
+            "--point-color" => {
+                args.point_coloring = iter
+                    .next()
+                    .ok_or("--point-color expects one of normal, depth")?
+                    .parse()?;
+            }
+            "--line-width" => {
+                args.line_style.width = iter
+                    .next()
+                    .ok_or("--line-width expects a width in pixels")?
+                    .parse::<f64>()
+                    .map_err(|e| format!("invalid line width: {}", e))?;
+            }
+            "--background" => {
+                args.background = iter
+                    .next()
+                    .ok_or(
+                        "--background expects a color, gradient:TOP:BOTTOM, checker or transparent",
+                    )?
+                    .parse()?;
+            }
+            "--backdrop" => {
+                let path = iter.next().ok_or("--backdrop expects an image path")?;
+                args.backdrop_path = Some(path);
+            }
+            "--random-fill" => {
+                let seed = iter
+                    .next()
+                    .ok_or("--random-fill expects a seed")?
+                    .parse::<u64>()
+                    .map_err(|e| format!("invalid seed: {}", e))?;
+                args.random_fill = Some(seed);
+            }
+            "--post" => {
+                let spec = iter
+                    .next()
+                    .ok_or("--post expects one of blur, bloom, vignette, fxaa")?;
+                post_process(&spec)?;
+                args.post_processing.push(spec);
+            }
+            "--ambient" => {
+                let spec = iter
+                    .next()
+                    .ok_or("--ambient expects SKY:GROUND, SKY:GROUND:WRAP or none")?;
+                args.ambient = ambient(&spec)?;
+            }
+            "--fog" => {
+                let falloff = iter
+                    .next()
+                    .ok_or("--fog expects linear:START:END, exp:DENSITY or exp2:DENSITY")?
+                    .parse()?;
+                args.fog = Some(falloff);
+            }
+            "--fog-color" => {
+                let color = iter
+                    .next()
+                    .ok_or("--fog-color expects a color such as #808080")?
+                    .parse()
+                    .map_err(|e| format!("invalid fog color: {}", e))?;
+                args.fog_color = Some(color);
+            }
+            "--dof" => {
+                let spec = iter
+                    .next()
+                    .ok_or("--dof expects a focal distance and optionally an aperture")?;
+                args.focus = Some(focus(&spec)?);
+            }
+            "--environment" => {
+                let path = iter.next().ok_or("--environment expects an image path")?;
+                args.environment = Some(EnvironmentSource::LatLong(path));
+            }
+            "--cube-map" => {
+                let paths = iter
+                    .next()
+                    .ok_or("--cube-map expects a cross image or six comma-separated faces")?;
+                let paths = paths.split(',').map(str::to_string).collect();
+                args.environment = Some(EnvironmentSource::CubeMap(paths));
+            }
+            "--ibl" => args.image_based_lighting = true,
+            "--pbr" => args.physically_based = true,
+            "--light" => {
+                let spec = iter
+                    .next()
+                    .ok_or("--light expects X,Y,Z, @X,Y,Z or @X,Y,Z:DX,DY,DZ:INNER,OUTER")?;
+                args.lights.push(light(&spec)?);
+            }
+            "--metallic" | "--roughness" => {
+                let value = iter
+                    .next()
+                    .ok_or(format!("{} expects a value between 0 and 1", arg))?
+                    .parse::<f64>()
+                    .map_err(|e| format!("invalid {}: {}", &arg[2..], e))?;
+                if !(0.0..=1.0).contains(&value) {
+                    return Err(format!("{} expects a value between 0 and 1", arg));
+                }
+                if arg == "--metallic" {
+                    args.metallic = Some(value);
+                } else {
+                    args.roughness = Some(value);
+                }
+            }
+            "--metal" => {
+                let reflectivity = iter
+                    .next()
+                    .ok_or("--metal expects a reflectivity between 0 and 1")?
+                    .parse::<f64>()
+                    .map_err(|e| format!("invalid reflectivity: {}", e))?;
+                args.reflectivity = Some(reflectivity);
+            }
+            "--label" => args.label = Some(iter.next().ok_or("--label expects a text")?),
+            "--overlay" => {
+                let overlays = iter
+                    .next()
+                    .ok_or("--overlay expects a list of axes, grid, lights and frustum")?;
+                for overlay in overlays.split(',') {
+                    args.overlays.push(overlay.parse()?);
+                }
+                args.overlays.sort();
+                args.overlays.dedup();
+            }
+            "--outline" => {
+                let thickness = iter
+                    .next()
+                    .ok_or("--outline expects a thickness in pixels")?
+                    .parse::<u32>()
+                    .map_err(|e| format!("invalid outline thickness: {}", e))?;
+                args.outline = Some(thickness);
+            }
+            "--size" => {
+                let size = iter.next().ok_or("--size expects WIDTHxHEIGHT")?;
+                let (width, height) = size.split_once('x').ok_or("--size expects WIDTHxHEIGHT")?;
+                let parse = |value: &str| match value.parse::<u32>() {
+                    Ok(value) if value > 0 => Ok(value),
+                    _ => Err(format!("invalid size '{}'", size)),
+                };
+                args.size = (parse(width)?, parse(height)?);
+            }
+            "--band-height" => {
+                let rows = iter
+                    .next()
+                    .ok_or("--band-height expects a number of rows")?
+                    .parse::<u32>()
+                    .map_err(|e| format!("invalid band height: {}", e))?;
+                args.band_height = Some(rows.max(1));
+            }
+            "--out" => args.out_dir = Some(iter.next().ok_or("--out expects a directory")?),
+            // read before the rest of the arguments
+            "--config" => {
+                iter.next().ok_or("--config expects a path")?;
+            }
+            "--no-config" => (),
+            "--jobs" => {
+                let jobs = iter
+                    .next()
+                    .ok_or("--jobs expects a number of threads")?
+                    .parse::<usize>()
+                    .map_err(|e| format!("invalid job count: {}", e))?;
+                args.jobs = jobs.max(1);
+            }
+            "--view" => {
+                let names = iter.next().ok_or(
+                    "--view expects a list of front, back, left, right, top, bottom, iso or all",
+                )?;
+                for name in names.split(',') {
+                    match name {
+                        "all" => args.views.extend(ViewPreset::ALL),
+                        name => args.views.push(name.parse()?),
+                    }
+                }
+            }
+            "--normals" => {
+                let spec = iter
+                    .next()
+                    .ok_or("--normals expects area or angle, optionally with :CREASE_DEGREES")?;
+                let (weighting, crease) = match spec.split_once(':') {
+                    Some((weighting, crease)) => {
+                        let degrees = crease
+                            .parse::<f64>()
+                            .map_err(|e| format!("invalid crease angle: {}", e))?;
+                        (weighting, degrees.to_radians())
+                    }
+                    None => (spec.as_str(), std::f64::consts::PI),
+                };
+                args.generate_normals = Some((weighting.parse()?, crease));
+            }
+            "--depth-bias" => {
+                let spec = iter
+                    .next()
+                    .ok_or("--depth-bias expects a constant, optionally with :SLOPE_SCALE")?;
+                let (constant, slope_scale) = spec.split_once(':').unwrap_or((&spec, "0"));
+                let parse = |value: &str| {
+                    value
+                        .parse::<f64>()
+                        .map_err(|e| format!("invalid depth bias: {}", e))
+                };
+                args.depth_bias = DepthBias {
+                    constant: parse(constant)?,
+                    slope_scale: parse(slope_scale)?,
+                };
+            }
+            "--weld" => {
+                let tolerance = iter
+                    .next()
+                    .ok_or("--weld expects a distance")?
+                    .parse::<f64>()
+                    .map_err(|e| format!("invalid weld distance: {}", e))?;
+                if tolerance.is_nan() || tolerance < 0.0 {
+                    return Err("--weld expects a distance of at least 0".to_string());
+                }
+                args.weld_tolerance = Some(tolerance);
+            }
+            "--optimize" => args.optimize = true,
+            "--ground" => args.ground = true,
+            "--metadata" => args.metadata = true,
+            "--report" => {
+                let path = iter.next().ok_or("--report expects a path")?;
+                args.report_path = Some(path.clone());
+            }
+            "--decimate" => {
+                let ratio = iter
+                    .next()
+                    .ok_or("--decimate expects the fraction of triangles to keep")?
+                    .parse::<f64>()
+                    .map_err(|e| format!("invalid decimation ratio: {}", e))?;
+                if !(ratio > 0.0 && ratio <= 1.0) {
+                    return Err("--decimate expects a fraction between 0 and 1".to_string());
+                }
+                args.decimation = Some(ratio);
+            }
+            "--subdivide" => {
+                let spec = iter
+                    .next()
+                    .ok_or("--subdivide expects a number of levels, optionally with :loop")?;
+                let (levels, smooth) = match spec.split_once(':') {
+                    Some((levels, "loop")) => (levels, true),
+                    Some((_, scheme)) => {
+                        return Err(format!("unknown subdivision scheme '{}'", scheme))
+                    }
+                    None => (spec.as_str(), false),
+                };
+                args.subdivisions = levels
+                    .parse::<u32>()
+                    .map_err(|e| format!("invalid subdivision level: {}", e))?;
+                args.loop_subdivision = smooth;
+            }
+            "--displace" => {
+                let spec = iter
+                    .next()
+                    .ok_or("--displace expects a height map path, optionally with :SCALE")?;
+                let (path, scale) = match spec.rsplit_once(':') {
+                    Some((path, scale)) if scale.parse::<f64>().is_ok() => {
+                        (path.to_string(), scale.parse().unwrap())
+                    }
+                    _ => (spec, 0.1),
+                };
+                args.displacement = Some((path, scale));
+            }
+            "--groups" => {
+                let names = iter
+                    .next()
+                    .ok_or("--groups expects a list of object or group names")?;
+                args.selected_groups
+                    .extend(names.split(',').map(str::to_string));
+            }
+            "--style" => {
+                let spec = iter
+                    .next()
+                    .ok_or("--style expects NAME=STYLE, e.g. hair=wireframe:#402010")?;
+                let (name, style) = spec
+                    .split_once('=')
+                    .ok_or("--style expects NAME=STYLE, e.g. hair=wireframe:#402010")?;
+                args.object_styles.push((name.to_string(), style.parse()?));
+            }
+            "--ortho" => {
+                let spec = iter
+                    .next()
+                    .ok_or("--ortho expects HEIGHT or HEIGHT:NEAR:FAR")?;
+                args.orthographic = Some(orthographic(&spec)?);
+            }
+            "--deferred" => args.deferred = true,
+            "--raytrace" => args.raytrace = true,
+            "--atlas" => args.atlas = true,
+            "--bake-ao" => {
+                let path = iter
+                    .next()
+                    .ok_or("--bake-ao expects an output texture path")?;
+                args.bake_ao = Some(path);
+            }
+            "--ao-samples" => {
+                args.ao_samples = iter
+                    .next()
+                    .ok_or("--ao-samples expects a number of rays")?
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|&samples| samples > 0)
+                    .ok_or("--ao-samples expects a positive number of rays")?;
+            }
+            "--ao-texture" => {
+                let path = iter.next().ok_or("--ao-texture expects an image path")?;
+                args.occlusion_path = Some(path);
+            }
+            "--checker" => {
+                let squares = iter
+                    .next()
+                    .ok_or("--checker expects a number of squares")?
+                    .parse::<u32>()
+                    .map_err(|e| format!("invalid number of squares: {}", e))?;
+                args.checker = Some(squares);
+            }
+            "--scalars" => {
+                let source = iter
+                    .next()
+                    .ok_or("--scalars expects curvature, height or a CSV file")?;
+                args.scalars = Some(match source.as_str() {
+                    "curvature" => ScalarSource::Curvature,
+                    "height" => ScalarSource::Height,
+                    _ => ScalarSource::File(source),
+                });
+            }
+            "--colormap" => {
+                let colormap = iter
+                    .next()
+                    .ok_or(
+                        "--colormap expects one of viridis, magma, plasma, turbo, grayscale, jet",
+                    )?
+                    .parse()?;
+                args.colormap = Some(colormap);
+            }
+            "--colormap-scale" => {
+                args.colormap_scale = iter
+                    .next()
+                    .ok_or("--colormap-scale expects linear or log")?
+                    .parse()?;
+            }
+            "--scalar-range" => {
+                let range = iter.next().ok_or("--scalar-range expects MIN:MAX")?;
+                let values = range
+                    .split(':')
+                    .map(|value| value.parse::<f64>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|e| format!("invalid scalar range: {}", e))?;
+                match values[..] {
+                    [min, max] if min < max => args.scalar_range = Some((min, max)),
+                    _ => return Err(format!("invalid scalar range '{}'", range)),
+                }
+            }
+            "--matcap" => {
+                let path = iter.next().ok_or("--matcap expects an image path")?;
+                args.matcap_path = Some(path);
+            }
+            "--texture-space" => {
+                args.texture_space = iter
+                    .next()
+                    .ok_or("--texture-space expects srgb or linear")?
+                    .parse()?;
+            }
+            "--pick" => {
+                let spec = iter.next().ok_or("--pick expects X,Y")?;
+                let (x, y) = spec.split_once(',').ok_or("--pick expects X,Y")?;
+                let parse = |value: &str| {
+                    value
+                        .parse::<u32>()
+                        .map_err(|_| format!("invalid pick position '{}'", spec))
+                };
+                args.pick = Some((parse(x)?, parse(y)?));
+            }
+            "--overdraw" => args.overdraw = true,
+            "--stats" => args.stats = true,
+            "-v" | "--verbose" => {
+                args.log_level = match args.log_level {
+                    Some(LevelFilter::Info | LevelFilter::Debug) => Some(LevelFilter::Debug),
+                    _ => Some(LevelFilter::Info),
+                };
+            }
+            "-q" | "--quiet" => args.log_level = Some(LevelFilter::Error),
+            "--progress" => args.progress = true,
+            "--time-budget" => {
+                let seconds = iter
+                    .next()
+                    .ok_or("--time-budget expects a number of seconds")?
+                    .parse::<f64>()
+                    .map_err(|e| format!("invalid time budget: {}", e))?;
+                let budget = Duration::try_from_secs_f64(seconds)
+                    .map_err(|_| "--time-budget expects a non-negative number of seconds")?;
+                args.time_budget = Some(budget);
+            }
+            "--unlit-vertex-colors" => args.unlit_vertex_colors = true,
+            "--line-aa" => args.line_style.antialiased = true,
+            "--rasterizer" => {
+                args.traversal = match iter.next().as_deref() {
+                    Some("bbox") => Traversal::BoundingBox,
+                    Some("scanline") => Traversal::Scanline,
+                    _ => return Err("--rasterizer expects bbox or scanline".to_string()),
+                };
+            }
+            "--precision" => {
+                args.precision = match iter.next().as_deref() {
+                    Some("float") => Precision::Float,
+                    Some("fixed") => Precision::Fixed,
+                    _ => return Err("--precision expects float or fixed".to_string()),
+                };
+            }
+            "--primitive" => {
+                let name = iter
+                    .next()
+                    .ok_or("--primitive expects one of sphere, cube, plane, torus")?;
+                primitive_mesh(&name)?;
+                args.primitive = Some(name);
+            }
+            _ if arg.starts_with('-') && arg != "-" => {
+                return Err(format!("unknown option '{}', see --help", arg));
+            }
+            _ => positional.push(arg),
+        }
+    }
+    if let (Some(colormap), SplatColoring::Depth(_)) = (args.colormap, args.point_coloring) {
+        args.point_coloring = SplatColoring::Depth(colormap);
+    }
+    Ok((args, positional))
+}
+
+/// Name of the settings file looked for in the working directory, then in the
+/// `rusterizer` directory of the XDG config directory.
+const CONFIG_FILE: &str = "rusterizer.toml";
+
+/// The settings file named with `--config` or found in the usual places, unless
+/// `--no-config` is given.
+fn config_path(command_line: &[String]) -> Option<PathBuf> {
+    if command_line.iter().any(|arg| arg == "--no-config") {
+        return None;
+    }
+    if let Some(i) = command_line.iter().position(|arg| arg == "--config") {
+        return command_line.get(i + 1).map(PathBuf::from);
+    }
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")));
+    std::iter::once(PathBuf::from(CONFIG_FILE))
+        .chain(config_home.map(|dir| dir.join("rusterizer").join(CONFIG_FILE)))
+        .find(|path| path.is_file())
+}
+
+/// The `command_line` after the flags standing for the settings in the settings file,
+/// leaving out the ones the command line gives itself.
+pub fn with_config(command_line: Vec<String>) -> Result<Vec<String>, RusterizerError> {
+    let Some(path) = config_path(&command_line) else {
+        return Ok(command_line);
+    };
+    let config = Config::load(&path)?;
+    let mut arguments = Vec::new();
+    for (key, value) in config.values() {
+        let value = value.to_string();
+        let (flags, given): (Vec<&str>, &[&str]) = match key.as_str() {
+            "size" => (vec!["--size", &value], &["--size"]),
+            "background" => (vec!["--background", &value], &["--background"]),
+            "style" => {
+                let flags = match value.as_str() {
+                    "shaded" => vec![],
+                    "pbr" => vec!["--pbr"],
+                    "deferred" => vec!["--deferred"],
+                    "raytrace" => vec!["--raytrace"],
+                    mode => vec!["--output-mode", mode],
+                };
+                (
+                    flags,
+                    &["--pbr", "--deferred", "--raytrace", "--output-mode"],
+                )
+            }
+            "output_dir" => (vec!["--out", &value], &["--out"]),
+            "camera.view" => (vec!["--view", &value], &["--view"]),
+            "camera.ortho" => (vec!["--ortho", &value], &["--ortho"]),
+            key => {
+                return Err(RusterizerError::InvalidArgument(format!(
+                    "{}: unknown setting '{}'",
+                    path.display(),
+                    key
+                )))
+            }
+        };
+        if !command_line.iter().any(|arg| given.contains(&arg.as_str())) {
+            arguments.extend(flags.into_iter().map(String::from));
+        }
+    }
+    arguments.extend(command_line);
+    Ok(arguments)
+}
+
+pub fn primitive_mesh(name: &str) -> Result<Mesh, String> {
+    match name {
+        "sphere" => Ok(geometry::sphere(0.8, 48, 24)),
+        "cube" => Ok(geometry::cuboid(Vec3f::new(1., 1., 1.))),
+        "plane" => {
+            // face the viewer instead of being seen edge-on
+            let mut plane = geometry::plane(1.5, 1.5, 8);
+            plane.transform(&Mat4::rotation_x(std::f64::consts::FRAC_PI_2));
+            Ok(plane)
+        }
+        "torus" => Ok(geometry::torus(0.6, 0.25, 48, 24)),
+        _ => Err(format!("unknown primitive '{}'", name)),
+    }
+}
+
+/// Parses an output mode, `None` standing for the shaded render.
+pub fn output_mode(name: &str) -> Result<Option<DebugView>, String> {
+    match name {
+        "shaded" => Ok(None),
+        view => Ok(Some(view.parse()?)),
+    }
+}
+
+/// Parses `HEIGHT` or `HEIGHT:NEAR:FAR`, the planes defaulting to -1 and 1 like the view
+/// without a projection.
+fn orthographic(spec: &str) -> Result<(f64, f64, f64), String> {
+    let values = spec
+        .split(':')
+        .map(|value| value.parse::<f64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("invalid orthographic view volume: {}", e))?;
+    match values[..] {
+        [height] if height > 0.0 => Ok((height, -1.0, 1.0)),
+        [height, near, far] if height > 0.0 && near < far => Ok((height, near, far)),
+        _ => Err(format!("invalid orthographic view volume '{}'", spec)),
+    }
+}
+
+/// Parses `X,Y,Z`, the direction towards a light, `@X,Y,Z`, the position of a point
+/// light, or `@X,Y,Z:DX,DY,DZ:INNER,OUTER`, the position of a spot light, the direction
+/// it shines in and the angles of its cone in degrees. Point and spot lights take an
+/// optional `:C,L,Q` for their constant, linear and quadratic falloff, and any light a
+/// final `:shadows` for ray-traced shadows.
+fn light(spec: &str) -> Result<Light, String> {
+    let (spec, shadows) = match spec.strip_suffix(":shadows") {
+        Some(spec) => (spec, true),
+        None => (spec, false),
+    };
+    let numbers = |values: &str| {
+        values
+            .split(',')
+            .map(|value| value.parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("invalid light '{}': {}", spec, e))
+    };
+    let vector = |values: &str, what: &str| match numbers(values)?[..] {
+        [x, y, z] => Ok(Vec3f::new(x, y, z)),
+        _ => Err(format!("invalid light {} '{}'", what, values)),
+    };
+    let falloff = |values: Option<&str>| match values {
+        Some(values) => match numbers(values)?[..] {
+            [constant, linear, quadratic] => Ok(Falloff {
+                constant,
+                linear,
+                quadratic,
+            }),
+            _ => Err(format!("invalid light falloff '{}'", values)),
+        },
+        None => Ok(Falloff::default()),
+    };
+    let light = match spec.strip_prefix('@') {
+        Some(placed) => match placed.split(':').collect::<Vec<_>>()[..] {
+            [position, ref terms @ ..] if terms.len() <= 1 => Light::point(
+                vector(position, "position")?,
+                falloff(terms.first().copied())?,
+            ),
+            [position, direction, cone, ref terms @ ..] if terms.len() <= 1 => {
+                let [inner, outer] = numbers(cone)?[..] else {
+                    return Err(format!("invalid spot light cone '{}'", cone));
+                };
+                Light::spot(
+                    vector(position, "position")?,
+                    vector(direction, "direction")?,
+                    inner.to_radians(),
+                    outer.to_radians(),
+                    falloff(terms.first().copied())?,
+                )
+            }
+            _ => return Err(format!("invalid light '{}'", spec)),
+        },
+        None => Light::directional(vector(spec, "direction")?),
+    };
+    let mut light = light.map_err(|e| e.to_string())?;
+    light.ray_traced_shadows = shadows;
+    Ok(light)
+}
+
+/// Parses `SKY:GROUND` or `SKY:GROUND:WRAP`, the colors of the ambient light from above
+/// and below and how far lights wrap around, or `none`.
+fn ambient(spec: &str) -> Result<Option<HemisphereLight>, String> {
+    if spec == "none" {
+        return Ok(None);
+    }
+    let color = |value: &str| {
+        value
+            .parse::<Color>()
+            .map(HdrColor::from)
+            .map_err(|e| format!("invalid ambient color '{}': {}", value, e))
+    };
+    let (sky, ground, wrap) = match spec.split(':').collect::<Vec<_>>()[..] {
+        [sky, ground] => (sky, ground, 0.0),
+        [sky, ground, wrap] => {
+            let wrap = wrap
+                .parse::<f64>()
+                .map_err(|e| format!("invalid ambient wrap: {}", e))?;
+            if !(0.0..=1.0).contains(&wrap) {
+                return Err("ambient wrap must be between 0 and 1".to_string());
+            }
+            (sky, ground, wrap)
+        }
+        _ => return Err(format!("invalid ambient light '{}'", spec)),
+    };
+    Ok(Some(HemisphereLight {
+        sky: color(sky)?,
+        ground: color(ground)?,
+        wrap,
+    }))
+}
+
+/// Parses `DISTANCE` or `DISTANCE:APERTURE`.
+fn focus(spec: &str) -> Result<Focus, String> {
+    let mut focus = Focus::default();
+    let (distance, aperture) = match spec.split_once(':') {
+        Some((distance, aperture)) => (distance, Some(aperture)),
+        None => (spec, None),
+    };
+    focus.focal_distance = distance
+        .parse()
+        .map_err(|e| format!("invalid focal distance: {}", e))?;
+    if let Some(aperture) = aperture {
+        focus.aperture = aperture
+            .parse()
+            .map_err(|e| format!("invalid aperture: {}", e))?;
+    }
+    Ok(focus)
+}
+
+/// Builds a pass from `name` or `name:parameter`, the parameter being the blur sigma,
+/// the bloom threshold or the vignette strength.
+pub fn post_process(spec: &str) -> Result<Box<dyn PostProcess>, String> {
+    let (name, parameter) = match spec.split_once(':') {
+        Some((name, parameter)) => {
+            let value = parameter
+                .parse::<f32>()
+                .map_err(|e| format!("invalid {} parameter: {}", name, e))?;
+            (name, Some(value))
+        }
+        None => (spec, None),
+    };
+    match name {
+        "blur" => Ok(Box::new(GaussianBlur {
+            sigma: parameter.unwrap_or(1.5),
+        })),
+        "bloom" => Ok(Box::new(Bloom {
+            threshold: parameter.unwrap_or(Bloom::default().threshold),
+            ..Default::default()
+        })),
+        "vignette" => Ok(Box::new(Vignette {
+            strength: parameter.unwrap_or(0.5),
+        })),
+        "fxaa" => Ok(Box::new(Fxaa::default())),
+        _ => Err(format!("unknown post-processing pass '{}'", name)),
+    }
+}
+
+#[test]
+fn test_unknown_options() {
+    let parse_args =
+        |arguments: &[&str]| parse(arguments.iter().map(|arg| arg.to_string()).collect());
+    match parse_args(&["--bogus-flag"]) {
+        Err(message) => assert!(message.contains("--bogus-flag")),
+        Ok(_) => panic!("unknown option accepted"),
+    }
+    // option values may start with a dash, and a lone dash is no option
+    let (args, positional) = parse_args(&["info", "--exposure", "-1", "model.obj", "-"]).unwrap();
+    assert_eq!(args.tone_mapping.exposure, -1.0);
+    assert_eq!(positional, ["info", "model.obj", "-"]);
+}
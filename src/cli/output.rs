@@ -0,0 +1,207 @@
+//! Paths of the files written, and the settings saved into them.
+
+use std::path::Path;
+
+use rusterizer::light::{Falloff, Light, LightSource};
+use rusterizer::math::Vec3f;
+
+use super::options::Args;
+
+/// `path` with `_suffix` appended to the file name before its extension.
+pub fn suffixed_path(path: &str, suffix: &str) -> String {
+    let path = Path::new(path);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(extension) => format!("{}_{}.{}", stem, suffix, extension.to_string_lossy()),
+        None => format!("{}_{}", stem, suffix),
+    };
+    path.with_file_name(name).to_string_lossy().into_owned()
+}
+
+/// What the fields of a templated output path stand for.
+pub struct OutputFields<'a> {
+    pub model: &'a str,
+    pub style: &'a str,
+    pub view: &'a str,
+    pub size: (u32, u32),
+    pub frame: u32,
+}
+
+/// Fills in the `{model}`, `{style}`, `{view}`, `{width}`, `{height}` and `{frame}` fields
+/// of `template`, padded with zeros to the width given like in `{frame:04}`. `{{` and
+/// `}}` stand for braces.
+pub fn expand_output_path(template: &str, fields: &OutputFields) -> Result<String, String> {
+    let mut path = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find(['{', '}']) {
+        path.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if rest.starts_with("{{") || rest.starts_with("}}") {
+            path.push_str(&rest[..1]);
+            rest = &rest[2..];
+            continue;
+        }
+        let end = match (rest.starts_with('{'), rest.find('}')) {
+            (true, Some(end)) => end,
+            _ => return Err(format!("unmatched brace in output path '{}'", template)),
+        };
+        let (name, width) = match rest[1..end].split_once(':') {
+            Some((name, width)) => (name, Some(width)),
+            None => (&rest[1..end], None),
+        };
+        let value = match name {
+            "model" => fields.model.to_string(),
+            "style" => fields.style.to_string(),
+            "view" => fields.view.to_string(),
+            "width" => fields.size.0.to_string(),
+            "height" => fields.size.1.to_string(),
+            "frame" => fields.frame.to_string(),
+            _ => return Err(format!("unknown output path field '{{{}}}'", name)),
+        };
+        match width.map(|width| (width.strip_prefix('0'), width)) {
+            None => path.push_str(&value),
+            Some((Some(digits), _)) if digits.parse::<usize>().is_ok() => {
+                let width = digits.parse::<usize>().unwrap_or_default();
+                path.push_str(&format!("{:0>width$}", value, width = width));
+            }
+            Some((_, width)) => {
+                return Err(format!("invalid width '{}' of output path field", width));
+            }
+        }
+        rest = &rest[end + 1..];
+    }
+    path.push_str(rest);
+    Ok(path)
+}
+
+/// The output path with its fields filled in for frame `frame` of a render of `model`
+/// from `view`.
+pub fn output_path(args: &Args, model: &str, view: &str, frame: u32) -> String {
+    let fields = OutputFields {
+        model,
+        style: style_name(args),
+        view,
+        size: args.size,
+        frame,
+    };
+    let path =
+        expand_output_path(&args.output_path, &fields).expect("validated when parsing arguments");
+    in_out_dir(args, path)
+}
+
+/// `path` in the `--out` directory if it is relative.
+pub fn in_out_dir(args: &Args, path: String) -> String {
+    match &args.out_dir {
+        Some(dir) if Path::new(&path).is_relative() => {
+            Path::new(dir).join(path).to_string_lossy().into_owned()
+        }
+        _ => path,
+    }
+}
+
+/// Name of what is rendered for output paths: the model file's, the primitive's or
+/// `scene`.
+pub fn model_name(args: &Args) -> String {
+    match (&args.obj_path, &args.primitive) {
+        (Some(path), _) => Path::new(path)
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned(),
+        (None, Some(primitive)) => primitive.clone(),
+        (None, None) => "scene".to_string(),
+    }
+}
+
+/// Short name of how the meshes are drawn, for output paths and metadata.
+fn style_name(args: &Args) -> &'static str {
+    if args.point_radius.is_some() {
+        "points"
+    } else if args.raytrace {
+        "raytrace"
+    } else if let Some(view) = args.debug_view {
+        view.name()
+    } else if let Some(source) = &args.scalars {
+        source.name()
+    } else if args.physically_based {
+        "pbr"
+    } else if args.matcap_path.is_some() {
+        "matcap"
+    } else if args.random_fill.is_some() {
+        "random"
+    } else {
+        "shaded"
+    }
+}
+
+/// Settings a render seen from `camera` was made with, saved into PNGs with
+/// `--metadata`.
+pub fn metadata(args: &Args, camera: &str) -> Vec<(String, String)> {
+    if !args.metadata {
+        return Vec::new();
+    }
+    let camera = match args.orthographic {
+        Some((height, near, far)) => {
+            format!(
+                "{}, orthographic height {} from {} to {}",
+                camera, height, near, far
+            )
+        }
+        None => camera.to_string(),
+    };
+    let lights = match &args.lights[..] {
+        [] => "headlight".to_string(),
+        lights => lights
+            .iter()
+            .map(describe_light)
+            .collect::<Vec<_>>()
+            .join("; "),
+    };
+    let command_line: Vec<String> = std::env::args().skip(1).collect();
+    [
+        (
+            "Software",
+            format!("rusterizer {}", env!("CARGO_PKG_VERSION")),
+        ),
+        ("Comment", command_line.join(" ")),
+        ("Camera", camera),
+        ("Light", lights),
+        ("Style", style_name(args).to_string()),
+    ]
+    .into_iter()
+    .map(|(keyword, value)| (keyword.to_string(), value))
+    .collect()
+}
+
+/// `light` the way `--light` takes it, angles in degrees.
+fn describe_light(light: &Light) -> String {
+    let vector = |v: Vec3f| format!("{},{},{}", v.x, v.y, v.z);
+    let falloff = |f: Falloff| format!("{},{},{}", f.constant, f.linear, f.quadratic);
+    let mut spec = match light.source() {
+        LightSource::Directional(direction) => vector(direction),
+        LightSource::Point {
+            position,
+            falloff: f,
+        } => {
+            format!("@{}:{}", vector(position), falloff(f))
+        }
+        LightSource::Spot {
+            position,
+            direction,
+            inner_angle,
+            outer_angle,
+            falloff: f,
+        } => format!(
+            "@{}:{}:{},{}:{}",
+            vector(position),
+            vector(direction),
+            inner_angle.to_degrees(),
+            outer_angle.to_degrees(),
+            falloff(f)
+        ),
+    };
+    if light.ray_traced_shadows {
+        spec.push_str(":shadows");
+    }
+    spec
+}
@@ -0,0 +1,81 @@
+//! Progress shown on stderr while rendering.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Shows how much of the run is done and an estimate of the time left on stderr,
+/// redrawn at most every [`ProgressBar::REDRAW_INTERVAL`].
+pub struct ProgressBar {
+    unit: &'static str,
+    total: u64,
+    /// Amount of work in every render, by which the bar moves on as a render starts.
+    pub per_render: u64,
+    start: Instant,
+    /// Renders started, work done and when the bar was last drawn.
+    state: Mutex<(u64, u64, Option<Instant>)>,
+}
+
+impl ProgressBar {
+    const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+    const WIDTH: usize = 30;
+
+    pub fn new(unit: &'static str, renders: u64, per_render: u64) -> Self {
+        ProgressBar {
+            unit,
+            total: renders * per_render,
+            per_render,
+            start: Instant::now(),
+            state: Mutex::new((0, 0, None)),
+        }
+    }
+
+    /// Starts the next render and returns the work done before it.
+    pub fn begin_render(&self) -> u64 {
+        let mut state = self.state.lock().unwrap();
+        state.0 += 1;
+        (state.0 - 1) * self.per_render
+    }
+
+    pub fn set(&self, done: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.1 = done.min(self.total);
+        if state
+            .2
+            .is_none_or(|drawn| drawn.elapsed() >= Self::REDRAW_INTERVAL)
+        {
+            state.2 = Some(Instant::now());
+            self.draw(state.1);
+        }
+    }
+
+    /// Draws the bar full and moves on to the next line.
+    pub fn finish(&self) {
+        self.draw(self.total);
+        eprintln!();
+    }
+
+    fn draw(&self, done: u64) {
+        let fraction = if self.total > 0 {
+            done as f64 / self.total as f64
+        } else {
+            1.0
+        };
+        let filled = (fraction * Self::WIDTH as f64) as usize;
+        let elapsed = self.start.elapsed().as_secs_f64();
+        let eta = if done > 0 {
+            format!("{:.1} s", elapsed * (1.0 - fraction) / fraction)
+        } else {
+            "?".to_string()
+        };
+        eprint!(
+            "\r[{}{}] {:3.0}% {}/{} {}, {} left ",
+            "#".repeat(filled),
+            ".".repeat(Self::WIDTH - filled),
+            fraction * 100.0,
+            done,
+            self.total,
+            self.unit,
+            eta
+        );
+    }
+}
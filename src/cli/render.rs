@@ -0,0 +1,853 @@
+//! `rusterizer [render] [MODEL [TEXTURE]]`: the model drawn from every requested view,
+//! turning, animated or on a contact sheet.
+
+use std::borrow::Cow;
+use std::sync::{Arc, Once};
+use std::time::Instant;
+
+use rusterizer::camera::{Orthographic, ViewPreset, Viewport};
+use rusterizer::color;
+use rusterizer::colormap::{Colormap, Scale};
+use rusterizer::contact_sheet::ContactSheet;
+use rusterizer::deferred::{GBuffer, Lighting};
+use rusterizer::draw_list::DrawList;
+use rusterizer::drawable::{Background, Drawable, Image, Point3f};
+use rusterizer::environment::EnvironmentMap;
+use rusterizer::error::RusterizerError;
+use rusterizer::fog::Fog;
+use rusterizer::light::Light;
+use rusterizer::loader;
+use rusterizer::math::{Mat4, Vec3f};
+use rusterizer::mesh::Mesh;
+use rusterizer::overlay::{self, Grid};
+use rusterizer::postprocess::{DepthOfField, Outline, OutlineStyle};
+use rusterizer::raytrace::raytrace;
+use rusterizer::render::{draw_mesh_debug, draw_point_cloud, draw_scalar_field};
+use rusterizer::scalar_field::load_scalars;
+use rusterizer::spatial::Bvh;
+use rusterizer::stats::{Overdraw, RenderStats, StageTimings};
+use rusterizer::{animation, export, tiled, DrawStyle};
+
+use super::options::{
+    output_mode, post_process, Args, EnvironmentSource, ObjectStyle, Overlay, ScalarSource,
+};
+use super::output::{in_out_dir, metadata, model_name, output_path, suffixed_path};
+use super::progress::ProgressBar;
+use super::{
+    apply_overrides, create_out_dir, fail, fail_saving, finish, load_color_texture, load_meshes,
+    load_texture, no_more_arguments, object_style,
+};
+
+pub fn parse(mut args: Args, positional: Vec<String>) -> Result<Args, String> {
+    let mut positional = positional.into_iter();
+    args.obj_path = positional.next();
+    args.tex_path = positional.next();
+    no_more_arguments(positional)?;
+    check(&args)?;
+    Ok(args)
+}
+
+/// Rejects options that cannot be used together, for `batch` too.
+pub fn check(args: &Args) -> Result<(), String> {
+    if args.backdrop_path.is_some() && args.environment.is_some() {
+        return Err("--backdrop cannot be combined with --environment".to_string());
+    }
+    let animated = match (&args.clip, &args.morph_path) {
+        (Some(_), _) => Some("--clip"),
+        (None, Some(_)) => Some("--morph"),
+        (None, None) => None,
+    };
+    if args.animation_path.is_some() && args.turntable_frames.is_none() && animated.is_none() {
+        return Err("--animation requires --turntable, --clip or --morph".to_string());
+    }
+    if let Some(animated) = animated {
+        let unsupported = [
+            ("--turntable", args.turntable_frames.is_some()),
+            ("--band-height", args.band_height.is_some()),
+            ("--view", !args.views.is_empty()),
+            ("--sheet", args.contact_sheet.is_some()),
+            ("batch", args.batch_dir.is_some()),
+        ];
+        if let Some((flag, _)) = unsupported.iter().find(|(_, used)| *used) {
+            return Err(format!("{} cannot be combined with {}", animated, flag));
+        }
+    }
+    if let (Some(animated), None) = (animated, &args.obj_path) {
+        return Err(format!("{} requires a glTF model", animated));
+    }
+    if args.background == Background::Transparent {
+        // these are written without an alpha channel, or cover the background
+        let unsupported = [
+            ("--band-height", args.band_height.is_some()),
+            ("--sheet", args.contact_sheet.is_some()),
+            ("--animation", args.animation_path.is_some()),
+            ("--environment", args.environment.is_some()),
+        ];
+        if let Some((flag, _)) = unsupported.iter().find(|(_, used)| *used) {
+            return Err(format!(
+                "a transparent background cannot be combined with {}",
+                flag
+            ));
+        }
+        if !args.output_path.to_ascii_lowercase().ends_with(".png") {
+            return Err("a transparent background needs PNG output".to_string());
+        }
+    }
+    if args.band_height.is_some() {
+        // these need the whole image at once
+        let unsupported = [
+            ("--turntable", args.turntable_frames.is_some()),
+            ("--deferred", args.deferred),
+            ("--pbr", args.physically_based),
+            (
+                "shadows",
+                args.lights.iter().any(|light| light.ray_traced_shadows),
+            ),
+            ("--outline", args.outline.is_some()),
+            ("--environment", args.environment.is_some()),
+            ("--post", !args.post_processing.is_empty()),
+            ("--dof", args.focus.is_some()),
+            ("--metadata", args.metadata),
+        ];
+        if let Some((flag, _)) = unsupported.iter().find(|(_, used)| *used) {
+            return Err(format!("--band-height cannot be combined with {}", flag));
+        }
+        if !args.output_path.to_ascii_lowercase().ends_with(".png") {
+            return Err("--band-height writes PNG output only".to_string());
+        }
+    }
+
+    if args.raytrace {
+        let unsupported = [
+            ("--deferred", args.deferred),
+            ("--pbr", args.physically_based),
+            ("--light", !args.lights.is_empty()),
+            ("--points", args.point_radius.is_some()),
+            ("--output-mode", args.debug_view.is_some()),
+            ("--scalars", args.scalars.is_some()),
+        ];
+        if let Some((flag, _)) = unsupported.iter().find(|(_, used)| *used) {
+            return Err(format!("--raytrace cannot be combined with {}", flag));
+        }
+    }
+    if args.scalars.is_some() {
+        let unsupported = [
+            ("--output-mode", args.debug_view.is_some()),
+            ("--points", args.point_radius.is_some()),
+            (
+                "batch",
+                args.batch_dir.is_some() && matches!(args.scalars, Some(ScalarSource::File(_))),
+            ),
+        ];
+        if let Some((flag, _)) = unsupported.iter().find(|(_, used)| *used) {
+            return Err(format!("--scalars cannot be combined with {}", flag));
+        }
+        if args.colormap_scale == Scale::Log && args.scalar_range.is_some_and(|(min, _)| min <= 0.0)
+        {
+            return Err("a log --colormap-scale needs a positive --scalar-range".to_string());
+        }
+    }
+    // only plain renders keep an ID buffer and count overdraw, which deferred shading
+    // leaves to its lighting pass
+    let deferred = args.deferred
+        || args.physically_based
+        || !args.lights.is_empty()
+        || (args.environment.is_some()
+            && (args.image_based_lighting || args.reflectivity.is_some()));
+    for (option, used) in [
+        ("--pick", args.pick.is_some()),
+        ("--overdraw", args.overdraw),
+    ] {
+        let unsupported = [
+            ("--turntable", args.turntable_frames.is_some()),
+            ("--band-height", args.band_height.is_some()),
+            ("--sheet", args.contact_sheet.is_some()),
+            ("--deferred", deferred),
+            ("batch", args.batch_dir.is_some()),
+        ];
+        if let Some((flag, _)) = unsupported.iter().find(|(_, conflicts)| used && *conflicts) {
+            return Err(format!("{} cannot be combined with {}", option, flag));
+        }
+    }
+    if args.turntable_frames.is_some() && !args.views.is_empty() {
+        return Err("--turntable cannot be combined with --view".to_string());
+    }
+    if args.contact_sheet.is_some() {
+        let unsupported = [
+            ("--turntable", args.turntable_frames.is_some()),
+            ("--band-height", args.band_height.is_some()),
+            ("batch", args.batch_dir.is_some()),
+        ];
+        if let Some((flag, _)) = unsupported.iter().find(|(_, used)| *used) {
+            return Err(format!("--sheet cannot be combined with {}", flag));
+        }
+    } else if !args.sheet_modes.is_empty() {
+        return Err("--sheet-modes requires --sheet".to_string());
+    }
+    if args.batch_dir.is_some() {
+        let unsupported = [
+            ("--turntable", args.turntable_frames.is_some()),
+            ("--band-height", args.band_height.is_some()),
+            ("--view", !args.views.is_empty()),
+        ];
+        if let Some((flag, _)) = unsupported.iter().find(|(_, used)| *used) {
+            return Err(format!("batch cannot be combined with {}", flag));
+        }
+    }
+    Ok(())
+}
+
+/// Loads the images and values the options name besides the model and its texture, and
+/// returns the environment, if one is given.
+pub fn load_resources(args: &mut Args, timings: &mut StageTimings) -> Option<EnvironmentMap> {
+    if let Some(path) = &args.matcap_path {
+        match timings.time("load", || load_color_texture(path, args)) {
+            Ok(matcap) => args.matcap = Some(matcap),
+            Err(e) => fail(e),
+        }
+    }
+    if let Some(ScalarSource::File(path)) = &args.scalars {
+        let values = timings
+            .time("load", || load_scalars(path))
+            .unwrap_or_else(|e| fail(e));
+        args.scalar_values = Some(Arc::new(values));
+    }
+    if let Some(path) = &args.backdrop_path {
+        match timings.time("load", || loader::load_texture(path)) {
+            Ok(backdrop) => args.backdrop = Some(Arc::new(backdrop.to_rgba8())),
+            Err(e) => fail(e),
+        }
+    }
+    args.environment.as_ref().map(|source| match source {
+        EnvironmentSource::LatLong(path) => {
+            match timings.time("load", || loader::load_texture(path)) {
+                Ok(image) => timings.time("environment", || EnvironmentMap::from_image(&image)),
+                Err(e) => fail(e),
+            }
+        }
+        EnvironmentSource::CubeMap(paths) => {
+            match timings.time("load", || loader::load_cube_map(paths)) {
+                Ok(cube_map) => {
+                    timings.time("environment", || EnvironmentMap::from_cube_map(&cube_map))
+                }
+                Err(e) => fail(e),
+            }
+        }
+    })
+}
+
+pub fn run(mut args: Args) {
+    let mut timings = StageTimings::new();
+    let mut stats = RenderStats::default();
+    // files written, in order, for the report
+    let mut outputs: Vec<String> = Vec::new();
+
+    let meshes = load_meshes(&args, &mut timings);
+    let texture = load_texture(&args, &mut timings);
+    let environment = load_resources(&mut args, &mut timings);
+    if let (Some(ScalarSource::File(path)), Some(values)) = (&args.scalars, &args.scalar_values) {
+        let vertices: usize = meshes.iter().map(|mesh| mesh.positions.len()).sum();
+        if values.len() != vertices {
+            fail(RusterizerError::InvalidArgument(format!(
+                "{}: {} values for {} vertices",
+                path,
+                values.len(),
+                vertices
+            )));
+        }
+    }
+    let animation = match &args.obj_path {
+        Some(path) if args.clip.is_some() || args.morph_path.is_some() => {
+            match timings.time("load", || load_animation(path, &args)) {
+                Ok(animation) => Some(animation),
+                Err(e) => fail(e),
+            }
+        }
+        _ => None,
+    };
+
+    if args.progress {
+        let frames = args
+            .turntable_frames
+            .or(animation.as_ref().map(|(frames, _)| *frames));
+        let renders = match (frames, args.band_height) {
+            (Some(frames), _) => frames as usize,
+            _ if args.contact_sheet.is_some() => {
+                args.views.len().max(1) * args.sheet_modes.len().max(1)
+            }
+            (None, Some(band_height)) => {
+                views(&args).len() * args.size.1.div_ceil(band_height.max(1)) as usize
+            }
+            (None, None) => views(&args).len(),
+        };
+        let triangles = meshes.iter().map(|mesh| mesh.indices.len() as u64).sum();
+        let bar = ProgressBar::new("triangles", renders as u64, triangles);
+        args.progress_bar = Some(Arc::new(bar));
+    }
+    create_out_dir(&args);
+    let animation_path = args.animation_path.take();
+    args.animation_path = animation_path.map(|path| in_out_dir(&args, path));
+
+    if let Some(frames) = args
+        .turntable_frames
+        .or(animation.as_ref().map(|(frames, _)| *frames))
+    {
+        let mut animation_frames = Vec::new();
+        for frame in 0..frames {
+            let (posed, model) = match &animation {
+                Some((_, pose)) => {
+                    let mut posed = timings.time("animate", || pose(frame));
+                    if let Err(e) = timings.time("load", || apply_overrides(&mut posed, &args)) {
+                        fail(e);
+                    }
+                    (Cow::Owned(posed), Mat4::identity())
+                }
+                None => {
+                    let angle = 2.0 * std::f64::consts::PI * frame as f64 / frames as f64;
+                    (Cow::Borrowed(&meshes[..]), Mat4::rotation_y(angle))
+                }
+            };
+            let mut image = timings.time("render", || {
+                render(
+                    &posed,
+                    texture.as_deref(),
+                    environment.as_ref(),
+                    &model,
+                    &args,
+                )
+            });
+            stats += image.stats();
+            if args.animation_path.is_some() {
+                animation_frames.push(timings.time("post-process", || image.into_rgb_buffer()));
+            } else {
+                let camera = match animation {
+                    Some(_) => format!("front, frame {} of {}", frame + 1, frames),
+                    None => format!(
+                        "turntable, {:.1} degrees",
+                        360.0 * frame as f64 / frames as f64
+                    ),
+                };
+                image.set_text(metadata(&args, &camera));
+                // frames keep their old names unless the output path numbers them
+                let path = if args.output_path.contains("{frame") {
+                    output_path(&args, &model_name(&args), "front", frame + 1)
+                } else {
+                    in_out_dir(&args, format!("frame_{:04}.png", frame + 1))
+                };
+                if let Err(e) = timings.time("save", || image.save(&path)) {
+                    fail_saving(&path, e);
+                }
+                outputs.push(path);
+            }
+        }
+        if let Some(path) = &args.animation_path {
+            if let Err(e) = timings.time("save", || {
+                animation::save_animation(&animation_frames, args.frame_delay_ms, path)
+            }) {
+                fail_saving(path, e);
+            }
+            outputs.push(path.clone());
+        }
+    } else if let Some(columns) = args.contact_sheet {
+        let views = match args.views.as_slice() {
+            [] => vec![ViewPreset::Front],
+            views => views.to_vec(),
+        };
+        let modes = match args.sheet_modes.as_slice() {
+            [] => vec!["shaded".to_string()],
+            modes => modes.to_vec(),
+        };
+        let mut tiles = Vec::new();
+        for view in &views {
+            for mode in &modes {
+                let mut tile_args = args.clone();
+                tile_args.debug_view = output_mode(mode).expect("validated when parsing arguments");
+                let image = timings.time("render", || {
+                    render(
+                        &meshes,
+                        texture.as_deref(),
+                        environment.as_ref(),
+                        &view.rotation(),
+                        &tile_args,
+                    )
+                });
+                stats += image.stats();
+                // name what differs between the tiles
+                let label = match (views.len(), modes.len()) {
+                    (1, 1) | (_, 1) => view.name().to_string(),
+                    (1, _) => mode.clone(),
+                    _ => format!("{} {}", view.name(), mode),
+                };
+                tiles.push((
+                    label,
+                    timings.time("post-process", || image.into_rgb_buffer()),
+                ));
+            }
+        }
+        let sheet = ContactSheet {
+            columns,
+            background: args.background.average_color(),
+            ..Default::default()
+        };
+        let composed = timings.time("post-process", || sheet.compose(&tiles));
+        let path = output_path(&args, &model_name(&args), "sheet", 1);
+        let text = metadata(&args, "contact sheet");
+        let result = timings.time("save", || {
+            if text.is_empty() || !path.to_ascii_lowercase().ends_with(".png") {
+                export::save_image(&composed, &path)
+            } else {
+                export::save_png_with_text(&composed.into(), &path, &text)
+            }
+        });
+        if let Err(e) = result {
+            fail_saving(&path, e);
+        }
+        outputs.push(path);
+    } else if let Some(band_height) = args.band_height {
+        let (width, height) = args.size;
+        for (view, output_path) in views(&args) {
+            let model = view.rotation();
+            // bands are rendered and encoded together
+            let result = timings.time("render", || {
+                tiled::save_png(&output_path, width, height, band_height, |band| {
+                    track_progress(band, &args);
+                    limit_time(band, &args);
+                    draw_scene(band, &meshes, texture.as_deref(), None, &model, &args);
+                    warn_if_cancelled(band);
+                    stats += band.stats();
+                })
+            });
+            if let Err(e) = result {
+                fail_saving(&output_path, e);
+            }
+            outputs.push(output_path);
+        }
+    } else {
+        for (view, output_path) in views(&args) {
+            let mut image = timings.time("render", || {
+                render(
+                    &meshes,
+                    texture.as_deref(),
+                    environment.as_ref(),
+                    &view.rotation(),
+                    &args,
+                )
+            });
+            image.set_text(metadata(&args, view.name()));
+            stats += image.stats();
+            if let Some((x, y)) = args.pick {
+                match image.pick(x, y) {
+                    Some(id) => println!(
+                        "{}: mesh {} triangle {}",
+                        output_path, id.object, id.triangle
+                    ),
+                    None => println!("{}: background", output_path),
+                }
+            }
+            if let Some(overdraw) = image.overdraw() {
+                outputs.extend(save_overdraw(overdraw, &output_path, &args));
+                continue;
+            }
+            // saving includes post-processing and tone mapping
+            if let Err(e) = timings.time("save", || image.save(&output_path)) {
+                fail_saving(&output_path, e);
+            }
+            outputs.push(output_path);
+        }
+    }
+    finish(&args, stats, timings, &outputs);
+}
+
+/// Vertical field of view of the environment background in degrees.
+const ENVIRONMENT_FOV: f64 = 60.0;
+
+pub fn render(
+    meshes: &[Mesh],
+    texture: Option<&image::RgbImage>,
+    environment: Option<&EnvironmentMap>,
+    model: &Mat4,
+    args: &Args,
+) -> Image {
+    let (width, height) = args.size;
+    let mut image = Image::new(width, height);
+    if args.pick.is_some() {
+        image.enable_id_buffer();
+    }
+    if args.overdraw {
+        image.count_overdraw();
+    }
+    track_progress(&mut image, args);
+    limit_time(&mut image, args);
+    draw_scene(&mut image, meshes, texture, environment, model, args);
+    warn_if_cancelled(&image);
+    image
+}
+
+/// Advances the progress bar, if any, with the triangles submitted to `image`.
+fn track_progress(image: &mut Image, args: &Args) {
+    if let Some(bar) = &args.progress_bar {
+        let bar = bar.clone();
+        let base = bar.begin_render();
+        image.set_progress(Some(Box::new(move |stats| {
+            bar.set(base + stats.triangles_submitted.min(bar.per_render))
+        })));
+    }
+}
+
+/// Stops drawing into `image` once the `--time-budget` of the whole run is used up.
+fn limit_time(image: &mut Image, args: &Args) {
+    if let Some(deadline) = args.deadline {
+        image.set_time_budget(Some(deadline.saturating_duration_since(Instant::now())));
+    }
+}
+
+/// Warns, once per run, that the output is incomplete because the time budget ran out.
+fn warn_if_cancelled(image: &Image) {
+    static WARNED: Once = Once::new();
+    if image.is_cancelled() {
+        WARNED.call_once(|| log::warn!("time budget used up, saving what was drawn until then"));
+    }
+}
+
+/// Draws everything requested by `args` into `image`, which may be a band of the output.
+fn draw_scene(
+    image: &mut Image,
+    meshes: &[Mesh],
+    texture: Option<&image::RgbImage>,
+    environment: Option<&EnvironmentMap>,
+    model: &Mat4,
+    args: &Args,
+) {
+    if args.debug_view.is_none() && args.scalars.is_none() {
+        // debug views and scalars are written as exact values
+        image.set_tone_mapping(args.tone_mapping);
+    }
+    image.set_line_style(args.line_style);
+    image.set_traversal(args.traversal);
+    image.set_precision(args.precision);
+    let (width, canvas_height) = args.size;
+    let aspect = width as f64 / canvas_height as f64;
+    let projection = args.orthographic.map(|(height, near, far)| {
+        Orthographic::from_height(height, aspect, near, far).projection()
+    });
+    // the view volume takes the aspect ratio into account, otherwise the square of
+    // normalized device coordinates is kept square
+    let viewport = match projection {
+        Some(_) => Viewport::full(width, canvas_height),
+        None => Viewport::letterboxed(width, canvas_height, 1.0),
+    };
+    image.set_projection(projection);
+    image.set_viewport(viewport);
+    if let Err(e) = image.set_backdrop(args.backdrop.clone()) {
+        fail(e);
+    }
+    image.set_ambient_light(args.ambient);
+    image.set_lights(args.lights.clone());
+    image.set_fog(args.fog.map(|falloff| Fog {
+        color: args.fog_color.unwrap_or(args.background.average_color()),
+        falloff,
+    }));
+
+    image.clear_background(&args.background);
+    if let Some(environment) = environment {
+        environment.draw_background(image, ENVIRONMENT_FOV);
+    }
+    // the environment only lights the model in the deferred lighting pass, and lights
+    // only light it per pixel there, which bands cannot do
+    let deferred = args.deferred
+        || args.physically_based
+        || (!args.lights.is_empty() && args.band_height.is_none())
+        || (environment.is_some() && (args.image_based_lighting || args.reflectivity.is_some()));
+    let gbuffer = (deferred || args.outline.is_some()).then(|| {
+        let mut gbuffer = GBuffer::new(image.width(), image.height());
+        gbuffer.set_projection(projection);
+        gbuffer.set_viewport(viewport);
+        for mesh in meshes {
+            gbuffer.draw_mesh(mesh, model, texture);
+        }
+        gbuffer
+    });
+
+    if let Some(radius) = args.point_radius {
+        for (object, mesh) in meshes.iter().enumerate() {
+            image.set_object_id(object as u32);
+            draw_point_cloud(image, mesh, model, radius, args.point_coloring);
+        }
+    } else if args.raytrace {
+        raytrace(image, meshes, model);
+    } else if let Some(view) = args.debug_view {
+        for (object, mesh) in meshes.iter().enumerate() {
+            image.set_object_id(object as u32);
+            draw_mesh_debug(image, mesh, model, view);
+        }
+    } else if let Some(source) = &args.scalars {
+        let values = scalar_values(meshes, source, args);
+        let range = args.scalar_range.unwrap_or_else(|| {
+            values
+                .iter()
+                .flatten()
+                .filter(|value| value.is_finite())
+                // a log scale leaves out the values it cannot place
+                .filter(|&&value| args.colormap_scale == Scale::Linear || value > 0.0)
+                .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), &value| {
+                    (min.min(value), max.max(value))
+                })
+        });
+        let colormap = args.colormap.unwrap_or(Colormap::Viridis);
+        for (object, (mesh, values)) in meshes.iter().zip(&values).enumerate() {
+            image.set_object_id(object as u32);
+            draw_scalar_field(
+                image,
+                mesh,
+                model,
+                values,
+                range,
+                colormap,
+                args.colormap_scale,
+            );
+        }
+    } else if let (true, Some(gbuffer)) = (deferred, &gbuffer) {
+        let headlight = [Light::HEADLIGHT];
+        let lights = match &args.lights[..] {
+            [] => &headlight[..],
+            lights => lights,
+        };
+        let occluders: Vec<Bvh> = if lights.iter().any(|light| light.ray_traced_shadows) {
+            meshes.iter().map(|mesh| Bvh::build(mesh, model)).collect()
+        } else {
+            Vec::new()
+        };
+        let lighting = Lighting {
+            lights,
+            occluders: &occluders,
+            ambient: environment.filter(|_| args.image_based_lighting),
+            hemisphere: args.ambient,
+            reflections: environment,
+            physically_based: args.physically_based,
+        };
+        gbuffer.resolve(image, &lighting);
+    } else {
+        let p1 = Point3f::new(0., 0., 0.);
+        let zero = Vec3f::new(0., 0., 0.);
+        let mut list = DrawList::new();
+        for mesh in meshes {
+            // a texture given on the command line overrides the material's own
+            let mesh_texture = texture.or(mesh.material.base_color_texture.as_deref());
+            let draw_style = match (
+                object_style(mesh, args),
+                args.random_fill,
+                args.matcap.as_deref(),
+                mesh_texture,
+            ) {
+                (Some(&ObjectStyle::Wireframe(color)), ..) => DrawStyle::Wireframe(color),
+                (Some(&ObjectStyle::Filled(color)), ..) => {
+                    DrawStyle::Filled(color.unwrap_or(mesh.material.base_color))
+                }
+                (Some(&ObjectStyle::Random(seed)), ..) => DrawStyle::FilledRandom(seed),
+                // loaded into the material along with the meshes
+                (Some(ObjectStyle::Textured(_)), ..) => {
+                    match mesh.material.base_color_texture.as_deref() {
+                        Some(tex) => DrawStyle::Textured(tex, (&p1, &p1, &p1)),
+                        None => DrawStyle::Filled(mesh.material.base_color),
+                    }
+                }
+                (None, Some(seed), ..) => DrawStyle::FilledRandom(seed),
+                (None, None, Some(matcap), _) => DrawStyle::Matcap(matcap, (zero, zero, zero)),
+                (None, None, None, Some(tex)) => DrawStyle::Textured(tex, (&p1, &p1, &p1)),
+                (None, None, None, None) if mesh.has_colors() => DrawStyle::VertexColors {
+                    colors: (color::WHITE, color::WHITE, color::WHITE),
+                    lit: !args.unlit_vertex_colors,
+                },
+                (None, None, None, None) => DrawStyle::Filled(mesh.material.base_color),
+            };
+            let command = list.draw_mesh(mesh, &Mat4::identity(), draw_style);
+            if let DrawStyle::Wireframe(_) = draw_style {
+                command.depth_bias = args.depth_bias;
+            }
+        }
+        // batch files already take a thread each
+        let threads = if args.batch_dir.is_some() {
+            1
+        } else {
+            args.jobs
+        };
+        list.execute(image, model, threads);
+    }
+    draw_overlays(image, model, &projection, args);
+
+    // depth of field needs the finished depth buffer and goes before the other passes
+    if let Some(focus) = &args.focus {
+        let dof = DepthOfField::from_depth(image.width(), image.depth_buffer(), focus);
+        image.add_post_process(Box::new(dof));
+    }
+    for spec in &args.post_processing {
+        image.add_post_process(post_process(spec).expect("validated when parsing arguments"));
+    }
+    if let (Some(thickness), Some(gbuffer)) = (args.outline, &gbuffer) {
+        let style = OutlineStyle {
+            thickness,
+            ..Default::default()
+        };
+        image.add_post_process(Box::new(Outline::detect(gbuffer, &style)));
+    }
+
+    if let Some(label) = &args.label {
+        // placed in the full image, so only the top band gets it
+        let top = image.canvas_height().saturating_sub(5);
+        if let Some(top) = top.checked_sub(image.band_offset()) {
+            image.draw_text(4, top, label, color::WHITE);
+        }
+    }
+}
+
+/// Draws the requested overlays into the finished render, seen through the view `model`.
+fn draw_overlays(image: &mut Image, model: &Mat4, projection: &Option<Mat4>, args: &Args) {
+    for overlay in &args.overlays {
+        match overlay {
+            Overlay::Grid => overlay::draw_grid(image, model, &Grid::default()),
+            Overlay::Axes => overlay::draw_axes(image, model, 1.0),
+            Overlay::Lights => {
+                let lights = match &args.lights[..] {
+                    [] => &[Light::HEADLIGHT][..],
+                    lights => lights,
+                };
+                overlay::draw_lights(image, lights, 0.2, color::YELLOW);
+            }
+            Overlay::Frustum => {
+                let front = projection.unwrap_or(Mat4::identity());
+                overlay::draw_frustum(image, model, &front, color::CYAN);
+            }
+        }
+    }
+}
+
+/// Every requested view and its output path. Several views get their name appended to
+/// the output file name unless it has a `{view}` field; no view at all renders the front
+/// view.
+fn views(args: &Args) -> Vec<(ViewPreset, String)> {
+    let model = model_name(args);
+    match args.views.as_slice() {
+        [] => vec![(ViewPreset::Front, output_path(args, &model, "front", 1))],
+        [view] => vec![(*view, output_path(args, &model, view.name(), 1))],
+        views => views
+            .iter()
+            .map(|view| {
+                let path = output_path(args, &model, view.name(), 1);
+                if args.output_path.contains("{view") {
+                    (*view, path)
+                } else {
+                    (*view, suffixed_path(&path, view.name()))
+                }
+            })
+            .collect(),
+    }
+}
+
+/// Saves heatmaps of the fragments covering and written to every pixel next to
+/// `path` on the same scale, and prints the average overdraw. Returns their paths.
+fn save_overdraw(overdraw: &Overdraw, path: &str, args: &Args) -> Vec<String> {
+    let (covered, written) = overdraw.averages();
+    println!(
+        "{}: {:.2} fragments covered and {:.2} written per pixel",
+        path, covered, written
+    );
+    let max = overdraw.covered.iter().copied().max().unwrap_or(0).max(1);
+    let colormap = args.colormap.unwrap_or(Colormap::Magma);
+    let mut paths = Vec::new();
+    for (name, counts) in [
+        ("covered", &overdraw.covered),
+        ("written", &overdraw.written),
+    ] {
+        let heatmap_path = suffixed_path(path, name);
+        if let Err(e) = overdraw.heatmap(counts, max, colormap).save(&heatmap_path) {
+            fail_saving(&heatmap_path, e);
+        }
+        paths.push(heatmap_path);
+    }
+    paths
+}
+
+/// Values of `source` at the vertices of every mesh.
+fn scalar_values(meshes: &[Mesh], source: &ScalarSource, args: &Args) -> Vec<Vec<f64>> {
+    match source {
+        ScalarSource::Curvature => meshes.iter().map(Mesh::mean_curvature).collect(),
+        ScalarSource::Height => meshes
+            .iter()
+            .map(|mesh| mesh.positions.iter().map(|p| p.y).collect())
+            .collect(),
+        ScalarSource::File(_) => {
+            let values = args.scalar_values.as_deref().map_or(&[][..], Vec::as_slice);
+            let mut start = 0;
+            meshes
+                .iter()
+                .map(|mesh| {
+                    let count = mesh.positions.len();
+                    start += count;
+                    values
+                        .iter()
+                        .skip(start - count)
+                        .take(count)
+                        .copied()
+                        .collect()
+                })
+                .collect()
+        }
+    }
+}
+
+/// Number of frames to render from a glTF model and the meshes posed for each: the
+/// frames of the `--clip` played at its frame rate, or else one per line of the
+/// `--morph` weights file. The last keyframe of a clip is left out so that looping
+/// playback does not show it twice, and the last line of weights holds for any frames
+/// after it.
+#[cfg(feature = "gltf")]
+fn load_animation(path: &str, args: &Args) -> Result<(u32, FramePoses), RusterizerError> {
+    let scene = loader::gltf::load_animated(path)?;
+    let clip = match &args.clip {
+        Some((name, fps)) => match scene.find_clip(name) {
+            Some(clip) => Some((clip, *fps)),
+            None => {
+                let names: Vec<String> = scene
+                    .clips
+                    .iter()
+                    .enumerate()
+                    .map(|(i, clip)| clip.name.clone().unwrap_or_else(|| i.to_string()))
+                    .collect();
+                return Err(RusterizerError::InvalidArgument(format!(
+                    "unknown clip '{}', the model has: {}",
+                    name,
+                    names.join(", ")
+                )));
+            }
+        },
+        None => None,
+    };
+    let weights = match &args.morph_path {
+        Some(path) => loader::weights::load(path)?,
+        None => Vec::new(),
+    };
+    let frames = match clip {
+        Some((clip, fps)) => (scene.clips[clip].duration * fps).round() as u32,
+        None => weights.len() as u32,
+    };
+    Ok((
+        frames.max(1),
+        Box::new(move |frame| {
+            let time = clip.map_or(0.0, |(_, fps)| frame as f64 / fps);
+            let frame_weights = weights
+                .get((frame as usize).min(weights.len().saturating_sub(1)))
+                .map(Vec::as_slice);
+            scene.pose_with_weights(clip.map(|(clip, _)| clip), time, frame_weights)
+        }),
+    ))
+}
+
+#[cfg(not(feature = "gltf"))]
+fn load_animation(_path: &str, _args: &Args) -> Result<(u32, FramePoses), RusterizerError> {
+    Err(RusterizerError::InvalidArgument(
+        "animation clips and morph weights require building with the `gltf` feature".to_string(),
+    ))
+}
+
+/// Meshes posed for a frame number.
+type FramePoses = Box<dyn Fn(u32) -> Vec<Mesh>>;
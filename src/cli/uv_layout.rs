@@ -0,0 +1,57 @@
+//! `rusterizer uv-layout MODEL [TEXTURE]`: the faces drawn in UV space over the texture.
+
+use rusterizer::drawable::Image;
+use rusterizer::mesh::Mesh;
+use rusterizer::stats::{RenderStats, StageTimings};
+use rusterizer::uv_layout::{draw_uv_layout, UvLayoutStyle};
+
+use super::options::Args;
+use super::output::{model_name, output_path};
+use super::{
+    create_out_dir, fail_saving, fail_usage, finish, load_meshes, load_texture, no_more_arguments,
+    reject_render_options,
+};
+
+pub fn parse(mut args: Args, positional: Vec<String>) -> Result<Args, String> {
+    let mut positional = positional.into_iter();
+    args.obj_path = positional.next();
+    if args.obj_path.is_none() && args.primitive.is_none() {
+        return Err("uv-layout expects a model".to_string());
+    }
+    args.tex_path = positional.next();
+    no_more_arguments(positional)?;
+    reject_render_options("uv-layout", &args)?;
+    Ok(args)
+}
+
+pub fn run(args: Args) {
+    let mut timings = StageTimings::new();
+    let meshes = load_meshes(&args, &mut timings);
+    let texture = load_texture(&args, &mut timings);
+    create_out_dir(&args);
+    let output_path = output_path(&args, &model_name(&args), "uv", 1);
+    let image = timings.time("render", || {
+        draw_uv_layouts(&meshes, texture.as_deref(), &args)
+    });
+    if let Err(e) = timings.time("save", || image.save(&output_path)) {
+        fail_saving(&output_path, e);
+    }
+    finish(&args, RenderStats::default(), timings, &[output_path]);
+}
+
+/// Draws the UV layout of `meshes` over `texture`, or the first texture of their own.
+fn draw_uv_layouts(meshes: &[Mesh], texture: Option<&image::RgbImage>, args: &Args) -> Image {
+    if !meshes.iter().any(Mesh::has_uvs) {
+        fail_usage("uv-layout needs a mesh with UVs".to_string());
+    }
+    let texture = texture.or_else(|| {
+        meshes
+            .iter()
+            .find_map(|mesh| mesh.material.base_color_texture.as_deref())
+    });
+    let mut image = Image::new(args.size.0, args.size.1);
+    image.clear_background(&args.background);
+    image.set_line_style(args.line_style);
+    draw_uv_layout(&mut image, meshes, texture, &UvLayoutStyle::default());
+    image
+}
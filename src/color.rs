@@ -1,13 +1,26 @@
+use std::fmt;
+use std::ops::{Add, Mul};
+
 use image::Rgb;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Color(pub u8, pub u8, pub u8);
 
 impl Color {
+    #[cfg(feature = "rand")]
     pub fn random() -> Self {
         Color(rand::random(), rand::random(), rand::random())
     }
 
+    /// Deterministic pseudo-random color derived from `seed` with the SplitMix64 mixer.
+    pub fn from_seed(seed: u64) -> Self {
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        Color(z as u8, (z >> 8) as u8, (z >> 16) as u8)
+    }
+
     pub fn scale(&self, x: f64) -> Self {
         let x = x.max(0.0);
         let r = (self.0 as f64) * x;
@@ -15,11 +28,222 @@ impl Color {
         let b = (self.2 as f64) * x;
         Color(r as u8, g as u8, b as u8)
     }
+
+    /// Parses `#rrggbb` or the shorthand `#rgb`; the leading `#` is optional.
+    pub fn from_hex(hex: &str) -> Result<Self, ParseColorError> {
+        let digits = hex.strip_prefix('#').unwrap_or(hex);
+        if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(ParseColorError::InvalidDigit);
+        }
+        let channel =
+            |i: usize, len: usize| u8::from_str_radix(&digits[i * len..(i + 1) * len], 16);
+        match digits.len() {
+            6 => Ok(Color(
+                channel(0, 2).unwrap(),
+                channel(1, 2).unwrap(),
+                channel(2, 2).unwrap(),
+            )),
+            // each digit is repeated, so #f80 is #ff8800
+            3 => Ok(Color(
+                channel(0, 1).unwrap() * 17,
+                channel(1, 1).unwrap() * 17,
+                channel(2, 1).unwrap() * 17,
+            )),
+            len => Err(ParseColorError::InvalidLength(len)),
+        }
+    }
+
+    /// Builds a color from hue in degrees (wrapped to [0, 360)) and saturation and value in [0, 1].
+    pub fn from_hsv(hue: f64, saturation: f64, value: f64) -> Self {
+        let (s, v) = (saturation.clamp(0.0, 1.0), value.clamp(0.0, 1.0));
+        let chroma = v * s;
+        Color::from_hue_chroma(hue, chroma, v - chroma)
+    }
+
+    /// Returns `(hue, saturation, value)`, hue in degrees; grays get a hue of 0.
+    pub fn to_hsv(&self) -> (f64, f64, f64) {
+        let (hue, max, min) = self.hue_max_min();
+        let saturation = if max > 0.0 { (max - min) / max } else { 0.0 };
+        (hue, saturation, max)
+    }
+
+    /// Builds a color from hue in degrees and saturation and lightness in [0, 1].
+    pub fn from_hsl(hue: f64, saturation: f64, lightness: f64) -> Self {
+        let (s, l) = (saturation.clamp(0.0, 1.0), lightness.clamp(0.0, 1.0));
+        let chroma = (1.0 - (2.0 * l - 1.0).abs()) * s;
+        Color::from_hue_chroma(hue, chroma, l - chroma / 2.0)
+    }
+
+    /// Returns `(hue, saturation, lightness)`, hue in degrees; grays get a hue of 0.
+    pub fn to_hsl(&self) -> (f64, f64, f64) {
+        let (hue, max, min) = self.hue_max_min();
+        let lightness = (max + min) / 2.0;
+        let saturation = if max > min {
+            (max - min) / (1.0 - (2.0 * lightness - 1.0).abs())
+        } else {
+            0.0
+        };
+        (hue, saturation, lightness)
+    }
+
+    fn from_hue_chroma(hue: f64, chroma: f64, min: f64) -> Self {
+        let sector = hue.rem_euclid(360.0) / 60.0;
+        let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+        let (r, g, b) = match sector as u32 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+        let to_u8 = |c: f64| ((c + min).clamp(0.0, 1.0) * 255.0).round() as u8;
+        Color(to_u8(r), to_u8(g), to_u8(b))
+    }
+
+    fn hue_max_min(&self) -> (f64, f64, f64) {
+        let (r, g, b) = (
+            self.0 as f64 / 255.0,
+            self.1 as f64 / 255.0,
+            self.2 as f64 / 255.0,
+        );
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let delta = max - min;
+        let hue = if delta == 0.0 {
+            0.0
+        } else if max == r {
+            60.0 * ((g - b) / delta).rem_euclid(6.0)
+        } else if max == g {
+            60.0 * ((b - r) / delta + 2.0)
+        } else {
+            60.0 * ((r - g) / delta + 4.0)
+        };
+        (hue, max, min)
+    }
+
+    /// Linear interpolation towards `other`, `t` being clamped to [0, 1].
+    pub fn lerp(&self, other: Color, t: f64) -> Self {
+        let t = t.clamp(0.0, 1.0);
+        let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t).round() as u8;
+        Color(
+            mix(self.0, other.0),
+            mix(self.1, other.1),
+            mix(self.2, other.2),
+        )
+    }
 }
 
-impl Into<Rgb<u8>> for Color {
-    fn into(self) -> Rgb<u8> {
-        Rgb([self.0, self.1, self.2])
+/// Saturating per-channel sum.
+impl Add for Color {
+    type Output = Color;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Color(
+            self.0.saturating_add(rhs.0),
+            self.1.saturating_add(rhs.1),
+            self.2.saturating_add(rhs.2),
+        )
+    }
+}
+
+/// Per-channel product treating channels as values in [0, 1], i.e. tinting by `rhs`.
+impl Mul for Color {
+    type Output = Color;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        let modulate = |a: u8, b: u8| ((a as u16 * b as u16 + 127) / 255) as u8;
+        Color(
+            modulate(self.0, rhs.0),
+            modulate(self.1, rhs.1),
+            modulate(self.2, rhs.2),
+        )
+    }
+}
+
+impl std::str::FromStr for Color {
+    type Err = ParseColorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Color::from_hex(s)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseColorError {
+    InvalidLength(usize),
+    InvalidDigit,
+}
+
+impl fmt::Display for ParseColorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseColorError::InvalidLength(len) => {
+                write!(f, "expected 3 or 6 hex digits, got {}", len)
+            }
+            ParseColorError::InvalidDigit => write!(f, "invalid hex digit"),
+        }
+    }
+}
+
+impl std::error::Error for ParseColorError {}
+
+/// Linear-light RGB color without an upper bound, used for shading and the HDR framebuffer.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct HdrColor(pub f32, pub f32, pub f32);
+
+impl HdrColor {
+    pub fn scale(&self, x: f64) -> Self {
+        let x = x.max(0.0) as f32;
+        HdrColor(self.0 * x, self.1 * x, self.2 * x)
+    }
+
+    pub fn map<F: Fn(f32) -> f32>(&self, f: F) -> Self {
+        HdrColor(f(self.0), f(self.1), f(self.2))
+    }
+
+    /// Clamps to [0, 1] and encodes with the sRGB transfer function.
+    pub fn to_srgb(self) -> Color {
+        Color(
+            linear_to_srgb(self.0),
+            linear_to_srgb(self.1),
+            linear_to_srgb(self.2),
+        )
+    }
+}
+
+fn srgb_to_linear(value: u8) -> f32 {
+    let v = value as f32 / 255.0;
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f32) -> u8 {
+    let v = value.clamp(0.0, 1.0);
+    let encoded = if v <= 0.0031308 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    };
+    (encoded * 255.0).round() as u8
+}
+
+impl From<Color> for HdrColor {
+    fn from(color: Color) -> Self {
+        HdrColor(
+            srgb_to_linear(color.0),
+            srgb_to_linear(color.1),
+            srgb_to_linear(color.2),
+        )
+    }
+}
+
+impl From<Color> for Rgb<u8> {
+    fn from(color: Color) -> Self {
+        Rgb([color.0, color.1, color.2])
     }
 }
 
@@ -29,4 +253,78 @@ impl From<Rgb<u8>> for Color {
     }
 }
 
+pub const BLACK: Color = Color(0, 0, 0);
 pub const WHITE: Color = Color(255, 255, 255);
+pub const GRAY: Color = Color(128, 128, 128);
+pub const DARK_GRAY: Color = Color(50, 50, 50);
+pub const RED: Color = Color(255, 0, 0);
+pub const GREEN: Color = Color(0, 255, 0);
+pub const BLUE: Color = Color(0, 0, 255);
+pub const YELLOW: Color = Color(255, 255, 0);
+pub const CYAN: Color = Color(0, 255, 255);
+pub const MAGENTA: Color = Color(255, 0, 255);
+pub const ORANGE: Color = Color(255, 165, 0);
+
+#[test]
+fn test_srgb_round_trip() {
+    for value in [0u8, 1, 10, 50, 128, 200, 254, 255] {
+        let color = Color(value, value, value);
+        let round_trip = HdrColor::from(color).to_srgb();
+        assert_eq!(round_trip.0, value);
+    }
+}
+
+#[test]
+fn test_from_hex() {
+    assert_eq!(Color::from_hex("#202030"), Ok(Color(0x20, 0x20, 0x30)));
+    assert_eq!(Color::from_hex("ff8000"), Ok(Color(255, 128, 0)));
+    assert_eq!(Color::from_hex("#f80"), Ok(Color(255, 136, 0)));
+    assert_eq!(
+        Color::from_hex("#12345"),
+        Err(ParseColorError::InvalidLength(5))
+    );
+    assert_eq!(
+        Color::from_hex("#gg0000"),
+        Err(ParseColorError::InvalidDigit)
+    );
+    assert_eq!(Color::from_hex("#éé"), Err(ParseColorError::InvalidDigit));
+}
+
+#[test]
+fn test_color_arithmetic() {
+    assert_eq!(BLACK.lerp(WHITE, 0.5), Color(128, 128, 128));
+    assert_eq!(RED.lerp(BLUE, 2.0), BLUE);
+    assert_eq!(Color(200, 10, 0) + Color(100, 10, 0), Color(255, 20, 0));
+    assert_eq!(WHITE * ORANGE, ORANGE);
+    assert_eq!(GRAY * GRAY, Color(64, 64, 64));
+}
+
+#[test]
+fn test_hsv_hsl() {
+    assert_eq!(Color::from_hsv(0.0, 1.0, 1.0), RED);
+    assert_eq!(Color::from_hsv(120.0, 1.0, 1.0), GREEN);
+    assert_eq!(Color::from_hsv(-120.0, 1.0, 1.0), BLUE);
+    assert_eq!(Color::from_hsl(60.0, 1.0, 0.5), YELLOW);
+    assert_eq!(Color::from_hsl(0.0, 0.0, 1.0), WHITE);
+    assert_eq!(CYAN.to_hsv(), (180.0, 1.0, 1.0));
+    assert_eq!(MAGENTA.to_hsl(), (300.0, 1.0, 0.5));
+
+    for color in [
+        ORANGE,
+        GRAY,
+        DARK_GRAY,
+        Color(12, 200, 99),
+        Color(250, 3, 140),
+    ] {
+        let (h, s, v) = color.to_hsv();
+        assert_eq!(Color::from_hsv(h, s, v), color);
+        let (h, s, l) = color.to_hsl();
+        assert_eq!(Color::from_hsl(h, s, l), color);
+    }
+}
+
+#[test]
+fn test_from_seed() {
+    assert_eq!(Color::from_seed(42), Color::from_seed(42));
+    assert_ne!(Color::from_seed(42), Color::from_seed(43));
+}
@@ -0,0 +1,209 @@
+use crate::color::Color;
+
+/// Viridis sampled at every eighth of its range, from matplotlib.
+const VIRIDIS: [Color; 9] = [
+    Color(68, 1, 84),
+    Color(71, 44, 122),
+    Color(59, 81, 139),
+    Color(44, 113, 142),
+    Color(33, 144, 141),
+    Color(39, 173, 129),
+    Color(92, 200, 99),
+    Color(170, 220, 50),
+    Color(253, 231, 37),
+];
+
+/// Magma sampled at every eighth of its range, from matplotlib.
+const MAGMA: [Color; 9] = [
+    Color(0, 0, 4),
+    Color(28, 16, 68),
+    Color(79, 18, 123),
+    Color(129, 37, 129),
+    Color(181, 54, 122),
+    Color(229, 80, 100),
+    Color(251, 135, 97),
+    Color(254, 194, 135),
+    Color(252, 253, 191),
+];
+
+/// Plasma sampled at every eighth of its range, from matplotlib.
+const PLASMA: [Color; 9] = [
+    Color(13, 8, 135),
+    Color(76, 2, 161),
+    Color(126, 3, 168),
+    Color(169, 35, 149),
+    Color(204, 71, 120),
+    Color(230, 108, 92),
+    Color(248, 149, 64),
+    Color(253, 197, 39),
+    Color(240, 249, 33),
+];
+
+/// Palette scalars are mapped through, from the low end of their range to the high.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Colormap {
+    /// Perceptually uniform dark purple through teal to yellow.
+    Viridis,
+    /// Perceptually uniform black through purple and orange to pale yellow.
+    Magma,
+    /// Perceptually uniform blue through magenta to yellow.
+    Plasma,
+    /// Near black through blue, cyan, green and yellow to dark red, a smoother jet.
+    Turbo,
+    /// Black to white, so that `t` is saved as `t * 255`.
+    Grayscale,
+    /// Blue through cyan, yellow and red.
+    Jet,
+}
+
+impl Colormap {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Colormap::Viridis => "viridis",
+            Colormap::Magma => "magma",
+            Colormap::Plasma => "plasma",
+            Colormap::Turbo => "turbo",
+            Colormap::Grayscale => "grayscale",
+            Colormap::Jet => "jet",
+        }
+    }
+
+    /// Color at `t` of the way from the low end to the high end, clamped to 0..1.
+    /// Values that are not a number are black.
+    pub fn color(&self, t: f64) -> Color {
+        if t.is_nan() {
+            return Color(0, 0, 0);
+        }
+        let t = t.clamp(0.0, 1.0);
+        let to_u8 = |c: f64| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+        match self {
+            Colormap::Viridis => gradient(&VIRIDIS, t),
+            Colormap::Magma => gradient(&MAGMA, t),
+            Colormap::Plasma => gradient(&PLASMA, t),
+            Colormap::Turbo => {
+                // the polynomial fit published along with turbo
+                let polynomial = |c: [f64; 6]| {
+                    to_u8(c[0] + t * (c[1] + t * (c[2] + t * (c[3] + t * (c[4] + t * c[5])))))
+                };
+                Color(
+                    polynomial([0.1357, 4.6154, -42.6603, 132.1311, -152.9424, 59.2864]),
+                    polynomial([0.0914, 2.1942, 4.8430, -14.1850, 4.2773, 2.8296]),
+                    polynomial([0.1067, 12.6419, -60.5820, 110.3628, -89.9031, 27.3482]),
+                )
+            }
+            Colormap::Grayscale => {
+                let gray = to_u8(t);
+                Color(gray, gray, gray)
+            }
+            Colormap::Jet => {
+                let ramp = |center: f64| to_u8(1.5 - (4.0 * t - center).abs());
+                Color(ramp(3.0), ramp(2.0), ramp(1.0))
+            }
+        }
+    }
+
+    /// Color of `value` placed in `range` by `scale`, values past either end clamped.
+    pub fn map(&self, value: f64, range: (f64, f64), scale: Scale) -> Color {
+        self.color(scale.position(value, range))
+    }
+}
+
+impl std::str::FromStr for Colormap {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "viridis" => Ok(Colormap::Viridis),
+            "magma" => Ok(Colormap::Magma),
+            "plasma" => Ok(Colormap::Plasma),
+            "turbo" => Ok(Colormap::Turbo),
+            "grayscale" => Ok(Colormap::Grayscale),
+            "jet" => Ok(Colormap::Jet),
+            _ => Err(format!("unknown colormap '{}'", s)),
+        }
+    }
+}
+
+/// Color of `t` between evenly spaced `stops`.
+fn gradient(stops: &[Color], t: f64) -> Color {
+    let x = t * (stops.len() - 1) as f64;
+    let i = (x as usize).min(stops.len() - 2);
+    let (Color(r0, g0, b0), Color(r1, g1, b1)) = (stops[i], stops[i + 1]);
+    let f = x - i as f64;
+    let lerp = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * f).round() as u8;
+    Color(lerp(r0, r1), lerp(g0, g1), lerp(b0, b1))
+}
+
+/// How values are spread over a [`Colormap`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Scale {
+    #[default]
+    Linear,
+    /// Logarithmic, for values spanning orders of magnitude. Only positive ranges can
+    /// be scaled so; values of 0 and below are past the low end.
+    Log,
+}
+
+impl Scale {
+    /// Where `value` falls from the low end of `range`, at 0, to the high end, at 1. A
+    /// range without width puts everything in the middle.
+    pub fn position(&self, value: f64, (min, max): (f64, f64)) -> f64 {
+        let (value, min, max) = match self {
+            Scale::Linear => (value, min, max),
+            Scale::Log if value <= 0.0 => return 0.0,
+            Scale::Log => (value.ln(), min.ln(), max.ln()),
+        };
+        if max > min {
+            (value - min) / (max - min)
+        } else {
+            0.5
+        }
+    }
+}
+
+impl std::str::FromStr for Scale {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "linear" => Ok(Scale::Linear),
+            "log" => Ok(Scale::Log),
+            _ => Err(format!("unknown scale '{}'", s)),
+        }
+    }
+}
+
+#[test]
+fn test_colormaps() {
+    assert_eq!(Colormap::Viridis.color(0.0), Color(68, 1, 84));
+    assert_eq!(Colormap::Viridis.color(1.5), Color(253, 231, 37));
+    assert_eq!(Colormap::Viridis.color(0.5), Color(33, 144, 141));
+    assert_eq!(Colormap::Magma.color(1.0), Color(252, 253, 191));
+    assert_eq!(Colormap::Plasma.color(0.0), Color(13, 8, 135));
+    assert_eq!(Colormap::Grayscale.color(0.5), Color(128, 128, 128));
+    assert_eq!(Colormap::Jet.color(0.0), Color(0, 0, 128));
+    assert_eq!(Colormap::Jet.color(0.5), Color(128, 255, 128));
+    assert_eq!(Colormap::Jet.color(1.0), Color(128, 0, 0));
+    // turbo runs from near black through blue and a bright green to dark red
+    let Color(r, g, b) = Colormap::Turbo.color(0.0);
+    assert!(r < 64 && g < 64 && b < 64);
+    let Color(r, g, b) = Colormap::Turbo.color(0.1);
+    assert!(b > r && b > g);
+    let Color(r, g, b) = Colormap::Turbo.color(0.5);
+    assert!(g > 200 && g > r && g > b);
+    let Color(r, g, b) = Colormap::Turbo.color(1.0);
+    assert!(r > g && r > b);
+    assert_eq!(Colormap::Turbo.color(f64::NAN), Color(0, 0, 0));
+    for name in ["viridis", "magma", "plasma", "turbo", "grayscale", "jet"] {
+        assert_eq!(name.parse::<Colormap>().unwrap().name(), name);
+    }
+
+    assert_eq!(Scale::Linear.position(15.0, (10.0, 20.0)), 0.5);
+    assert!((Scale::Log.position(10.0, (1.0, 100.0)) - 0.5).abs() < 1e-12);
+    assert_eq!(Scale::Log.position(-1.0, (1.0, 100.0)), 0.0);
+    assert_eq!(Scale::Linear.position(3.0, (3.0, 3.0)), 0.5);
+    assert_eq!(
+        Colormap::Grayscale.map(100.0, (1.0, 100.0), Scale::Log),
+        Color(255, 255, 255)
+    );
+}
@@ -0,0 +1,247 @@
+use std::fmt;
+
+use crate::error::RusterizerError;
+
+/// Value of a setting in a [`Config`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    Array(Vec<Value>),
+}
+
+/// Values as they would be passed on the command line, arrays separated by commas.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::String(s) => write!(f, "{}", s),
+            Value::Integer(i) => write!(f, "{}", i),
+            Value::Float(x) => write!(f, "{}", x),
+            Value::Boolean(b) => write!(f, "{}", b),
+            Value::Array(values) => {
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Settings read from the subset of TOML a settings file needs: `key = value` lines of
+/// strings, numbers, booleans and single-line arrays of them, grouped under `[table]`
+/// headers, with `#` comments.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Config {
+    /// Settings in the order given, keys in tables prefixed with the table name and a
+    /// dot.
+    values: Vec<(String, Value)>,
+}
+
+impl Config {
+    pub fn parse(text: &str) -> Result<Self, RusterizerError> {
+        let mut config = Config::default();
+        let mut table = String::new();
+        for (number, line) in text.lines().enumerate() {
+            let error = |msg: &str| RusterizerError::Parse(format!("line {}: {}", number + 1, msg));
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[') {
+                let name = name
+                    .strip_suffix(']')
+                    .ok_or_else(|| error("unclosed table"))?;
+                let name = name.trim();
+                if !is_key(name) {
+                    return Err(error(&format!("invalid table name '{}'", name)));
+                }
+                table = format!("{}.", name);
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| error("expected key = value"))?;
+            let key = key.trim();
+            if !is_key(key) {
+                return Err(error(&format!("invalid key '{}'", key)));
+            }
+            let key = format!("{}{}", table, key);
+            if config.get(&key).is_some() {
+                return Err(error(&format!("'{}' is set twice", key)));
+            }
+            let value = parse_value(value.trim()).map_err(|msg| error(&msg))?;
+            config.values.push((key, value));
+        }
+        Ok(config)
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn load<P: AsRef<std::path::Path>>(path: P) -> Result<Self, RusterizerError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|e| RusterizerError::in_file(path, e))?;
+        Config::parse(&text).map_err(|e| match e {
+            RusterizerError::Parse(msg) => {
+                RusterizerError::Parse(format!("{}: {}", path.display(), msg))
+            }
+            e => e,
+        })
+    }
+
+    /// The value of `key`, `table.key` for keys in a table.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.values
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value)
+    }
+
+    /// Every key and its value, in the order given.
+    pub fn values(&self) -> &[(String, Value)] {
+        &self.values
+    }
+}
+
+fn is_key(key: &str) -> bool {
+    !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// `line` up to a `#` outside of a string.
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (Some('"'), '\\') if !escaped => {
+                escaped = true;
+                continue;
+            }
+            (Some(q), c) if c == q && !escaped => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '#') => return &line[..i],
+            _ => (),
+        }
+        escaped = false;
+    }
+    line
+}
+
+fn parse_value(text: &str) -> Result<Value, String> {
+    let (value, rest) = parse_prefix(text)?;
+    match rest.trim() {
+        "" => Ok(value),
+        rest => Err(format!("unexpected '{}' after the value", rest)),
+    }
+}
+
+/// Parses the value at the start of `text`, returning it and the text after it.
+fn parse_prefix(text: &str) -> Result<(Value, &str), String> {
+    let text = text.trim_start();
+    if let Some(rest) = text.strip_prefix('"') {
+        let mut value = String::new();
+        let mut chars = rest.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => return Ok((Value::String(value), &rest[i + 1..])),
+                '\\' => value.push(match chars.next().map(|(_, c)| c) {
+                    Some('n') => '\n',
+                    Some('t') => '\t',
+                    Some(c @ ('"' | '\\')) => c,
+                    c => return Err(format!("unknown escape '\\{}'", c.unwrap_or(' '))),
+                }),
+                c => value.push(c),
+            }
+        }
+        return Err("unclosed string".to_string());
+    }
+    if let Some(rest) = text.strip_prefix('\'') {
+        let end = rest.find('\'').ok_or("unclosed string")?;
+        return Ok((Value::String(rest[..end].to_string()), &rest[end + 1..]));
+    }
+    if let Some(mut rest) = text.strip_prefix('[') {
+        let mut values = Vec::new();
+        loop {
+            rest = rest.trim_start();
+            if let Some(after) = rest.strip_prefix(']') {
+                return Ok((Value::Array(values), after));
+            }
+            let (value, after) = parse_prefix(rest)?;
+            values.push(value);
+            rest = after.trim_start();
+            match rest.strip_prefix(',') {
+                Some(after) => rest = after,
+                None if rest.starts_with(']') => (),
+                None => return Err("expected ',' or ']' in array".to_string()),
+            }
+        }
+    }
+    // a bare value runs up to the end of an array element
+    let end = text.find([',', ']']).unwrap_or(text.len());
+    let (word, rest) = (text[..end].trim(), &text[end..]);
+    let digits = word.replace('_', "");
+    let value = match word {
+        "true" => Value::Boolean(true),
+        "false" => Value::Boolean(false),
+        _ => match (digits.parse::<i64>(), digits.parse::<f64>()) {
+            (Ok(i), _) => Value::Integer(i),
+            (_, Ok(x)) if word.starts_with(|c: char| c.is_ascii_digit() || "+-.".contains(c)) => {
+                Value::Float(x)
+            }
+            _ if word.is_empty() => return Err("missing value".to_string()),
+            _ => return Err(format!("invalid value '{}'", word)),
+        },
+    };
+    Ok((value, rest))
+}
+
+#[test]
+fn test_parse_config() {
+    let config = Config::parse(
+        "# defaults\n\
+         size = \"640x480\"  # comment\n\
+         background = '#202020'\n\
+         samples = 1_024\n\
+         \n\
+         [camera]\n\
+         view = [\"front\", 'iso']\n\
+         ortho = 2.5\n\
+         flip = false\n",
+    )
+    .unwrap();
+    assert_eq!(config.get("size"), Some(&Value::String("640x480".into())));
+    assert_eq!(config.get("background").unwrap().to_string(), "#202020");
+    assert_eq!(config.get("samples"), Some(&Value::Integer(1024)));
+    assert_eq!(config.get("camera.view").unwrap().to_string(), "front,iso");
+    assert_eq!(config.get("camera.ortho"), Some(&Value::Float(2.5)));
+    assert_eq!(config.get("camera.flip"), Some(&Value::Boolean(false)));
+    assert_eq!(config.get("view"), None);
+    assert_eq!(config.values().len(), 6);
+
+    assert_eq!(
+        Config::parse("label = \"say \\\"hi\\\"\"")
+            .unwrap()
+            .get("label"),
+        Some(&Value::String("say \"hi\"".into()))
+    );
+    for (text, line) in [
+        ("a = 1\nb", "line 2"),
+        ("a = 1\na = 2", "line 2"),
+        ("a = \"open", "line 1"),
+        ("a = [1, 2", "line 1"),
+        ("a = yes", "line 1"),
+        ("[table", "line 1"),
+    ] {
+        match Config::parse(text) {
+            Err(RusterizerError::Parse(msg)) => assert!(msg.starts_with(line), "{}", msg),
+            result => panic!("{:?} parsed as {:?}", text, result),
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use image::{Rgb, RgbImage};
+
+use crate::color::{self, Color};
+use crate::font;
+
+/// Tiles several renders into one image, row by row, with a label under each tile.
+#[derive(Clone, Debug)]
+pub struct ContactSheet {
+    /// Tiles per row, or 0 for the smallest grid that is at least as wide as it is tall.
+    pub columns: u32,
+    /// Gap in pixels around and between the tiles.
+    pub spacing: u32,
+    pub background: Color,
+    pub label_color: Color,
+}
+
+impl Default for ContactSheet {
+    fn default() -> Self {
+        ContactSheet {
+            columns: 0,
+            spacing: 4,
+            background: color::BLACK,
+            label_color: color::WHITE,
+        }
+    }
+}
+
+impl ContactSheet {
+    /// Columns and rows used for `count` tiles.
+    pub fn grid(&self, count: u32) -> (u32, u32) {
+        let columns = match self.columns {
+            0 => (1..)
+                .find(|columns| columns * columns >= count)
+                .unwrap_or(1),
+            columns => columns,
+        };
+        (columns, count.div_ceil(columns))
+    }
+
+    /// Composes the tiles, each given with its label. Every cell is as large as the
+    /// largest tile; labels that do not fit under a tile are cut off, and an empty label
+    /// leaves the space under its tile blank. No label row is added if all are empty.
+    pub fn compose(&self, tiles: &[(String, RgbImage)]) -> RgbImage {
+        let cell_width = tiles
+            .iter()
+            .map(|(_, tile)| tile.width())
+            .max()
+            .unwrap_or(0);
+        let tile_height = tiles
+            .iter()
+            .map(|(_, tile)| tile.height())
+            .max()
+            .unwrap_or(0);
+        let label_height = if tiles.iter().any(|(label, _)| !label.is_empty()) {
+            font::GLYPH_HEIGHT + self.spacing
+        } else {
+            0
+        };
+        let cell_height = tile_height + label_height;
+        let (columns, rows) = self.grid(tiles.len() as u32);
+        let mut sheet = RgbImage::from_pixel(
+            columns * (cell_width + self.spacing) + self.spacing,
+            rows * (cell_height + self.spacing) + self.spacing,
+            self.background.into(),
+        );
+
+        for (i, (label, tile)) in tiles.iter().enumerate() {
+            let (column, row) = (i as u32 % columns, i as u32 / columns);
+            let x = self.spacing + column * (cell_width + self.spacing);
+            let y = self.spacing + row * (cell_height + self.spacing);
+            // center smaller tiles in their cell
+            let tile_x = x + (cell_width - tile.width()) / 2;
+            let tile_y = y + (tile_height - tile.height()) / 2;
+            image::imageops::replace(&mut sheet, tile, tile_x as i64, tile_y as i64);
+
+            let max_chars = (cell_width / font::GLYPH_WIDTH) as usize;
+            let label: String = label.chars().take(max_chars).collect();
+            let label_width = label.chars().count() as u32 * font::GLYPH_WIDTH;
+            let label_x = x + (cell_width - label_width) / 2;
+            draw_label(
+                &mut sheet,
+                label_x,
+                y + tile_height + self.spacing,
+                &label,
+                self.label_color,
+            );
+        }
+        sheet
+    }
+}
+
+/// Draws `text` with its top-left corner at `(x, y)` of a top-down image.
+fn draw_label(image: &mut RgbImage, x: u32, y: u32, text: &str, color: Color) {
+    for (i, c) in text.chars().enumerate() {
+        let glyph_x = x + i as u32 * font::GLYPH_WIDTH;
+        for (row, bits) in font::glyph(c).iter().enumerate() {
+            for column in 0..font::GLYPH_WIDTH {
+                let (px, py) = (glyph_x + column, y + row as u32);
+                if bits & (1 << column) != 0 && px < image.width() && py < image.height() {
+                    image.put_pixel(px, py, Rgb::from(color));
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_compose() {
+    let sheet = ContactSheet {
+        spacing: 2,
+        ..Default::default()
+    };
+    assert_eq!(sheet.grid(1), (1, 1));
+    assert_eq!(sheet.grid(3), (2, 2));
+    assert_eq!(sheet.grid(5), (3, 2));
+
+    let red = RgbImage::from_pixel(16, 8, Rgb([255, 0, 0]));
+    let blue = RgbImage::from_pixel(16, 8, Rgb([0, 0, 255]));
+    let tiles = vec![
+        ("a".to_string(), red.clone()),
+        (String::new(), blue),
+        ("c".to_string(), red),
+    ];
+    let composed = sheet.compose(&tiles);
+    // two columns of 16 pixels, two rows of 8 pixel tiles with a label row under each
+    assert_eq!(composed.dimensions(), (2 + 2 * 18, 2 + 2 * (8 + 8 + 2 + 2)));
+    assert_eq!(composed.get_pixel(2, 2).0, [255, 0, 0]);
+    assert_eq!(composed.get_pixel(20, 2).0, [0, 0, 255]);
+    assert_eq!(composed.get_pixel(1, 1).0, [0, 0, 0]);
+    // the labels are drawn under the tiles, only where there is one
+    let label_rows = |x0: u32| {
+        (x0..x0 + 16)
+            .flat_map(|x| (12..20).map(move |y| (x, y)))
+            .any(|(x, y)| composed.get_pixel(x, y).0 == [255, 255, 255])
+    };
+    assert!(label_rows(2));
+    assert!(!label_rows(20));
+}
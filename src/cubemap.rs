@@ -0,0 +1,201 @@
+use image::DynamicImage;
+
+use crate::color::HdrColor;
+use crate::environment;
+use crate::error::RusterizerError;
+use crate::math::Vec3f;
+
+/// Side of a cube map, in the order the faces are given in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Face {
+    PositiveX,
+    NegativeX,
+    PositiveY,
+    NegativeY,
+    PositiveZ,
+    NegativeZ,
+}
+
+impl Face {
+    pub const ALL: [Face; 6] = [
+        Face::PositiveX,
+        Face::NegativeX,
+        Face::PositiveY,
+        Face::NegativeY,
+        Face::PositiveZ,
+        Face::NegativeZ,
+    ];
+}
+
+/// Six square images of linear colors on the sides of a cube around the scene, sampled by
+/// direction.
+///
+/// Faces are laid out as in the usual cube map files, seen from inside the cube with the
+/// +Z face in front of the camera, which looks down -Z: +X is on the right and +Y above.
+#[derive(Clone, Debug)]
+pub struct CubeMap {
+    size: u32,
+    faces: [Vec<HdrColor>; 6],
+}
+
+impl CubeMap {
+    /// Takes the faces in the order of [`Face::ALL`]; floating point images are taken as
+    /// linear, everything else as sRGB, like environment maps.
+    pub fn from_faces(faces: &[DynamicImage; 6]) -> Result<Self, RusterizerError> {
+        let size = faces[0].width();
+        if size == 0
+            || faces
+                .iter()
+                .any(|f| f.width() != size || f.height() != size)
+        {
+            return Err(RusterizerError::Texture(
+                "cube map faces must be square and of the same size".to_string(),
+            ));
+        }
+        Ok(CubeMap {
+            size,
+            faces: faces.each_ref().map(environment::linear_pixels),
+        })
+    }
+
+    /// Cuts the faces out of a horizontal cross, four faces wide and three high, or a
+    /// vertical one, three wide and four high with the -Z face upside down at the bottom:
+    ///
+    /// ```text
+    ///     +Y                  +Y
+    /// -X  +Z  +X  -Z      -X  +Z  +X
+    ///     -Y                  -Y
+    ///                         -Z
+    /// ```
+    pub fn from_cross(image: &DynamicImage) -> Result<Self, RusterizerError> {
+        let (width, height) = (image.width(), image.height());
+        let (size, horizontal) = if width * 3 == height * 4 {
+            (width / 4, true)
+        } else if width * 4 == height * 3 {
+            (width / 3, false)
+        } else {
+            return Err(RusterizerError::Texture(format!(
+                "a {}x{} image is not a 4:3 or 3:4 cube map cross",
+                width, height
+            )));
+        };
+        let cell = |column: u32, row: u32| image.crop_imm(column * size, row * size, size, size);
+        let back = if horizontal {
+            cell(3, 1)
+        } else {
+            cell(1, 3).rotate180()
+        };
+        Self::from_faces(&[
+            cell(2, 1),
+            cell(0, 1),
+            cell(1, 0),
+            cell(1, 2),
+            cell(1, 1),
+            back,
+        ])
+    }
+
+    /// Width and height of every face in pixels.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// The face `direction` points at and where, from (0, 0) at the top left corner of the
+    /// face to (1, 1) at the bottom right.
+    pub fn face_coordinates(direction: &Vec3f) -> (Face, f64, f64) {
+        // the cube map convention is left-handed, with +Z in front
+        let (x, y, z) = (direction.x, direction.y, -direction.z);
+        let (ax, ay, az) = (x.abs(), y.abs(), z.abs());
+        let (face, s, t, major) = if ax >= ay && ax >= az {
+            if x > 0.0 {
+                (Face::PositiveX, -z, -y, ax)
+            } else {
+                (Face::NegativeX, z, -y, ax)
+            }
+        } else if ay >= az {
+            if y > 0.0 {
+                (Face::PositiveY, x, z, ay)
+            } else {
+                (Face::NegativeY, x, -z, ay)
+            }
+        } else if z > 0.0 {
+            (Face::PositiveZ, x, -y, az)
+        } else {
+            (Face::NegativeZ, -x, -y, az)
+        };
+        if major == 0.0 {
+            return (Face::PositiveZ, 0.5, 0.5);
+        }
+        (face, (s / major + 1.0) / 2.0, (t / major + 1.0) / 2.0)
+    }
+
+    /// Bilinear lookup within the face `direction` points at.
+    pub fn sample(&self, direction: &Vec3f) -> HdrColor {
+        let (face, u, v) = Self::face_coordinates(direction);
+        let pixels = &self.faces[face as usize];
+        let last = self.size as f64 - 1.0;
+        let x = (u * self.size as f64 - 0.5).clamp(0.0, last);
+        let y = (v * self.size as f64 - 0.5).clamp(0.0, last);
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = ((x - x0) as f32, (y - y0) as f32);
+        let texel = |x: f64, y: f64| {
+            let (x, y) = (x.min(last) as u32, y.min(last) as u32);
+            pixels[(y * self.size + x) as usize]
+        };
+        let lerp = |a: HdrColor, b: HdrColor, t: f32| {
+            HdrColor(
+                a.0 + (b.0 - a.0) * t,
+                a.1 + (b.1 - a.1) * t,
+                a.2 + (b.2 - a.2) * t,
+            )
+        };
+        let top = lerp(texel(x0, y0), texel(x0 + 1.0, y0), tx);
+        let bottom = lerp(texel(x0, y0 + 1.0), texel(x0 + 1.0, y0 + 1.0), tx);
+        lerp(top, bottom, ty)
+    }
+}
+
+#[test]
+fn test_cross_faces() {
+    use image::{Rgb, RgbImage};
+
+    // every face a different shade of red, by its index
+    let shade = |face: Face| Rgb([face as u8 * 40 + 10, 0, 0]);
+    let cells = [
+        (2, 1, Face::PositiveX),
+        (0, 1, Face::NegativeX),
+        (1, 0, Face::PositiveY),
+        (1, 2, Face::NegativeY),
+        (1, 1, Face::PositiveZ),
+        (3, 1, Face::NegativeZ),
+    ];
+    let cross = RgbImage::from_fn(16, 12, |x, y| {
+        cells
+            .iter()
+            .find(|&&(column, row, _)| (column, row) == (x / 4, y / 4))
+            .map_or(Rgb([0, 255, 0]), |&(_, _, face)| shade(face))
+    });
+    let cube = CubeMap::from_cross(&DynamicImage::ImageRgb8(cross)).unwrap();
+    assert_eq!(cube.size(), 4);
+
+    let directions = [
+        (Vec3f::new(1., 0., 0.), Face::PositiveX),
+        (Vec3f::new(-1., 0.2, 0.), Face::NegativeX),
+        (Vec3f::new(0.3, 1., 0.), Face::PositiveY),
+        (Vec3f::new(0., -1., -0.5), Face::NegativeY),
+        (Vec3f::new(0., 0., -1.), Face::PositiveZ),
+        (Vec3f::new(-0.1, 0.1, 1.), Face::NegativeZ),
+    ];
+    for (direction, face) in directions {
+        assert_eq!(CubeMap::face_coordinates(&direction).0, face);
+        let expected = HdrColor::from(crate::color::Color::from(shade(face)));
+        assert_eq!(cube.sample(&direction), expected);
+    }
+
+    // in front of the camera, right is right and up is up
+    let (_, u, v) = CubeMap::face_coordinates(&Vec3f::new(0.5, 0.5, -1.));
+    assert!(u > 0.5 && v < 0.5);
+
+    let square = DynamicImage::new_rgb8(8, 8);
+    assert!(CubeMap::from_cross(&square).is_err());
+}
@@ -0,0 +1,618 @@
+use std::f64::consts::PI;
+
+use image::RgbImage;
+
+use crate::camera::Viewport;
+use crate::color::HdrColor;
+use crate::drawable::{rasterize, sample_texture, Drawable, Image};
+use crate::environment::EnvironmentMap;
+use crate::light::{HemisphereLight, Light};
+use crate::math::{self, Aabb, Mat4, Vec3f};
+use crate::mesh::Mesh;
+use crate::ray::Ray;
+use crate::spatial::Bvh;
+
+/// How far shadow rays start off the surface along its normal, so that they do not hit
+/// the face they leave.
+const SHADOW_BIAS: f64 = 1e-4;
+
+/// What [`GBuffer::resolve`] lights the surfaces with.
+#[derive(Clone, Copy, Default)]
+pub struct Lighting<'a> {
+    pub lights: &'a [Light],
+    /// Geometry casting the shadows of lights with [`Light::ray_traced_shadows`], built
+    /// with the same model transform as the meshes drawn into the buffer.
+    pub occluders: &'a [Bvh],
+    /// Environment lighting every surface diffusely.
+    pub ambient: Option<&'a EnvironmentMap>,
+    /// Sky and ground light added to the ambient light, wrapping the Lambert terms around.
+    pub hemisphere: Option<HemisphereLight>,
+    /// Environment mirrored by materials with a reflectivity, or by every surface with
+    /// physically based shading.
+    pub reflections: Option<&'a EnvironmentMap>,
+    /// Shade with the metallic-roughness model instead of Lambert terms and the
+    /// reflectivity.
+    pub physically_based: bool,
+}
+
+/// Per-pixel surface attributes written by the geometry pass and consumed by
+/// [`GBuffer::resolve`], which lights every covered pixel exactly once.
+///
+/// Rows are stored bottom-up like [`Image`]; uncovered pixels have a depth of negative
+/// infinity.
+pub struct GBuffer {
+    width: u32,
+    height: u32,
+    /// Positions after the model transform.
+    pub position: Vec<Vec3f>,
+    /// Unit normals after the model transform.
+    pub normal: Vec<Vec3f>,
+    /// Linear surface color before lighting.
+    pub albedo: Vec<HdrColor>,
+    /// Material reflectivity, see [`crate::mesh::Material::reflectivity`].
+    pub reflectivity: Vec<f32>,
+    /// Material metalness, see [`crate::mesh::Material::metallic`].
+    pub metallic: Vec<f32>,
+    /// Material roughness, see [`crate::mesh::Material::roughness`].
+    pub roughness: Vec<f32>,
+    /// Ambient light reaching the surface, see
+    /// [`crate::mesh::Material::occlusion_texture`].
+    pub occlusion: Vec<f32>,
+    /// Depth, larger values being nearer as in the z-buffer of [`Image`].
+    pub depth: Vec<f64>,
+    projection: Option<Mat4>,
+    viewport: Viewport,
+}
+
+impl GBuffer {
+    pub fn new(width: u32, height: u32) -> Self {
+        let size = (width * height) as usize;
+        GBuffer {
+            width,
+            height,
+            position: vec![Vec3f::new(0., 0., 0.); size],
+            normal: vec![Vec3f::new(0., 0., 0.); size],
+            albedo: vec![HdrColor::default(); size],
+            reflectivity: vec![0.0; size],
+            metallic: vec![0.0; size],
+            roughness: vec![1.0; size],
+            occlusion: vec![1.0; size],
+            depth: vec![f64::NEG_INFINITY; size],
+            projection: None,
+            viewport: Viewport::full(width, height),
+        }
+    }
+
+    /// Projects the geometry like [`Image::set_projection`], clipping it against the near
+    /// and far planes; positions and normals stay in view space.
+    pub fn set_projection(&mut self, projection: Option<Mat4>) {
+        self.projection = projection;
+    }
+
+    /// Maps the geometry into a rectangle of the buffer like [`Image::set_viewport`].
+    pub fn set_viewport(&mut self, viewport: Viewport) {
+        self.viewport = viewport;
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn is_covered(&self, x: u32, y: u32) -> bool {
+        self.depth[(y * self.width + x) as usize] > f64::NEG_INFINITY
+    }
+
+    /// Geometry pass: rasterizes the front-facing triangles of `mesh` transformed by
+    /// `model`, keeping the attributes of the nearest surface in every pixel.
+    ///
+    /// The albedo comes from `texture` (or the material's) when the mesh has UVs, then
+    /// from vertex colors, then from the material color. Meshes without normals get flat
+    /// face normals.
+    pub fn draw_mesh(&mut self, mesh: &Mesh, model: &Mat4, texture: Option<&RgbImage>) {
+        let view = Aabb {
+            min: Vec3f::new(-1., -1., f64::NEG_INFINITY),
+            max: Vec3f::new(1., 1., f64::INFINITY),
+        };
+        let projection = self.projection.unwrap_or_else(Mat4::identity);
+        if let Some(bounds) = mesh.bounds() {
+            if !view.overlaps(&bounds.transformed(&(projection * *model))) {
+                return;
+            }
+        }
+        let clip = self.projection.is_some();
+        let texture = texture
+            .or(mesh.material.base_color_texture.as_deref())
+            .filter(|_| mesh.has_uvs());
+        let base_color = HdrColor::from(mesh.material.base_color);
+        let reflectivity = mesh.material.reflectivity.clamp(0.0, 1.0) as f32;
+        let metallic_roughness = mesh
+            .material
+            .metallic_roughness_texture
+            .as_ref()
+            .filter(|_| mesh.has_uvs());
+        let occlusion = mesh
+            .material
+            .occlusion_texture
+            .as_ref()
+            .filter(|_| mesh.has_uvs());
+        let normal_matrix = model
+            .inverse()
+            .map_or(*model, |inverse| inverse.transpose());
+
+        for &[idx1, idx2, idx3] in &mesh.indices {
+            let v = [idx1, idx2, idx3].map(|idx| model.transform_point(&mesh.positions[idx]));
+            let face_normal = math::cross(&(v[1] - v[0]), &(v[2] - v[0])).normalized();
+            if face_normal.z < 0.0 {
+                // facing away
+                continue;
+            }
+            let to_screen = |v: &Vec3f| self.viewport.to_screen(&projection.transform_point(v));
+            let (p1, p2, p3) = (to_screen(&v[0]), to_screen(&v[1]), to_screen(&v[2]));
+            let normals = [idx1, idx2, idx3].map(|idx| {
+                if mesh.has_normals() {
+                    normal_matrix.transform_vector(&mesh.normals[idx])
+                } else {
+                    face_normal
+                }
+            });
+
+            let width = self.width;
+            rasterize(width, self.height, &p1, &p2, &p3, |x, y, (a, b, c), z| {
+                let idx = (y * width + x) as usize;
+                let outside = !self.viewport.contains(x, y) || (clip && !(-1.0..=1.0).contains(&z));
+                if self.depth[idx] >= z || outside {
+                    return;
+                }
+                let mix = |x: [Vec3f; 3]| x[0] * a + x[1] * b + x[2] * c;
+                self.depth[idx] = z;
+                self.position[idx] = mix(v);
+                self.normal[idx] = mix(normals).normalized();
+                self.reflectivity[idx] = reflectivity;
+                let uv = || {
+                    let uvs = [mesh.uvs[idx1], mesh.uvs[idx2], mesh.uvs[idx3]];
+                    let u = a * uvs[0][0] + b * uvs[1][0] + c * uvs[2][0];
+                    let v = a * uvs[0][1] + b * uvs[1][1] + c * uvs[2][1];
+                    (u, v)
+                };
+                let (mut metallic, mut roughness) =
+                    (mesh.material.metallic, mesh.material.roughness);
+                if let Some(tex) = metallic_roughness {
+                    let (u, v) = uv();
+                    let [_, g, b, _] = tex.sample(u, v);
+                    roughness *= g as f64;
+                    metallic *= b as f64;
+                }
+                self.metallic[idx] = metallic.clamp(0.0, 1.0) as f32;
+                self.roughness[idx] = roughness.clamp(0.0, 1.0) as f32;
+                self.occlusion[idx] = occlusion.map_or(1.0, |tex| {
+                    let (u, v) = uv();
+                    tex.sample(u, v)[0]
+                });
+                self.albedo[idx] = if let Some(tex) = texture {
+                    let (u, v) = uv();
+                    HdrColor::from(sample_texture(tex, u, v))
+                } else if mesh.has_colors() {
+                    let colors = [idx1, idx2, idx3].map(|idx| HdrColor::from(mesh.colors[idx]));
+                    let mix = |x1: f32, x2: f32, x3: f32| {
+                        (a * x1 as f64 + b * x2 as f64 + c * x3 as f64) as f32
+                    };
+                    HdrColor(
+                        mix(colors[0].0, colors[1].0, colors[2].0),
+                        mix(colors[0].1, colors[1].1, colors[2].1),
+                        mix(colors[0].2, colors[1].2, colors[2].2),
+                    )
+                } else {
+                    base_color
+                };
+            });
+        }
+    }
+
+    /// Lighting pass: shades every covered pixel with the sum of the Lambert terms of the
+    /// lights not shadowed from the pixel, dimmed by their falloff, plus the diffuse
+    /// ambient light, mixes in the mirrored environment by the material's reflectivity,
+    /// and writes the result along with its depth into `image`. Ambient light and
+    /// reflections are darkened by the occlusion.
+    pub fn resolve(&self, image: &mut Image, lighting: &Lighting) {
+        let mut lights = Vec::with_capacity(lighting.lights.len());
+        // the projection is orthographic, so every pixel is viewed along -Z
+        let view = Vec3f::new(0., 0., -1.);
+        let width = self.width.min(image.width());
+        let height = self.height.min(image.height());
+        for y in 0..height {
+            for x in 0..width {
+                let idx = (y * self.width + x) as usize;
+                if !self.is_covered(x, y) || !image.check_and_set_zbuf(x, y, self.depth[idx]) {
+                    continue;
+                }
+                lights.clear();
+                lights.extend(
+                    lighting
+                        .lights
+                        .iter()
+                        .filter_map(|light| self.incident_light(idx, light, lighting)),
+                );
+                if lighting.physically_based {
+                    let color = self.shade_physically_based(idx, &lights, lighting);
+                    image.point_hdr(x, y, color);
+                    continue;
+                }
+                let normal = &self.normal[idx];
+                let albedo = self.albedo[idx];
+                let intensity: f64 = lights
+                    .iter()
+                    .map(|(direction, attenuation)| {
+                        let n_dot_l = math::dot(normal, direction);
+                        let diffuse = match &lighting.hemisphere {
+                            Some(hemisphere) => hemisphere.wrapped_diffuse(n_dot_l),
+                            None => n_dot_l,
+                        };
+                        diffuse * attenuation
+                    })
+                    .sum();
+                let mut light = HdrColor(1.0, 1.0, 1.0).scale(intensity);
+                let occlusion = self.occlusion[idx] as f64;
+                if let Some(hemisphere) = &lighting.hemisphere {
+                    light = add(light, hemisphere.irradiance(normal).scale(occlusion));
+                }
+                if let Some(environment) = lighting.ambient {
+                    light = add(light, environment.irradiance(normal).scale(occlusion));
+                }
+                let mut color = modulate(albedo, light);
+
+                let reflectivity = self.reflectivity[idx];
+                if let (Some(environment), true) = (lighting.reflections, reflectivity > 0.0) {
+                    let reflected = view - *normal * (2.0 * math::dot(&view, normal));
+                    // metals tint their reflections with their own color
+                    let mirrored =
+                        modulate(albedo, environment.sample(&reflected)).scale(occlusion);
+                    color = add(
+                        color.scale(1.0 - reflectivity as f64),
+                        mirrored.scale(reflectivity as f64),
+                    );
+                }
+                image.point_hdr(x, y, color);
+            }
+        }
+    }
+
+    /// Direction from the surface in pixel `idx` towards `light` and the fraction of the
+    /// light reaching it, or `None` if the surface faces away beyond the wrap of the
+    /// lighting or a shadow ray towards the light hits any of its occluders. Shadow rays
+    /// are only cast for lit surfaces.
+    fn incident_light(
+        &self,
+        idx: usize,
+        light: &Light,
+        lighting: &Lighting,
+    ) -> Option<(Vec3f, f64)> {
+        let normal = self.normal[idx];
+        let incident = light.incident(&self.position[idx])?;
+        let wrap = lighting
+            .hemisphere
+            .map_or(0.0, |hemisphere| hemisphere.wrap);
+        if math::dot(&normal, &incident.direction) <= -wrap || incident.attenuation <= 0.0 {
+            return None;
+        }
+        if light.ray_traced_shadows {
+            let ray = Ray {
+                far: incident.distance,
+                ..Ray::new(
+                    self.position[idx] + normal * SHADOW_BIAS,
+                    incident.direction,
+                )
+            };
+            if lighting.occluders.iter().any(|bvh| bvh.occludes(&ray)) {
+                return None;
+            }
+        }
+        Some((incident.direction, incident.attenuation))
+    }
+
+    /// Cook-Torrance shading of a pixel with the GGX distribution, the Smith-Schlick
+    /// geometry term and Schlick's Fresnel approximation. Lights have a radiance of pi so
+    /// that a rough white dielectric lit head-on is as bright as with the Lambert terms.
+    fn shade_physically_based(
+        &self,
+        idx: usize,
+        lights: &[(Vec3f, f64)],
+        lighting: &Lighting,
+    ) -> HdrColor {
+        let normal = self.normal[idx];
+        let albedo = self.albedo[idx];
+        let metallic = self.metallic[idx] as f64;
+        // perfectly smooth surfaces would reflect the lights in single pixels
+        let roughness = (self.roughness[idx] as f64).max(0.03);
+        let alpha2 = roughness.powi(4);
+        // towards the viewer
+        let view = Vec3f::new(0., 0., 1.);
+        let n_dot_v = math::dot(&normal, &view).max(1e-4);
+        let f0 = lerp(HdrColor(0.04, 0.04, 0.04), albedo, metallic);
+        let k = (roughness + 1.0).powi(2) / 8.0;
+        let geometry = |x: f64| x / (x * (1.0 - k) + k);
+
+        let mut color = HdrColor::default();
+        for (light, attenuation) in lights {
+            let n_dot_l = math::dot(&normal, light);
+            if n_dot_l <= 0.0 {
+                continue;
+            }
+            let half = (*light + view).normalized();
+            let n_dot_h = math::dot(&normal, &half).max(0.0);
+            let distribution = alpha2 / (PI * (n_dot_h * n_dot_h * (alpha2 - 1.0) + 1.0).powi(2));
+            let fresnel = fresnel(f0, math::dot(&view, &half).max(0.0));
+            let specular =
+                distribution * geometry(n_dot_l) * geometry(n_dot_v) / (4.0 * n_dot_l * n_dot_v);
+            let diffuse = modulate(albedo, fresnel.map(|f| 1.0 - f)).scale((1.0 - metallic) / PI);
+            let reflected = add(diffuse, fresnel.scale(specular));
+            color = add(color, reflected.scale(PI * n_dot_l * attenuation));
+        }
+
+        let fresnel = fresnel(f0, n_dot_v);
+        let occlusion = self.occlusion[idx] as f64;
+        let diffuse = fresnel.map(|f| (1.0 - f) * (1.0 - metallic as f32));
+        if let Some(environment) = lighting.ambient {
+            let ambient = modulate(environment.irradiance(&normal), albedo);
+            color = add(color, modulate(ambient, diffuse).scale(occlusion));
+        }
+        if let Some(hemisphere) = &lighting.hemisphere {
+            let ambient = modulate(hemisphere.irradiance(&normal), albedo);
+            color = add(color, modulate(ambient, diffuse).scale(occlusion));
+        }
+        if let Some(environment) = lighting.reflections {
+            let reflected = normal * (2.0 * n_dot_v) - view;
+            // rougher surfaces blur the reflection towards the diffuse irradiance
+            let radiance = lerp(
+                environment.sample(&reflected),
+                environment.irradiance(&reflected),
+                roughness,
+            );
+            let (scale, bias) = environment_brdf(roughness, n_dot_v);
+            let specular = f0.map(|f| f * scale + bias);
+            color = add(color, modulate(radiance, specular).scale(occlusion));
+        }
+        color
+    }
+}
+
+/// Schlick's approximation of the reflected fraction at `cos_theta` from the normal.
+fn fresnel(f0: HdrColor, cos_theta: f64) -> HdrColor {
+    let weight = (1.0 - cos_theta).clamp(0.0, 1.0).powi(5) as f32;
+    f0.map(|f| f + (1.0 - f) * weight)
+}
+
+/// Scale and bias of the specular color that integrate the BRDF over the hemisphere for
+/// environment lighting, fitted by Karis for mobile shading.
+fn environment_brdf(roughness: f64, n_dot_v: f64) -> (f32, f32) {
+    let c0 = [-1.0, -0.0275, -0.572, 0.022];
+    let c1 = [1.0, 0.0425, 1.04, -0.04];
+    let r = [0, 1, 2, 3].map(|i| roughness * c0[i] + c1[i]);
+    let a004 = (r[0] * r[0]).min((-9.28 * n_dot_v).exp2()) * r[0] + r[1];
+    ((-1.04 * a004 + r[2]) as f32, (1.04 * a004 + r[3]) as f32)
+}
+
+fn lerp(a: HdrColor, b: HdrColor, t: f64) -> HdrColor {
+    let t = t as f32;
+    HdrColor(
+        a.0 + (b.0 - a.0) * t,
+        a.1 + (b.1 - a.1) * t,
+        a.2 + (b.2 - a.2) * t,
+    )
+}
+
+fn add(a: HdrColor, b: HdrColor) -> HdrColor {
+    HdrColor(a.0 + b.0, a.1 + b.1, a.2 + b.2)
+}
+
+fn modulate(a: HdrColor, b: HdrColor) -> HdrColor {
+    HdrColor(a.0 * b.0, a.1 * b.1, a.2 * b.2)
+}
+
+#[test]
+fn test_geometry_and_lighting_pass() {
+    use crate::color::Color;
+    use crate::light::Falloff;
+
+    let mut mesh = crate::geometry::plane(1.5, 1.5, 1);
+    mesh.transform(&Mat4::rotation_x(std::f64::consts::FRAC_PI_2));
+    mesh.material.base_color = Color(200, 100, 50);
+
+    let mut gbuffer = GBuffer::new(32, 32);
+    gbuffer.draw_mesh(&mesh, &Mat4::identity(), None);
+    assert!(gbuffer.is_covered(16, 16));
+    assert!(!gbuffer.is_covered(0, 0));
+    let center = (16 * 32 + 16) as usize;
+    assert!((gbuffer.normal[center] - Vec3f::new(0., 0., 1.)).length() < 1e-9);
+
+    let mut image = Image::new(32, 32);
+    let lighting = Lighting {
+        lights: &[Light::directional(Vec3f::new(0., 0., 1.)).unwrap()],
+        ..Default::default()
+    };
+    gbuffer.resolve(&mut image, &lighting);
+    let pixels = image.to_rgb_image();
+    assert_eq!(pixels.get_pixel(16, 16).0, [200, 100, 50]);
+    assert_eq!(pixels.get_pixel(0, 0).0, [0, 0, 0]);
+
+    // two lights at 60 degrees add up to the same brightness as one head-on
+    let mut image = Image::new(32, 32);
+    let sqrt3 = 3f64.sqrt();
+    let lights = [
+        Light::directional(Vec3f::new(sqrt3, 0., 1.)).unwrap(),
+        Light::directional(Vec3f::new(-sqrt3, 0., 1.)).unwrap(),
+    ];
+    let lighting = Lighting {
+        lights: &lights,
+        ..Default::default()
+    };
+    gbuffer.resolve(&mut image, &lighting);
+    assert_eq!(image.to_rgb_image().get_pixel(16, 16)[0], 200);
+
+    // a point light lights the surface right below it most, dimmed by its falloff
+    let above = Vec3f::new(0., 0., 2.);
+    let render = |falloff: Falloff| {
+        let mut image = Image::new(32, 32);
+        let lighting = Lighting {
+            lights: &[Light::point(above, falloff).unwrap()],
+            ..Default::default()
+        };
+        gbuffer.resolve(&mut image, &lighting);
+        let pixels = image.to_rgb_image();
+        (pixels.get_pixel(16, 16)[0], pixels.get_pixel(4, 16)[0])
+    };
+    let (center, edge) = render(Falloff::NONE);
+    assert!(center > 195 && edge < center);
+    let (dimmed, dimmed_edge) = render(Falloff::INVERSE_SQUARE);
+    assert!(
+        dimmed < 120 && dimmed_edge < dimmed,
+        "{} {}",
+        dimmed,
+        dimmed_edge
+    );
+}
+
+#[test]
+fn test_ambient_environment() {
+    let mut mesh = crate::geometry::plane(1.5, 1.5, 1);
+    mesh.transform(&Mat4::rotation_x(std::f64::consts::FRAC_PI_2));
+    let mut gbuffer = GBuffer::new(16, 16);
+    gbuffer.draw_mesh(&mesh, &Mat4::identity(), None);
+
+    // a uniform environment alone lights a white surface to its own color
+    let sky = image::RgbImage::from_pixel(32, 16, image::Rgb([80, 80, 80]));
+    let environment = EnvironmentMap::from_image(&image::DynamicImage::ImageRgb8(sky));
+    let mut image = Image::new(16, 16);
+    let lighting = Lighting {
+        ambient: Some(&environment),
+        ..Default::default()
+    };
+    gbuffer.resolve(&mut image, &lighting);
+    let value = image.to_rgb_image().get_pixel(8, 8)[0];
+    assert!((value as i32 - 80).abs() <= 2, "{}", value);
+
+    // and an occlusion texture lets only part of it through
+    let mut occluded = mesh.clone();
+    let half = image::GrayImage::from_pixel(4, 4, image::Luma([128]));
+    occluded.material.occlusion_texture = Some(crate::texture::Texture::from_image(
+        &image::DynamicImage::ImageLuma8(half),
+        crate::texture::TextureFormat::SCALAR,
+    ));
+    let mut gbuffer = GBuffer::new(16, 16);
+    gbuffer.draw_mesh(&occluded, &Mat4::identity(), None);
+    let mut image = Image::new(16, 16);
+    gbuffer.resolve(&mut image, &lighting);
+    let dimmed = image.to_rgb_image().get_pixel(8, 8)[0];
+    assert!(dimmed > 20 && dimmed < value - 20, "{}", dimmed);
+}
+
+#[test]
+fn test_reflections() {
+    let mut mesh = crate::geometry::plane(1.5, 1.5, 1);
+    mesh.transform(&Mat4::rotation_x(std::f64::consts::FRAC_PI_2));
+    mesh.material.reflectivity = 1.0;
+    let mut gbuffer = GBuffer::new(16, 16);
+    gbuffer.draw_mesh(&mesh, &Mat4::identity(), None);
+
+    // the environment behind the viewer is red, the one in front blue
+    let sky = image::RgbImage::from_fn(64, 32, |x, _| {
+        let behind = !(16..48).contains(&x);
+        image::Rgb(if behind { [255, 0, 0] } else { [0, 0, 255] })
+    });
+    let environment = EnvironmentMap::from_image(&image::DynamicImage::ImageRgb8(sky));
+    let mut image = Image::new(16, 16);
+    let lighting = Lighting {
+        lights: &[Light::directional(Vec3f::new(0., 0., 1.)).unwrap()],
+        reflections: Some(&environment),
+        ..Default::default()
+    };
+    gbuffer.resolve(&mut image, &lighting);
+    // a mirror facing the viewer shows what is behind the viewer
+    assert_eq!(image.to_rgb_image().get_pixel(8, 8).0, [255, 0, 0]);
+}
+
+#[test]
+fn test_physically_based() {
+    use crate::color::Color;
+
+    let mut mesh = crate::geometry::plane(1.5, 1.5, 1);
+    mesh.transform(&Mat4::rotation_x(std::f64::consts::FRAC_PI_2));
+    mesh.material.base_color = Color(200, 100, 50);
+    let render = |mesh: &Mesh, lights: &[Light], environment: Option<&EnvironmentMap>| {
+        let mut gbuffer = GBuffer::new(16, 16);
+        gbuffer.draw_mesh(mesh, &Mat4::identity(), None);
+        let mut image = Image::new(16, 16);
+        let lighting = Lighting {
+            lights,
+            reflections: environment,
+            physically_based: true,
+            ..Default::default()
+        };
+        gbuffer.resolve(&mut image, &lighting);
+        image.to_rgb_image().get_pixel(8, 8).0
+    };
+    let head_on = [Light::directional(Vec3f::new(0., 0., 1.)).unwrap()];
+    let grazing = [Light::directional(Vec3f::new(1., 0., 0.5)).unwrap()];
+
+    // a rough dielectric is about as bright as with the Lambert term, with a faint white
+    // specular sheen
+    let [r, g, b] = render(&mesh, &head_on, None);
+    assert!(r.abs_diff(200) <= 4 && g.abs_diff(100) <= 4 && b.abs_diff(50) <= 8);
+
+    // a polished metal has no diffuse light, only a highlight of its own color
+    mesh.material.metallic = 1.0;
+    mesh.material.roughness = 0.2;
+    assert_eq!(render(&mesh, &head_on, None), [255, 255, 255]);
+    assert!(render(&mesh, &grazing, None).iter().all(|&c| c < 10));
+
+    // and mirrors the environment tinted by it
+    let sky = image::RgbImage::from_pixel(32, 16, image::Rgb([255, 255, 255]));
+    let environment = EnvironmentMap::from_image(&image::DynamicImage::ImageRgb8(sky));
+    let [r, g, b] = render(&mesh, &[], Some(&environment));
+    assert!(r > g && g > b && r > 150);
+}
+
+#[test]
+fn test_ray_traced_shadows() {
+    // a small square hovering over a larger one, both facing the viewer
+    let mut floor = crate::geometry::plane(1.6, 1.6, 1);
+    floor.transform(&Mat4::rotation_x(std::f64::consts::FRAC_PI_2));
+    let mut blocker = crate::geometry::plane(0.4, 0.4, 1);
+    blocker.transform(&Mat4::rotation_x(std::f64::consts::FRAC_PI_2));
+    for position in &mut blocker.positions {
+        position.z += 0.5;
+    }
+    let meshes = [floor, blocker];
+    let mut gbuffer = GBuffer::new(32, 32);
+    for mesh in &meshes {
+        gbuffer.draw_mesh(mesh, &Mat4::identity(), None);
+    }
+    let occluders: Vec<Bvh> = meshes
+        .iter()
+        .map(|mesh| Bvh::build(mesh, &Mat4::identity()))
+        .collect();
+
+    // light from the right at 45 degrees throws the shadow of the blocker half a unit to
+    // the left, onto pixel 8
+    let render = |ray_traced_shadows: bool| {
+        let mut light = Light::directional(Vec3f::new(1., 0., 1.)).unwrap();
+        light.ray_traced_shadows = ray_traced_shadows;
+        let mut image = Image::new(32, 32);
+        let lighting = Lighting {
+            lights: &[light],
+            occluders: &occluders,
+            ..Default::default()
+        };
+        gbuffer.resolve(&mut image, &lighting);
+        image.to_rgb_image()
+    };
+    let (lit, shadowed) = (render(false), render(true));
+    assert_eq!(lit.get_pixel(8, 16), lit.get_pixel(24, 16));
+    assert_eq!(shadowed.get_pixel(8, 16).0, [0, 0, 0]);
+    // neither the blocker nor the floor beside the shadow shade themselves
+    assert_eq!(shadowed.get_pixel(16, 16), lit.get_pixel(16, 16));
+    assert_eq!(shadowed.get_pixel(24, 16), lit.get_pixel(24, 16));
+}
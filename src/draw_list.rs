@@ -0,0 +1,221 @@
+use image::RgbImage;
+
+use crate::drawable::{DepthBias, Image};
+use crate::math::Mat4;
+use crate::mesh::Mesh;
+use crate::render::{draw_geometry, process_geometry};
+use crate::DrawStyle;
+
+/// A mesh to be drawn by a [`DrawList`].
+#[derive(Clone, Copy)]
+pub struct DrawCommand<'a> {
+    pub mesh: &'a Mesh,
+    pub model: Mat4,
+    pub style: DrawStyle<'a, 'a>,
+    /// Object ID written to the ID buffer, the index of the command unless changed.
+    pub object: u32,
+    pub depth_bias: DepthBias,
+}
+
+/// Draw commands recorded without touching any pixels, to be drawn together by
+/// [`DrawList::execute`] as often as needed, for example once for every view.
+#[derive(Clone)]
+pub struct DrawList<'a> {
+    commands: Vec<DrawCommand<'a>>,
+    sort_by_state: bool,
+}
+
+impl Default for DrawList<'_> {
+    fn default() -> Self {
+        DrawList {
+            commands: Vec::new(),
+            sort_by_state: true,
+        }
+    }
+}
+
+impl<'a> DrawList<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether [`execute`](Self::execute) groups the commands by style and texture, so that
+    /// the fragment loop keeps reading the same texels, rather than drawing them in the
+    /// order they were recorded. On by default; only faces at exactly the same depth
+    /// can come out differently, as the first one drawn stays in front.
+    pub fn set_state_sorting(&mut self, sort: bool) {
+        self.sort_by_state = sort;
+    }
+
+    /// Records drawing `mesh` transformed by `model` as
+    /// [`draw_mesh`](crate::render::draw_mesh) would, returning the command for its
+    /// object ID and depth bias to be changed.
+    pub fn draw_mesh(
+        &mut self,
+        mesh: &'a Mesh,
+        model: &Mat4,
+        style: DrawStyle<'a, 'a>,
+    ) -> &mut DrawCommand<'a> {
+        let object = self.commands.len() as u32;
+        self.commands.push(DrawCommand {
+            mesh,
+            model: *model,
+            style,
+            object,
+            depth_bias: DepthBias::default(),
+        });
+        self.commands.last_mut().expect("just pushed")
+    }
+
+    pub fn commands(&self) -> &[DrawCommand<'a>] {
+        &self.commands
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.commands.clear();
+    }
+
+    /// Order in which [`execute`](Self::execute) draws the commands, as indices into
+    /// [`commands`](Self::commands).
+    pub fn draw_order(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.commands.len()).collect();
+        if self.sort_by_state {
+            // stable, so commands sharing a state keep their recorded order
+            order.sort_by_key(|&index| state_key(&self.commands[index].style));
+        }
+        order
+    }
+
+    /// Draws every command into `image` with `view` applied after its model transform:
+    /// the meshes are transformed together on up to `threads` threads, then rasterized in
+    /// [`draw_order`](Self::draw_order). The image's depth bias is kept.
+    pub fn execute(&self, image: &mut Image, view: &Mat4, threads: usize) {
+        let models: Vec<Mat4> = self
+            .commands
+            .iter()
+            .map(|command| *view * command.model)
+            .collect();
+        let objects: Vec<(&Mesh, &Mat4)> = self
+            .commands
+            .iter()
+            .zip(&models)
+            .map(|(command, model)| (command.mesh, model))
+            .collect();
+        let geometries = process_geometry(image, &objects, threads);
+        let depth_bias = image.depth_bias();
+        for index in self.draw_order() {
+            let Some(geometry) = &geometries[index] else {
+                continue;
+            };
+            if image.is_cancelled() {
+                break;
+            }
+            let command = &self.commands[index];
+            image.set_object_id(command.object);
+            image.set_depth_bias(command.depth_bias);
+            draw_geometry(
+                image,
+                command.mesh,
+                geometry,
+                &command.style,
+                &models[index],
+            );
+        }
+        image.set_depth_bias(depth_bias);
+    }
+}
+
+/// Key grouping styles that shade alike: by kind, then by the address of the texture they
+/// sample.
+fn state_key(style: &DrawStyle) -> (u8, usize) {
+    match style {
+        DrawStyle::Wireframe(_) => (0, 0),
+        DrawStyle::Filled(_) => (1, 0),
+        DrawStyle::FilledRandom(_) => (2, 0),
+        DrawStyle::PerFace(_) => (3, 0),
+        DrawStyle::VertexColors { .. } => (4, 0),
+        DrawStyle::Textured(texture, _) => (5, *texture as *const RgbImage as usize),
+        DrawStyle::Matcap(texture, _) => (6, *texture as *const RgbImage as usize),
+    }
+}
+
+#[test]
+fn test_draw_list() {
+    use crate::color::Color;
+    use crate::render::draw_mesh;
+
+    let sphere = crate::geometry::sphere(0.5, 16, 8);
+    let mut cube = crate::geometry::cuboid(crate::math::Vec3f::new(0.6, 0.6, 0.6));
+    cube.transform(&Mat4::rotation_y(0.4));
+    let red = DrawStyle::Filled(Color(255, 0, 0));
+    let wire = DrawStyle::Wireframe(Color(0, 255, 0));
+
+    let mut list = DrawList::new();
+    list.draw_mesh(&cube, &Mat4::identity(), red);
+    let command = list.draw_mesh(&sphere, &Mat4::rotation_x(0.3), wire);
+    command.depth_bias = DepthBias {
+        constant: 0.01,
+        slope_scale: 0.0,
+    };
+    assert_eq!(list.len(), 2);
+
+    // the same list drawn from two views matches drawing each view directly
+    for view in [Mat4::identity(), Mat4::rotation_y(1.0)] {
+        let mut direct = Image::new(32, 32);
+        direct.enable_id_buffer();
+        direct.set_object_id(0);
+        draw_mesh(&mut direct, &cube, &red, &view);
+        direct.set_object_id(1);
+        direct.set_depth_bias(list.commands()[1].depth_bias);
+        draw_mesh(&mut direct, &sphere, &wire, &(view * Mat4::rotation_x(0.3)));
+
+        let mut listed = Image::new(32, 32);
+        listed.enable_id_buffer();
+        list.execute(&mut listed, &view, 2);
+        assert_eq!(direct.to_rgb_image(), listed.to_rgb_image());
+        assert_eq!(direct.id_buffer(), listed.id_buffer());
+        assert_eq!(listed.depth_bias(), DepthBias::default());
+    }
+}
+
+#[test]
+fn test_state_sorting() {
+    use crate::color::Color;
+    use crate::drawable::Point3f;
+
+    let quad = crate::geometry::plane(1.0, 1.0, 1);
+    let (brick, wood) = (RgbImage::new(2, 2), RgbImage::new(2, 2));
+    let uvs = Point3f::new(0., 0., 0.);
+    let corners = (&uvs, &uvs, &uvs);
+    let mut list = DrawList::new();
+    for texture in [&brick, &wood, &brick, &wood] {
+        list.draw_mesh(
+            &quad,
+            &Mat4::identity(),
+            DrawStyle::Textured(texture, corners),
+        );
+    }
+    list.draw_mesh(&quad, &Mat4::identity(), DrawStyle::Filled(Color(0, 0, 0)));
+
+    // the textures are grouped, each in the order recorded
+    let order = list.draw_order();
+    assert_eq!(order[0], 4);
+    let texture_of = |index: usize| match list.commands()[index].style {
+        DrawStyle::Textured(texture, _) => texture as *const RgbImage,
+        _ => std::ptr::null(),
+    };
+    assert_eq!(texture_of(order[1]), texture_of(order[2]));
+    assert_eq!(texture_of(order[3]), texture_of(order[4]));
+    assert!(order[1] < order[2] && order[3] < order[4]);
+
+    list.set_state_sorting(false);
+    assert_eq!(list.draw_order(), vec![0, 1, 2, 3, 4]);
+}
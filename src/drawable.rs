@@ -1,15 +1,36 @@
+use std::borrow::Cow;
+#[cfg(feature = "fs")]
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use image::{ImageResult, RgbImage};
+#[cfg(feature = "fs")]
+use image::ImageResult;
+use image::{RgbImage, RgbaImage};
 
-use crate::color::Color;
+use crate::camera::Viewport;
+use crate::color::{Color, HdrColor};
+use crate::error::RusterizerError;
+#[cfg(feature = "fs")]
+use crate::export;
+use crate::fog::Fog;
+use crate::font;
+use crate::light::{HemisphereLight, Light};
+use crate::math::{Aabb, Mat4, Vec3f};
+use crate::postprocess::PostProcess;
+use crate::raster::{self, for_each_line_pixel, Precision, Traversal, TriangleSetup};
+use crate::ray::Ray;
+use crate::stats::{Overdraw, RenderStats};
+use crate::target::RenderTarget;
+use crate::tonemap::ToneMapping;
 use crate::DrawStyle;
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug)]
 pub struct Point<T> {
-    x: T,
-    y: T,
-    z: T,
+    pub x: T,
+    pub y: T,
+    pub z: T,
 }
 
 impl<T> Point<T> {
@@ -104,6 +125,9 @@ pub trait Drawable {
     fn clear(&mut self, color: Color);
     fn point(&mut self, x: u32, y: u32, color: Color);
     fn line(&mut self, x0: u32, y0: u32, x1: u32, y1: u32, color: Color);
+    /// Draws a line in screen space, interpolating depth between the endpoints and
+    /// drawing only the pixels that pass the z-buffer test.
+    fn line3d(&mut self, a: &Point3f, b: &Point3f, color: Color);
     fn triangle(
         &mut self,
         a: &Point3f,
@@ -112,256 +136,2278 @@ pub trait Drawable {
         draw_style: &DrawStyle,
         intensity: f64,
     );
+    /// Fills a depth-tested triangle with the colors `shade` gives every covered pixel
+    /// from its barycentric coordinates and interpolated depth. Pixels it returns `None`
+    /// for are left alone, depth included, so it can cut out patterns as well as color
+    /// them.
+    fn triangle_with<F: FnMut((f64, f64, f64), f64) -> Option<Color>>(
+        &mut self,
+        a: &Point3f,
+        b: &Point3f,
+        c: &Point3f,
+        shade: F,
+    ) where
+        Self: Sized;
     fn check_and_set_zbuf(&mut self, x: u32, y: u32, z_value: f64) -> bool;
+
+    /// Outlines the axis-aligned rectangle with corners `(x0, y0)` and `(x1, y1)`, inclusive.
+    fn rect(&mut self, x0: u32, y0: u32, x1: u32, y1: u32, color: Color) {
+        let (x0, x1) = (x0.min(x1) as i64, x0.max(x1) as i64);
+        let (y0, y1) = (y0.min(y1) as i64, y0.max(y1) as i64);
+        for x in x0..=x1 {
+            clipped_point(self, x, y0, color);
+            clipped_point(self, x, y1, color);
+        }
+        for y in y0..=y1 {
+            clipped_point(self, x0, y, color);
+            clipped_point(self, x1, y, color);
+        }
+    }
+
+    fn filled_rect(&mut self, x0: u32, y0: u32, x1: u32, y1: u32, color: Color) {
+        let (x0, x1) = (x0.min(x1) as i64, x0.max(x1) as i64);
+        for y in y0.min(y1)..=y0.max(y1) {
+            clipped_span(self, x0, x1, y as i64, color);
+        }
+    }
+
+    fn circle(&mut self, cx: u32, cy: u32, radius: u32, color: Color) {
+        self.ellipse(cx, cy, radius, radius, color);
+    }
+
+    fn filled_circle(&mut self, cx: u32, cy: u32, radius: u32, color: Color) {
+        self.filled_ellipse(cx, cy, radius, radius, color);
+    }
+
+    /// Outlines the axis-aligned ellipse with radii `rx` and `ry` using the midpoint algorithm.
+    fn ellipse(&mut self, cx: u32, cy: u32, rx: u32, ry: u32, color: Color) {
+        let (cx, cy) = (cx as i64, cy as i64);
+        let (rx, ry) = (rx as i64, ry as i64);
+        if rx == 0 || ry == 0 {
+            for y in cy - ry..=cy + ry {
+                clipped_span(self, cx - rx, cx + rx, y, color);
+            }
+            return;
+        }
+        let mut plot4 = |x: i64, y: i64| {
+            clipped_point(self, cx + x, cy + y, color);
+            clipped_point(self, cx - x, cy + y, color);
+            clipped_point(self, cx + x, cy - y, color);
+            clipped_point(self, cx - x, cy - y, color);
+        };
+
+        let (rx2, ry2) = ((rx * rx) as f64, (ry * ry) as f64);
+        let (mut x, mut y) = (0i64, ry);
+        let (mut px, mut py) = (0.0, 2.0 * rx2 * y as f64);
+        // region 1: slope shallower than -1
+        let mut p = ry2 - rx2 * ry as f64 + 0.25 * rx2;
+        plot4(x, y);
+        while px < py {
+            x += 1;
+            px += 2.0 * ry2;
+            if p < 0.0 {
+                p += ry2 + px;
+            } else {
+                y -= 1;
+                py -= 2.0 * rx2;
+                p += ry2 + px - py;
+            }
+            plot4(x, y);
+        }
+        // region 2: slope steeper than -1
+        let mut p = ry2 * (x as f64 + 0.5).powi(2) + rx2 * ((y - 1) as f64).powi(2) - rx2 * ry2;
+        while y > 0 {
+            y -= 1;
+            py -= 2.0 * rx2;
+            if p > 0.0 {
+                p += rx2 - py;
+            } else {
+                x += 1;
+                px += 2.0 * ry2;
+                p += rx2 - py + px;
+            }
+            plot4(x, y);
+        }
+    }
+
+    /// Draws `text` with the embedded 8x8 font, `(x, y)` being the top-left corner of the
+    /// first glyph. Since `y` grows upwards, glyph rows and new lines go towards smaller `y`.
+    fn draw_text(&mut self, x: u32, y: u32, text: &str, color: Color) {
+        let (mut pen_x, mut pen_y) = (x as i64, y as i64);
+        for c in text.chars() {
+            if c == '\n' {
+                pen_x = x as i64;
+                pen_y -= font::GLYPH_HEIGHT as i64;
+                continue;
+            }
+            for (row, bits) in font::glyph(c).iter().enumerate() {
+                for column in 0..font::GLYPH_WIDTH {
+                    if bits & (1 << column) != 0 {
+                        clipped_point(self, pen_x + column as i64, pen_y - row as i64, color);
+                    }
+                }
+            }
+            pen_x += font::GLYPH_WIDTH as i64;
+        }
+    }
+
+    fn filled_ellipse(&mut self, cx: u32, cy: u32, rx: u32, ry: u32, color: Color) {
+        let (cx, cy) = (cx as i64, cy as i64);
+        let (rx, ry) = (rx as f64, ry as i64);
+        for dy in -ry..=ry {
+            let half_width = if ry == 0 {
+                rx
+            } else {
+                rx * (1.0 - (dy as f64 / ry as f64).powi(2)).sqrt()
+            };
+            let half_width = half_width.round() as i64;
+            clipped_span(self, cx - half_width, cx + half_width, cy + dy, color);
+        }
+    }
+}
+
+/// Plots a pixel given in signed coordinates, ignoring it when outside of `drawable`.
+fn clipped_point<D: Drawable + ?Sized>(drawable: &mut D, x: i64, y: i64, color: Color) {
+    if x >= 0 && y >= 0 && x < drawable.width() as i64 && y < drawable.height() as i64 {
+        drawable.point(x as u32, y as u32, color);
+    }
+}
+
+/// Fills the horizontal span `x0..=x1` of row `y`, clipped to `drawable`.
+fn clipped_span<D: Drawable + ?Sized>(drawable: &mut D, x0: i64, x1: i64, y: i64, color: Color) {
+    if y < 0 || y >= drawable.height() as i64 {
+        return;
+    }
+    let x0 = x0.max(0);
+    let x1 = x1.min(drawable.width() as i64 - 1);
+    for x in x0..=x1 {
+        drawable.point(x as u32, y as u32, color);
+    }
+}
+
+/// Rasterization settings for [`Drawable::line`] and [`Drawable::line3d`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LineStyle {
+    /// Line width in pixels.
+    pub width: f64,
+    /// Blend edge pixels by their coverage instead of drawing hard pixels.
+    pub antialiased: bool,
+}
+
+impl Default for LineStyle {
+    fn default() -> Self {
+        LineStyle {
+            width: 1.0,
+            antialiased: false,
+        }
+    }
+}
+
+/// What [`Image::clear_background`] fills the image with.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Background {
+    Solid(Color),
+    /// Blend from `top` to `bottom` down the full image.
+    Gradient {
+        top: Color,
+        bottom: Color,
+    },
+    /// Squares `size` pixels wide alternating between two colors, from the top left.
+    Checkerboard {
+        size: u32,
+        light: Color,
+        dark: Color,
+    },
+    /// Black, and transparent in RGBA output wherever nothing is drawn.
+    Transparent,
+}
+
+impl Background {
+    /// The gray checkerboard image editors show behind transparent pixels, with squares
+    /// `size` pixels wide.
+    pub const fn checkerboard(size: u32) -> Self {
+        Background::Checkerboard {
+            size,
+            light: Color(204, 204, 204),
+            dark: Color(153, 153, 153),
+        }
+    }
+
+    /// One color standing for the background where a single one is needed, such as the
+    /// color of fog: the average of the gradient or checkerboard colors.
+    pub fn average_color(&self) -> Color {
+        match *self {
+            Background::Solid(color) => color,
+            Background::Gradient { top, bottom } => top.lerp(bottom, 0.5),
+            Background::Checkerboard { light, dark, .. } => light.lerp(dark, 0.5),
+            Background::Transparent => Color(0, 0, 0),
+        }
+    }
+}
+
+impl std::str::FromStr for Background {
+    type Err = String;
+
+    /// Parses a color, `gradient:TOP:BOTTOM`, `checker[:SIZE]` or `transparent`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let color = |color: &str| {
+            color
+                .parse::<Color>()
+                .map_err(|e| format!("invalid background color: {}", e))
+        };
+        match s.split(':').collect::<Vec<_>>()[..] {
+            ["transparent"] => Ok(Background::Transparent),
+            ["checker"] => Ok(Background::checkerboard(16)),
+            ["checker", size] => match size.parse::<u32>() {
+                Ok(size) if size > 0 => Ok(Background::checkerboard(size)),
+                _ => Err(format!("invalid checkerboard size '{}'", size)),
+            },
+            ["gradient", top, bottom] => Ok(Background::Gradient {
+                top: color(top)?,
+                bottom: color(bottom)?,
+            }),
+            [solid] => Ok(Background::Solid(color(solid)?)),
+            _ => Err(format!(
+                "unknown background '{}', expected a color, gradient:TOP:BOTTOM, checker[:SIZE] \
+                 or transparent",
+                s
+            )),
+        }
+    }
 }
 
+/// Shared flag a host application sets to abort a render running on another thread, see
+/// [`Image::set_cancel_token`].
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        CancelToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Offset added to depths before the depth test, pulling what is drawn towards the viewer
+/// so that decals and wireframes win over coplanar surfaces drawn without one.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DepthBias {
+    /// Offset in depth units.
+    pub constant: f64,
+    /// Multiple of the largest change in depth from one pixel to the next across a
+    /// triangle, which grows as the triangle turns edge-on; lines and points get none.
+    pub slope_scale: f64,
+}
+
+/// How [`Image::to_screen`] maps positions to pixels, apart from the image so that it can
+/// be shared between threads.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScreenMapping {
+    projection: Option<Mat4>,
+    viewport: Viewport,
+    band_offset: u32,
+}
+
+impl ScreenMapping {
+    pub fn to_screen(&self, v: &Vec3f) -> Point3f {
+        let v = match &self.projection {
+            Some(projection) => projection.transform_point(v),
+            None => *v,
+        };
+        let mut screen = self.viewport.to_screen(&v);
+        screen.y -= self.band_offset as f64;
+        screen
+    }
+}
+
+/// Order in which the corners of a triangle go round as seen on screen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Winding {
+    CounterClockwise,
+    Clockwise,
+}
+
+/// What drew a pixel, as kept by the ID buffer of an [`Image`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct PixelId {
+    /// Set by the caller with [`Image::set_object_id`], for example the index of a mesh.
+    pub object: u32,
+    /// Index of the face within its mesh when drawn by [`draw_mesh`](crate::render::draw_mesh),
+    /// with the mesh's lines and then its points numbered on after the faces, or of the
+    /// vertex for [`draw_point_cloud`](crate::render::draw_point_cloud).
+    pub triangle: u32,
+}
+
+/// Linear HDR color buffer with rows stored bottom-up.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Framebuffer {
+    width: u32,
+    height: u32,
+    pub pixels: Vec<HdrColor>,
+}
+
+impl Framebuffer {
+    pub fn new(width: u32, height: u32) -> Self {
+        Framebuffer {
+            width,
+            height,
+            pixels: vec![HdrColor::default(); (width * height) as usize],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> HdrColor {
+        self.pixels[(y * self.width + x) as usize]
+    }
+
+    /// Like [`Framebuffer::get`], clamping coordinates outside the buffer to its edges.
+    pub fn get_clamped(&self, x: i64, y: i64) -> HdrColor {
+        let x = x.clamp(0, self.width as i64 - 1) as u32;
+        let y = y.clamp(0, self.height as i64 - 1) as u32;
+        self.get(x, y)
+    }
+
+    pub fn set(&mut self, x: u32, y: u32, color: HdrColor) {
+        self.pixels[(y * self.width + x) as usize] = color;
+    }
+}
+
+/// Render target holding a linear HDR color buffer and a z-buffer.
+///
+/// Rows are stored bottom-up; fog, post-processing passes and tone mapping are applied
+/// when the image is exported.
 pub struct Image {
-    image: RgbImage,
+    width: u32,
+    height: u32,
+    framebuffer: Framebuffer,
     z_buffer: Vec<f64>,
+    tone_mapping: ToneMapping,
+    line_style: LineStyle,
+    fog: Option<Fog>,
+    ambient_light: Option<HemisphereLight>,
+    lights: Vec<Light>,
+    post_processing: Vec<Box<dyn PostProcess>>,
+    stats: RenderStats,
+    /// Height of the full image this one is a band of, see [`Image::band`].
+    canvas_height: u32,
+    /// Row of the full image this one starts at.
+    band_offset: u32,
+    /// Farthest depth of every [`HI_Z_TILE`]-sized tile of the z-buffer, which may lag
+    /// behind the z-buffer but is never nearer than any of the tile's pixels.
+    hi_z: Vec<f64>,
+    /// Tiles whose farthest depth may have moved nearer since it was last computed.
+    hi_z_stale: Vec<bool>,
+    /// Camera projection applied by [`Image::to_screen`], see [`Image::set_projection`].
+    projection: Option<Mat4>,
+    /// Where normalized device coordinates land in the full image.
+    viewport: Viewport,
+    depth_bias: DepthBias,
+    /// Offset the depth bias gives what is being drawn.
+    depth_offset: f64,
+    /// How triangles find their pixels, see [`Image::set_traversal`].
+    traversal: Traversal,
+    /// How the corners of triangles are held, see [`Image::set_precision`].
+    precision: Precision,
+    /// Winding of the triangles kept, see [`Image::set_culling`].
+    front_face: Option<Winding>,
+    /// What last wrote the depth of every pixel, if enabled with
+    /// [`Image::enable_id_buffer`]. Rows are stored bottom-up.
+    id_buffer: Option<Vec<Option<PixelId>>>,
+    /// Written to the ID buffer by what is drawn next.
+    current_id: PixelId,
+    /// Fragments drawn into every pixel, if enabled with [`Image::count_overdraw`].
+    overdraw: Option<Overdraw>,
+    /// Rectangle of the full image outside which nothing is drawn, see
+    /// [`Image::set_scissor`].
+    scissor: Option<Viewport>,
+    /// Called as triangles are submitted, see [`Image::set_progress`].
+    progress: Option<ProgressCallback>,
+    cancel_token: Option<CancelToken>,
+    /// When rendering stops, see [`Image::set_time_budget`].
+    deadline: Option<Instant>,
+    /// Whether rendering was stopped by the cancel token or the deadline.
+    cancelled: bool,
+    /// Whether the image was last cleared to [`Background::Transparent`].
+    transparent: bool,
+    /// Photo shown where nothing is drawn, see [`Image::set_backdrop`].
+    backdrop: Option<Arc<RgbaImage>>,
+    /// Keywords and values saved along with PNGs, see [`Image::set_text`].
+    text: Vec<(String, String)>,
 }
 
+/// Told the counters of an [`Image`] as rendering goes on.
+pub type ProgressCallback = Box<dyn FnMut(&RenderStats) + Send>;
+
+/// Number of submitted triangles between calls of the progress callback.
+pub const PROGRESS_INTERVAL: u64 = 4096;
+
+/// Number of submitted triangles between checks of the cancel token and deadline.
+const CANCEL_CHECK_INTERVAL: u64 = 64;
+
+/// Side of the square tiles of the hierarchical z-buffer, in pixels.
+const HI_Z_TILE: u32 = 8;
+
 impl Image {
     pub fn new(width: u32, height: u32) -> Image {
+        Image::band(width, height, 0, height)
+    }
+
+    /// Rows `offset..offset + height` of a `width` x `canvas_height` image, counted from
+    /// the bottom. Geometry drawn through [`Image::to_screen`] lands where it would in the
+    /// full image, so a large render can be assembled from bands drawn one at a time.
+    pub fn band(width: u32, canvas_height: u32, offset: u32, height: u32) -> Image {
+        let tiles = (width.div_ceil(HI_Z_TILE) * height.div_ceil(HI_Z_TILE)) as usize;
         Image {
-            image: RgbImage::new(width, height),
+            canvas_height,
+            band_offset: offset,
+            width,
+            height,
+            framebuffer: Framebuffer::new(width, height),
             z_buffer: vec![f64::NEG_INFINITY; (width * height) as usize],
+            hi_z: vec![f64::NEG_INFINITY; tiles],
+            hi_z_stale: vec![false; tiles],
+            tone_mapping: ToneMapping::default(),
+            line_style: LineStyle::default(),
+            fog: None,
+            ambient_light: None,
+            lights: Vec::new(),
+            post_processing: Vec::new(),
+            stats: RenderStats::default(),
+            projection: None,
+            viewport: Viewport::full(width, canvas_height),
+            depth_bias: DepthBias::default(),
+            depth_offset: 0.0,
+            traversal: Traversal::default(),
+            precision: Precision::default(),
+            front_face: None,
+            id_buffer: None,
+            current_id: PixelId::default(),
+            overdraw: None,
+            scissor: None,
+            progress: None,
+            cancel_token: None,
+            deadline: None,
+            cancelled: false,
+            transparent: false,
+            backdrop: None,
+            text: Vec::new(),
+        }
+    }
+
+    fn hi_z_index(&self, tx: u32, ty: u32) -> usize {
+        (ty * self.width.div_ceil(HI_Z_TILE) + tx) as usize
+    }
+
+    /// Farthest depth in a tile, recomputed if writes since the last call may have
+    /// moved it nearer.
+    fn tile_farthest(&mut self, tx: u32, ty: u32) -> f64 {
+        let idx = self.hi_z_index(tx, ty);
+        if !self.hi_z_stale[idx] {
+            return self.hi_z[idx];
+        }
+        let (x0, x1) = (tx * HI_Z_TILE, ((tx + 1) * HI_Z_TILE).min(self.width));
+        let (y0, y1) = (ty * HI_Z_TILE, ((ty + 1) * HI_Z_TILE).min(self.height));
+        let mut farthest = f64::INFINITY;
+        for y in y0..y1 {
+            let row = (y * self.width) as usize;
+            let row_farthest = self.z_buffer[row + x0 as usize..row + x1 as usize]
+                .iter()
+                .fold(f64::INFINITY, |a, &b| if b < a { b } else { a });
+            if row_farthest < farthest {
+                farthest = row_farthest;
+            }
+            if farthest == f64::NEG_INFINITY {
+                // still not fully covered
+                break;
+            }
+        }
+        self.hi_z[idx] = farthest;
+        self.hi_z_stale[idx] = false;
+        farthest
+    }
+
+    /// Height of the full image, which is the image's own unless it is a band.
+    pub fn canvas_height(&self) -> u32 {
+        self.canvas_height
+    }
+
+    /// First row of the full image covered by this one.
+    pub fn band_offset(&self) -> u32 {
+        self.band_offset
+    }
+
+    /// Sets the projection taking view space to normalized device coordinates, such as
+    /// [`Orthographic::projection`](crate::camera::Orthographic::projection). With a
+    /// projection, depths outside [-1, 1] fail the depth test, clipping the geometry
+    /// against the near and far planes.
+    pub fn set_projection(&mut self, projection: Option<Mat4>) {
+        self.projection = projection;
+    }
+
+    /// Restricts rendering through [`Image::to_screen`] to a rectangle of the full image,
+    /// given with rows counted from the bottom of the canvas. Pixels outside it fail the
+    /// depth test.
+    pub fn set_viewport(&mut self, viewport: Viewport) {
+        self.viewport = viewport;
+    }
+
+    pub fn viewport(&self) -> Viewport {
+        self.viewport
+    }
+
+    /// Clips all drawing, clearing included, to a rectangle of the full image given with
+    /// rows counted from the bottom of the canvas, leaving the pixels outside it as they
+    /// are. Unlike the viewport, the scissor does not change where geometry lands.
+    pub fn set_scissor(&mut self, scissor: Option<Viewport>) {
+        self.scissor = scissor;
+    }
+
+    pub fn scissor(&self) -> Option<Viewport> {
+        self.scissor
+    }
+
+    /// First and last pixel of this image inside both the viewport and the scissor
+    /// rectangle, `None` if there are none.
+    fn drawable_area(&self) -> Option<((u32, u32), (u32, u32))> {
+        let band = self.band_offset as i64;
+        let (mut min, mut max) = ((0, 0), (self.width as i64 - 1, self.height as i64 - 1));
+        for rect in [Some(self.viewport), self.scissor].into_iter().flatten() {
+            let (x, y) = (rect.x as i64, rect.y as i64 - band);
+            min = (min.0.max(x), min.1.max(y));
+            max = (
+                max.0.min(x + rect.width as i64 - 1),
+                max.1.min(y + rect.height as i64 - 1),
+            );
+        }
+        (min.0 <= max.0 && min.1 <= max.1)
+            .then_some(((min.0 as u32, min.1 as u32), (max.0 as u32, max.1 as u32)))
+    }
+
+    /// Whether the pixel of this image lies inside the scissor rectangle, if any.
+    fn in_scissor(&self, x: u32, y: u32) -> bool {
+        self.scissor
+            .is_none_or(|scissor| scissor.contains(x, y + self.band_offset))
+    }
+
+    /// Biases the depth of everything drawn from now on, until it is set back to the
+    /// default of none.
+    pub fn set_depth_bias(&mut self, depth_bias: DepthBias) {
+        self.depth_bias = depth_bias;
+        self.depth_offset = depth_bias.constant;
+    }
+
+    pub fn depth_bias(&self) -> DepthBias {
+        self.depth_bias
+    }
+
+    /// Chooses how filled triangles find the pixels they cover. The result is the same
+    /// either way, only the time taken differs.
+    pub fn set_traversal(&mut self, traversal: Traversal) {
+        self.traversal = traversal;
+    }
+
+    /// Chooses how the corners of filled triangles are held while finding the pixels
+    /// they cover. [`Precision::Fixed`] covers the same pixels on every platform; the
+    /// deferred renderer keeps doubles.
+    pub fn set_precision(&mut self, precision: Precision) {
+        self.precision = precision;
+    }
+
+    /// Drops triangles whose corners do not go round the way of `front_face` on screen,
+    /// before any of their pixels are visited. With `None`, the default, both sides are
+    /// drawn.
+    pub fn set_culling(&mut self, front_face: Option<Winding>) {
+        self.front_face = front_face;
+    }
+
+    /// Records from now on which object and triangle last wrote the depth of every pixel,
+    /// to be looked up with [`Image::pick`].
+    pub fn enable_id_buffer(&mut self) {
+        if self.id_buffer.is_none() {
+            self.id_buffer = Some(vec![None; (self.width * self.height) as usize]);
+        }
+    }
+
+    /// Counts from now on how many fragments cover every pixel and how many of them
+    /// pass the depth test, to be looked up with [`Image::overdraw`].
+    pub fn count_overdraw(&mut self) {
+        if self.overdraw.is_none() {
+            self.overdraw = Some(Overdraw::new(self.width, self.height));
+        }
+    }
+
+    pub fn overdraw(&self) -> Option<&Overdraw> {
+        self.overdraw.as_ref()
+    }
+
+    /// Object ID written to the ID buffer by everything drawn from now on.
+    pub fn set_object_id(&mut self, object: u32) {
+        self.current_id.object = object;
+    }
+
+    /// Triangle ID written to the ID buffer by everything drawn from now on, set for
+    /// every face by the mesh drawing functions.
+    pub fn set_triangle_id(&mut self, triangle: u32) {
+        self.current_id.triangle = triangle;
+    }
+
+    /// The ID buffer, if enabled, with rows stored bottom-up like the depth buffer.
+    pub fn id_buffer(&self) -> Option<&[Option<PixelId>]> {
+        self.id_buffer.as_deref()
+    }
+
+    /// What is visible at pixel `(x, y)` of the finished image, with rows counted from
+    /// the top as in the saved image, or `None` for the background, pixels outside the
+    /// image, or without an ID buffer.
+    pub fn pick(&self, x: u32, y: u32) -> Option<PixelId> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let row = self.height - 1 - y;
+        self.id_buffer.as_ref()?[(row * self.width + x) as usize]
+    }
+
+    /// Ray through the center of pixel `(x, y)`, rows counted from the top as for
+    /// [`Image::pick`], in the coordinates taken by [`Image::to_screen`]. It runs from the
+    /// near to the far plane of the projection, or along the whole view axis without one.
+    /// `None` outside the image or for a projection that cannot be inverted.
+    pub fn pixel_ray(&self, x: u32, y: u32) -> Option<Ray> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let row = self.band_offset + self.height - 1 - y;
+        let (ndc_x, ndc_y) = self.viewport.to_ndc(x as f64 + 0.5, row as f64 + 0.5);
+        let Some(projection) = self.projection else {
+            return Some(Ray {
+                origin: Vec3f::new(ndc_x, ndc_y, 0.),
+                direction: Vec3f::new(0., 0., -1.),
+                near: f64::NEG_INFINITY,
+                far: f64::INFINITY,
+                cull_back_faces: false,
+            });
+        };
+        let inverse = projection.inverse()?;
+        let near = inverse.transform_point(&Vec3f::new(ndc_x, ndc_y, 1.));
+        let far = inverse.transform_point(&Vec3f::new(ndc_x, ndc_y, -1.));
+        Some(Ray {
+            far: (far - near).length(),
+            ..Ray::new(near, far - near)
+        })
+    }
+
+    /// Maps normalized device coordinates in [-1, 1], or view space coordinates if a
+    /// projection is set, to pixel coordinates in the viewport, passing the depth through.
+    pub fn to_screen(&self, v: &Vec3f) -> Point3f {
+        self.screen_mapping().to_screen(v)
+    }
+
+    pub fn screen_mapping(&self) -> ScreenMapping {
+        ScreenMapping {
+            projection: self.projection,
+            viewport: self.viewport,
+            band_offset: self.band_offset,
+        }
+    }
+
+    /// The region of the coordinates taken by [`Image::to_screen`] that lands in the
+    /// image, unbounded in depth unless a projection clips against the near and far
+    /// planes.
+    pub fn view_bounds(&self) -> Aabb {
+        let (left, bottom) = self.viewport.to_ndc(0.0, self.band_offset as f64);
+        let top_right = (self.width as f64, (self.band_offset + self.height) as f64);
+        let (right, top) = self.viewport.to_ndc(top_right.0, top_right.1);
+        let (near, far) = match self.projection {
+            Some(_) => (1.0, -1.0),
+            None => (f64::INFINITY, f64::NEG_INFINITY),
+        };
+        let bounds = Aabb {
+            min: Vec3f::new(left.max(-1.0), bottom.max(-1.0), far),
+            max: Vec3f::new(right.min(1.0), top.min(1.0), near),
+        };
+        match self.projection.and_then(|projection| projection.inverse()) {
+            Some(inverse) => bounds.transformed(&inverse),
+            None => bounds,
+        }
+    }
+
+    /// Whether any of the box `bounds` transformed by `model` would pass the depth test
+    /// against what has been drawn so far, found by rasterizing its sides without writing
+    /// anything. An object inside the box can be skipped when it would not, saving the
+    /// time to draw it.
+    ///
+    /// Boxes reaching past the near plane, or behind the eye, count as visible.
+    pub fn occlusion_query(&self, bounds: &Aabb, model: &Mat4) -> bool {
+        let corners = bounds
+            .corners()
+            .map(|corner| model.transform_point(&corner));
+        if let Some(projection) = &self.projection {
+            let m = &projection.m;
+            let in_front = corners.iter().all(|p| {
+                let clip = |row: [f64; 4]| row[0] * p.x + row[1] * p.y + row[2] * p.z + row[3];
+                let (z, w) = (clip(m[2]), clip(m[3]));
+                w > 0.0 && z <= w
+            });
+            if !in_front {
+                return true;
+            }
+        }
+        let Some((min, max)) = self.drawable_area() else {
+            return false;
+        };
+        let screen = corners.map(|corner| {
+            let p = self.to_screen(&corner);
+            [p.x, p.y, p.z]
+        });
+        // corner `i` has the maximum x, y and z where bits 0, 1 and 2 of `i` are set
+        for axis in 0..3 {
+            for side in [0, 1 << axis] {
+                let [u, v] = [1 << ((axis + 1) % 3), 1 << ((axis + 2) % 3)];
+                let quad = [side, side | u, side | u | v, side | v].map(|i| screen[i]);
+                for [a, b, c] in [[quad[0], quad[1], quad[2]], [quad[0], quad[2], quad[3]]] {
+                    let mut visible = false;
+                    raster::for_each_triangle_pixel(min, max, a, b, c, |x, y, _, z| {
+                        visible = visible || self.depth_test(x, y, z).is_some();
+                    });
+                    if visible {
+                        return true;
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    /// Counts a whole mesh skipped for lying outside the view.
+    pub(crate) fn record_object_culled(&mut self) {
+        self.stats.objects_culled += 1;
+    }
+
+    /// What has been drawn into the image so far.
+    pub fn stats(&self) -> RenderStats {
+        self.stats
+    }
+
+    /// Calls `progress` with the counters so far every [`PROGRESS_INTERVAL`] submitted
+    /// triangles, so long renders can report how far they got.
+    pub fn set_progress(&mut self, progress: Option<ProgressCallback>) {
+        self.progress = progress;
+    }
+
+    /// Stops drawing once `token` is cancelled. What was drawn until then is kept, so the
+    /// partially rendered image can still be exported.
+    pub fn set_cancel_token(&mut self, token: Option<CancelToken>) {
+        self.cancel_token = token;
+    }
+
+    /// Stops drawing once `budget` has passed from now, like a cancelled token.
+    pub fn set_time_budget(&mut self, budget: Option<Duration>) {
+        self.deadline = budget.map(|budget| Instant::now() + budget);
+    }
+
+    /// Whether drawing was stopped by the cancel token or the time budget. Every triangle
+    /// submitted after that is counted but left out.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled
+    }
+
+    fn record_submitted(&mut self) {
+        self.stats.triangles_submitted += 1;
+        if self
+            .stats
+            .triangles_submitted
+            .is_multiple_of(PROGRESS_INTERVAL)
+        {
+            if let Some(progress) = &mut self.progress {
+                progress(&self.stats);
+            }
+        }
+        // checked on the first triangle so a token cancelled up front draws nothing
+        if !self.cancelled
+            && (self.stats.triangles_submitted - 1).is_multiple_of(CANCEL_CHECK_INTERVAL)
+        {
+            self.cancelled = self
+                .cancel_token
+                .as_ref()
+                .is_some_and(CancelToken::is_cancelled)
+                || self
+                    .deadline
+                    .is_some_and(|deadline| Instant::now() >= deadline);
+        }
+    }
+
+    /// Counts a triangle dropped for facing away from the viewer.
+    pub(crate) fn record_culled(&mut self) {
+        self.record_submitted();
+        self.stats.triangles_culled += 1;
+    }
+
+    /// Counts a triangle about to be rasterized.
+    pub(crate) fn record_triangle(&mut self, a: &Point3f, b: &Point3f, c: &Point3f) {
+        self.record_submitted();
+        let (width, height) = (self.width as f64, self.height as f64);
+        if [a, b, c]
+            .iter()
+            .any(|p| p.x < 0.0 || p.y < 0.0 || p.x > width || p.y > height)
+        {
+            self.stats.triangles_clipped += 1;
+        }
+    }
+
+    /// Fogs every drawn pixel by its depth; the cleared background is left alone.
+    pub fn set_fog(&mut self, fog: Option<Fog>) {
+        self.fog = fog;
+    }
+
+    /// Adds soft light to the faces [`draw_mesh`](crate::render::draw_mesh) lights, so
+    /// that those turned away from the light are not left black.
+    pub fn set_ambient_light(&mut self, ambient_light: Option<HemisphereLight>) {
+        self.ambient_light = ambient_light;
+    }
+
+    /// Lays `backdrop`, a photo as large as the full image, under the render in the
+    /// final pixels: it takes the place of the background wherever nothing is drawn,
+    /// blended over it by its alpha. Post-processing and tone mapping leave it alone.
+    pub fn set_backdrop(
+        &mut self,
+        backdrop: Option<Arc<RgbaImage>>,
+    ) -> Result<(), RusterizerError> {
+        if let Some(backdrop) = &backdrop {
+            if backdrop.dimensions() != (self.width, self.canvas_height) {
+                return Err(RusterizerError::InvalidArgument(format!(
+                    "backdrop is {}x{}, the image {}x{}",
+                    backdrop.width(),
+                    backdrop.height(),
+                    self.width,
+                    self.canvas_height
+                )));
+            }
+        }
+        self.backdrop = backdrop;
+        Ok(())
+    }
+
+    pub fn ambient_light(&self) -> Option<HemisphereLight> {
+        self.ambient_light
+    }
+
+    /// Lights [`draw_mesh`](crate::render::draw_mesh) shades faces with in place of
+    /// [`Light::HEADLIGHT`], each face flat as lit at its centroid. Shadows are left to
+    /// deferred shading.
+    pub fn set_lights(&mut self, lights: Vec<Light>) {
+        self.lights = lights;
+    }
+
+    pub fn lights(&self) -> &[Light] {
+        &self.lights
+    }
+
+    /// The color buffer as rendered, before fog, post-processing and tone mapping.
+    pub fn framebuffer(&self) -> &Framebuffer {
+        &self.framebuffer
+    }
+
+    /// Depths of the drawn pixels, larger values being nearer and uncovered pixels negative
+    /// infinity. Rows are stored bottom-up like the color buffer.
+    pub fn depth_buffer(&self) -> &[f64] {
+        &self.z_buffer
+    }
+
+    /// Appends a pass to the chain run on export, in the order the passes were added.
+    pub fn add_post_process(&mut self, pass: Box<dyn PostProcess>) -> &mut Self {
+        self.post_processing.push(pass);
+        self
+    }
+
+    /// Applies fog and runs the post-processing chain over a copy of the color buffer.
+    pub fn post_processed(&self) -> Cow<'_, Framebuffer> {
+        let mut framebuffer = Cow::Borrowed(&self.framebuffer);
+        if let Some(fog) = &self.fog {
+            let fogged = framebuffer.to_mut();
+            for (pixel, &z) in fogged.pixels.iter_mut().zip(&self.z_buffer) {
+                if z > f64::NEG_INFINITY {
+                    *pixel = fog.apply(*pixel, z);
+                }
+            }
+        }
+        for pass in &self.post_processing {
+            framebuffer = Cow::Owned(pass.apply(&framebuffer));
+        }
+        framebuffer
+    }
+
+    pub fn set_tone_mapping(&mut self, tone_mapping: ToneMapping) {
+        self.tone_mapping = tone_mapping;
+    }
+
+    pub fn set_line_style(&mut self, line_style: LineStyle) {
+        self.line_style = line_style;
+    }
+
+    /// Whether lines can take the single-pixel Bresenham path.
+    fn is_thin_line(&self) -> bool {
+        self.line_style.width == 1.0 && !self.line_style.antialiased
+    }
+
+    /// Mixes `color` into the pixel proportionally to `coverage` in [0, 1].
+    fn blend_hdr(&mut self, x: u32, y: u32, color: HdrColor, coverage: f32) {
+        if !self.in_scissor(x, y) {
+            return;
+        }
+        let idx = (y * self.width + x) as usize;
+        let old = self.framebuffer.pixels[idx];
+        self.framebuffer.pixels[idx] = HdrColor(
+            old.0 + (color.0 - old.0) * coverage,
+            old.1 + (color.1 - old.1) * coverage,
+            old.2 + (color.2 - old.2) * coverage,
+        );
+    }
+
+    /// Draws a line of the configured width as a capsule around the segment, computing
+    /// per-pixel coverage from the distance to it. Depth is interpolated along the
+    /// segment and tested only when `depth_test` is set.
+    fn wide_line(&mut self, a: &Point3f, b: &Point3f, color: Color, depth_test: bool) {
+        if self.width == 0 || self.height == 0 {
+            return;
+        }
+        let LineStyle { width, antialiased } = self.line_style;
+        let half_width = width.max(0.0) / 2.0;
+        let reach = half_width + if antialiased { 0.5 } else { 0.0 };
+        let color = HdrColor::from(color);
+
+        let min_x = (a.x.min(b.x) - reach).floor().max(0.0) as u32;
+        let min_y = (a.y.min(b.y) - reach).floor().max(0.0) as u32;
+        let max_x = ((a.x.max(b.x) + reach).ceil().max(0.0) as u32).min(self.width - 1);
+        let max_y = ((a.y.max(b.y) + reach).ceil().max(0.0) as u32).min(self.height - 1);
+
+        let (dx, dy) = (b.x - a.x, b.y - a.y);
+        let length_squared = dx * dx + dy * dy;
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                let (px, py) = (x as f64 + 0.5 - a.x, y as f64 + 0.5 - a.y);
+                let t = if length_squared > 0.0 {
+                    ((px * dx + py * dy) / length_squared).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                let distance = ((px - t * dx).powi(2) + (py - t * dy).powi(2)).sqrt();
+                let coverage = if antialiased {
+                    (half_width + 0.5 - distance).clamp(0.0, 1.0)
+                } else if distance <= half_width.max(0.5) {
+                    1.0
+                } else {
+                    0.0
+                };
+                if coverage <= 0.0 {
+                    continue;
+                }
+                if depth_test && !self.check_and_set_zbuf(x, y, a.z + (b.z - a.z) * t) {
+                    continue;
+                }
+                self.blend_hdr(x, y, color, coverage as f32);
+            }
+        }
+    }
+
+    pub fn point_hdr(&mut self, x: u32, y: u32, color: HdrColor) {
+        if !self.in_scissor(x, y) {
+            return;
+        }
+        let idx = (y * self.width + x) as usize;
+        self.framebuffer.pixels[idx] = color;
+    }
+
+    /// Returns a post-processed and tone-mapped copy of the rendered image in the
+    /// conventional top-down orientation.
+    pub fn to_rgb_image(&self) -> RgbImage {
+        let framebuffer = self.post_processed();
+        RgbImage::from_fn(self.width, self.height, |x, y| {
+            let [r, g, b, _] = self.output_pixel(&framebuffer, x, self.height - 1 - y);
+            image::Rgb([r, g, b])
+        })
+    }
+
+    /// Consumes the image and returns its final pixels without touching the disk, for
+    /// servers and tests that use the render directly. The buffer's `dimensions()` and
+    /// `as_raw()` give the size and the tightly packed RGB bytes, rows top to bottom.
+    pub fn into_rgb_buffer(self) -> RgbImage {
+        self.to_rgb_image()
+    }
+
+    /// Same as [`Image::into_rgb_buffer`] with an alpha channel, opaque unless the
+    /// background is [transparent](Background::Transparent).
+    pub fn into_rgba_buffer(self) -> RgbaImage {
+        self.to_rgba_image()
+    }
+
+    /// Like [`Image::to_rgb_image`] with an alpha channel, opaque unless the background
+    /// is [transparent](Background::Transparent).
+    pub fn to_rgba_image(&self) -> RgbaImage {
+        let mut buffer = RgbaImage::new(self.width, self.height);
+        self.write_rgba(&mut buffer);
+        buffer
+    }
+
+    /// Writes the final pixels as RGBA bytes, rows top to bottom, in the layout of an HTML
+    /// canvas `ImageData`, so a browser demo can reuse one buffer for every frame.
+    ///
+    /// Panics if `pixels` is not exactly `width * height * 4` bytes long.
+    pub fn write_rgba(&self, pixels: &mut [u8]) {
+        assert_eq!(pixels.len(), (self.width * self.height * 4) as usize);
+        let framebuffer = self.post_processed();
+        for (i, pixel) in pixels.chunks_exact_mut(4).enumerate() {
+            let (x, y) = (i as u32 % self.width, i as u32 / self.width);
+            pixel.copy_from_slice(&self.output_pixel(&framebuffer, x, self.height - 1 - y));
+        }
+    }
+
+    /// Final color and alpha of pixel `(x, y)` of the post-processed `framebuffer`, rows
+    /// counted from the bottom.
+    fn output_pixel(&self, framebuffer: &Framebuffer, x: u32, y: u32) -> [u8; 4] {
+        let Color(r, g, b) = self.tone_mapping.apply(framebuffer.get(x, y));
+        // nothing drawn leaves the depth untouched
+        if self.z_buffer[(y * self.width + x) as usize] > f64::NEG_INFINITY {
+            return [r, g, b, 255];
+        }
+        let Some(backdrop) = &self.backdrop else {
+            return [r, g, b, if self.transparent { 0 } else { 255 }];
+        };
+        let over = backdrop
+            .get_pixel(x, self.canvas_height - 1 - (y + self.band_offset))
+            .0;
+        if self.transparent {
+            return over;
+        }
+        // blended in linear light
+        let alpha = over[3] as f32 / 255.0;
+        let under = HdrColor::from(Color(r, g, b));
+        let over = HdrColor::from(Color(over[0], over[1], over[2]));
+        let Color(r, g, b) = HdrColor(
+            under.0 + (over.0 - under.0) * alpha,
+            under.1 + (over.1 - under.1) * alpha,
+            under.2 + (over.2 - under.2) * alpha,
+        )
+        .to_srgb();
+        [r, g, b, 255]
+    }
+
+    /// Saves the image in the format given by the extension, see [`export::save_image`].
+    /// Transparent backgrounds are kept by formats with an alpha channel other than the
+    /// crate's own, and PNGs carry the [text](Image::set_text) set.
+    #[cfg(feature = "fs")]
+    pub fn save<Q: AsRef<Path>>(&self, path: Q) -> ImageResult<()> {
+        let alpha = self.transparent && export::NativeFormat::from_path(&path).is_none();
+        let png = path
+            .as_ref()
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("png"));
+        if png && !self.text.is_empty() {
+            let image = if alpha {
+                self.to_rgba_image().into()
+            } else {
+                self.to_rgb_image().into()
+            };
+            return export::save_png_with_text(&image, path, &self.text);
+        }
+        if alpha {
+            return self.to_rgba_image().save(path);
+        }
+        export::save_image(&self.to_rgb_image(), path)
+    }
+
+    /// Sets keyword and value pairs describing the render, such as the settings it was
+    /// made with, which [`Image::save`] writes into PNG files.
+    pub fn set_text(&mut self, text: Vec<(String, String)>) {
+        self.text = text;
+    }
+
+    pub fn text(&self) -> &[(String, String)] {
+        &self.text
+    }
+
+    /// Fills the image with `background`, laid out over the full image when this is a
+    /// band of it; the scissor rectangle is respected like by [`Drawable::clear`].
+    pub fn clear_background(&mut self, background: &Background) {
+        match *background {
+            Background::Solid(color) => self.clear(color),
+            Background::Transparent => self.clear(Color(0, 0, 0)),
+            Background::Gradient { top, bottom } => {
+                let (top, bottom) = (HdrColor::from(top), HdrColor::from(bottom));
+                for y in 0..self.height {
+                    // blended in linear light, rows counted from the bottom
+                    let t = ((y + self.band_offset) as f32 + 0.5) / self.canvas_height as f32;
+                    let color = HdrColor(
+                        bottom.0 + (top.0 - bottom.0) * t,
+                        bottom.1 + (top.1 - bottom.1) * t,
+                        bottom.2 + (top.2 - bottom.2) * t,
+                    );
+                    for x in 0..self.width {
+                        self.point_hdr(x, y, color);
+                    }
+                }
+            }
+            Background::Checkerboard { size, light, dark } => {
+                let size = size.max(1);
+                for y in 0..self.height {
+                    let row = (self.canvas_height - 1 - (y + self.band_offset)) / size;
+                    for x in 0..self.width {
+                        let color = if (x / size + row).is_multiple_of(2) {
+                            light
+                        } else {
+                            dark
+                        };
+                        self.point_hdr(x, y, color.into());
+                    }
+                }
+            }
         }
-    }
-
-    pub fn save<Q: AsRef<Path>>(&self, path: Q) -> ImageResult<()> {
-        image::DynamicImage::from(self.image.clone())
-            .flipv()
-            .save(path)
+        self.transparent = *background == Background::Transparent;
     }
 }
 
 impl Drawable for Image {
     fn width(&self) -> u32 {
-        self.image.width()
+        self.width
     }
 
     fn height(&self) -> u32 {
-        self.image.height()
+        self.height
     }
 
     fn clear(&mut self, color: Color) {
-        for pixel in self.image.pixels_mut() {
-            *pixel = color.into();
+        self.transparent = false;
+        let Some(scissor) = self.scissor else {
+            self.framebuffer.pixels.fill(color.into());
+            return;
+        };
+        let x0 = scissor.x.min(self.width) as usize;
+        let x1 = (scissor.x + scissor.width).min(self.width) as usize;
+        let y0 = scissor.y.saturating_sub(self.band_offset).min(self.height);
+        let y1 = (scissor.y + scissor.height)
+            .saturating_sub(self.band_offset)
+            .min(self.height);
+        for y in y0..y1 {
+            let row = (y * self.width) as usize;
+            self.framebuffer.pixels[row + x0..row + x1].fill(color.into());
         }
     }
 
     fn point(&mut self, x: u32, y: u32, color: Color) {
-        self.image.put_pixel(x, y, color.into());
+        self.point_hdr(x, y, color.into());
     }
 
-    fn line(&mut self, mut x0: u32, mut y0: u32, mut x1: u32, mut y1: u32, color: Color) {
-        // TODO: clip inside drawable bounds
-        let steep;
-        if x0.abs_diff(x1) < y0.abs_diff(y1) {
-            steep = true;
-            std::mem::swap(&mut x0, &mut y0);
-            std::mem::swap(&mut x1, &mut y1);
-        } else {
-            steep = false;
+    fn line(&mut self, x0: u32, y0: u32, x1: u32, y1: u32, color: Color) {
+        if !self.is_thin_line() {
+            let a = Point3f::new(x0 as f64, y0 as f64, 0.);
+            let b = Point3f::new(x1 as f64, y1 as f64, 0.);
+            self.wide_line(&a, &b, color, false);
+            return;
         }
+        // TODO: clip inside drawable bounds
+        for_each_line_pixel(x0, y0, x1, y1, |x, y, _| self.point(x, y, color));
+    }
 
-        if x0 > x1 {
-            std::mem::swap(&mut x0, &mut x1);
-            std::mem::swap(&mut y0, &mut y1);
+    fn line3d(&mut self, a: &Point3f, b: &Point3f, color: Color) {
+        if !self.is_thin_line() {
+            self.wide_line(a, b, color, true);
+            return;
         }
+        line(self, a, b, color);
+    }
+
+    fn triangle(
+        &mut self,
+        a: &Point3f,
+        b: &Point3f,
+        c: &Point3f,
+        draw_style: &DrawStyle,
+        intensity: f64,
+    ) {
+        let light = HdrColor(1.0, 1.0, 1.0).scale(intensity);
+        self.triangle_with_setup(a, b, c, setup_triangle(a, b, c), draw_style, light);
+    }
 
-        let dx = (x1 - x0) as i32;
-        let dy = y1 as i32 - y0 as i32;
+    fn triangle_with<F: FnMut((f64, f64, f64), f64) -> Option<Color>>(
+        &mut self,
+        a: &Point3f,
+        b: &Point3f,
+        c: &Point3f,
+        mut shade: F,
+    ) {
+        let setup = setup_triangle(a, b, c);
+        if !self.submit_triangle(a, b, c, setup.as_ref()) {
+            return;
+        }
+        let Some(setup) = setup else {
+            self.stats.triangles_degenerate += 1;
+            return;
+        };
+        self.apply_slope_bias(a, b, c);
+        triangle_shaded(self, &setup, |bary, z| shade(bary, z).map(HdrColor::from));
+        self.depth_offset = self.depth_bias.constant;
+    }
 
-        let derror2 = dy.abs() * 2;
-        let mut error2 = 0;
-        let mut y = y0 as i32;
-        for x in x0..=x1 {
-            if steep {
-                self.image.put_pixel(y as u32, x, color.into());
-            } else {
-                self.image.put_pixel(x, y as u32, color.into());
-            }
-            error2 += derror2;
-            if error2 > dx {
-                y += dy.signum();
-                error2 -= dx * 2;
+    fn check_and_set_zbuf(&mut self, x: u32, y: u32, z_value: f64) -> bool {
+        self.count_covered(x, y);
+        match self.depth_test(x, y, z_value) {
+            Some(z_value) => {
+                self.set_depth(x, y, z_value);
+                true
             }
+            None => false,
         }
     }
+}
 
-    fn triangle(
+impl Image {
+    /// [`Drawable::triangle`] with the `setup` of the triangle made beforehand, such as
+    /// by the geometry stage of [`draw_mesh`](crate::render::draw_mesh), and colored
+    /// `light` in place of the intensity.
+    pub(crate) fn triangle_with_setup(
         &mut self,
         a: &Point3f,
         b: &Point3f,
         c: &Point3f,
+        setup: Option<TriangleSetup>,
         draw_style: &DrawStyle,
-        intensity: f64,
+        light: HdrColor,
     ) {
-        match draw_style {
-            &DrawStyle::Wireframe(color) => {
-                triangle_wireframe(self, &a.into(), &b.into(), &c.into(), color)
+        if !self.submit_triangle(a, b, c, setup.as_ref()) {
+            return;
+        }
+        self.apply_slope_bias(a, b, c);
+        fill_triangle(self, a, b, c, setup, draw_style, light);
+        self.depth_offset = self.depth_bias.constant;
+    }
+
+    /// Counts a triangle about to be drawn, culling it if it faces away. Returns whether
+    /// it is to be drawn.
+    fn submit_triangle(
+        &mut self,
+        a: &Point3f,
+        b: &Point3f,
+        c: &Point3f,
+        setup: Option<&TriangleSetup>,
+    ) -> bool {
+        if let Some(front_face) = self.front_face {
+            // rows count upwards, so a positive area goes round counter-clockwise, and
+            // triangles without an area face neither way
+            let front = match front_face {
+                Winding::CounterClockwise => 1.0,
+                Winding::Clockwise => -1.0,
+            };
+            if !setup.is_some_and(|setup| setup.signed_area() * front > 0.0) {
+                self.record_culled();
+                return false;
             }
-            _ => triangle_barycentric(self, a, b, c, draw_style, intensity),
-        };
+        }
+        self.record_triangle(a, b, c);
+        !self.cancelled
     }
 
-    fn check_and_set_zbuf(&mut self, x: u32, y: u32, z_value: f64) -> bool {
-        let idx = (y * self.height() + x) as usize;
-        if self.z_buffer[idx] < z_value {
-            self.z_buffer[idx] = z_value;
-            true
-        } else {
-            false
+    /// Adds the slope part of the depth bias for the triangle to the constant one, until
+    /// `depth_offset` is reset after drawing it.
+    fn apply_slope_bias(&mut self, a: &Point3f, b: &Point3f, c: &Point3f) {
+        if self.depth_bias.slope_scale != 0.0 {
+            self.depth_offset =
+                self.depth_bias.constant + self.depth_bias.slope_scale * depth_slope(a, b, c);
+        }
+    }
+
+    /// The biased depth of a fragment at `z_value` if it is drawable and nearer than the
+    /// z-buffer, without writing it.
+    fn depth_test(&self, x: u32, y: u32, z_value: f64) -> Option<f64> {
+        if self.projection.is_some() && !(-1.0..=1.0).contains(&z_value) {
+            return None;
+        }
+        if !self.viewport.contains(x, y + self.band_offset) || !self.in_scissor(x, y) {
+            return None;
+        }
+        let z_value = z_value + self.depth_offset;
+        (self.z_buffer[(y * self.width + x) as usize] < z_value).then_some(z_value)
+    }
+
+    fn set_depth(&mut self, x: u32, y: u32, z_value: f64) {
+        let idx = (y * self.width + x) as usize;
+        // the tile only gets nearer when one of its farthest pixels is overwritten
+        let tile = self.hi_z_index(x / HI_Z_TILE, y / HI_Z_TILE);
+        if self.z_buffer[idx] <= self.hi_z[tile] {
+            self.hi_z_stale[tile] = true;
+        }
+        self.z_buffer[idx] = z_value;
+        if let Some(ids) = &mut self.id_buffer {
+            ids[idx] = Some(self.current_id);
+        }
+        if let Some(overdraw) = &mut self.overdraw {
+            overdraw.written[idx] += 1;
+        }
+        self.stats.pixels_shaded += 1;
+    }
+
+    /// Counts a fragment about to be depth tested, if counting overdraw.
+    fn count_covered(&mut self, x: u32, y: u32) {
+        if let Some(overdraw) = &mut self.overdraw {
+            overdraw.count_covered(x, y);
         }
     }
 }
 
-#[allow(unused)]
-fn triangle_wireframe(
-    image: &mut Image,
-    u: &ScreenPoint,
-    v: &ScreenPoint,
-    w: &ScreenPoint,
-    color: Color,
-) {
-    image.line(u.x, u.y, v.x, v.y, color);
-    image.line(v.x, v.y, w.x, w.y, color);
-    image.line(u.x, u.y, w.x, w.y, color);
-}
-
-#[allow(unused)]
-fn triangle_line_sweep(
-    image: &mut Image,
-    u: &ScreenPoint,
-    v: &ScreenPoint,
-    w: &ScreenPoint,
-    color: Color,
-) {
-    let mut points = [u, v, w];
-    points.sort_by_key(|p| p.y);
-    let min_y = points[0].y;
-    let max_y = points[2].y;
-
-    for y in min_y..=max_y {
-        let x0 = intersect_y(points[0], points[2], y);
-        let x1 = if y <= points[1].y {
-            intersect_y(points[0], points[1], y)
-        } else {
-            intersect_y(points[1], points[2], y)
-        };
-        let left_x = x0.min(x1).ceil() as u32;
-        let right_x = x0.max(x1) as u32;
-        image.line(left_x, y, right_x, y, color);
+impl RenderTarget for Image {
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn put_pixel(&mut self, x: u32, y: u32, color: HdrColor) {
+        self.point_hdr(x, y, color);
+    }
+
+    fn depth_test(&self, x: u32, y: u32, z: f64) -> Option<f64> {
+        Image::depth_test(self, x, y, z)
+    }
+
+    fn set_depth(&mut self, x: u32, y: u32, z: f64) {
+        Image::set_depth(self, x, y, z);
+    }
+
+    fn drawable_area(&self) -> Option<((u32, u32), (u32, u32))> {
+        Image::drawable_area(self)
+    }
+
+    fn traversal(&self) -> Traversal {
+        self.traversal
+    }
+
+    fn precision(&self) -> Precision {
+        self.precision
+    }
+
+    /// Whether the tile of the hierarchical z-buffer holding `min`, which `max` lies in
+    /// too, is nearer than `z` with the depth bias added.
+    fn is_hidden(&mut self, min: (u32, u32), _max: (u32, u32), z: f64) -> bool {
+        self.tile_farthest(min.0 / HI_Z_TILE, min.1 / HI_Z_TILE) >= z + self.depth_offset
+    }
+
+    fn stats_mut(&mut self) -> Option<&mut RenderStats> {
+        Some(&mut self.stats)
+    }
+
+    fn overdraw_mut(&mut self) -> Option<&mut Overdraw> {
+        self.overdraw.as_mut()
+    }
+
+    fn wireframe_edge(&mut self, a: &Point3f, b: &Point3f, color: Color) {
+        Drawable::line3d(self, a, b, color);
+    }
+}
+
+fn setup_triangle(a: &Point3f, b: &Point3f, c: &Point3f) -> Option<TriangleSetup> {
+    let corner = |p: &Point3f| [p.x, p.y, p.z];
+    TriangleSetup::new(corner(a), corner(b), corner(c))
+}
+
+/// Cuts the segment from `a` to `b` down to its part with non-negative coordinates, as
+/// pixel coordinates are unsigned; the far edges are left to the caller.
+pub(crate) fn clip_to_positive(a: &Point3f, b: &Point3f) -> Option<(Point3f, Point3f)> {
+    let (mut t0, mut t1) = (0.0f64, 1.0f64);
+    for (start, end) in [(a.x, b.x), (a.y, b.y)] {
+        if start < 0.0 && end < 0.0 {
+            return None;
+        }
+        let t = start / (start - end);
+        if start < 0.0 {
+            t0 = t0.max(t);
+        } else if end < 0.0 {
+            t1 = t1.min(t);
+        }
+    }
+    if t0 > t1 {
+        return None;
+    }
+    let at = |t: f64| {
+        Point3f::new(
+            (a.x + (b.x - a.x) * t).max(0.0),
+            (a.y + (b.y - a.y) * t).max(0.0),
+            a.z + (b.z - a.z) * t,
+        )
+    };
+    Some((at(t0), at(t1)))
+}
+
+/// Depth of the segment from `a` to `b` where it passes nearest to the center of pixel
+/// `(x, y)`, matching the depths triangles are sampled at.
+pub(crate) fn depth_along(a: &Point3f, b: &Point3f, x: u32, y: u32) -> f64 {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    let length_squared = dx * dx + dy * dy;
+    if length_squared == 0.0 {
+        return a.z.max(b.z);
     }
+    let (px, py) = (x as f64 + 0.5 - a.x, y as f64 + 0.5 - a.y);
+    let t = ((px * dx + py * dy) / length_squared).clamp(0.0, 1.0);
+    a.z + (b.z - a.z) * t
 }
 
-const LIMIT: f64 = 1e-9;
+/// Color of a [`DrawStyle::FilledRandom`] or [`DrawStyle::PerFace`] triangle drawn on its
+/// own rather than as part of a mesh, `None` for the other styles.
+pub(crate) fn direct_flat_color(
+    draw_style: &DrawStyle,
+    a: &Point3f,
+    b: &Point3f,
+    c: &Point3f,
+) -> Option<Color> {
+    match *draw_style {
+        DrawStyle::FilledRandom(seed) => {
+            // without a face index, identify the triangle by its vertex positions
+            let hash = [a, b, c]
+                .iter()
+                .flat_map(|p| [p.x, p.y, p.z])
+                .fold(seed, |hash, v| {
+                    (hash ^ v.to_bits()).wrapping_mul(0x0100_0000_01B3)
+                });
+            Some(Color::from_seed(hash))
+        }
+        DrawStyle::PerFace(face_color) => Some(face_color(0)),
+        _ => None,
+    }
+}
 
-fn determine_color(bary_coords: (f64, f64, f64), draw_style: &DrawStyle, intensity: f64) -> Color {
+pub(crate) fn determine_color(
+    bary_coords: (f64, f64, f64),
+    draw_style: &DrawStyle,
+    light: HdrColor,
+) -> HdrColor {
+    let lit = |color: HdrColor| HdrColor(color.0 * light.0, color.1 * light.1, color.2 * light.2);
     match draw_style {
         &DrawStyle::Textured(tex, (tp1, tp2, tp3)) => {
             let (a, b, c) = bary_coords;
             let u = a * tp1.x + b * tp2.x + c * tp3.x;
             let v = a * tp1.y + b * tp2.y + c * tp3.y;
-            let x = (u * tex.width() as f64) as u32;
-            let y = (v * tex.height() as f64) as u32;
-            let color = tex.get_pixel(x, y);
-            Color::from(*color).scale(intensity)
+            lit(HdrColor::from(sample_texture(tex, u, v)))
+        }
+        DrawStyle::Filled(color) => lit(HdrColor::from(*color)),
+        &DrawStyle::Matcap(tex, (n1, n2, n3)) => {
+            let (a, b, c) = bary_coords;
+            let normal = (n1 * a + n2 * b + n3 * c).normalized();
+            HdrColor::from(sample_texture(
+                tex,
+                normal.x * 0.5 + 0.5,
+                normal.y * 0.5 + 0.5,
+            ))
+        }
+        &DrawStyle::VertexColors {
+            colors: (c1, c2, c3),
+            lit: is_lit,
+        } => {
+            let (a, b, c) = bary_coords;
+            let (h1, h2, h3) = (HdrColor::from(c1), HdrColor::from(c2), HdrColor::from(c3));
+            let mix =
+                |x1: f32, x2: f32, x3: f32| (a * x1 as f64 + b * x2 as f64 + c * x3 as f64) as f32;
+            let color = HdrColor(
+                mix(h1.0, h2.0, h3.0),
+                mix(h1.1, h2.1, h3.1),
+                mix(h1.2, h2.2, h3.2),
+            );
+            if is_lit {
+                lit(color)
+            } else {
+                color
+            }
+        }
+        DrawStyle::Wireframe(_) | DrawStyle::FilledRandom(_) | DrawStyle::PerFace(_) => {
+            panic!("should not end here")
         }
-        DrawStyle::Filled(color) => color.scale(intensity),
-        DrawStyle::FilledRandom => Color::random().scale(intensity),
-        DrawStyle::Wireframe(_) => panic!("should not end here"),
     }
 }
 
-fn triangle_barycentric(
-    image: &mut Image,
-    p1: &Point3f,
-    p2: &Point3f,
-    p3: &Point3f,
+/// Nearest-neighbour lookup with `v = 0` at row 0, clamping coordinates past 1.
+pub(crate) fn sample_texture(tex: &RgbImage, u: f64, v: f64) -> Color {
+    let x = ((u * tex.width() as f64) as u32).min(tex.width() - 1);
+    let y = ((v * tex.height() as f64) as u32).min(tex.height() - 1);
+    Color::from(*tex.get_pixel(x, y))
+}
+
+/// Largest change in depth per pixel along x or y across the plane of a screen-space
+/// triangle, 0 for triangles seen edge-on.
+fn depth_slope(a: &Point3f, b: &Point3f, c: &Point3f) -> f64 {
+    let (ux, uy, uz) = (b.x - a.x, b.y - a.y, b.z - a.z);
+    let (vx, vy, vz) = (c.x - a.x, c.y - a.y, c.z - a.z);
+    let (nx, ny, nz) = (uy * vz - uz * vy, uz * vx - ux * vz, ux * vy - uy * vx);
+    if nz == 0.0 {
+        return 0.0;
+    }
+    (nx / nz).abs().max((ny / nz).abs())
+}
+
+/// Rasterizes a depth-tested triangle into `target`, asking `shade` for the color of
+/// every covered pixel given its barycentric coordinates and depth; pixels it has none
+/// for are skipped.
+///
+/// Tiles the target knows to be nearer than the whole triangle, like those of the
+/// hierarchical z-buffer of an [`Image`], are skipped without evaluating any of their
+/// pixels.
+pub fn triangle_shaded<T, F>(target: &mut T, setup: &TriangleSetup, mut shade: F)
+where
+    T: RenderTarget + ?Sized,
+    F: FnMut((f64, f64, f64), f64) -> Option<HdrColor>,
+{
+    let snapped;
+    let setup = match target.precision() {
+        Precision::Fixed if !setup.is_fixed() => {
+            let [p1, p2, p3] = setup.corners();
+            // snapping may leave nothing to draw
+            let Some(setup) = TriangleSetup::new_fixed(p1, p2, p3) else {
+                return;
+            };
+            snapped = setup;
+            &snapped
+        }
+        _ => setup,
+    };
+    let Some((min, max)) = target
+        .drawable_area()
+        .and_then(|(min, max)| setup.pixels_within(min, max))
+    else {
+        return;
+    };
+    if setup.is_subpixel() {
+        if let Some(stats) = target.stats_mut() {
+            stats.triangles_subpixel += 1;
+        }
+    }
+    let [p1, p2, p3] = setup.corners();
+    // slack for interpolated depths overshooting the vertices at the edges
+    let nearest = p1[2].max(p2[2]).max(p3[2]) + 1e-6;
+    let traversal = target.traversal();
+    let mut occluded = true;
+    for ty in min.1 / HI_Z_TILE..=max.1 / HI_Z_TILE {
+        for tx in min.0 / HI_Z_TILE..=max.0 / HI_Z_TILE {
+            let tile_min = ((tx * HI_Z_TILE).max(min.0), (ty * HI_Z_TILE).max(min.1));
+            let tile_max = (
+                ((tx + 1) * HI_Z_TILE - 1).min(max.0),
+                ((ty + 1) * HI_Z_TILE - 1).min(max.1),
+            );
+            if target.is_hidden(tile_min, tile_max, nearest) {
+                if let Some(overdraw) = target.overdraw_mut() {
+                    setup.for_each_pixel(traversal, tile_min, tile_max, |x, y, _, _| {
+                        overdraw.count_covered(x, y)
+                    });
+                }
+                continue;
+            }
+            occluded = false;
+            setup.for_each_pixel(traversal, tile_min, tile_max, |x, y, bary, z| {
+                if let Some(overdraw) = target.overdraw_mut() {
+                    overdraw.count_covered(x, y);
+                }
+                let Some(biased) = target.depth_test(x, y, z) else {
+                    return;
+                };
+                if let Some(color) = shade(bary, z) {
+                    target.set_depth(x, y, biased);
+                    target.put_pixel(x, y, color);
+                }
+            });
+        }
+    }
+    if occluded {
+        if let Some(stats) = target.stats_mut() {
+            stats.triangles_occluded += 1;
+        }
+    }
+}
+
+/// Draws a depth-tested triangle given in pixel coordinates into `target`, like
+/// [`Drawable::triangle`].
+pub fn triangle<T: RenderTarget + ?Sized>(
+    target: &mut T,
+    a: &Point3f,
+    b: &Point3f,
+    c: &Point3f,
     draw_style: &DrawStyle,
     intensity: f64,
 ) {
-    let min_p: ScreenPoint = ScreenPoint::from(p1.min(p2).min(p3));
-    let max_p: ScreenPoint = ScreenPoint::from(p1.max(p2).max(p3));
-
-    let width = image.width();
-    let height = image.height();
-    let min_p = ScreenPoint::new(min_p.x.min(width - 1), min_p.y.min(height - 1), min_p.z);
-    let max_p = ScreenPoint::new(max_p.x.min(width - 1), max_p.y.min(height - 1), max_p.z);
-
-    for y in min_p.y..=max_p.y {
-        for x in min_p.x..=max_p.x {
-            let p = ScreenPoint::new(x, y, 0).into();
-            let (a, b, c) = barycentric(&p1, &p2, &p3, &p);
-            if a >= -LIMIT && b >= -LIMIT && c >= -LIMIT {
-                let z = a * p1.z + b * p2.z + c * p3.z;
-                if image.check_and_set_zbuf(x, y, z) {
-                    let color = determine_color((a, b, c), draw_style, intensity);
-                    image.point(x, y, color);
-                }
+    let light = HdrColor(1.0, 1.0, 1.0).scale(intensity);
+    fill_triangle(target, a, b, c, setup_triangle(a, b, c), draw_style, light);
+}
+
+/// Draws a triangle already set up, or its outline for [`DrawStyle::Wireframe`].
+fn fill_triangle<T: RenderTarget + ?Sized>(
+    target: &mut T,
+    a: &Point3f,
+    b: &Point3f,
+    c: &Point3f,
+    setup: Option<TriangleSetup>,
+    draw_style: &DrawStyle,
+    light: HdrColor,
+) {
+    match (draw_style, setup) {
+        (&DrawStyle::Wireframe(color), _) => {
+            target.wireframe_edge(a, b, color);
+            target.wireframe_edge(b, c, color);
+            target.wireframe_edge(a, c, color);
+        }
+        (_, Some(setup)) => {
+            let flat = direct_flat_color(draw_style, a, b, c).map(DrawStyle::Filled);
+            let draw_style = flat.as_ref().unwrap_or(draw_style);
+            triangle_shaded(target, &setup, |bary, _| {
+                Some(determine_color(bary, draw_style, light))
+            });
+        }
+        // nothing to fill, and no barycentric coordinates to interpolate with
+        (_, None) => {
+            if let Some(stats) = target.stats_mut() {
+                stats.triangles_degenerate += 1;
             }
         }
     }
 }
 
-fn barycentric(p1: &Point3f, p2: &Point3f, p3: &Point3f, p: &Point3f) -> (f64, f64, f64) {
-    let denom = (p1.x - p3.x) * (p2.y - p3.y) - (p1.y - p3.y) * (p2.x - p3.x);
-    let lambda1 = ((p.x - p3.x) * (p2.y - p3.y) + (p3.x - p2.x) * (p.y - p3.y)) / denom;
-    let lambda2 = ((p3.x - p.x) * (p1.y - p3.y) + (p3.x - p1.x) * (p3.y - p.y)) / denom;
-    (lambda1, lambda2, 1.0 - lambda1 - lambda2)
+/// Draws a one pixel wide depth-tested line between two points in pixel coordinates into
+/// `target`.
+pub fn line<T: RenderTarget + ?Sized>(target: &mut T, a: &Point3f, b: &Point3f, color: Color) {
+    let Some((a, b)) = clip_to_positive(a, b) else {
+        return;
+    };
+    let (sa, sb) = (ScreenPoint::from(&a), ScreenPoint::from(&b));
+    let (width, height) = (target.width(), target.height());
+    let color = HdrColor::from(color);
+    for_each_line_pixel(sa.x, sa.y, sb.x, sb.y, |x, y, _| {
+        if x >= width || y >= height {
+            return;
+        }
+        if let Some(overdraw) = target.overdraw_mut() {
+            overdraw.count_covered(x, y);
+        }
+        if let Some(z) = target.depth_test(x, y, depth_along(&a, &b, x, y)) {
+            target.set_depth(x, y, z);
+            target.put_pixel(x, y, color);
+        }
+    });
+}
+
+/// Calls `fragment` with the position, barycentric coordinates and interpolated depth
+/// of every pixel of a `width` x `height` target covered by the triangle.
+pub(crate) fn rasterize<F: FnMut(u32, u32, (f64, f64, f64), f64)>(
+    width: u32,
+    height: u32,
+    p1: &Point3f,
+    p2: &Point3f,
+    p3: &Point3f,
+    fragment: F,
+) {
+    if width == 0 || height == 0 {
+        return;
+    }
+    let corner = |p: &Point3f| [p.x, p.y, p.z];
+    raster::for_each_triangle_pixel(
+        (0, 0),
+        (width - 1, height - 1),
+        corner(p1),
+        corner(p2),
+        corner(p3),
+        fragment,
+    );
+}
+
+#[test]
+fn test_line3d_depth_test() {
+    let mut image = Image::new(8, 8);
+    let red = Color(255, 0, 0);
+    let green = Color(0, 255, 0);
+    image.line3d(&Point3f::new(0., 2., 1.), &Point3f::new(7., 2., 1.), red);
+    // crosses the red line from behind on the left and in front on the right
+    image.line3d(&Point3f::new(0., 2., 0.), &Point3f::new(7., 2., 2.), green);
+    let pixels = image.to_rgb_image();
+    let row = Drawable::height(&image) - 1 - 2;
+    assert_eq!(Color::from(*pixels.get_pixel(1, row)), red);
+    assert_eq!(Color::from(*pixels.get_pixel(6, row)), green);
+}
+
+#[test]
+fn test_wide_lines() {
+    let count_lit = |image: &Image| image.to_rgb_image().pixels().filter(|p| p[0] > 0).count();
+    let red = Color(255, 0, 0);
+
+    let mut thin = Image::new(16, 16);
+    thin.line(2, 8, 12, 8, red);
+    assert_eq!(count_lit(&thin), 11);
+
+    let mut thick = Image::new(16, 16);
+    thick.set_line_style(LineStyle {
+        width: 3.0,
+        antialiased: false,
+    });
+    thick.line(2, 8, 12, 8, red);
+    // three rows along the segment plus the rounded caps
+    assert!(count_lit(&thick) >= 33);
+
+    let mut smooth = Image::new(16, 16);
+    smooth.set_line_style(LineStyle {
+        width: 1.0,
+        antialiased: true,
+    });
+    smooth.line3d(&Point3f::new(2., 3., 0.), &Point3f::new(12., 9., 0.), red);
+    let pixels = smooth.to_rgb_image();
+    // diagonal lines get partially covered pixels next to the fully covered ones
+    assert!(pixels.pixels().any(|p| p[0] > 0 && p[0] < 255));
+    assert!(pixels.pixels().any(|p| p[0] == 255));
+
+    // an empty image has nothing to draw into
+    let mut empty = Image::new(0, 0);
+    empty.set_line_style(LineStyle {
+        width: 3.0,
+        antialiased: false,
+    });
+    empty.line(0, 0, 5, 5, red);
+}
+
+#[test]
+fn test_rect_and_circle_primitives() {
+    let count_lit = |image: &Image| image.to_rgb_image().pixels().filter(|p| p[0] > 0).count();
+    let red = Color(255, 0, 0);
+
+    let mut image = Image::new(32, 32);
+    image.filled_rect(2, 3, 5, 4, red);
+    assert_eq!(count_lit(&image), 8);
+
+    let mut image = Image::new(32, 32);
+    image.rect(2, 2, 6, 6, red);
+    assert_eq!(count_lit(&image), 16);
+
+    let mut image = Image::new(32, 32);
+    image.circle(16, 16, 5, red);
+    let pixels = image.to_rgb_image();
+    // extreme points of the outline, remembering the image is exported flipped
+    for (x, y) in [(21, 16), (11, 16), (16, 21), (16, 11)] {
+        assert_eq!(pixels.get_pixel(x, 31 - y)[0], 255);
+    }
+    assert_eq!(pixels.get_pixel(16, 15)[0], 0);
+
+    let mut image = Image::new(32, 32);
+    image.filled_circle(16, 16, 5, red);
+    let area = count_lit(&image) as f64;
+    assert!((area - std::f64::consts::PI * 25.0).abs() < 12.0);
+
+    // shapes hanging over the border are clipped instead of panicking
+    let mut image = Image::new(8, 8);
+    image.filled_ellipse(0, 7, 6, 3, red);
+    image.ellipse(7, 0, 6, 3, red);
+    image.rect(4, 4, 20, 20, red);
+    assert!(count_lit(&image) > 0);
+}
+
+#[test]
+fn test_draw_text() {
+    let red = Color(255, 0, 0);
+    let mut image = Image::new(32, 32);
+    image.draw_text(2, 29, "I\nI", red);
+    let pixels = image.to_rgb_image();
+    // the top row of 'I' is 0x1E: pixels 1 to 4 of the glyph
+    let top = 31 - 29;
+    let lit: Vec<u32> = (0..10)
+        .filter(|&x| pixels.get_pixel(x, top)[0] == 255)
+        .collect();
+    assert_eq!(lit, vec![3, 4, 5, 6]);
+    // the second line starts eight rows further down
+    assert_eq!(pixels.get_pixel(3, top + 8)[0], 255);
+    // text running off the image is clipped
+    image.draw_text(28, 2, "clipped", red);
+}
+
+#[test]
+fn test_fog_uses_depth() {
+    let mut image = Image::new(4, 1);
+    image.clear(Color(0, 0, 255));
+    let white = Color(255, 255, 255);
+    for (x, z) in [(0, 1.0), (1, -1.0)] {
+        image.check_and_set_zbuf(x, 0, z);
+        image.point(x, 0, white);
+    }
+    image.set_fog(Some(Fog {
+        color: Color(0, 0, 0),
+        falloff: crate::fog::FogFalloff::Linear {
+            start: 0.0,
+            end: 2.0,
+        },
+    }));
+    let pixels = image.to_rgb_image();
+    assert_eq!(pixels.get_pixel(0, 0).0, [255, 255, 255]);
+    assert_eq!(pixels.get_pixel(1, 0).0, [0, 0, 0]);
+    // the background is not fogged
+    assert_eq!(pixels.get_pixel(2, 0).0, [0, 0, 255]);
+}
+
+#[test]
+fn test_stats() {
+    let mut image = Image::new(8, 8);
+    let style = DrawStyle::Filled(Color(255, 255, 255));
+    let (a, b) = (Point3f::new(0., 0., 0.), Point3f::new(8., 0., 0.));
+    image.triangle(&a, &b, &Point3f::new(0., 8., 0.), &style, 1.0);
+    let shaded = image.stats().pixels_shaded;
+    assert!(shaded > 0);
+    image.triangle(&a, &b, &Point3f::new(0., 12., 1.), &style, 1.0);
+    image.record_culled();
+    // covering the center of pixel (2, 2) and nothing else
+    let tiny = [(2.3, 2.3), (2.9, 2.4), (2.4, 2.9)].map(|(x, y)| Point3f::new(x, y, 2.));
+    image.triangle(&tiny[0], &tiny[1], &tiny[2], &style, 1.0);
+
+    let stats = image.stats();
+    assert_eq!(stats.triangles_submitted, 4);
+    assert_eq!(stats.triangles_culled, 1);
+    assert_eq!(stats.triangles_clipped, 1);
+    assert_eq!(stats.triangles_subpixel, 1);
+    assert!(stats.pixels_shaded > shaded);
+    assert_eq!(image.z_buffer[2 * 8 + 2], 2.0);
+}
+
+#[test]
+fn test_fixed_precision() {
+    let style = DrawStyle::Filled(Color(255, 255, 255));
+    let render = |precision: Precision, corners: [(f64, f64); 3]| {
+        let mut image = Image::new(16, 16);
+        image.set_precision(precision);
+        let [a, b, c] = corners.map(|(x, y)| Point3f::new(x, y, 0.));
+        image.triangle(&a, &b, &c, &style, 1.0);
+        image.to_rgb_image()
+    };
+    // fixed point draws what doubles draw at the corners snapped to 256ths of a pixel
+    let corners = [(0.3, 0.7), (15.1, 3.33), (4.49, 15.2)];
+    let snap = |c: f64| (c * raster::FIXED_ONE as f64).round() / raster::FIXED_ONE as f64;
+    let snapped = corners.map(|(x, y)| (snap(x), snap(y)));
+    assert_eq!(
+        render(Precision::Fixed, corners),
+        render(Precision::Float, snapped)
+    );
+}
+
+#[test]
+fn test_overdraw() {
+    let style = DrawStyle::Filled(Color(255, 255, 255));
+    let corners = |z: f64| [(0., 0.), (16., 0.), (0., 16.)].map(|(x, y)| Point3f::new(x, y, z));
+    let counts = |depths: [f64; 2]| {
+        let mut image = Image::new(16, 16);
+        image.count_overdraw();
+        for z in depths {
+            let [a, b, c] = corners(z);
+            image.triangle(&a, &b, &c, &style, 1.0);
+        }
+        let overdraw = image.overdraw().unwrap();
+        let idx = 2 * 16 + 2;
+        (overdraw.covered[idx], overdraw.written[idx])
+    };
+    // drawn back to front both are written, front to back the hidden one is skipped
+    assert_eq!(counts([0.0, 1.0]), (2, 2));
+    assert_eq!(counts([1.0, 0.0]), (2, 1));
+
+    let mut image = Image::new(16, 16);
+    image.count_overdraw();
+    let [a, b, c] = corners(0.0);
+    image.triangle(&a, &b, &c, &style, 1.0);
+    let overdraw = image.overdraw().unwrap();
+    assert_eq!(overdraw.averages(), (1.0, 1.0));
+    // the top right corner of the saved image is outside the triangle
+    let heatmap = overdraw.heatmap(&overdraw.written, 1, crate::colormap::Colormap::Grayscale);
+    assert_eq!(heatmap.get_pixel(15, 0).0, [0, 0, 0]);
+    assert_eq!(heatmap.get_pixel(1, 14).0, [255, 255, 255]);
+}
+
+#[test]
+fn test_culling() {
+    let mut image = Image::new(8, 8);
+    let style = DrawStyle::Filled(Color(255, 255, 255));
+    let (a, b, c) = (
+        Point3f::new(0., 0., 0.),
+        Point3f::new(8., 0., 0.),
+        Point3f::new(0., 8., 0.),
+    );
+    image.set_culling(Some(Winding::CounterClockwise));
+    image.triangle(&a, &c, &b, &style, 1.0);
+    assert_eq!(image.stats().pixels_shaded, 0);
+    image.triangle(&a, &b, &c, &style, 1.0);
+    let shaded = image.stats().pixels_shaded;
+    assert!(shaded > 0);
+
+    image.set_culling(Some(Winding::Clockwise));
+    image.triangle(&a, &b, &c, &style, 1.0);
+    // without an area a triangle faces neither way
+    image.triangle(&a, &b, &Point3f::new(4., 0., 0.), &style, 1.0);
+    let stats = image.stats();
+    assert_eq!((stats.triangles_culled, stats.triangles_degenerate), (3, 0));
+    assert_eq!(stats.pixels_shaded, shaded);
+}
+
+#[test]
+fn test_occlusion_query() {
+    let mut image = Image::new(16, 16);
+    let boxed = |min: (f64, f64, f64), max: (f64, f64, f64)| Aabb {
+        min: Vec3f::new(min.0, min.1, min.2),
+        max: Vec3f::new(max.0, max.1, max.2),
+    };
+    let behind = boxed((-0.5, -0.5, -0.5), (0.5, 0.5, 0.0));
+    let through = boxed((-0.5, -0.5, 0.0), (0.5, 0.5, 0.8));
+    let model = Mat4::rotation_y(0.3);
+    assert!(image.occlusion_query(&behind, &model));
+
+    // a wall over the whole image at a depth of 0.5
+    let style = DrawStyle::Filled(Color(255, 255, 255));
+    let corner = |x: f64, y: f64| Point3f::new(x, y, 0.5);
+    image.triangle(
+        &corner(0., 0.),
+        &corner(32., 0.),
+        &corner(0., 32.),
+        &style,
+        1.0,
+    );
+    let rgb = image.to_rgb_image();
+    let shaded = image.stats().pixels_shaded;
+    assert!(!image.occlusion_query(&behind, &model));
+    assert!(image.occlusion_query(&through, &model));
+    // nothing was written
+    assert_eq!(image.to_rgb_image(), rgb);
+    assert_eq!(image.stats().pixels_shaded, shaded);
+
+    // past the near plane is always visible
+    image.set_projection(Some(Mat4::identity()));
+    assert!(!image.occlusion_query(&behind, &model));
+    let near = boxed((-0.5, -0.5, 1.2), (0.5, 0.5, 1.5));
+    assert!(image.occlusion_query(&near, &Mat4::identity()));
+}
+
+#[test]
+fn test_triangle_with() {
+    let mut image = Image::new(8, 8);
+    let (a, b, c) = (
+        Point3f::new(0., 0., 1.),
+        Point3f::new(16., 0., 1.),
+        Point3f::new(0., 16., 1.),
+    );
+    // stripes along the first corner's weight, with gaps between them
+    image.triangle_with(&a, &b, &c, |(weight, _, _), depth| {
+        assert_eq!(depth, 1.0);
+        ((weight * 8.0) as u32)
+            .is_multiple_of(2)
+            .then_some(Color(255, 0, 0))
+    });
+    let behind = DrawStyle::Filled(Color(0, 0, 255));
+    let far = |p: &Point3f| Point3f::new(p.x, p.y, 0.);
+    image.triangle(&far(&a), &far(&b), &far(&c), &behind, 1.0);
+
+    // the gaps kept no depth, so the farther triangle shows through them
+    let rgb = image.to_rgb_image();
+    let pixel = |x: u32, y: u32| rgb.get_pixel(x, 7 - y).0;
+    assert_eq!(pixel(0, 0), [0, 0, 255]);
+    assert_eq!(pixel(2, 0), [255, 0, 0]);
+    assert_eq!(pixel(4, 0), [0, 0, 255]);
+}
+
+#[test]
+fn test_progress() {
+    use std::sync::{Arc, Mutex};
+
+    let reported = Arc::new(Mutex::new(Vec::new()));
+    let mut image = Image::new(4, 4);
+    let sink = reported.clone();
+    image.set_progress(Some(Box::new(move |stats: &RenderStats| {
+        sink.lock().unwrap().push(stats.triangles_submitted)
+    })));
+    for _ in 0..PROGRESS_INTERVAL * 2 + 1 {
+        image.record_culled();
+    }
+    let expected = vec![PROGRESS_INTERVAL, PROGRESS_INTERVAL * 2];
+    assert_eq!(*reported.lock().unwrap(), expected);
+}
+
+#[test]
+fn test_cancel() {
+    let (a, b, c) = (
+        Point3f::new(0., 0., 0.),
+        Point3f::new(8., 0., 0.),
+        Point3f::new(0., 8., 0.),
+    );
+    let style = DrawStyle::Filled(Color(255, 255, 255));
+    let token = CancelToken::new();
+    let mut image = Image::new(8, 8);
+    image.set_cancel_token(Some(token.clone()));
+    image.triangle(&a, &b, &c, &style, 1.0);
+    let drawn = image.stats().pixels_shaded;
+    assert!(drawn > 0 && !image.is_cancelled());
+
+    // noticed at the next check, keeping what was drawn
+    token.cancel();
+    for _ in 1..CANCEL_CHECK_INTERVAL {
+        image.record_culled();
+    }
+    image.triangle(&a, &b, &c, &style, 1.0);
+    assert!(image.is_cancelled());
+    assert_eq!(image.stats().pixels_shaded, drawn);
+
+    // a spent budget draws nothing
+    let mut image = Image::new(8, 8);
+    image.set_time_budget(Some(Duration::ZERO));
+    image.triangle(&a, &b, &c, &style, 1.0);
+    assert!(image.is_cancelled());
+    assert_eq!(image.stats().pixels_shaded, 0);
+}
+
+#[test]
+fn test_degenerate_triangles_are_skipped() {
+    let mut image = Image::new(8, 8);
+    let style = DrawStyle::VertexColors {
+        colors: (Color(255, 0, 0), Color(0, 255, 0), Color(0, 0, 255)),
+        lit: true,
+    };
+    let (a, b) = (Point3f::new(1., 1., 0.), Point3f::new(6., 6., 1.));
+    // collinear, then with a repeated corner
+    image.triangle(&a, &b, &Point3f::new(3.5, 3.5, 0.5), &style, f64::NAN);
+    image.triangle(&a, &b, &b, &style, 1.0);
+    let stats = image.stats();
+    assert_eq!(stats.triangles_submitted, 2);
+    assert_eq!(stats.triangles_degenerate, 2);
+    assert_eq!(stats.pixels_shaded, 0);
+    assert!(image.depth_buffer().iter().all(|&z| z == f64::NEG_INFINITY));
+
+    // their edges still show as a wireframe
+    image.triangle(&a, &b, &b, &DrawStyle::Wireframe(Color(255, 255, 255)), 1.0);
+    assert!(image.stats().pixels_shaded > 0);
+}
+
+#[test]
+fn test_into_rgb_buffer() {
+    let mut image = Image::new(3, 2);
+    // the bottom-left pixel comes last in the top-down buffer
+    image.point(0, 0, Color(10, 20, 30));
+    let buffer = image.into_rgb_buffer();
+    assert_eq!(buffer.dimensions(), (3, 2));
+    let raw = buffer.as_raw();
+    assert_eq!(raw.len(), 3 * 2 * 3);
+    assert_eq!(raw[9..12], [10, 20, 30]);
+    assert!(raw[..9].iter().all(|&b| b == 0));
+}
+
+#[test]
+fn test_into_rgba_buffer() {
+    let mut image = Image::new(3, 2);
+    image.point(0, 0, Color(10, 20, 30));
+    let raw = image.into_rgba_buffer().into_raw();
+    assert_eq!(raw.len(), 3 * 2 * 4);
+    assert_eq!(raw[12..16], [10, 20, 30, 255]);
+    assert_eq!(raw[..4], [0, 0, 0, 255]);
+}
+
+#[test]
+fn test_backgrounds() {
+    let mut image = Image::new(4, 4);
+    image.clear_background(&"gradient:#ffffff:#000000".parse().unwrap());
+    let pixels = image.to_rgb_image();
+    assert!(pixels.get_pixel(0, 0)[0] > 200 && pixels.get_pixel(3, 3)[0] < 100);
+    assert_eq!(pixels.get_pixel(0, 1), pixels.get_pixel(3, 1));
+
+    let mut image = Image::new(4, 4);
+    image.clear_background(&Background::Checkerboard {
+        size: 2,
+        light: Color(200, 200, 200),
+        dark: Color(100, 100, 100),
+    });
+    let pixels = image.to_rgb_image();
+    assert_eq!(pixels.get_pixel(0, 0)[0], 200);
+    assert_eq!(pixels.get_pixel(2, 0)[0], 100);
+    assert_eq!(pixels.get_pixel(3, 3)[0], 200);
+
+    // only what is drawn is opaque, until cleared to a color
+    let mut image = Image::new(3, 2);
+    image.clear_background(&Background::Transparent);
+    image.check_and_set_zbuf(0, 0, 0.0);
+    image.point(0, 0, Color(10, 20, 30));
+    let raw = image.to_rgba_image().into_raw();
+    assert_eq!(raw[12..16], [10, 20, 30, 255]);
+    assert_eq!(raw[..4], [0, 0, 0, 0]);
+    image.clear(Color(0, 0, 0));
+    assert_eq!(image.into_rgba_buffer().into_raw()[3], 255);
+
+    assert!("checker:0".parse::<Background>().is_err());
+    assert!("gradient:#fff".parse::<Background>().is_err());
+}
+
+#[test]
+fn test_backdrop() {
+    let mut image = Image::new(2, 2);
+    let mut backdrop = RgbaImage::new(2, 2);
+    backdrop.put_pixel(0, 0, image::Rgba([255, 0, 0, 255]));
+    backdrop.put_pixel(1, 0, image::Rgba([0, 0, 255, 0]));
+    assert!(image
+        .set_backdrop(Some(Arc::new(RgbaImage::new(3, 2))))
+        .is_err());
+    image.set_backdrop(Some(Arc::new(backdrop))).unwrap();
+
+    // the backdrop shows where nothing is drawn, over the background by its alpha
+    image.clear(Color(0, 255, 0));
+    image.check_and_set_zbuf(0, 0, 0.0);
+    image.point(0, 0, Color(10, 20, 30));
+    let pixels = image.to_rgba_image();
+    assert_eq!(pixels.get_pixel(0, 0).0, [255, 0, 0, 255]);
+    assert_eq!(pixels.get_pixel(1, 0).0, [0, 255, 0, 255]);
+    assert_eq!(pixels.get_pixel(0, 1).0, [10, 20, 30, 255]);
+
+    // and keeps its own alpha over a transparent background
+    image.clear_background(&Background::Transparent);
+    assert_eq!(image.to_rgba_image().get_pixel(1, 0).0, [0, 0, 255, 0]);
+}
+
+#[test]
+fn test_hidden_triangles_are_skipped() {
+    let mut image = Image::new(32, 32);
+    let near = DrawStyle::Filled(Color(255, 0, 0));
+    let far = DrawStyle::Filled(Color(0, 255, 0));
+    let quad = |image: &mut Image, z: f64, style: &DrawStyle| {
+        let corners =
+            [(-1., -1.), (40., -1.), (40., 40.), (-1., 40.)].map(|(x, y)| Point3f::new(x, y, z));
+        image.triangle(&corners[0], &corners[1], &corners[2], style, 1.0);
+        image.triangle(&corners[0], &corners[2], &corners[3], style, 1.0);
+    };
+    quad(&mut image, 0.5, &near);
+    let shaded = image.stats().pixels_shaded;
+
+    quad(&mut image, 0.0, &far);
+    let stats = image.stats();
+    assert_eq!(stats.triangles_occluded, 2);
+    assert_eq!(stats.pixels_shaded, shaded);
+    assert_eq!(image.to_rgb_image().get_pixel(16, 16).0, [255, 0, 0]);
+
+    // anything in front still gets drawn
+    quad(&mut image, 0.8, &far);
+    assert_eq!(image.to_rgb_image().get_pixel(16, 16).0, [0, 255, 0]);
+}
+
+#[test]
+fn test_projection_clips_depth() {
+    use crate::camera::Orthographic;
+
+    let mut image = Image::new(8, 8);
+    let volume = Orthographic::from_height(4.0, 1.0, 0.0, 10.0);
+    image.set_projection(Some(volume.projection()));
+    // two units of view space span the left half of the image
+    assert_eq!(image.to_screen(&Vec3f::new(-2., 0., -5.)).x, 0.0);
+    assert_eq!(image.to_screen(&Vec3f::new(0., 0., -5.)).x, 4.0);
+    assert!(image.check_and_set_zbuf(0, 0, image.to_screen(&Vec3f::new(0., 0., -5.)).z));
+    assert!(!image.check_and_set_zbuf(1, 0, image.to_screen(&Vec3f::new(0., 0., 1.)).z));
+    assert!(!image.check_and_set_zbuf(2, 0, image.to_screen(&Vec3f::new(0., 0., -11.)).z));
+    assert!(image.view_bounds().max.z <= 1e-9 && image.view_bounds().min.z >= -10.0 - 1e-9);
+}
+
+#[test]
+fn test_depth_bias() {
+    let (a, b, c) = (
+        Point3f::new(0., 0., 0.),
+        Point3f::new(8., 0., 4.),
+        Point3f::new(0., 8., 0.),
+    );
+    assert_eq!(depth_slope(&a, &b, &c), 0.5);
+
+    // a wireframe over a surface in the same plane only shows with a bias; the surface
+    // reaches past the triangle, as only pixels on top and left edges are filled
+    let red = Color(255, 0, 0);
+    let wire_pixels = |depth: f64, bias: DepthBias| {
+        let corner = |x: f64, y: f64| Point3f::new(x, y, x * depth);
+        let mut image = Image::new(8, 8);
+        let (b2, c2) = (corner(16., 0.), corner(0., 16.));
+        image.triangle(&a, &b2, &c2, &DrawStyle::Filled(Color(255, 255, 255)), 1.0);
+        image.set_depth_bias(bias);
+        let (b, c) = (corner(8., 0.), corner(0., 8.));
+        image.triangle(&a, &b, &c, &DrawStyle::Wireframe(red), 1.0);
+        let buffer = image.into_rgb_buffer();
+        buffer.pixels().filter(|p| p.0 == [255, 0, 0]).count()
+    };
+    assert_eq!(wire_pixels(0.0, DepthBias::default()), 0);
+    let constant = DepthBias {
+        constant: 1e-3,
+        slope_scale: 0.0,
+    };
+    assert_eq!(wire_pixels(0.0, constant), 22);
+    // on a slope, lines running up to half a pixel from the pixel centers the surface is
+    // sampled at may end up either side of it, so the bias has to make up for half a
+    // pixel of the slope
+    assert!(wire_pixels(0.5, DepthBias::default()) < 22);
+    let sloped = DepthBias {
+        constant: 0.0,
+        slope_scale: 1.0,
+    };
+    assert_eq!(wire_pixels(0.5, sloped), 22);
 }
 
 #[test]
-fn test_barycentric() {
-    let p1 = Point3f::new(5., 5., 0.);
-    let p2 = Point3f::new(10., 5., 0.);
-    let p3 = Point3f::new(10., 7., 0.);
-
-    fn close_enough(res: (f64, f64, f64), ref_vals: (f64, f64, f64)) -> bool {
-        const EPS: f64 = 1e-6;
-        (res.0 - ref_vals.0).abs() <= EPS
-            && (res.1 - ref_vals.1).abs() <= EPS
-            && (res.2 - ref_vals.2).abs() <= EPS
-    }
-    assert!(close_enough(
-        barycentric(&p1, &p2, &p3, &p1),
-        (1.0, 0.0, 0.0)
-    ));
-    assert!(close_enough(
-        barycentric(&p1, &p2, &p3, &p2),
-        (0.0, 1.0, 0.0)
-    ));
-    assert!(close_enough(
-        barycentric(&p1, &p2, &p3, &p3),
-        (0.0, 0.0, 1.0)
-    ));
-
-    let outside = Point3f::new(100., 100., 0.);
-    let (a, b, c) = barycentric(&p1, &p2, &p3, &outside);
-    assert!([a, b, c].iter().any(|&x| x < 0.0));
-}
-
-fn intersect_y(p1: &ScreenPoint, p2: &ScreenPoint, y: u32) -> f64 {
-    if p1.x == p2.x {
-        return p1.x as f64;
-    }
-    let x1 = p1.x as f64;
-    let y1 = p1.y as f64;
-    let x2 = p2.x as f64;
-    let y2 = p2.y as f64;
-    let delta = (y2 - y1) / (x2 - x1);
-    (y as f64 - y1) / delta + x1
+fn test_viewport_restricts_drawing() {
+    let mut image = Image::new(16, 8);
+    image.set_viewport(Viewport::letterboxed(16, 8, 1.0));
+    let corners = [(-2., -2.), (2., -2.), (2., 2.), (-2., 2.)].map(|(x, y)| {
+        let p = image.to_screen(&Vec3f::new(x, y, 0.));
+        Point3f::new(p.x.max(0.0), p.y.max(0.0), 0.)
+    });
+    let style = DrawStyle::Filled(Color(255, 255, 255));
+    image.triangle(&corners[0], &corners[1], &corners[2], &style, 1.0);
+    image.triangle(&corners[0], &corners[2], &corners[3], &style, 1.0);
+    let pixels = image.to_rgb_image();
+    // only the centered 8x8 square is covered
+    for x in 0..16 {
+        let expected = if (4..12).contains(&x) { 255 } else { 0 };
+        assert_eq!(pixels.get_pixel(x, 4)[0], expected, "{}", x);
+    }
 }
 
 #[test]
-fn test_intersect_y() {
-    let p1 = ScreenPoint::new(5, 10, 0);
-    let p2 = ScreenPoint::new(5, 20, 0);
-    assert_eq!(intersect_y(&p1, &p2, 15), 5.0);
-
-    let p1 = ScreenPoint::new(5, 10, 0);
-    let p2 = ScreenPoint::new(20, 20, 0);
-    assert_eq!(intersect_y(&p1, &p2, 15), 12.5);
+fn test_scissor() {
+    // a band of the upper half, so the scissor is given in rows of the full image
+    let mut image = Image::band(8, 16, 8, 8);
+    image.clear(Color(255, 0, 0));
+    let scissor = Viewport {
+        x: 2,
+        y: 6,
+        width: 4,
+        height: 4,
+    };
+    image.set_scissor(Some(scissor));
+    image.clear(Color(0, 0, 255));
+    let (a, b, c, d) = (
+        Point3f::new(0., 0., 0.),
+        Point3f::new(8., 0., 0.),
+        Point3f::new(8., 8., 0.),
+        Point3f::new(0., 8., 0.),
+    );
+    let style = DrawStyle::Filled(Color(0, 255, 0));
+    image.triangle(&a, &b, &c, &style, 1.0);
+    image.triangle(&a, &c, &d, &style, 1.0);
+    image.line(0, 7, 7, 7, Color(255, 255, 255));
+    for y in 0..8 {
+        for x in 0..8 {
+            let inside = (2..6).contains(&x) && y < 2;
+            let expected = if inside {
+                Color(0, 255, 0)
+            } else {
+                Color(255, 0, 0)
+            };
+            assert_eq!(
+                image.framebuffer().get(x, y),
+                expected.into(),
+                "{} {}",
+                x,
+                y
+            );
+            assert_eq!(image.depth_buffer()[(y * 8 + x) as usize] == 0.0, inside);
+        }
+    }
 }
@@ -0,0 +1,252 @@
+use std::f64::consts::PI;
+
+use image::DynamicImage;
+
+use crate::color::{Color, HdrColor};
+use crate::cubemap::CubeMap;
+use crate::drawable::{Drawable, Image};
+use crate::math::{self, Vec3f};
+
+/// Equirectangular map of linear colors, row 0 looking straight up.
+struct LatLong {
+    width: u32,
+    height: u32,
+    pixels: Vec<HdrColor>,
+}
+
+impl LatLong {
+    fn direction(&self, x: f64, y: f64) -> Vec3f {
+        let phi = (x / self.width as f64 - 0.5) * 2.0 * PI;
+        let theta = (0.5 - y / self.height as f64) * PI;
+        Vec3f::new(
+            theta.cos() * phi.sin(),
+            theta.sin(),
+            -theta.cos() * phi.cos(),
+        )
+    }
+
+    /// Bilinear lookup, wrapping around horizontally.
+    fn sample(&self, direction: &Vec3f) -> HdrColor {
+        let d = direction.normalized();
+        let u = 0.5 + d.x.atan2(-d.z) / (2.0 * PI);
+        let v = 0.5 - d.y.clamp(-1.0, 1.0).asin() / PI;
+        let x = u * self.width as f64 - 0.5;
+        let y = (v * self.height as f64 - 0.5).clamp(0.0, self.height as f64 - 1.0);
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = ((x - x0) as f32, (y - y0) as f32);
+        let texel = |x: f64, y: f64| {
+            let x = (x as i64).rem_euclid(self.width as i64) as u32;
+            let y = (y as u32).min(self.height - 1);
+            self.pixels[(y * self.width + x) as usize]
+        };
+        let lerp = |a: HdrColor, b: HdrColor, t: f32| {
+            HdrColor(
+                a.0 + (b.0 - a.0) * t,
+                a.1 + (b.1 - a.1) * t,
+                a.2 + (b.2 - a.2) * t,
+            )
+        };
+        let top = lerp(texel(x0, y0), texel(x0 + 1.0, y0), tx);
+        let bottom = lerp(texel(x0, y0 + 1.0), texel(x0 + 1.0, y0 + 1.0), tx);
+        lerp(top, bottom, ty)
+    }
+
+    /// Box-filters the map down to `width` x `height`.
+    fn downsampled(&self, width: u32, height: u32) -> LatLong {
+        // source texels covered by target texel `i` of `count`, at least one
+        let span = |i: u32, count: u32, size: u32| {
+            let start = i * size / count;
+            start..((i + 1) * size / count).clamp(start + 1, size)
+        };
+        let mut pixels = Vec::with_capacity((width * height) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = HdrColor::default();
+                let mut count = 0;
+                for sy in span(y, height, self.height) {
+                    for sx in span(x, width, self.width) {
+                        let p = self.pixels[(sy * self.width + sx) as usize];
+                        sum = HdrColor(sum.0 + p.0, sum.1 + p.1, sum.2 + p.2);
+                        count += 1;
+                    }
+                }
+                pixels.push(sum.scale(1.0 / count as f64));
+            }
+        }
+        LatLong {
+            width,
+            height,
+            pixels,
+        }
+    }
+}
+
+/// Environment surrounding the scene, used as a background and as a source of diffuse
+/// image-based ambient light.
+pub struct EnvironmentMap {
+    radiance: LatLong,
+    /// Cosine-weighted average of the radiance around each direction.
+    irradiance: LatLong,
+}
+
+impl EnvironmentMap {
+    /// Takes an equirectangular image; floating point images such as Radiance HDR files
+    /// are taken as linear, everything else as sRGB.
+    pub fn from_image(image: &DynamicImage) -> Self {
+        Self::from_radiance(LatLong {
+            width: image.width(),
+            height: image.height(),
+            pixels: linear_pixels(image),
+        })
+    }
+
+    /// Takes a cube map, resampled to an equirectangular map four faces wide.
+    pub fn from_cube_map(cube_map: &CubeMap) -> Self {
+        let (width, height) = (cube_map.size() * 4, cube_map.size() * 2);
+        let mut radiance = LatLong {
+            width,
+            height,
+            pixels: Vec::with_capacity((width * height) as usize),
+        };
+        for y in 0..height {
+            for x in 0..width {
+                let direction = radiance.direction(x as f64 + 0.5, y as f64 + 0.5);
+                radiance.pixels.push(cube_map.sample(&direction));
+            }
+        }
+        Self::from_radiance(radiance)
+    }
+
+    fn from_radiance(radiance: LatLong) -> Self {
+        let irradiance = convolve_irradiance(&radiance);
+        EnvironmentMap {
+            radiance,
+            irradiance,
+        }
+    }
+
+    /// Radiance seen looking along `direction`.
+    pub fn sample(&self, direction: &Vec3f) -> HdrColor {
+        self.radiance.sample(direction)
+    }
+
+    /// Diffuse light arriving at a surface facing `normal`, such that a white Lambertian
+    /// surface inside a uniform environment takes on the environment's color.
+    pub fn irradiance(&self, normal: &Vec3f) -> HdrColor {
+        self.irradiance.sample(normal)
+    }
+
+    /// Fills `image` with the environment as seen by a camera looking down -Z with a
+    /// vertical field of view of `fov` degrees. Depth is left untouched.
+    pub fn draw_background(&self, image: &mut Image, fov: f64) {
+        let (width, height) = (image.width(), image.height());
+        let tan_half_fov = (fov.to_radians() / 2.0).tan();
+        let aspect = width as f64 / height as f64;
+        for y in 0..height {
+            for x in 0..width {
+                let ndc_x = (x as f64 + 0.5) / width as f64 * 2.0 - 1.0;
+                let ndc_y = (y as f64 + 0.5) / height as f64 * 2.0 - 1.0;
+                let direction =
+                    Vec3f::new(ndc_x * tan_half_fov * aspect, ndc_y * tan_half_fov, -1.0);
+                image.point_hdr(x, y, self.sample(&direction));
+            }
+        }
+    }
+}
+
+/// Pixels of `image` as linear colors: floating point images such as Radiance HDR files
+/// are taken as linear, everything else as sRGB.
+pub(crate) fn linear_pixels(image: &DynamicImage) -> Vec<HdrColor> {
+    match image {
+        DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_) => image
+            .to_rgb32f()
+            .pixels()
+            .map(|p| HdrColor(p[0], p[1], p[2]))
+            .collect(),
+        _ => image
+            .to_rgb8()
+            .pixels()
+            .map(|p| HdrColor::from(Color::from(*p)))
+            .collect(),
+    }
+}
+
+fn convolve_irradiance(radiance: &LatLong) -> LatLong {
+    let source = radiance.downsampled(64, 32);
+    // solid angle of each source texel shrinks towards the poles
+    let samples: Vec<(Vec3f, HdrColor)> = (0..source.height)
+        .flat_map(|y| (0..source.width).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let direction = source.direction(x as f64 + 0.5, y as f64 + 0.5);
+            let theta = (0.5 - (y as f64 + 0.5) / source.height as f64) * PI;
+            let solid_angle =
+                theta.cos() * (2.0 * PI / source.width as f64) * (PI / source.height as f64);
+            let color = source.pixels[(y * source.width + x) as usize].scale(solid_angle / PI);
+            (direction, color)
+        })
+        .collect();
+
+    let mut irradiance = LatLong {
+        width: 32,
+        height: 16,
+        pixels: Vec::new(),
+    };
+    for y in 0..irradiance.height {
+        for x in 0..irradiance.width {
+            let normal = irradiance.direction(x as f64 + 0.5, y as f64 + 0.5);
+            let mut sum = HdrColor::default();
+            for (direction, color) in &samples {
+                let weight = math::dot(&normal, direction);
+                if weight > 0.0 {
+                    let c = color.scale(weight);
+                    sum = HdrColor(sum.0 + c.0, sum.1 + c.1, sum.2 + c.2);
+                }
+            }
+            irradiance.pixels.push(sum);
+        }
+    }
+    irradiance
+}
+
+#[test]
+fn test_uniform_environment() {
+    let sky = image::RgbImage::from_pixel(64, 32, image::Rgb([100, 150, 200]));
+    let environment = EnvironmentMap::from_image(&DynamicImage::ImageRgb8(sky));
+    let expected = HdrColor::from(Color(100, 150, 200));
+
+    let mut image = Image::new(8, 8);
+    environment.draw_background(&mut image, 60.0);
+    assert_eq!(image.to_rgb_image().get_pixel(3, 3).0, [100, 150, 200]);
+
+    let irradiance = environment.irradiance(&Vec3f::new(0.3, 0.8, 0.2));
+    assert!((irradiance.0 / expected.0 - 1.0).abs() < 0.05);
+    assert!((irradiance.2 / expected.2 - 1.0).abs() < 0.05);
+}
+
+#[test]
+fn test_sky_and_ground() {
+    // bright upper half over a black lower half
+    let sky = image::RgbImage::from_fn(64, 32, |_, y| {
+        image::Rgb(if y < 16 { [255; 3] } else { [0; 3] })
+    });
+    let environment = EnvironmentMap::from_image(&DynamicImage::ImageRgb8(sky));
+    assert_eq!(environment.sample(&Vec3f::new(0., 1., 0.)).0, 1.0);
+    assert_eq!(environment.sample(&Vec3f::new(0., -1., 0.)).0, 0.0);
+    let up = environment.irradiance(&Vec3f::new(0., 1., 0.)).0;
+    let side = environment.irradiance(&Vec3f::new(1., 0., 0.)).0;
+    let down = environment.irradiance(&Vec3f::new(0., -1., 0.)).0;
+    assert!(up > 0.9 && (side - 0.5).abs() < 0.1 && down < 0.1);
+}
+
+#[test]
+fn test_cube_map_environment() {
+    // the +Y face lit, all others black
+    let black = DynamicImage::new_rgb8(8, 8);
+    let white = DynamicImage::ImageRgb8(image::RgbImage::from_pixel(8, 8, image::Rgb([255; 3])));
+    let mut faces = [(); 6].map(|_| black.clone());
+    faces[2] = white;
+    let environment = EnvironmentMap::from_cube_map(&CubeMap::from_faces(&faces).unwrap());
+    assert_eq!(environment.sample(&Vec3f::new(0.1, 1., 0.)).0, 1.0);
+    assert_eq!(environment.sample(&Vec3f::new(0., 0., -1.)).0, 0.0);
+    assert!(environment.irradiance(&Vec3f::new(0., 1., 0.)).0 > 0.3);
+}
@@ -0,0 +1,78 @@
+use std::fmt;
+use std::io;
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+/// Everything that can go wrong loading models and textures or rendering them to disk.
+#[derive(Debug)]
+pub enum RusterizerError {
+    /// Reading or writing a file failed.
+    Io(io::Error),
+    /// A model or data file is malformed.
+    Parse(String),
+    /// An image used as a texture, environment or height map could not be decoded.
+    Texture(String),
+    /// An option or parameter is out of range or conflicts with another.
+    InvalidArgument(String),
+}
+
+impl RusterizerError {
+    /// An I/O error naming the file it happened on.
+    #[cfg(feature = "fs")]
+    pub fn in_file(path: &Path, error: io::Error) -> Self {
+        RusterizerError::Io(io::Error::new(
+            error.kind(),
+            format!("{}: {}", path.display(), error),
+        ))
+    }
+}
+
+impl fmt::Display for RusterizerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RusterizerError::Io(e) => write!(f, "I/O error: {}", e),
+            RusterizerError::Parse(msg) => write!(f, "parse error: {}", msg),
+            RusterizerError::Texture(msg) => write!(f, "texture error: {}", msg),
+            RusterizerError::InvalidArgument(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for RusterizerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RusterizerError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for RusterizerError {
+    fn from(e: io::Error) -> Self {
+        RusterizerError::Io(e)
+    }
+}
+
+// unsupported output formats are the caller's to fix, the rest are encoding problems
+impl From<image::ImageError> for RusterizerError {
+    fn from(e: image::ImageError) -> Self {
+        match e {
+            image::ImageError::IoError(e) => RusterizerError::Io(e),
+            image::ImageError::Unsupported(e) => RusterizerError::InvalidArgument(e.to_string()),
+            e => RusterizerError::Texture(e.to_string()),
+        }
+    }
+}
+
+#[cfg(feature = "fs")]
+#[test]
+fn test_display() {
+    let missing = io::Error::new(io::ErrorKind::NotFound, "no such file");
+    let error = RusterizerError::in_file(Path::new("model.obj"), missing);
+    assert_eq!(error.to_string(), "I/O error: model.obj: no such file");
+    assert!(matches!(&error, RusterizerError::Io(e) if e.kind() == io::ErrorKind::NotFound));
+    assert_eq!(
+        RusterizerError::Parse("line 3: expected a number".to_string()).to_string(),
+        "parse error: line 3: expected a number"
+    );
+}
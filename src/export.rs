@@ -0,0 +1,215 @@
+use std::borrow::Cow;
+#[cfg(feature = "fs")]
+use std::fs::File;
+#[cfg(feature = "fs")]
+use std::io::BufWriter;
+use std::io::Write;
+use std::path::Path;
+
+use image::error::EncodingError;
+use image::{DynamicImage, ImageError, ImageFormat, ImageResult, RgbImage};
+
+/// Output formats written by the crate itself rather than through the `image` crate.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NativeFormat {
+    Ppm,
+    Pgm,
+    Tga,
+}
+
+impl NativeFormat {
+    pub fn from_path<Q: AsRef<Path>>(path: Q) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "ppm" => Some(NativeFormat::Ppm),
+            "pgm" => Some(NativeFormat::Pgm),
+            "tga" => Some(NativeFormat::Tga),
+            _ => None,
+        }
+    }
+}
+
+/// Saves `image`, using the crate's own PPM/PGM/TGA writers for those extensions and the
+/// `image` crate for everything else.
+#[cfg(feature = "fs")]
+pub fn save_image<Q: AsRef<Path>>(image: &RgbImage, path: Q) -> ImageResult<()> {
+    match NativeFormat::from_path(&path) {
+        Some(format) => Ok(save(image, format, path)?),
+        None => image.save(path),
+    }
+}
+
+#[cfg(feature = "fs")]
+pub fn save<Q: AsRef<Path>>(
+    image: &RgbImage,
+    format: NativeFormat,
+    path: Q,
+) -> std::io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    match format {
+        NativeFormat::Ppm => write_ppm(image, &mut writer)?,
+        NativeFormat::Pgm => write_pgm(image, &mut writer)?,
+        NativeFormat::Tga => write_tga(image, &mut writer)?,
+    }
+    writer.flush()
+}
+
+/// Writes `image` as an 8-bit RGB or RGBA PNG, by whether it has an alpha channel,
+/// carrying `text` as tEXt chunks of a keyword and its value, which image viewers and
+/// tools like `exiftool` show. Characters outside Latin-1, which tEXt cannot hold, are
+/// replaced by `?`.
+pub fn write_png_with_text<W: Write>(
+    writer: W,
+    image: &DynamicImage,
+    text: &[(String, String)],
+) -> ImageResult<()> {
+    let to_image_error = |e: png::EncodingError| {
+        ImageError::Encoding(EncodingError::new(ImageFormat::Png.into(), e))
+    };
+    let latin1 = |s: &str| -> String {
+        s.chars()
+            .map(|c| if (c as u32) < 256 { c } else { '?' })
+            .collect()
+    };
+
+    let mut encoder = png::Encoder::new(writer, image.width(), image.height());
+    let pixels = if image.color().has_alpha() {
+        encoder.set_color(png::ColorType::Rgba);
+        Cow::Owned(image.to_rgba8().into_raw())
+    } else {
+        encoder.set_color(png::ColorType::Rgb);
+        match image {
+            DynamicImage::ImageRgb8(rgb) => Cow::Borrowed(rgb.as_raw()),
+            _ => Cow::Owned(image.to_rgb8().into_raw()),
+        }
+    };
+    encoder.set_depth(png::BitDepth::Eight);
+    for (keyword, value) in text {
+        encoder
+            .add_text_chunk(latin1(keyword), latin1(value))
+            .map_err(to_image_error)?;
+    }
+    let mut writer = encoder.write_header().map_err(to_image_error)?;
+    writer.write_image_data(&pixels).map_err(to_image_error)?;
+    writer.finish().map_err(to_image_error)
+}
+
+/// Like [`write_png_with_text`], writing to the file at `path`.
+#[cfg(feature = "fs")]
+pub fn save_png_with_text<Q: AsRef<Path>>(
+    image: &DynamicImage,
+    path: Q,
+    text: &[(String, String)],
+) -> ImageResult<()> {
+    let file = BufWriter::new(File::create(path)?);
+    write_png_with_text(file, image, text)
+}
+
+/// Writes a binary (P6) PPM.
+pub fn write_ppm<W: Write>(image: &RgbImage, writer: &mut W) -> std::io::Result<()> {
+    write!(writer, "P6\n{} {}\n255\n", image.width(), image.height())?;
+    writer.write_all(image.as_raw())
+}
+
+/// Writes a binary (P5) PGM using Rec. 601 luma weights.
+pub fn write_pgm<W: Write>(image: &RgbImage, writer: &mut W) -> std::io::Result<()> {
+    write!(writer, "P5\n{} {}\n255\n", image.width(), image.height())?;
+    let gray: Vec<u8> = image
+        .pixels()
+        .map(|p| {
+            let luma = 0.299 * p[0] as f64 + 0.587 * p[1] as f64 + 0.114 * p[2] as f64;
+            luma.round() as u8
+        })
+        .collect();
+    writer.write_all(&gray)
+}
+
+/// Writes an uncompressed 24-bit TGA with a bottom-left origin, laid out the same way
+/// as tinyrenderer's `TGAImage::write_tga_file` so reference outputs can be compared byte by byte.
+pub fn write_tga<W: Write>(image: &RgbImage, writer: &mut W) -> std::io::Result<()> {
+    const FOOTER: &[u8] = b"TRUEVISION-XFILE.\0";
+    let (width, height) = image.dimensions();
+    if width > u16::MAX as u32 || height > u16::MAX as u32 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "image too large for TGA",
+        ));
+    }
+
+    let mut header = [0u8; 18];
+    header[2] = 2; // uncompressed true-color
+    header[12..14].copy_from_slice(&(width as u16).to_le_bytes());
+    header[14..16].copy_from_slice(&(height as u16).to_le_bytes());
+    header[16] = 24;
+    header[17] = 0x00; // bottom-left origin
+    writer.write_all(&header)?;
+
+    let mut row = Vec::with_capacity(width as usize * 3);
+    for y in (0..height).rev() {
+        row.clear();
+        for x in 0..width {
+            let p = image.get_pixel(x, y);
+            row.extend_from_slice(&[p[2], p[1], p[0]]);
+        }
+        writer.write_all(&row)?;
+    }
+
+    // developer and extension area references followed by the signature
+    writer.write_all(&[0u8; 8])?;
+    writer.write_all(FOOTER)
+}
+
+#[test]
+fn test_format_from_path() {
+    assert_eq!(NativeFormat::from_path("out.TGA"), Some(NativeFormat::Tga));
+    assert_eq!(NativeFormat::from_path("out.pgm"), Some(NativeFormat::Pgm));
+    assert_eq!(NativeFormat::from_path("out.png"), None);
+    assert_eq!(NativeFormat::from_path("out"), None);
+}
+
+#[test]
+fn test_write_ppm() {
+    let image = RgbImage::from_raw(2, 1, vec![1, 2, 3, 4, 5, 6]).unwrap();
+    let mut out = Vec::new();
+    write_ppm(&image, &mut out).unwrap();
+    assert_eq!(out, b"P6\n2 1\n255\n\x01\x02\x03\x04\x05\x06");
+}
+
+#[test]
+fn test_write_tga() {
+    // top row red, bottom row blue
+    let image = RgbImage::from_raw(1, 2, vec![255, 0, 0, 0, 0, 255]).unwrap();
+    let mut out = Vec::new();
+    write_tga(&image, &mut out).unwrap();
+    assert_eq!(out.len(), 18 + 6 + 8 + 18);
+    assert_eq!(out[2], 2);
+    assert_eq!(&out[12..16], &[1, 0, 2, 0]);
+    assert_eq!(out[16], 24);
+    // rows are stored bottom-up in BGR order
+    assert_eq!(&out[18..24], &[255, 0, 0, 0, 0, 255]);
+    assert!(out.ends_with(b"TRUEVISION-XFILE.\0"));
+}
+
+#[test]
+fn test_write_png_with_text() {
+    let image = RgbImage::from_raw(2, 1, vec![1, 2, 3, 4, 5, 6]).unwrap();
+    let text = [
+        ("Software".to_string(), "rusterizer".to_string()),
+        ("Camera".to_string(), "front \u{2192} iso".to_string()),
+    ];
+    let mut out = Vec::new();
+    write_png_with_text(&mut out, &image.clone().into(), &text).unwrap();
+
+    let mut reader = png::Decoder::new(out.as_slice()).read_info().unwrap();
+    let chunks: Vec<(String, String)> = reader
+        .info()
+        .uncompressed_latin1_text
+        .iter()
+        .map(|chunk| (chunk.keyword.clone(), chunk.text.clone()))
+        .collect();
+    assert_eq!(chunks[0], text[0]);
+    assert_eq!(chunks[1].1, "front ? iso");
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    reader.next_frame(&mut pixels).unwrap();
+    assert_eq!(pixels, image.into_raw());
+}
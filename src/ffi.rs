@@ -0,0 +1,275 @@
+//! C API for embedding the rasterizer in other applications, declared in
+//! `include/rusterizer.h`.
+//!
+//! Build a library with `cargo rustc --release --features ffi --crate-type cdylib` (or
+//! `staticlib`) and regenerate the header after changing this module with
+//! `cbindgen --config cbindgen.toml --output include/rusterizer.h`.
+//!
+//! Panics never unwind into C: functions returning a status report them as
+//! [`RusterizerStatus::Panicked`], [`rusterizer_renderer_new`] as null.
+
+use std::panic::{self, AssertUnwindSafe};
+use std::slice;
+
+use crate::color::{self, Color};
+use crate::drawable::{Drawable, Image};
+use crate::loader::{obj, ply, stl};
+use crate::math::Mat4;
+use crate::mesh::Mesh;
+use crate::render::draw_mesh;
+use crate::target::PixelLayout;
+use crate::DrawStyle;
+
+/// Result of the fallible functions of the C API.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RusterizerStatus {
+    Ok = 0,
+    NullPointer = -1,
+    ParseError = -2,
+    BufferTooSmall = -3,
+    /// An internal error stopped the call; the renderer may have been left with part of
+    /// what it was doing done.
+    Panicked = -4,
+}
+
+/// Runs `f`, returning `on_panic` if it panics instead of unwinding into the caller,
+/// which would abort the host process.
+fn catch_panic<T>(on_panic: T, f: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(on_panic)
+}
+
+/// Model formats accepted by [`rusterizer_load_mesh`].
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RusterizerFormat {
+    Obj,
+    Ply,
+    Stl,
+}
+
+/// Meshes and view settings shared by every render, opaque to C.
+pub struct Renderer {
+    width: u32,
+    height: u32,
+    meshes: Vec<Mesh>,
+    camera: Mat4,
+    background: Color,
+}
+
+/// Creates a renderer producing `width` x `height` images, to be freed with
+/// [`rusterizer_renderer_free`].
+#[no_mangle]
+pub extern "C" fn rusterizer_renderer_new(width: u32, height: u32) -> *mut Renderer {
+    catch_panic(std::ptr::null_mut(), || {
+        Box::into_raw(Box::new(Renderer {
+            width,
+            height,
+            meshes: Vec::new(),
+            camera: Mat4::identity(),
+            background: color::BLACK,
+        }))
+    })
+}
+
+/// Frees a renderer; passing null does nothing.
+///
+/// # Safety
+///
+/// `renderer` must be null or come from [`rusterizer_renderer_new`] and not be used again.
+#[no_mangle]
+pub unsafe extern "C" fn rusterizer_renderer_free(renderer: *mut Renderer) {
+    catch_panic((), || {
+        if !renderer.is_null() {
+            drop(Box::from_raw(renderer));
+        }
+    })
+}
+
+/// Parses a model from `len` bytes at `data` and adds its meshes to the scene.
+///
+/// # Safety
+///
+/// `renderer` must be a live renderer and `data` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rusterizer_load_mesh(
+    renderer: *mut Renderer,
+    data: *const u8,
+    len: usize,
+    format: RusterizerFormat,
+) -> RusterizerStatus {
+    catch_panic(RusterizerStatus::Panicked, || {
+        let (Some(renderer), false) = (renderer.as_mut(), data.is_null()) else {
+            return RusterizerStatus::NullPointer;
+        };
+        let data = slice::from_raw_parts(data, len);
+        let meshes = match format {
+            RusterizerFormat::Obj => std::str::from_utf8(data)
+                .map_err(|e| e.to_string())
+                .and_then(|text| obj::parse(text).map_err(|e| e.to_string())),
+            RusterizerFormat::Ply => ply::parse(data).map(|m| vec![m]).map_err(|e| e.to_string()),
+            RusterizerFormat::Stl => stl::parse(data).map(|m| vec![m]).map_err(|e| e.to_string()),
+        };
+        match meshes {
+            Ok(meshes) => {
+                renderer.meshes.extend(meshes);
+                RusterizerStatus::Ok
+            }
+            Err(_) => RusterizerStatus::ParseError,
+        }
+    })
+}
+
+/// Removes every mesh from the scene.
+///
+/// # Safety
+///
+/// `renderer` must be null or a live renderer.
+#[no_mangle]
+pub unsafe extern "C" fn rusterizer_clear_meshes(renderer: *mut Renderer) {
+    catch_panic((), || {
+        if let Some(renderer) = renderer.as_mut() {
+            renderer.meshes.clear();
+        }
+    })
+}
+
+/// Sets the transform from model space to the view, given as 16 row-major values.
+/// The view shows -1 to 1 on x and y and looks along -z.
+///
+/// # Safety
+///
+/// `renderer` must be a live renderer and `matrix` must point to 16 readable doubles.
+#[no_mangle]
+pub unsafe extern "C" fn rusterizer_set_camera(
+    renderer: *mut Renderer,
+    matrix: *const f64,
+) -> RusterizerStatus {
+    catch_panic(RusterizerStatus::Panicked, || {
+        let (Some(renderer), false) = (renderer.as_mut(), matrix.is_null()) else {
+            return RusterizerStatus::NullPointer;
+        };
+        let values = slice::from_raw_parts(matrix, 16);
+        let mut m = [[0.0; 4]; 4];
+        for (row, chunk) in m.iter_mut().zip(values.chunks_exact(4)) {
+            row.copy_from_slice(chunk);
+        }
+        renderer.camera = Mat4::new(m);
+        RusterizerStatus::Ok
+    })
+}
+
+/// Sets the color of the pixels not covered by any mesh.
+///
+/// # Safety
+///
+/// `renderer` must be null or a live renderer.
+#[no_mangle]
+pub unsafe extern "C" fn rusterizer_set_background(renderer: *mut Renderer, r: u8, g: u8, b: u8) {
+    catch_panic((), || {
+        if let Some(renderer) = renderer.as_mut() {
+            renderer.background = Color(r, g, b);
+        }
+    })
+}
+
+/// Renders the scene into `pixels`, which holds top-down rows of `stride` bytes in the
+/// given layout.
+///
+/// # Safety
+///
+/// `renderer` must be a live renderer and `pixels` must point to `len` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rusterizer_render(
+    renderer: *const Renderer,
+    pixels: *mut u8,
+    len: usize,
+    stride: usize,
+    layout: PixelLayout,
+) -> RusterizerStatus {
+    catch_panic(RusterizerStatus::Panicked, || {
+        let (Some(renderer), false) = (renderer.as_ref(), pixels.is_null()) else {
+            return RusterizerStatus::NullPointer;
+        };
+        let (width, height) = (renderer.width, renderer.height);
+        let bytes_per_pixel = layout.bytes_per_pixel();
+        if stride < width as usize * bytes_per_pixel || len < stride * height as usize {
+            return RusterizerStatus::BufferTooSmall;
+        }
+        let pixels = slice::from_raw_parts_mut(pixels, len);
+
+        let mut image = Image::new(width, height);
+        image.clear(renderer.background);
+        for mesh in &renderer.meshes {
+            let draw_style = if mesh.has_colors() {
+                DrawStyle::VertexColors {
+                    colors: (color::WHITE, color::WHITE, color::WHITE),
+                    lit: true,
+                }
+            } else {
+                DrawStyle::Filled(mesh.material.base_color)
+            };
+            draw_mesh(&mut image, mesh, &draw_style, &renderer.camera);
+        }
+
+        let rendered = image.into_rgb_buffer();
+        for (row, line) in rendered.rows().zip(pixels.chunks_mut(stride)) {
+            for (pixel, bytes) in row.zip(line.chunks_exact_mut(bytes_per_pixel)) {
+                let [r, g, b] = pixel.0;
+                match layout {
+                    PixelLayout::Rgb => bytes.copy_from_slice(&[r, g, b]),
+                    PixelLayout::Rgba => bytes.copy_from_slice(&[r, g, b, 255]),
+                    PixelLayout::Bgra => bytes.copy_from_slice(&[b, g, r, 255]),
+                }
+            }
+        }
+        RusterizerStatus::Ok
+    })
+}
+
+#[test]
+fn test_render_through_c_api() {
+    let triangle = b"v -1 -1 0\nv 1 -1 0\nv 0 1 0\nf 1 2 3\n";
+    unsafe {
+        let renderer = rusterizer_renderer_new(8, 8);
+        let status = rusterizer_load_mesh(
+            renderer,
+            triangle.as_ptr(),
+            triangle.len(),
+            RusterizerFormat::Obj,
+        );
+        assert_eq!(status, RusterizerStatus::Ok);
+        let garbage = b"solid nothing";
+        let status = rusterizer_load_mesh(renderer, garbage.as_ptr(), 3, RusterizerFormat::Ply);
+        assert_eq!(status, RusterizerStatus::ParseError);
+        rusterizer_set_background(renderer, 0, 0, 255);
+
+        let mut pixels = vec![0u8; 8 * 8 * 4];
+        let status = rusterizer_render(renderer, pixels.as_mut_ptr(), 10, 32, PixelLayout::Bgra);
+        assert_eq!(status, RusterizerStatus::BufferTooSmall);
+        let status = rusterizer_render(
+            renderer,
+            pixels.as_mut_ptr(),
+            pixels.len(),
+            32,
+            PixelLayout::Bgra,
+        );
+        assert_eq!(status, RusterizerStatus::Ok);
+        // the corner shows the background, the bottom center the white triangle
+        assert_eq!(pixels[..4], [255, 0, 0, 255]);
+        assert_eq!(pixels[7 * 32 + 4 * 4..7 * 32 + 5 * 4], [255, 255, 255, 255]);
+
+        // a broken mesh makes drawing panic, which comes back as a status rather than
+        // unwinding into C
+        (&mut *renderer).meshes[0].indices.push([0, 1, 99]);
+        let status = rusterizer_render(
+            renderer,
+            pixels.as_mut_ptr(),
+            pixels.len(),
+            32,
+            PixelLayout::Bgra,
+        );
+        assert_eq!(status, RusterizerStatus::Panicked);
+        rusterizer_renderer_free(renderer);
+    }
+}
@@ -0,0 +1,101 @@
+use crate::color::{Color, HdrColor};
+
+/// How fog thickens with distance.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FogFalloff {
+    /// No fog up to `start`, fully fogged from `end` on.
+    Linear { start: f64, end: f64 },
+    /// `1 - exp(-density * distance)`.
+    Exponential { density: f64 },
+    /// `1 - exp(-(density * distance)^2)`, clearer near the viewer.
+    ExponentialSquared { density: f64 },
+}
+
+impl std::str::FromStr for FogFalloff {
+    type Err = String;
+
+    /// Parses `linear:START:END`, `exp:DENSITY` or `exp2:DENSITY`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(':');
+        let name = parts.next().unwrap_or_default();
+        let values = parts
+            .map(|value| value.parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("invalid fog parameter: {}", e))?;
+        match (name, values.as_slice()) {
+            ("linear", &[start, end]) => Ok(FogFalloff::Linear { start, end }),
+            ("exp", &[density]) => Ok(FogFalloff::Exponential { density }),
+            ("exp2", &[density]) => Ok(FogFalloff::ExponentialSquared { density }),
+            _ => Err(format!(
+                "unknown fog '{}', expected linear:START:END, exp:DENSITY or exp2:DENSITY",
+                s
+            )),
+        }
+    }
+}
+
+/// Blends surfaces toward `color` by their depth.
+///
+/// Distances are measured in depth units from the `z = 1` plane nearest to the viewer, so
+/// the far side of the unit cube is at distance 2.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Fog {
+    pub color: Color,
+    pub falloff: FogFalloff,
+}
+
+impl Fog {
+    /// Fraction of the fog color at `distance`, from 0 to 1.
+    pub fn factor(&self, distance: f64) -> f64 {
+        let distance = distance.max(0.0);
+        let factor = match self.falloff {
+            FogFalloff::Linear { start, end } if end > start => (distance - start) / (end - start),
+            FogFalloff::Linear { start, .. } => (distance >= start) as u8 as f64,
+            FogFalloff::Exponential { density } => 1.0 - (-density * distance).exp(),
+            FogFalloff::ExponentialSquared { density } => {
+                1.0 - (-(density * distance).powi(2)).exp()
+            }
+        };
+        factor.clamp(0.0, 1.0)
+    }
+
+    /// Fogs `color` of a surface at depth `z`, larger values being nearer.
+    pub fn apply(&self, color: HdrColor, z: f64) -> HdrColor {
+        let t = self.factor(1.0 - z) as f32;
+        let fog = HdrColor::from(self.color);
+        HdrColor(
+            color.0 + (fog.0 - color.0) * t,
+            color.1 + (fog.1 - color.1) * t,
+            color.2 + (fog.2 - color.2) * t,
+        )
+    }
+}
+
+#[test]
+fn test_fog_factor() {
+    let linear = Fog {
+        color: Color(0, 0, 0),
+        falloff: FogFalloff::Linear {
+            start: 0.5,
+            end: 1.5,
+        },
+    };
+    assert_eq!(linear.factor(0.0), 0.0);
+    assert_eq!(linear.factor(1.0), 0.5);
+    assert_eq!(linear.factor(3.0), 1.0);
+
+    let exponential = Fog {
+        falloff: FogFalloff::Exponential { density: 1.0 },
+        ..linear
+    };
+    assert_eq!(exponential.factor(0.0), 0.0);
+    assert!((exponential.factor(1.0) - (1.0 - (-1.0f64).exp())).abs() < 1e-12);
+    let squared = Fog {
+        falloff: FogFalloff::ExponentialSquared { density: 1.0 },
+        ..linear
+    };
+    assert!(squared.factor(0.5) < exponential.factor(0.5));
+
+    assert_eq!("linear:0.5:1.5".parse::<FogFalloff>(), Ok(linear.falloff));
+    assert!("exp".parse::<FogFalloff>().is_err());
+}
@@ -0,0 +1,291 @@
+use std::f64::consts::PI;
+
+use crate::math::Vec3f;
+use crate::mesh::Mesh;
+
+/// Generates triangles for a `rows` x `cols` grid of vertices stored row by row,
+/// counter-clockwise when the column direction crossed with the row direction faces outwards.
+fn grid_indices(rows: usize, cols: usize) -> Vec<[usize; 3]> {
+    let mut indices = Vec::with_capacity((rows - 1) * (cols - 1) * 2);
+    for i in 0..rows - 1 {
+        for j in 0..cols - 1 {
+            let a = i * cols + j;
+            let b = (i + 1) * cols + j;
+            let c = (i + 1) * cols + j + 1;
+            let d = i * cols + j + 1;
+            indices.push([a, c, b]);
+            indices.push([a, d, c]);
+        }
+    }
+    indices
+}
+
+/// UV sphere centered at the origin.
+pub fn sphere(radius: f64, segments: usize, rings: usize) -> Mesh {
+    let segments = segments.max(3);
+    let rings = rings.max(2);
+    let mut mesh = Mesh::default();
+    for i in 0..=rings {
+        let theta = PI * i as f64 / rings as f64;
+        for j in 0..=segments {
+            let phi = 2.0 * PI * j as f64 / segments as f64;
+            let normal = Vec3f::new(
+                theta.sin() * phi.cos(),
+                theta.cos(),
+                theta.sin() * phi.sin(),
+            );
+            mesh.positions.push(normal * radius);
+            mesh.normals.push(normal);
+            mesh.uvs
+                .push([j as f64 / segments as f64, 1.0 - i as f64 / rings as f64]);
+        }
+    }
+    mesh.indices = grid_indices(rings + 1, segments + 1);
+    mesh.name = Some("sphere".to_string());
+    mesh
+}
+
+/// Axis-aligned box centered at the origin with hard edges: every face has its own vertices.
+pub fn cuboid(size: Vec3f) -> Mesh {
+    let half = size * 0.5;
+    let scale = |v: Vec3f| Vec3f::new(v.x * half.x, v.y * half.y, v.z * half.z);
+    // (normal, u axis, v axis) with u x v == normal
+    let faces = [
+        (
+            Vec3f::new(1., 0., 0.),
+            Vec3f::new(0., 0., -1.),
+            Vec3f::new(0., 1., 0.),
+        ),
+        (
+            Vec3f::new(-1., 0., 0.),
+            Vec3f::new(0., 0., 1.),
+            Vec3f::new(0., 1., 0.),
+        ),
+        (
+            Vec3f::new(0., 1., 0.),
+            Vec3f::new(1., 0., 0.),
+            Vec3f::new(0., 0., -1.),
+        ),
+        (
+            Vec3f::new(0., -1., 0.),
+            Vec3f::new(1., 0., 0.),
+            Vec3f::new(0., 0., 1.),
+        ),
+        (
+            Vec3f::new(0., 0., 1.),
+            Vec3f::new(1., 0., 0.),
+            Vec3f::new(0., 1., 0.),
+        ),
+        (
+            Vec3f::new(0., 0., -1.),
+            Vec3f::new(-1., 0., 0.),
+            Vec3f::new(0., 1., 0.),
+        ),
+    ];
+
+    let mut mesh = Mesh::default();
+    for (normal, u, v) in faces {
+        let base = mesh.positions.len();
+        for (su, sv) in [(-1., -1.), (1., -1.), (1., 1.), (-1., 1.)] {
+            mesh.positions.push(scale(normal + u * su + v * sv));
+            mesh.normals.push(normal);
+            mesh.uvs.push([(su + 1.) / 2., (sv + 1.) / 2.]);
+        }
+        mesh.indices.push([base, base + 1, base + 2]);
+        mesh.indices.push([base, base + 2, base + 3]);
+    }
+    mesh.name = Some("cuboid".to_string());
+    mesh
+}
+
+/// Plane in the XZ plane facing +Y, split into `subdivisions` x `subdivisions` quads.
+pub fn plane(width: f64, depth: f64, subdivisions: usize) -> Mesh {
+    let subdivisions = subdivisions.max(1);
+    let mut mesh = Mesh::default();
+    for i in 0..=subdivisions {
+        let v = i as f64 / subdivisions as f64;
+        for j in 0..=subdivisions {
+            let u = j as f64 / subdivisions as f64;
+            mesh.positions
+                .push(Vec3f::new((u - 0.5) * width, 0., (0.5 - v) * depth));
+            mesh.normals.push(Vec3f::new(0., 1., 0.));
+            mesh.uvs.push([u, v]);
+        }
+    }
+    mesh.indices = grid_indices(subdivisions + 1, subdivisions + 1);
+    mesh.name = Some("plane".to_string());
+    mesh
+}
+
+/// Torus around the Y axis with the tube of radius `minor_radius` centered `major_radius`
+/// away from the origin.
+pub fn torus(
+    major_radius: f64,
+    minor_radius: f64,
+    major_segments: usize,
+    minor_segments: usize,
+) -> Mesh {
+    let major_segments = major_segments.max(3);
+    let minor_segments = minor_segments.max(3);
+    let mut mesh = Mesh::default();
+    for i in 0..=major_segments {
+        let u = 2.0 * PI * i as f64 / major_segments as f64;
+        for j in 0..=minor_segments {
+            let v = 2.0 * PI * j as f64 / minor_segments as f64;
+            let normal = Vec3f::new(v.cos() * u.cos(), v.sin(), v.cos() * u.sin());
+            let center = Vec3f::new(u.cos(), 0., u.sin()) * major_radius;
+            mesh.positions.push(center + normal * minor_radius);
+            mesh.normals.push(normal);
+            mesh.uvs.push([
+                i as f64 / major_segments as f64,
+                j as f64 / minor_segments as f64,
+            ]);
+        }
+    }
+    mesh.indices = grid_indices(major_segments + 1, minor_segments + 1);
+    mesh.name = Some("torus".to_string());
+    mesh
+}
+
+/// Triangulates the polygon given by `polygon` indices into `positions` by ear clipping,
+/// preserving its winding. Handles concave polygons; falls back to a fan if the
+/// polygon is too degenerate to clip.
+pub fn triangulate_polygon(positions: &[Vec3f], polygon: &[usize]) -> Vec<[usize; 3]> {
+    if polygon.len() < 3 {
+        return Vec::new();
+    }
+    if polygon.len() == 3 {
+        return vec![[polygon[0], polygon[1], polygon[2]]];
+    }
+
+    // Newell's method gives a robust normal for non-planar polygons
+    let mut normal = Vec3f::new(0., 0., 0.);
+    for (i, &current) in polygon.iter().enumerate() {
+        let a = positions[current];
+        let b = positions[polygon[(i + 1) % polygon.len()]];
+        normal.x += (a.y - b.y) * (a.z + b.z);
+        normal.y += (a.z - b.z) * (a.x + b.x);
+        normal.z += (a.x - b.x) * (a.y + b.y);
+    }
+    // project onto the plane of the two axes least aligned with the normal, keeping orientation
+    let (ax, ay, az) = (normal.x.abs(), normal.y.abs(), normal.z.abs());
+    let project = |p: &Vec3f| -> (f64, f64) {
+        if az >= ax && az >= ay {
+            (p.x, p.y * normal.z.signum())
+        } else if ax >= ay {
+            (p.y, p.z * normal.x.signum())
+        } else {
+            (p.z, p.x * normal.y.signum())
+        }
+    };
+    let points: Vec<(f64, f64)> = polygon.iter().map(|&i| project(&positions[i])).collect();
+    let cross = |o: (f64, f64), a: (f64, f64), b: (f64, f64)| {
+        (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+    };
+    let inside = |p: (f64, f64), a, b, c| {
+        cross(a, b, p) >= 0.0 && cross(b, c, p) >= 0.0 && cross(c, a, p) >= 0.0
+    };
+
+    let mut remaining: Vec<usize> = (0..polygon.len()).collect();
+    let mut triangles = Vec::with_capacity(polygon.len() - 2);
+    while remaining.len() > 3 {
+        let n = remaining.len();
+        // starting at the second vertex makes convex polygons come out as a fan
+        let ear = (0..n).map(|k| (k + 1) % n).find(|&i| {
+            let (prev, curr, next) = (
+                remaining[(i + n - 1) % n],
+                remaining[i],
+                remaining[(i + 1) % n],
+            );
+            let (a, b, c) = (points[prev], points[curr], points[next]);
+            cross(a, b, c) > 0.0
+                && remaining
+                    .iter()
+                    .filter(|&&k| k != prev && k != curr && k != next)
+                    .all(|&k| !inside(points[k], a, b, c))
+        });
+        let Some(i) = ear else {
+            // degenerate input, fan the rest
+            for k in 1..remaining.len() - 1 {
+                triangles.push([remaining[0], remaining[k], remaining[k + 1]]);
+            }
+            remaining.clear();
+            break;
+        };
+        triangles.push([
+            remaining[(i + n - 1) % n],
+            remaining[i],
+            remaining[(i + 1) % n],
+        ]);
+        remaining.remove(i);
+    }
+    if remaining.len() == 3 {
+        triangles.push([remaining[0], remaining[1], remaining[2]]);
+    }
+    triangles
+        .into_iter()
+        .map(|[a, b, c]| [polygon[a], polygon[b], polygon[c]])
+        .collect()
+}
+
+#[cfg(test)]
+fn assert_outward(mesh: &Mesh) {
+    assert_eq!(mesh.normals.len(), mesh.positions.len());
+    assert_eq!(mesh.uvs.len(), mesh.positions.len());
+    for &[a, b, c] in &mesh.indices {
+        let (pa, pb, pc) = (mesh.positions[a], mesh.positions[b], mesh.positions[c]);
+        let face_normal = crate::math::cross(&(pb - pa), &(pc - pa));
+        if face_normal.length() < 1e-12 {
+            // collapsed triangles at the sphere poles
+            continue;
+        }
+        let vertex_normal = mesh.normals[a] + mesh.normals[b] + mesh.normals[c];
+        assert!(crate::math::dot(&face_normal, &vertex_normal) > 0.0);
+    }
+}
+
+#[test]
+fn test_primitives_face_outward() {
+    assert_outward(&sphere(1.0, 16, 8));
+    assert_outward(&cuboid(Vec3f::new(1., 2., 3.)));
+    assert_outward(&plane(2.0, 1.0, 4));
+    assert_outward(&torus(1.0, 0.25, 16, 8));
+}
+
+#[test]
+fn test_cuboid_extent() {
+    let mesh = cuboid(Vec3f::new(2., 4., 6.));
+    assert_eq!(mesh.positions.len(), 24);
+    assert_eq!(mesh.indices.len(), 12);
+    for p in &mesh.positions {
+        assert_eq!((p.x.abs(), p.y.abs(), p.z.abs()), (1., 2., 3.));
+    }
+}
+
+#[test]
+fn test_triangulate_concave_polygon() {
+    // an arrow-head shaped quad, concave at vertex 3; a fan from vertex 0 would be fine
+    // but a fan from vertex 1 would spill outside
+    let positions = vec![
+        Vec3f::new(0., 0., 0.),
+        Vec3f::new(2., 1., 0.),
+        Vec3f::new(0., 2., 0.),
+        Vec3f::new(0.5, 1., 0.),
+    ];
+    let polygon = [1, 2, 3, 0];
+    let triangles = triangulate_polygon(&positions, &polygon);
+    assert_eq!(triangles.len(), 2);
+    let area: f64 = triangles
+        .iter()
+        .map(|&[a, b, c]| {
+            let n = crate::math::cross(
+                &(positions[b] - positions[a]),
+                &(positions[c] - positions[a]),
+            );
+            // all triangles keep the counter-clockwise winding
+            assert!(n.z > 0.0);
+            n.z / 2.0
+        })
+        .sum();
+    assert!((area - 1.5).abs() < 1e-9);
+}
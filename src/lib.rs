@@ -0,0 +1,125 @@
+//! Software rasterizer rendering meshes into images.
+//!
+//! Everything but [`math`], [`font`] and [`raster`] needs the default `std` feature;
+//! without it the crate is `no_std` and needs no allocator, for use on embedded targets.
+//!
+//! Loading and saving by path need the `fs` feature and `Color::random` the `rand`
+//! feature. Building with `--no-default-features --features std` leaves both out for
+//! `wasm32-unknown-unknown`, where a render is handed to a canvas with
+//! `Image::write_rgba`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "std")]
+use color::Color;
+#[cfg(feature = "std")]
+use drawable::Point3f;
+#[cfg(feature = "std")]
+use math::Vec3f;
+
+#[cfg(feature = "fs")]
+pub mod animation;
+#[cfg(feature = "std")]
+pub mod atlas;
+#[cfg(feature = "std")]
+pub mod bake;
+#[cfg(feature = "std")]
+pub mod camera;
+#[cfg(feature = "std")]
+pub mod color;
+#[cfg(feature = "std")]
+pub mod colormap;
+#[cfg(feature = "std")]
+pub mod config;
+#[cfg(feature = "std")]
+pub mod contact_sheet;
+#[cfg(feature = "std")]
+pub mod cubemap;
+#[cfg(feature = "std")]
+pub mod deferred;
+#[cfg(feature = "std")]
+pub mod draw_list;
+#[cfg(feature = "std")]
+pub mod drawable;
+#[cfg(feature = "std")]
+pub mod environment;
+#[cfg(feature = "std")]
+pub mod error;
+#[cfg(feature = "std")]
+pub mod export;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "std")]
+pub mod fog;
+pub mod font;
+#[cfg(feature = "std")]
+pub mod geometry;
+#[cfg(feature = "std")]
+pub mod light;
+#[cfg(feature = "std")]
+pub mod loader;
+pub mod math;
+#[cfg(feature = "std")]
+pub mod mesh;
+#[cfg(feature = "std")]
+pub mod optimize;
+#[cfg(feature = "std")]
+pub mod overlay;
+#[cfg(feature = "std")]
+pub mod postprocess;
+pub mod raster;
+#[cfg(feature = "std")]
+pub mod ray;
+#[cfg(feature = "std")]
+pub mod raytrace;
+#[cfg(feature = "std")]
+pub mod render;
+#[cfg(feature = "std")]
+pub mod report;
+#[cfg(feature = "std")]
+pub mod scalar_field;
+#[cfg(feature = "std")]
+pub mod simplify;
+#[cfg(feature = "std")]
+pub mod spatial;
+#[cfg(feature = "std")]
+pub mod stats;
+#[cfg(feature = "std")]
+pub mod target;
+#[cfg(feature = "std")]
+pub mod texture;
+#[cfg(feature = "std")]
+pub mod tiled;
+#[cfg(feature = "std")]
+pub mod tonemap;
+#[cfg(feature = "std")]
+pub mod uv_layout;
+#[cfg(feature = "std")]
+pub mod validate;
+
+pub type Intensity = f64;
+
+#[cfg(feature = "std")]
+#[allow(unused)]
+#[derive(Clone, Copy)]
+pub enum DrawStyle<'a, 'b> {
+    Wireframe(Color),
+    Filled(Color),
+    /// Flat color per triangle, picked from a hash of the seed and the triangle so renders
+    /// are reproducible.
+    FilledRandom(u64),
+    /// Flat color per triangle from a callback taking the face index within the mesh.
+    /// Triangles drawn directly on a [`drawable::Drawable`] count as face 0.
+    PerFace(&'a dyn Fn(usize) -> Color),
+    /// Per-vertex colors interpolated across the triangle, scaled by the light intensity
+    /// when `lit` is set.
+    VertexColors {
+        colors: (Color, Color, Color),
+        lit: bool,
+    },
+    Textured(&'a image::RgbImage, (&'b Point3f, &'b Point3f, &'b Point3f)),
+    /// Unlit color looked up in a material capture image by the view-space normal, which
+    /// is interpolated from the given vertex normals. The center of the image faces the
+    /// viewer and its top edge points up.
+    Matcap(&'a image::RgbImage, (Vec3f, Vec3f, Vec3f)),
+}
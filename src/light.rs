@@ -0,0 +1,350 @@
+use std::f64::consts::PI;
+
+use crate::color::HdrColor;
+use crate::error::RusterizerError;
+use crate::math::{self, Vec3, Vec3f};
+
+/// Unit direction towards the viewer, from which [`Light::HEADLIGHT`] shines.
+pub const HEADLIGHT_DIRECTION: Vec3f = Vec3 {
+    x: 0.0,
+    y: 0.0,
+    z: 1.0,
+};
+
+/// How the light of a [`Light::point`] dims with the distance `d` from it: it is divided
+/// by `constant + linear * d + quadratic * d * d`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Falloff {
+    pub constant: f64,
+    pub linear: f64,
+    pub quadratic: f64,
+}
+
+impl Falloff {
+    /// As bright at every distance.
+    pub const NONE: Falloff = Falloff {
+        constant: 1.0,
+        linear: 0.0,
+        quadratic: 0.0,
+    };
+
+    /// Dimming with the square of the distance like real lights, at full strength one unit
+    /// away.
+    pub const INVERSE_SQUARE: Falloff = Falloff {
+        constant: 0.0,
+        linear: 0.0,
+        quadratic: 1.0,
+    };
+
+    /// Fraction of the light left at `distance` from it.
+    pub fn attenuation(&self, distance: f64) -> f64 {
+        let divisor = self.constant + self.linear * distance + self.quadratic * distance * distance;
+        if divisor > 0.0 {
+            1.0 / divisor
+        } else {
+            f64::INFINITY
+        }
+    }
+}
+
+impl Default for Falloff {
+    fn default() -> Self {
+        Falloff::NONE
+    }
+}
+
+/// Where a [`Light`] shines from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LightSource {
+    /// Parallel light from a unit direction towards it, like sunlight.
+    Directional(Vec3f),
+    /// Light spreading out from a position.
+    Point { position: Vec3f, falloff: Falloff },
+    /// Light from a position shining in a cone around a unit direction, with the angles
+    /// from its axis where it starts to fade and where it is gone.
+    Spot {
+        position: Vec3f,
+        direction: Vec3f,
+        inner_angle: f64,
+        outer_angle: f64,
+        falloff: Falloff,
+    },
+}
+
+/// Light on a surface from a [`Light`] at some point.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Incident {
+    /// Unit vector from the point towards the light.
+    pub direction: Vec3f,
+    /// Distance to the light, infinite for directional ones.
+    pub distance: f64,
+    /// Fraction of the light reaching the point.
+    pub attenuation: f64,
+}
+
+/// Light source of a scene, checked when it is made so that shading never has to deal
+/// with directions without a length.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Light {
+    source: LightSource,
+    /// Trace a ray from every lit pixel towards the light, leaving the pixel unlit by it if
+    /// any of [`Lighting::occluders`](crate::deferred::Lighting::occluders) is in the way.
+    pub ray_traced_shadows: bool,
+}
+
+impl Light {
+    /// Light from the viewer, which lights the faces turned towards it.
+    pub const HEADLIGHT: Light = Light {
+        source: LightSource::Directional(HEADLIGHT_DIRECTION),
+        ray_traced_shadows: false,
+    };
+
+    /// Light from `direction` without shadows. Fails for a direction that is zero or not
+    /// finite.
+    pub fn directional(direction: Vec3f) -> Result<Self, RusterizerError> {
+        Ok(Light {
+            source: LightSource::Directional(unit_direction(direction)?),
+            ray_traced_shadows: false,
+        })
+    }
+
+    /// Light from `position` dimmed by `falloff`, without shadows. Fails for a position
+    /// that is not finite, or a falloff with negative terms or none at all.
+    pub fn point(position: Vec3f, falloff: Falloff) -> Result<Self, RusterizerError> {
+        check_position(&position)?;
+        check_falloff(&falloff)?;
+        Ok(Light {
+            source: LightSource::Point { position, falloff },
+            ray_traced_shadows: false,
+        })
+    }
+
+    /// Light from `position` shining along `direction` in a cone, at full strength up to
+    /// `inner_angle` from its axis and fading out smoothly up to `outer_angle`, in
+    /// radians. Fails where [`Light::point`] and [`Light::directional`] do, or for angles
+    /// not in `0..=inner_angle..=outer_angle..=pi`.
+    pub fn spot(
+        position: Vec3f,
+        direction: Vec3f,
+        inner_angle: f64,
+        outer_angle: f64,
+        falloff: Falloff,
+    ) -> Result<Self, RusterizerError> {
+        check_position(&position)?;
+        check_falloff(&falloff)?;
+        if !(0.0 <= inner_angle && inner_angle <= outer_angle && outer_angle <= PI) {
+            return Err(RusterizerError::InvalidArgument(format!(
+                "invalid spot light cone {} to {}",
+                inner_angle, outer_angle
+            )));
+        }
+        Ok(Light {
+            source: LightSource::Spot {
+                position,
+                direction: unit_direction(direction)?,
+                inner_angle,
+                outer_angle,
+                falloff,
+            },
+            ray_traced_shadows: false,
+        })
+    }
+
+    pub fn source(&self) -> LightSource {
+        self.source
+    }
+
+    /// Light arriving at `point`, `None` for a light at the point itself, which has no
+    /// direction to come from.
+    pub fn incident(&self, point: &Vec3f) -> Option<Incident> {
+        let towards = |position: Vec3f, falloff: Falloff| {
+            let offset = position - *point;
+            let distance = offset.length();
+            (distance > 0.0).then(|| Incident {
+                direction: offset * (1.0 / distance),
+                distance,
+                attenuation: falloff.attenuation(distance),
+            })
+        };
+        match self.source {
+            LightSource::Directional(direction) => Some(Incident {
+                direction,
+                distance: f64::INFINITY,
+                attenuation: 1.0,
+            }),
+            LightSource::Point { position, falloff } => towards(position, falloff),
+            LightSource::Spot {
+                position,
+                direction,
+                inner_angle,
+                outer_angle,
+                falloff,
+            } => {
+                let mut incident = towards(position, falloff)?;
+                let cos_angle = -math::dot(&incident.direction, &direction);
+                let (cos_inner, cos_outer) = (inner_angle.cos(), outer_angle.cos());
+                let cone = if cos_inner > cos_outer {
+                    let t = ((cos_angle - cos_outer) / (cos_inner - cos_outer)).clamp(0.0, 1.0);
+                    t * t * (3.0 - 2.0 * t)
+                } else if cos_angle >= cos_outer {
+                    1.0
+                } else {
+                    0.0
+                };
+                incident.attenuation *= cone;
+                Some(incident)
+            }
+        }
+    }
+}
+
+fn check_position(position: &Vec3f) -> Result<(), RusterizerError> {
+    if [position.x, position.y, position.z]
+        .iter()
+        .all(|c| c.is_finite())
+    {
+        return Ok(());
+    }
+    Err(RusterizerError::InvalidArgument(format!(
+        "invalid light position {},{},{}",
+        position.x, position.y, position.z
+    )))
+}
+
+fn unit_direction(direction: Vec3f) -> Result<Vec3f, RusterizerError> {
+    let length = direction.length();
+    if !length.is_finite() || length == 0.0 {
+        return Err(RusterizerError::InvalidArgument(format!(
+            "invalid light direction {},{},{}",
+            direction.x, direction.y, direction.z
+        )));
+    }
+    Ok(direction * (1.0 / length))
+}
+
+fn check_falloff(falloff: &Falloff) -> Result<(), RusterizerError> {
+    let terms = [falloff.constant, falloff.linear, falloff.quadratic];
+    if terms.iter().all(|&t| t.is_finite() && t >= 0.0) && terms.iter().any(|&t| t > 0.0) {
+        return Ok(());
+    }
+    Err(RusterizerError::InvalidArgument(format!(
+        "invalid light falloff {},{},{}",
+        falloff.constant, falloff.linear, falloff.quadratic
+    )))
+}
+
+/// Soft light filling in what direct lights leave dark: ambient light blended from the
+/// sky color on surfaces facing up to the ground color on those facing down, and direct
+/// light wrapped around past the edge of the side it shines on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HemisphereLight {
+    pub sky: HdrColor,
+    pub ground: HdrColor,
+    /// How far direct light reaches around to the side facing away from it, from 0 for
+    /// not at all to 1 for everywhere but straight opposite it.
+    pub wrap: f64,
+}
+
+impl HemisphereLight {
+    /// Ambient light on a surface with the unit `normal`, up being +Y.
+    pub fn irradiance(&self, normal: &Vec3f) -> HdrColor {
+        let up = ((normal.y + 1.0) / 2.0).clamp(0.0, 1.0) as f32;
+        HdrColor(
+            self.ground.0 + (self.sky.0 - self.ground.0) * up,
+            self.ground.1 + (self.sky.1 - self.ground.1) * up,
+            self.ground.2 + (self.sky.2 - self.ground.2) * up,
+        )
+    }
+
+    /// Lambert term of a direct light at an angle with the cosine `n_dot_l` to the
+    /// normal, wrapped around by [`wrap`](Self::wrap).
+    pub fn wrapped_diffuse(&self, n_dot_l: f64) -> f64 {
+        ((n_dot_l + self.wrap) / (1.0 + self.wrap)).max(0.0)
+    }
+}
+
+#[test]
+fn test_light() {
+    let light = Light::directional(Vec3f::new(0., 3., 4.)).unwrap();
+    let incident = light.incident(&Vec3f::new(5., 5., 5.)).unwrap();
+    assert!((incident.direction - Vec3f::new(0., 0.6, 0.8)).length() < 1e-12);
+    assert_eq!(incident.attenuation, 1.0);
+    assert!(Light::directional(Vec3f::new(0., 0., 0.)).is_err());
+    assert!(Light::directional(Vec3f::new(f64::NAN, 0., 1.)).is_err());
+
+    let bulb = Light::point(Vec3f::new(0., 2., 0.), Falloff::INVERSE_SQUARE).unwrap();
+    let incident = bulb.incident(&Vec3f::new(0., 0., 0.)).unwrap();
+    assert_eq!(incident.direction, Vec3f::new(0., 1., 0.));
+    assert_eq!((incident.distance, incident.attenuation), (2.0, 0.25));
+    assert!(bulb.incident(&Vec3f::new(0., 2., 0.)).is_none());
+    let negative = Falloff {
+        linear: -1.0,
+        ..Falloff::NONE
+    };
+    assert!(Light::point(Vec3f::new(0., 0., 0.), negative).is_err());
+    let zero = Falloff {
+        quadratic: 0.0,
+        ..Falloff::INVERSE_SQUARE
+    };
+    assert!(Light::point(Vec3f::new(0., 0., 0.), zero).is_err());
+}
+
+#[test]
+fn test_hemisphere_light() {
+    let hemisphere = HemisphereLight {
+        sky: HdrColor(0.4, 0.4, 0.8),
+        ground: HdrColor(0.2, 0.0, 0.0),
+        wrap: 0.5,
+    };
+    assert_eq!(
+        hemisphere.irradiance(&Vec3f::new(0., 1., 0.)),
+        hemisphere.sky
+    );
+    assert_eq!(
+        hemisphere.irradiance(&Vec3f::new(0., -1., 0.)),
+        hemisphere.ground
+    );
+    assert_eq!(
+        hemisphere.irradiance(&Vec3f::new(1., 0., 0.)),
+        HdrColor(0.3, 0.2, 0.4)
+    );
+    // lit past the side facing the light, but not up to straight behind it
+    assert_eq!(hemisphere.wrapped_diffuse(1.0), 1.0);
+    assert!(hemisphere.wrapped_diffuse(-0.25) > 0.0);
+    assert_eq!(hemisphere.wrapped_diffuse(-0.5), 0.0);
+}
+
+#[test]
+fn test_spot_light() {
+    use std::f64::consts::FRAC_PI_4;
+
+    let spot = Light::spot(
+        Vec3f::new(0., 1., 0.),
+        Vec3f::new(0., -2., 0.),
+        0.1,
+        FRAC_PI_4,
+        Falloff::NONE,
+    )
+    .unwrap();
+    let attenuation = |x: f64| spot.incident(&Vec3f::new(x, 0., 0.)).unwrap().attenuation;
+    // full inside the inner cone, fading out towards 45 degrees and dark past it
+    assert_eq!(attenuation(0.05), 1.0);
+    let fading = attenuation(0.5);
+    assert!(0.0 < fading && fading < 1.0, "{}", fading);
+    assert!(attenuation(0.8) < fading);
+    assert_eq!(attenuation(1.1), 0.0);
+
+    let spot = |inner: f64, outer: f64| {
+        Light::spot(
+            Vec3f::new(0., 0., 0.),
+            Vec3f::new(0., 0., 1.),
+            inner,
+            outer,
+            Falloff::NONE,
+        )
+    };
+    assert!(spot(0.5, 0.5).is_ok());
+    assert!(spot(0.5, 0.4).is_err());
+    assert!(spot(-0.1, 0.4).is_err());
+    assert!(spot(0.1, 4.0).is_err());
+}
@@ -0,0 +1,807 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use ::gltf::animation::util::ReadOutputs;
+use ::gltf::animation::Interpolation;
+use ::gltf::image::{Data as ImageData, Format};
+use ::gltf::mesh::Mode;
+use ::gltf::Node;
+use image::RgbImage;
+
+use crate::color::{Color, HdrColor};
+use crate::error::RusterizerError;
+use crate::math::{Mat4, Vec3f};
+use crate::mesh::{Material, Mesh};
+use crate::texture::{ColorSpace, Texels, Texture};
+
+/// Imports every triangle primitive of the default scene as a separate [`Mesh`],
+/// with node transforms already applied and skins in their rest pose.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Vec<Mesh>, RusterizerError> {
+    Ok(load_animated(path)?.pose(None, 0.0))
+}
+
+/// Imports the default scene keeping its node hierarchy, skins and animations, to be
+/// posed with [`AnimatedScene::pose`].
+pub fn load_animated<P: AsRef<Path>>(path: P) -> Result<AnimatedScene, RusterizerError> {
+    let path = path.as_ref();
+    let (document, buffers, images) = ::gltf::import(path).map_err(|e| match e {
+        ::gltf::Error::Io(e) => RusterizerError::in_file(path, e),
+        ::gltf::Error::Image(e) => RusterizerError::Texture(format!("{}: {}", path.display(), e)),
+        e => RusterizerError::Parse(format!("{}: {}", path.display(), e)),
+    })?;
+    Ok(AnimatedScene::new(&document, &buffers, images))
+}
+
+/// Node transform as translation, rotation quaternion `[x, y, z, w]` and scale, with
+/// the weights of the morph targets of its mesh.
+#[derive(Clone, Debug, PartialEq)]
+struct NodePose {
+    translation: Vec3f,
+    rotation: [f64; 4],
+    scale: Vec3f,
+    weights: Vec<f64>,
+}
+
+impl NodePose {
+    fn matrix(&self) -> Mat4 {
+        let [x, y, z, w] = self.rotation;
+        let rotation = [
+            [
+                1. - 2. * (y * y + z * z),
+                2. * (x * y - z * w),
+                2. * (x * z + y * w),
+            ],
+            [
+                2. * (x * y + z * w),
+                1. - 2. * (x * x + z * z),
+                2. * (y * z - x * w),
+            ],
+            [
+                2. * (x * z - y * w),
+                2. * (y * z + x * w),
+                1. - 2. * (x * x + y * y),
+            ],
+        ];
+        let (t, s) = (self.translation, self.scale);
+        let row = |r: [f64; 3], t: f64| [r[0] * s.x, r[1] * s.y, r[2] * s.z, t];
+        Mat4::new([
+            row(rotation[0], t.x),
+            row(rotation[1], t.y),
+            row(rotation[2], t.z),
+            [0., 0., 0., 1.],
+        ])
+    }
+}
+
+/// Joints and weights binding each vertex of a primitive to a skin.
+#[derive(Clone, Debug)]
+struct Skinning {
+    skin: usize,
+    joints: Vec<[usize; 4]>,
+    weights: Vec<[f64; 4]>,
+}
+
+/// Offsets of the vertices of a primitive at full weight; normals may be left out.
+#[derive(Clone, Debug)]
+struct MorphTarget {
+    positions: Vec<Vec3f>,
+    normals: Vec<Vec3f>,
+}
+
+/// A triangle primitive as stored in the file, before morphing, node transforms and
+/// skinning.
+#[derive(Clone, Debug)]
+struct Primitive {
+    node: usize,
+    mesh: Mesh,
+    morph_targets: Vec<MorphTarget>,
+    skinning: Option<Skinning>,
+}
+
+#[derive(Clone, Debug)]
+struct Skin {
+    /// Node of every joint.
+    joints: Vec<usize>,
+    /// Transforms from the model to the space of each joint in the bind pose.
+    inverse_bind: Vec<Mat4>,
+}
+
+#[derive(Clone, Debug)]
+enum Keyframes {
+    Translation(Vec<Vec3f>),
+    Rotation(Vec<[f64; 4]>),
+    Scale(Vec<Vec3f>),
+    Weights(Vec<Vec<f64>>),
+}
+
+/// Keyframes of one property of one node.
+#[derive(Clone, Debug)]
+struct Channel {
+    node: usize,
+    times: Vec<f64>,
+    keyframes: Keyframes,
+    /// Hold each value until the next keyframe instead of interpolating.
+    step: bool,
+}
+
+impl Channel {
+    /// Overrides the animated property of `pose` with its value at `time`, holding the
+    /// first and last values outside the keyframes.
+    fn apply(&self, time: f64, pose: &mut NodePose) {
+        let Some(&last) = self.times.last() else {
+            return;
+        };
+        let time = time.clamp(self.times[0], last);
+        let next = self
+            .times
+            .partition_point(|&t| t <= time)
+            .min(self.times.len() - 1);
+        let previous = next.saturating_sub(1);
+        let span = self.times[next] - self.times[previous];
+        let t = if self.step || span <= 0.0 {
+            0.0
+        } else {
+            (time - self.times[previous]) / span
+        };
+        let lerp = |a: Vec3f, b: Vec3f| a * (1.0 - t) + b * t;
+        match &self.keyframes {
+            Keyframes::Translation(values) => {
+                pose.translation = lerp(values[previous], values[next]);
+            }
+            Keyframes::Rotation(values) => {
+                pose.rotation = slerp(values[previous], values[next], t);
+            }
+            Keyframes::Scale(values) => pose.scale = lerp(values[previous], values[next]),
+            Keyframes::Weights(values) => {
+                pose.weights = values[previous]
+                    .iter()
+                    .zip(&values[next])
+                    .map(|(a, b)| a * (1.0 - t) + b * t)
+                    .collect();
+            }
+        }
+    }
+}
+
+/// Spherical interpolation between unit quaternions along the shorter arc.
+fn slerp(a: [f64; 4], b: [f64; 4], t: f64) -> [f64; 4] {
+    let mut cos = (0..4).map(|i| a[i] * b[i]).sum::<f64>();
+    let b = if cos < 0.0 {
+        cos = -cos;
+        b.map(|c| -c)
+    } else {
+        b
+    };
+    let (wa, wb) = if cos > 0.9995 {
+        // nearly parallel: linear interpolation is exact enough and stable
+        (1.0 - t, t)
+    } else {
+        let angle = cos.acos();
+        let sin = angle.sin();
+        (((1.0 - t) * angle).sin() / sin, (t * angle).sin() / sin)
+    };
+    let q: [f64; 4] = std::array::from_fn(|i| a[i] * wa + b[i] * wb);
+    let length = q.iter().map(|c| c * c).sum::<f64>().sqrt();
+    q.map(|c| c / length)
+}
+
+/// An animation of the file, playing all its channels together.
+#[derive(Clone, Debug)]
+pub struct Clip {
+    pub name: Option<String>,
+    /// Time of the last keyframe in seconds.
+    pub duration: f64,
+    channels: Vec<Channel>,
+}
+
+/// The default scene of a glTF file with its node hierarchy, skins and animation clips.
+#[derive(Clone, Debug)]
+pub struct AnimatedScene {
+    parents: Vec<Option<usize>>,
+    rest: Vec<NodePose>,
+    /// Nodes of the scene, every parent before its children.
+    order: Vec<usize>,
+    primitives: Vec<Primitive>,
+    skins: Vec<Skin>,
+    pub clips: Vec<Clip>,
+}
+
+impl AnimatedScene {
+    fn new(
+        document: &::gltf::Document,
+        buffers: &[::gltf::buffer::Data],
+        images: Vec<ImageData>,
+    ) -> Self {
+        let textures: Vec<Option<Arc<RgbImage>>> = images
+            .into_iter()
+            .map(|data| convert_image(data).map(Arc::new))
+            .collect();
+        let read = |buffer: ::gltf::Buffer| Some(&buffers[buffer.index()].0[..]);
+
+        let mut parents = vec![None; document.nodes().len()];
+        let mut rest = Vec::new();
+        for node in document.nodes() {
+            let (translation, rotation, scale) = node.transform().decomposed();
+            let weights = node
+                .weights()
+                .or_else(|| node.mesh().and_then(|mesh| mesh.weights()))
+                .unwrap_or_default();
+            rest.push(NodePose {
+                translation: to_vec3f(translation),
+                rotation: rotation.map(|c| c as f64),
+                scale: to_vec3f(scale),
+                weights: weights.iter().map(|&w| w as f64).collect(),
+            });
+            for child in node.children() {
+                parents[child.index()] = Some(node.index());
+            }
+        }
+
+        let mut order = Vec::new();
+        if let Some(scene) = document
+            .default_scene()
+            .or_else(|| document.scenes().next())
+        {
+            let mut pending: Vec<Node> = scene.nodes().collect();
+            pending.reverse();
+            while let Some(node) = pending.pop() {
+                order.push(node.index());
+                let mut children: Vec<Node> = node.children().collect();
+                children.reverse();
+                pending.extend(children);
+            }
+        }
+
+        let nodes: Vec<Node> = document.nodes().collect();
+        let mut primitives = Vec::new();
+        for &index in &order {
+            let node = &nodes[index];
+            if let Some(gltf_mesh) = node.mesh() {
+                for primitive in gltf_mesh.primitives() {
+                    if let Some(mut primitive) =
+                        read_primitive(&gltf_mesh, &primitive, buffers, &textures)
+                    {
+                        primitive.node = index;
+                        if let (Some(skin), Some(skinning)) = (node.skin(), &mut primitive.skinning)
+                        {
+                            skinning.skin = skin.index();
+                        } else {
+                            primitive.skinning = None;
+                        }
+                        primitives.push(primitive);
+                    }
+                }
+            }
+        }
+
+        let skins = document
+            .skins()
+            .map(|skin| {
+                let joints: Vec<usize> = skin.joints().map(|joint| joint.index()).collect();
+                let inverse_bind = skin.reader(read).read_inverse_bind_matrices().map_or_else(
+                    || vec![Mat4::identity(); joints.len()],
+                    |matrices| matrices.map(convert_matrix).collect(),
+                );
+                Skin {
+                    joints,
+                    inverse_bind,
+                }
+            })
+            .collect();
+
+        let clips = document
+            .animations()
+            .map(|animation| {
+                let channels: Vec<Channel> = animation
+                    .channels()
+                    .filter_map(|channel| read_channel(&channel, read))
+                    .collect();
+                let duration = channels
+                    .iter()
+                    .filter_map(|channel| channel.times.last().copied())
+                    .fold(0.0, f64::max);
+                Clip {
+                    name: animation.name().map(str::to_string),
+                    duration,
+                    channels,
+                }
+            })
+            .collect();
+
+        AnimatedScene {
+            parents,
+            rest,
+            order,
+            primitives,
+            skins,
+            clips,
+        }
+    }
+
+    /// Index of the clip with the given name, or given by its index.
+    pub fn find_clip(&self, name: &str) -> Option<usize> {
+        self.clips
+            .iter()
+            .position(|clip| clip.name.as_deref() == Some(name))
+            .or_else(|| name.parse().ok().filter(|&index| index < self.clips.len()))
+    }
+
+    /// Every primitive as a [`Mesh`] posed `time` seconds into `clip`, or in the rest
+    /// pose without a clip. Morph targets are blended in first, then skinned vertices
+    /// are blended from the transforms of up to four joints on the CPU.
+    pub fn pose(&self, clip: Option<usize>, time: f64) -> Vec<Mesh> {
+        self.pose_with_weights(clip, time, None)
+    }
+
+    /// Like [`AnimatedScene::pose`], with `weights` replacing the morph target weights
+    /// of every mesh when given. Missing weights count as 0 and extra ones are ignored.
+    pub fn pose_with_weights(
+        &self,
+        clip: Option<usize>,
+        time: f64,
+        weights: Option<&[f64]>,
+    ) -> Vec<Mesh> {
+        let mut local = self.rest.clone();
+        if let Some(clip) = clip.and_then(|clip| self.clips.get(clip)) {
+            for channel in &clip.channels {
+                channel.apply(time, &mut local[channel.node]);
+            }
+        }
+        let mut global = vec![Mat4::identity(); local.len()];
+        for &node in &self.order {
+            let transform = local[node].matrix();
+            global[node] = match self.parents[node] {
+                Some(parent) => global[parent] * transform,
+                None => transform,
+            };
+        }
+
+        self.primitives
+            .iter()
+            .map(|primitive| {
+                let mut mesh = primitive.mesh.clone();
+                let weights = weights.unwrap_or(&local[primitive.node].weights);
+                morph(&mut mesh, &primitive.morph_targets, weights);
+                match &primitive.skinning {
+                    Some(skinning) => {
+                        skin_mesh(&mut mesh, skinning, &self.skins[skinning.skin], &global)
+                    }
+                    None => mesh.transform(&global[primitive.node]),
+                }
+                mesh
+            })
+            .collect()
+    }
+}
+
+/// Offsets the vertices by every morph target scaled by its weight.
+fn morph(mesh: &mut Mesh, targets: &[MorphTarget], weights: &[f64]) {
+    let mut morphed_normals = false;
+    for (target, &weight) in targets.iter().zip(weights) {
+        if weight == 0.0 {
+            continue;
+        }
+        for (p, offset) in mesh.positions.iter_mut().zip(&target.positions) {
+            *p = *p + *offset * weight;
+        }
+        if mesh.has_normals() {
+            for (n, offset) in mesh.normals.iter_mut().zip(&target.normals) {
+                *n = *n + *offset * weight;
+                morphed_normals = true;
+            }
+        }
+    }
+    if morphed_normals {
+        for n in &mut mesh.normals {
+            if n.length_squared() > 0.0 {
+                *n = n.normalized();
+            }
+        }
+    }
+}
+
+/// Moves every vertex by the weighted sum of its joints' transforms; the node of a
+/// skinned mesh does not move it.
+fn skin_mesh(mesh: &mut Mesh, skinning: &Skinning, skin: &Skin, global: &[Mat4]) {
+    let joints: Vec<Mat4> = skin
+        .joints
+        .iter()
+        .zip(&skin.inverse_bind)
+        .map(|(&node, inverse_bind)| global[node] * *inverse_bind)
+        .collect();
+    let normal_matrices: Vec<Mat4> = joints
+        .iter()
+        .map(|joint| {
+            joint
+                .inverse()
+                .map_or(*joint, |inverse| inverse.transpose())
+        })
+        .collect();
+    let zero = Vec3f::new(0., 0., 0.);
+    for (v, (indices, weights)) in skinning.joints.iter().zip(&skinning.weights).enumerate() {
+        let influences = || {
+            indices
+                .iter()
+                .zip(weights)
+                .filter(|&(&joint, &weight)| weight > 0.0 && joint < joints.len())
+        };
+        let total: f64 = influences().map(|(_, weight)| weight).sum();
+        if total <= 0.0 {
+            continue;
+        }
+        mesh.positions[v] = influences().fold(zero, |sum, (&joint, &weight)| {
+            sum + joints[joint].transform_point(&mesh.positions[v]) * (weight / total)
+        });
+        if mesh.has_normals() {
+            let normal = influences().fold(zero, |sum, (&joint, &weight)| {
+                sum + normal_matrices[joint].transform_vector(&mesh.normals[v]) * weight
+            });
+            if normal.length_squared() > 0.0 {
+                mesh.normals[v] = normal.normalized();
+            }
+        }
+    }
+}
+
+fn read_channel<'a, 's, F>(channel: &::gltf::animation::Channel<'a>, read: F) -> Option<Channel>
+where
+    F: Clone + Fn(::gltf::Buffer<'a>) -> Option<&'s [u8]>,
+{
+    let reader = channel.reader(read);
+    let times: Vec<f64> = reader.read_inputs()?.map(|t| t as f64).collect();
+    let interpolation = channel.sampler().interpolation();
+    let cubic = interpolation == Interpolation::CubicSpline;
+    let keyframes = match reader.read_outputs()? {
+        ReadOutputs::Translations(values) => {
+            Keyframes::Translation(keyframe_values(values.map(to_vec3f), cubic))
+        }
+        ReadOutputs::Rotations(values) => Keyframes::Rotation(keyframe_values(
+            values.into_f32().map(|q| q.map(|c| c as f64)),
+            cubic,
+        )),
+        ReadOutputs::Scales(values) => {
+            Keyframes::Scale(keyframe_values(values.map(to_vec3f), cubic))
+        }
+        ReadOutputs::MorphTargetWeights(values) => {
+            let values: Vec<f64> = values.into_f32().map(|w| w as f64).collect();
+            let per_keyframe = times.len() * if cubic { 3 } else { 1 };
+            if per_keyframe == 0 || !values.len().is_multiple_of(per_keyframe) {
+                return None;
+            }
+            let targets = (values.len() / per_keyframe).max(1);
+            Keyframes::Weights(keyframe_values(
+                values.chunks(targets).map(<[f64]>::to_vec),
+                cubic,
+            ))
+        }
+    };
+    let count = match &keyframes {
+        Keyframes::Translation(values) | Keyframes::Scale(values) => values.len(),
+        Keyframes::Rotation(values) => values.len(),
+        Keyframes::Weights(values) => values.len(),
+    };
+    if count != times.len() {
+        log::warn!("skipping glTF animation channel with mismatched keyframes");
+        return None;
+    }
+    Some(Channel {
+        node: channel.target().node().index(),
+        times,
+        keyframes,
+        step: interpolation == Interpolation::Step,
+    })
+}
+
+/// Cubic splines store an in-tangent, the value and an out-tangent per keyframe; the
+/// tangents are dropped and the values interpolated linearly.
+fn keyframe_values<T>(values: impl Iterator<Item = T>, cubic: bool) -> Vec<T> {
+    if cubic {
+        values.skip(1).step_by(3).collect()
+    } else {
+        values.collect()
+    }
+}
+
+/// Reads a triangle primitive without applying any node transform; `node` and the skin
+/// of its skinning are left for the caller to fill in.
+fn read_primitive(
+    gltf_mesh: &::gltf::Mesh,
+    primitive: &::gltf::Primitive,
+    buffers: &[::gltf::buffer::Data],
+    textures: &[Option<Arc<RgbImage>>],
+) -> Option<Primitive> {
+    if primitive.mode() != Mode::Triangles {
+        log::warn!(
+            "skipping non-triangle glTF primitive {:?} of mesh {}",
+            primitive.mode(),
+            gltf_mesh.name().unwrap_or("(unnamed)")
+        );
+        return None;
+    }
+    let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+    let positions: Vec<Vec3f> = reader.read_positions()?.map(to_vec3f).collect();
+    let normals = reader
+        .read_normals()
+        .map_or_else(Vec::new, |normals| normals.map(to_vec3f).collect());
+
+    let pbr = primitive.material().pbr_metallic_roughness();
+    let base_color_texture = pbr.base_color_texture();
+    let uv_set = base_color_texture
+        .as_ref()
+        .map_or(0, |info| info.tex_coord());
+    let uvs = reader.read_tex_coords(uv_set).map_or_else(Vec::new, |uvs| {
+        uvs.into_f32().map(|[u, v]| [u as f64, v as f64]).collect()
+    });
+
+    // glTF vertex colors are linear, ours are sRGB encoded
+    let colors = reader.read_colors(0).map_or_else(Vec::new, |colors| {
+        colors
+            .into_rgb_f32()
+            .map(|[r, g, b]| HdrColor(r, g, b).to_srgb())
+            .collect()
+    });
+
+    let indices: Vec<u32> = match reader.read_indices() {
+        Some(indices) => indices.into_u32().collect(),
+        None => (0..positions.len() as u32).collect(),
+    };
+    let indices = indices
+        .chunks_exact(3)
+        .map(|t| [t[0] as usize, t[1] as usize, t[2] as usize])
+        .collect();
+
+    let skinning = match (reader.read_joints(0), reader.read_weights(0)) {
+        (Some(joints), Some(weights)) => Some(Skinning {
+            skin: 0,
+            joints: joints.into_u16().map(|j| j.map(|j| j as usize)).collect(),
+            weights: weights.into_f32().map(|w| w.map(|w| w as f64)).collect(),
+        }),
+        _ => None,
+    }
+    .filter(|skinning| {
+        skinning.joints.len() == positions.len() && skinning.weights.len() == positions.len()
+    });
+
+    let morph_targets = reader
+        .read_morph_targets()
+        .map(|(positions, normals, _)| MorphTarget {
+            positions: positions.map_or_else(Vec::new, |p| p.map(to_vec3f).collect()),
+            normals: normals.map_or_else(Vec::new, |n| n.map(to_vec3f).collect()),
+        })
+        .collect();
+
+    let [r, g, b, _] = pbr.base_color_factor();
+    let to_u8 = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+    let material = Material {
+        base_color: Color(to_u8(r), to_u8(g), to_u8(b)),
+        base_color_texture: base_color_texture
+            .and_then(|info| textures[info.texture().source().index()].clone()),
+        // only smooth metals come out as mirrors
+        reflectivity: (pbr.metallic_factor() * (1.0 - pbr.roughness_factor())) as f64,
+        metallic: pbr.metallic_factor() as f64,
+        roughness: pbr.roughness_factor() as f64,
+        metallic_roughness_texture: pbr
+            .metallic_roughness_texture()
+            .and_then(|info| textures[info.texture().source().index()].clone())
+            .map(|texels| Texture {
+                texels: Texels::Rgb(texels),
+                color_space: ColorSpace::Linear,
+            }),
+        occlusion_texture: primitive
+            .material()
+            .occlusion_texture()
+            .and_then(|info| textures[info.texture().source().index()].clone())
+            .map(|texels| Texture {
+                texels: Texels::Rgb(texels),
+                color_space: ColorSpace::Linear,
+            }),
+    };
+
+    Some(Primitive {
+        node: 0,
+        mesh: Mesh {
+            name: gltf_mesh.name().map(str::to_string),
+            positions,
+            normals,
+            uvs,
+            colors,
+            indices,
+            material,
+            ..Default::default()
+        },
+        morph_targets,
+        skinning,
+    })
+}
+
+fn to_vec3f([x, y, z]: [f32; 3]) -> Vec3f {
+    Vec3f::new(x as f64, y as f64, z as f64)
+}
+
+/// glTF stores matrices column-major.
+fn convert_matrix(m: [[f32; 4]; 4]) -> Mat4 {
+    let mut rows = [[0.0; 4]; 4];
+    for (i, row) in rows.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = m[j][i] as f64;
+        }
+    }
+    Mat4::new(rows)
+}
+
+fn convert_image(data: ImageData) -> Option<RgbImage> {
+    let channels = match data.format {
+        Format::R8 => 1,
+        Format::R8G8 => 2,
+        Format::R8G8B8 => 3,
+        Format::R8G8B8A8 => 4,
+        format => {
+            log::warn!("skipping glTF texture of unsupported format {:?}", format);
+            return None;
+        }
+    };
+    let pixels = data.pixels;
+    Some(RgbImage::from_fn(data.width, data.height, |x, y| {
+        let idx = ((y * data.width + x) * channels) as usize;
+        match channels {
+            1 | 2 => image::Rgb([pixels[idx]; 3]),
+            _ => image::Rgb([pixels[idx], pixels[idx + 1], pixels[idx + 2]]),
+        }
+    }))
+}
+
+/// Packs a JSON document and its binary buffer into a GLB file and imports it.
+#[cfg(test)]
+fn import_glb(json: &str, bin: &[u8]) -> AnimatedScene {
+    let mut json = json.as_bytes().to_vec();
+    json.resize(json.len().div_ceil(4) * 4, b' ');
+    let chunk = |kind: &[u8; 4], data: &[u8]| {
+        let mut chunk = (data.len() as u32).to_le_bytes().to_vec();
+        chunk.extend(kind);
+        chunk.extend(data);
+        chunk
+    };
+    let body = [chunk(b"JSON", &json), chunk(b"BIN\0", bin)].concat();
+    let mut glb = b"glTF".to_vec();
+    glb.extend(2u32.to_le_bytes());
+    glb.extend((12 + body.len() as u32).to_le_bytes());
+    glb.extend(body);
+
+    let (document, buffers, images) = ::gltf::import_slice(&glb).unwrap();
+    AnimatedScene::new(&document, &buffers, images)
+}
+
+#[test]
+fn test_skinned_animation() {
+    // a triangle whose top vertex follows a child joint sliding along x over a second
+    let floats = |values: &[f32]| {
+        values
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect::<Vec<u8>>()
+    };
+    let mut bin = floats(&[-1., 0., 0., 1., 0., 0., 0., 1., 0.]);
+    bin.extend(
+        [0u16, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0]
+            .iter()
+            .flat_map(|j| j.to_le_bytes()),
+    );
+    bin.extend(floats(&[1., 0., 0., 0.].repeat(3)));
+    let mut inverse_binds = [0f32; 32];
+    for i in 0..4 {
+        inverse_binds[i * 5] = 1.0;
+        inverse_binds[16 + i * 5] = 1.0;
+    }
+    inverse_binds[16 + 13] = -1.0;
+    bin.extend(floats(&inverse_binds));
+    bin.extend(floats(&[0., 1.]));
+    bin.extend(floats(&[0., 1., 0., 1., 1., 0.]));
+    let json = format!(
+        r#"{{
+        "asset": {{"version": "2.0"}},
+        "scene": 0,
+        "scenes": [{{"nodes": [0, 1]}}],
+        "nodes": [
+            {{"mesh": 0, "skin": 0}},
+            {{"children": [2]}},
+            {{"translation": [0, 1, 0]}}
+        ],
+        "meshes": [{{"primitives": [{{
+            "attributes": {{"POSITION": 0, "JOINTS_0": 1, "WEIGHTS_0": 2}}
+        }}]}}],
+        "skins": [{{"joints": [1, 2], "inverseBindMatrices": 3}}],
+        "animations": [{{
+            "name": "slide",
+            "channels": [{{"sampler": 0, "target": {{"node": 2, "path": "translation"}}}}],
+            "samplers": [{{"input": 4, "output": 5}}]
+        }}],
+        "buffers": [{{"byteLength": {}}}],
+        "bufferViews": [
+            {{"buffer": 0, "byteOffset": 0, "byteLength": 36}},
+            {{"buffer": 0, "byteOffset": 36, "byteLength": 24}},
+            {{"buffer": 0, "byteOffset": 60, "byteLength": 48}},
+            {{"buffer": 0, "byteOffset": 108, "byteLength": 128}},
+            {{"buffer": 0, "byteOffset": 236, "byteLength": 8}},
+            {{"buffer": 0, "byteOffset": 244, "byteLength": 24}}
+        ],
+        "accessors": [
+            {{"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+              "min": [-1, 0, 0], "max": [1, 1, 0]}},
+            {{"bufferView": 1, "componentType": 5123, "count": 3, "type": "VEC4"}},
+            {{"bufferView": 2, "componentType": 5126, "count": 3, "type": "VEC4"}},
+            {{"bufferView": 3, "componentType": 5126, "count": 2, "type": "MAT4"}},
+            {{"bufferView": 4, "componentType": 5126, "count": 2, "type": "SCALAR",
+              "min": [0], "max": [1]}},
+            {{"bufferView": 5, "componentType": 5126, "count": 2, "type": "VEC3"}}
+        ]
+    }}"#,
+        bin.len()
+    );
+
+    let scene = import_glb(&json, &bin);
+    assert_eq!(scene.find_clip("slide"), Some(0));
+    assert_eq!(scene.find_clip("0"), Some(0));
+    assert_eq!(scene.clips[0].duration, 1.0);
+
+    let top = |meshes: Vec<Mesh>| meshes[0].positions[2];
+    assert!((top(scene.pose(None, 0.0)) - Vec3f::new(0., 1., 0.)).length() < 1e-6);
+    assert!((top(scene.pose(Some(0), 0.5)) - Vec3f::new(0.5, 1., 0.)).length() < 1e-6);
+    // held at the last keyframe, while the vertices bound to the root joint stay put
+    let posed = scene.pose(Some(0), 2.0);
+    assert!((posed[0].positions[2] - Vec3f::new(1., 1., 0.)).length() < 1e-6);
+    assert!((posed[0].positions[0] - Vec3f::new(-1., 0., 0.)).length() < 1e-6);
+}
+
+#[test]
+fn test_morph_targets() {
+    // a triangle whose top vertex a target raises by one, half way at rest
+    let floats = |values: &[f32]| {
+        values
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect::<Vec<u8>>()
+    };
+    let mut bin = floats(&[-1., 0., 0., 1., 0., 0., 0., 1., 0.]);
+    bin.extend(floats(&[0., 0., 0., 0., 0., 0., 0., 1., 0.]));
+    bin.extend(floats(&[0., 1.]));
+    bin.extend(floats(&[0., 1.]));
+    let json = format!(
+        r#"{{
+        "asset": {{"version": "2.0"}},
+        "scenes": [{{"nodes": [0]}}],
+        "nodes": [{{"mesh": 0}}],
+        "meshes": [{{
+            "primitives": [{{"attributes": {{"POSITION": 0}}, "targets": [{{"POSITION": 1}}]}}],
+            "weights": [0.5]
+        }}],
+        "animations": [{{
+            "channels": [{{"sampler": 0, "target": {{"node": 0, "path": "weights"}}}}],
+            "samplers": [{{"input": 2, "output": 3}}]
+        }}],
+        "buffers": [{{"byteLength": {}}}],
+        "bufferViews": [
+            {{"buffer": 0, "byteOffset": 0, "byteLength": 36}},
+            {{"buffer": 0, "byteOffset": 36, "byteLength": 36}},
+            {{"buffer": 0, "byteOffset": 72, "byteLength": 8}},
+            {{"buffer": 0, "byteOffset": 80, "byteLength": 8}}
+        ],
+        "accessors": [
+            {{"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+              "min": [-1, 0, 0], "max": [1, 1, 0]}},
+            {{"bufferView": 1, "componentType": 5126, "count": 3, "type": "VEC3",
+              "min": [0, 0, 0], "max": [0, 1, 0]}},
+            {{"bufferView": 2, "componentType": 5126, "count": 2, "type": "SCALAR",
+              "min": [0], "max": [1]}},
+            {{"bufferView": 3, "componentType": 5126, "count": 2, "type": "SCALAR"}}
+        ]
+    }}"#,
+        bin.len()
+    );
+
+    let scene = import_glb(&json, &bin);
+    let top = |meshes: Vec<Mesh>| meshes[0].positions[2].y;
+    assert!((top(scene.pose(None, 0.0)) - 1.5).abs() < 1e-6);
+    assert!((top(scene.pose(Some(0), 0.25)) - 1.25).abs() < 1e-6);
+    assert!((top(scene.pose_with_weights(Some(0), 0.25, Some(&[1.0]))) - 2.0).abs() < 1e-6);
+    assert!((top(scene.pose_with_weights(None, 0.0, Some(&[]))) - 1.0).abs() < 1e-6);
+}
@@ -0,0 +1,187 @@
+#[cfg(feature = "fs")]
+use std::collections::HashMap;
+#[cfg(feature = "fs")]
+use std::path::{Path, PathBuf};
+#[cfg(feature = "fs")]
+use std::sync::Mutex;
+
+#[cfg(feature = "fs")]
+use crate::cubemap::CubeMap;
+use crate::error::RusterizerError;
+#[cfg(feature = "fs")]
+use crate::mesh::Mesh;
+#[cfg(feature = "fs")]
+use crate::texture::{Texture, TextureFormat};
+
+#[cfg(feature = "gltf")]
+pub mod gltf;
+pub mod obj;
+pub mod ply;
+pub mod stl;
+pub mod weights;
+
+/// Loads the meshes of a model file by its extension: glTF, PLY and STL files are
+/// recognized and anything else is read as OBJ.
+#[cfg(feature = "fs")]
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Vec<Mesh>, RusterizerError> {
+    let path = path.as_ref();
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("ply") => ply::load(path).map(|mesh| vec![mesh]),
+        Some("stl") => stl::load(path).map(|mesh| vec![mesh]),
+        Some("gltf" | "glb") => load_gltf(path),
+        _ => obj::load(path),
+    }
+}
+
+#[cfg(feature = "gltf")]
+fn load_gltf(path: &Path) -> Result<Vec<Mesh>, RusterizerError> {
+    gltf::load(path)
+}
+
+#[cfg(all(feature = "fs", not(feature = "gltf")))]
+fn load_gltf(_path: &Path) -> Result<Vec<Mesh>, RusterizerError> {
+    Err(RusterizerError::InvalidArgument(
+        "glTF support requires building with the `gltf` feature".to_string(),
+    ))
+}
+
+/// Loads an image to sample as a texture, in the orientation it is stored in.
+#[cfg(feature = "fs")]
+pub fn load_texture<P: AsRef<Path>>(path: P) -> Result<image::DynamicImage, RusterizerError> {
+    let path = path.as_ref();
+    image::open(path).map_err(|e| match e {
+        image::ImageError::IoError(e) => RusterizerError::in_file(path, e),
+        e => RusterizerError::Texture(format!("{}: {}", path.display(), e)),
+    })
+}
+
+/// Loads an image as a texture of the given format, flipped so that row 0 is at `v = 0`.
+#[cfg(feature = "fs")]
+pub fn load_texture_as<P: AsRef<Path>>(
+    path: P,
+    format: TextureFormat,
+) -> Result<Texture, RusterizerError> {
+    load_texture(path).map(|image| Texture::from_image(&image, format))
+}
+
+/// Loads a cube map from a single cross image or from six faces in the order of
+/// [`Face::ALL`](crate::cubemap::Face::ALL).
+#[cfg(feature = "fs")]
+pub fn load_cube_map<P: AsRef<Path>>(paths: &[P]) -> Result<CubeMap, RusterizerError> {
+    match paths {
+        [path] => CubeMap::from_cross(&load_texture(path)?),
+        [px, nx, py, ny, pz, nz] => CubeMap::from_faces(&[
+            load_texture(px)?,
+            load_texture(nx)?,
+            load_texture(py)?,
+            load_texture(ny)?,
+            load_texture(pz)?,
+            load_texture(nz)?,
+        ]),
+        _ => Err(RusterizerError::InvalidArgument(format!(
+            "a cube map needs one cross image or six faces, got {} images",
+            paths.len()
+        ))),
+    }
+}
+
+/// Textures loaded by path and format, shared by every mesh, frame and thread that asks
+/// for the same file instead of being decoded again.
+#[cfg(feature = "fs")]
+#[derive(Debug, Default)]
+pub struct TextureCache {
+    textures: Mutex<HashMap<(PathBuf, TextureFormat), Texture>>,
+}
+
+#[cfg(feature = "fs")]
+impl TextureCache {
+    pub fn new() -> Self {
+        TextureCache::default()
+    }
+
+    /// The texture at `path` as [`load_texture_as`] loads it, loading it on first use.
+    pub fn get<P: AsRef<Path>>(
+        &self,
+        path: P,
+        format: TextureFormat,
+    ) -> Result<Texture, RusterizerError> {
+        let key = (path.as_ref().to_path_buf(), format);
+        if let Some(texture) = self.textures.lock().unwrap().get(&key) {
+            return Ok(texture.clone());
+        }
+        // decoded unlocked so other textures can load meanwhile; a race loads one twice
+        let texture = load_texture_as(&key.0, format)?;
+        let mut textures = self.textures.lock().unwrap();
+        Ok(textures.entry(key).or_insert(texture).clone())
+    }
+
+    /// Number of textures held.
+    pub fn len(&self) -> usize {
+        self.textures.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drops the cache's references; textures still used by meshes stay alive.
+    pub fn clear(&self) {
+        self.textures.lock().unwrap().clear();
+    }
+}
+
+#[cfg(feature = "fs")]
+fn read(path: &Path) -> Result<Vec<u8>, RusterizerError> {
+    std::fs::read(path).map_err(|e| RusterizerError::in_file(path, e))
+}
+
+#[cfg(feature = "fs")]
+fn read_to_string(path: &Path) -> Result<String, RusterizerError> {
+    std::fs::read_to_string(path).map_err(|e| RusterizerError::in_file(path, e))
+}
+
+/// Names the file a parse error was found in.
+#[cfg(feature = "fs")]
+fn in_file(path: &Path, error: RusterizerError) -> RusterizerError {
+    match error {
+        RusterizerError::Parse(msg) => {
+            RusterizerError::Parse(format!("{}: {}", path.display(), msg))
+        }
+        error => error,
+    }
+}
+
+fn parse_error<T, S: Into<String>>(msg: S) -> Result<T, RusterizerError> {
+    Err(RusterizerError::Parse(msg.into()))
+}
+
+#[cfg(feature = "fs")]
+#[test]
+fn test_texture_cache() {
+    let path = std::env::temp_dir().join("rusterizer_test_texture_cache.png");
+    let mut texture = image::RgbImage::new(2, 2);
+    texture.put_pixel(0, 0, image::Rgb([255, 0, 0]));
+    texture.save(&path).unwrap();
+
+    let cache = TextureCache::new();
+    let first = cache.get(&path, TextureFormat::COLOR).unwrap();
+    let second = cache.get(&path, TextureFormat::COLOR).unwrap();
+    let gray = cache.get(&path, TextureFormat::SCALAR).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(std::sync::Arc::ptr_eq(
+        &first.to_srgb_rgb(),
+        &second.to_srgb_rgb()
+    ));
+    assert_eq!(gray.format(), TextureFormat::SCALAR);
+    assert_eq!(cache.len(), 2);
+    // flipped, so the top row of the file is at v = 1
+    assert_eq!(first.to_srgb_rgb().get_pixel(0, 1).0, [255, 0, 0]);
+
+    let missing = path.with_extension("missing.png");
+    assert!(cache.get(missing, TextureFormat::COLOR).is_err());
+    cache.clear();
+    assert!(cache.is_empty());
+}
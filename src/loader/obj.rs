@@ -0,0 +1,445 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+use wavefront_obj::obj::{ObjSet, Object, Primitive, Shape, VTNIndex};
+
+use crate::color::Color;
+use crate::error::RusterizerError;
+use crate::math::{self, Vec3f};
+use crate::mesh::Mesh;
+
+#[cfg(feature = "fs")]
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Vec<Mesh>, RusterizerError> {
+    let path = path.as_ref();
+    parse(super::read_to_string(path)?).map_err(|e| super::in_file(path, e))
+}
+
+/// Parses OBJ content, including the common `v x y z r g b` vertex color extension.
+///
+/// Every object is split into one mesh per set of `g` groups, each mesh listing its
+/// groups in [`Mesh::groups`]. Files without normals get them from their smoothing
+/// groups: smooth within a group and hard across groups, with faces outside any
+/// smoothing group left flat.
+pub fn parse<S: AsRef<str>>(content: S) -> Result<Vec<Mesh>, RusterizerError> {
+    let (content, colors) = split_vertex_colors(content.as_ref());
+    let obj_set = wavefront_obj::obj::parse(content)
+        .map_err(|e| RusterizerError::Parse(format!("line {}: {}", e.line_number, e.message)))?;
+
+    // objects own consecutive runs of the file's vertices
+    let mut offset = 0;
+    let meshes = obj_set
+        .objects
+        .iter()
+        .flat_map(|obj| {
+            let object_colors = colors
+                .get(offset..offset + obj.vertices.len())
+                .unwrap_or_default();
+            offset += obj.vertices.len();
+            let object_colors: Vec<Color> = object_colors.iter().flatten().copied().collect();
+            let colors = if object_colors.len() == obj.vertices.len() {
+                &object_colors[..]
+            } else {
+                &[]
+            };
+            let smoothing = smoothing_normals(obj);
+            group_sets(obj)
+                .into_iter()
+                .map(|groups| {
+                    let mesh = convert(obj, colors, |shape| shape.groups == groups, &smoothing);
+                    Mesh { groups, ..mesh }
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    Ok(meshes)
+}
+
+/// The distinct group sets of the object's shapes in file order.
+fn group_sets(obj: &Object) -> Vec<Vec<String>> {
+    let mut sets: Vec<Vec<String>> = Vec::new();
+    for shape in obj.geometry.iter().flat_map(|geometry| &geometry.shapes) {
+        if !sets.contains(&shape.groups) {
+            sets.push(shape.groups.clone());
+        }
+    }
+    sets
+}
+
+/// Area-weighted normals of every position within each smoothing group it is used in, if
+/// the object has smoothing groups but not normals of its own.
+fn smoothing_normals(obj: &Object) -> Option<HashMap<(usize, u32), Vec3f>> {
+    let triangles = || {
+        obj.geometry
+            .iter()
+            .flat_map(|geometry| &geometry.shapes)
+            .filter_map(|shape| match shape.primitive {
+                Primitive::Triangle(a, b, c) => Some((shape, [a, b, c])),
+                _ => None,
+            })
+    };
+    let has_normals = triangles().all(|(_, keys)| keys.iter().all(|key| key.2.is_some()));
+    let has_smoothing = triangles().any(|(shape, _)| smoothing_group(shape).is_some());
+    if has_normals || !has_smoothing {
+        return None;
+    }
+    let mut normals = HashMap::new();
+    for (shape, keys) in triangles() {
+        if let Some(group) = smoothing_group(shape) {
+            // the cross product's length weights the faces by area
+            let normal = face_normal(obj, keys);
+            for (idx, _, _) in keys {
+                let sum = normals
+                    .entry((idx, group))
+                    .or_insert(Vec3f::new(0., 0., 0.));
+                *sum = *sum + normal;
+            }
+        }
+    }
+    Some(normals)
+}
+
+fn smoothing_group(shape: &Shape) -> Option<u32> {
+    shape
+        .smoothing_groups
+        .iter()
+        .copied()
+        .find(|&group| group != 0)
+}
+
+/// Unnormalized normal of a triangle, as long as twice its area.
+fn face_normal(obj: &Object, keys: [VTNIndex; 3]) -> Vec3f {
+    let [a, b, c] = keys.map(|(idx, _, _)| {
+        let v = &obj.vertices[idx];
+        Vec3f::new(v.x, v.y, v.z)
+    });
+    math::cross(&(b - a), &(c - a))
+}
+
+/// Strips the colors off `v` lines that have them, as the OBJ parser only accepts
+/// positions, returning the color of every vertex in file order.
+fn split_vertex_colors(content: &str) -> (Cow<'_, str>, Vec<Option<Color>>) {
+    let mut colors = Vec::new();
+    let mut stripped = String::new();
+    for line in content.lines() {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.as_slice() {
+            ["v", x, y, z, rgb @ ..] if rgb.len() == 3 => match vertex_color(rgb) {
+                Some(color) => {
+                    stripped.push_str(&format!("v {} {} {}\n", x, y, z));
+                    colors.push(Some(color));
+                    continue;
+                }
+                None => colors.push(None),
+            },
+            ["v", ..] => colors.push(None),
+            _ => {}
+        }
+        stripped.push_str(line);
+        stripped.push('\n');
+    }
+    if colors.iter().any(Option::is_some) {
+        (Cow::Owned(stripped), colors)
+    } else {
+        (Cow::Borrowed(content), colors)
+    }
+}
+
+fn vertex_color(rgb: &[&str]) -> Option<Color> {
+    let to_u8 = |c: &str| {
+        c.parse::<f64>()
+            .ok()
+            .map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)
+    };
+    Some(Color(to_u8(rgb[0])?, to_u8(rgb[1])?, to_u8(rgb[2])?))
+}
+
+/// Converts every object of the set into its own [`Mesh`].
+pub fn meshes(obj_set: &ObjSet) -> Vec<Mesh> {
+    obj_set.objects.iter().map(Mesh::from).collect()
+}
+
+impl From<&Object> for Mesh {
+    /// Converts the whole object into one mesh, whatever its groups.
+    fn from(obj: &Object) -> Self {
+        let mut groups = group_sets(obj).concat();
+        groups.dedup();
+        let mesh = convert(obj, &[], |_| true, &smoothing_normals(obj));
+        Mesh { groups, ..mesh }
+    }
+}
+
+/// Which faces the generated normal of a vertex is averaged over.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Shading {
+    /// The normals come from the file, or there are none.
+    Given,
+    Smooth(u32),
+    /// Flat face, by index among the converted triangles.
+    Flat(usize),
+}
+
+/// OBJ indexes positions, texture coordinates and normals separately, so every distinct
+/// combination used by a triangle becomes one mesh vertex, split further by smoothing
+/// group when normals are generated. Only the shapes accepted by `filter` are converted.
+/// `colors` is either empty or holds one color per OBJ vertex.
+fn convert<F: Fn(&Shape) -> bool>(
+    obj: &Object,
+    colors: &[Color],
+    filter: F,
+    smoothing: &Option<HashMap<(usize, u32), Vec3f>>,
+) -> Mesh {
+    let mut vertex_map: HashMap<(VTNIndex, Shading), usize> = HashMap::new();
+    let mut keys = Vec::new();
+    let mut vertex = |key: VTNIndex, shading: Shading| {
+        *vertex_map.entry((key, shading)).or_insert_with(|| {
+            keys.push((key, shading));
+            keys.len() - 1
+        })
+    };
+    let mut indices = Vec::new();
+    let mut lines = Vec::new();
+    let mut points = Vec::new();
+    let mut flat_normals = Vec::new();
+
+    for shape in obj.geometry.iter().flat_map(|geometry| &geometry.shapes) {
+        if !filter(shape) {
+            continue;
+        }
+        match shape.primitive {
+            Primitive::Triangle(a, b, c) => {
+                let shading = match (smoothing, smoothing_group(shape)) {
+                    (None, _) => Shading::Given,
+                    (Some(_), Some(group)) => Shading::Smooth(group),
+                    (Some(_), None) => {
+                        flat_normals.push(face_normal(obj, [a, b, c]).normalized());
+                        Shading::Flat(flat_normals.len() - 1)
+                    }
+                };
+                indices.push([vertex(a, shading), vertex(b, shading), vertex(c, shading)]);
+            }
+            Primitive::Line(a, b) => {
+                lines.push([vertex(a, Shading::Given), vertex(b, Shading::Given)])
+            }
+            Primitive::Point(a) => points.push(vertex(a, Shading::Given)),
+        }
+    }
+
+    let positions = keys
+        .iter()
+        .map(|&((idx, _, _), _)| {
+            let v = &obj.vertices[idx];
+            Vec3f::new(v.x, v.y, v.z)
+        })
+        .collect();
+    // attributes are only kept when every triangle vertex has them,
+    // vertices used only by lines and points get zeroes
+    let triangle_keys = || indices.iter().flatten().map(|&i| keys[i].0);
+    let uvs = if triangle_keys().all(|(_, t, _)| t.is_some()) {
+        keys.iter()
+            .map(|&((_, t, _), _)| {
+                t.map_or([0., 0.], |t| [obj.tex_vertices[t].u, obj.tex_vertices[t].v])
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let zero = Vec3f::new(0., 0., 0.);
+    let normals = if let Some(smoothing) = smoothing {
+        keys.iter()
+            .map(|&((idx, _, _), shading)| match shading {
+                Shading::Given => zero,
+                Shading::Smooth(group) => smoothing[&(idx, group)].normalized(),
+                Shading::Flat(face) => flat_normals[face],
+            })
+            .collect()
+    } else if triangle_keys().all(|(_, _, n)| n.is_some()) {
+        keys.iter()
+            .map(|&((_, _, n), _)| {
+                n.map_or(zero, |n| {
+                    Vec3f::new(obj.normals[n].x, obj.normals[n].y, obj.normals[n].z)
+                })
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    let colors = if colors.is_empty() {
+        Vec::new()
+    } else {
+        keys.iter().map(|&((idx, _, _), _)| colors[idx]).collect()
+    };
+
+    Mesh {
+        name: Some(obj.name.clone()),
+        positions,
+        normals,
+        uvs,
+        colors,
+        indices,
+        lines,
+        points,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_from_object() {
+    let meshes = parse(
+        "o quad
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+f 1/1 2/2 3/3
+f 1/1 3/3 4/4
+f 1/4 3/3 4/4
+",
+    )
+    .unwrap();
+    assert_eq!(meshes.len(), 1);
+    let mesh = &meshes[0];
+    assert_eq!(mesh.name.as_deref(), Some("quad"));
+    // vertex 1 is used with two different texture coordinates
+    assert_eq!(mesh.positions.len(), 5);
+    assert_eq!(mesh.uvs.len(), 5);
+    assert!(!mesh.has_normals());
+    assert_eq!(mesh.indices.len(), 3);
+    let origin_uvs: Vec<[f64; 2]> = (0..mesh.positions.len())
+        .filter(|&i| mesh.positions[i] == Vec3f::new(0., 0., 0.))
+        .map(|i| mesh.uvs[i])
+        .collect();
+    assert_eq!(origin_uvs.len(), 2);
+    assert!(origin_uvs.contains(&[0., 0.]) && origin_uvs.contains(&[0., 1.]));
+}
+
+#[test]
+fn test_polygons_are_triangulated() {
+    let meshes = parse(
+        "v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+v -1 0.5 0
+f 1 2 3 4
+f 1 2 3 4 5
+",
+    )
+    .unwrap();
+    // wavefront_obj fans quads and n-gons before they reach the mesh conversion
+    assert_eq!(meshes[0].indices.len(), 2 + 3);
+}
+
+#[test]
+fn test_lines_and_points() {
+    let meshes = parse(
+        "v 0 0 0
+v 1 0 0
+v 1 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+f 1/1 2/2 3/3
+l 1 3
+f 2
+",
+    )
+    .unwrap();
+    let mesh = &meshes[0];
+    assert_eq!(mesh.indices.len(), 1);
+    assert_eq!(mesh.lines.len(), 1);
+    assert_eq!(mesh.points.len(), 1);
+    // the untextured line and point vertices do not drop the triangle UVs
+    assert_eq!(mesh.uvs.len(), mesh.positions.len());
+}
+
+#[test]
+fn test_vertex_colors() {
+    let meshes = parse(
+        "v 0 0 0 1 0 0
+v 1 0 0 0 1 0
+v 1 1 0 0 0 1
+f 1 2 3
+o uncolored
+v 0 0 0
+v 1 0 0
+v 1 1 0
+f 4 5 6
+",
+    )
+    .unwrap();
+    let mut colors = meshes[0].colors.clone();
+    colors.sort_by_key(|c| (c.0, c.1, c.2));
+    assert_eq!(
+        colors,
+        vec![Color(0, 0, 255), Color(0, 255, 0), Color(255, 0, 0)]
+    );
+    assert!(!meshes[1].has_colors());
+}
+
+#[test]
+fn test_groups() {
+    let meshes = parse(
+        "o figure
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+g body
+f 1 2 3
+g eyes
+f 1 3 4
+g body
+f 2 3 4
+",
+    )
+    .unwrap();
+    assert_eq!(meshes.len(), 2);
+    assert_eq!(meshes[0].groups, ["body"]);
+    assert_eq!(meshes[0].indices.len(), 2);
+    assert_eq!(meshes[1].groups, ["eyes"]);
+    assert_eq!(meshes[1].name.as_deref(), Some("figure"));
+}
+
+#[test]
+fn test_smoothing_groups() {
+    // a tent of two faces meeting at the ridge from 1 to 2, then the same with the
+    // faces in different smoothing groups
+    let tent = |second_group: u32| {
+        let content = format!(
+            "v 0 1 0
+v 0 1 1
+v -1 0 0
+v 1 0 0
+s 1
+f 1 3 2
+s {}
+f 1 2 4
+",
+            second_group
+        );
+        parse(content).unwrap().remove(0)
+    };
+    let smooth = tent(1);
+    assert_eq!(smooth.positions.len(), 4);
+    let ridge = smooth
+        .positions
+        .iter()
+        .position(|p| p.x == 0.0 && p.y == 1.0)
+        .unwrap();
+    assert!((smooth.normals[ridge] - Vec3f::new(0., 1., 0.)).length() < 1e-9);
+
+    let hard = tent(2);
+    assert_eq!(hard.positions.len(), 6);
+    assert!(hard.normals.iter().all(|n| n.y.abs() < 0.8));
+
+    let flat = tent(0);
+    assert_eq!(flat.positions.len(), 6);
+    assert_eq!(flat.normals.len(), 6);
+}
@@ -0,0 +1,505 @@
+use std::io::Write;
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+use crate::color::Color;
+use crate::error::RusterizerError;
+use crate::geometry;
+use crate::loader::parse_error;
+use crate::math::Vec3f;
+use crate::mesh::Mesh;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ScalarType {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl ScalarType {
+    fn parse(name: &str) -> Result<Self, RusterizerError> {
+        Ok(match name {
+            "char" | "int8" => ScalarType::I8,
+            "uchar" | "uint8" => ScalarType::U8,
+            "short" | "int16" => ScalarType::I16,
+            "ushort" | "uint16" => ScalarType::U16,
+            "int" | "int32" => ScalarType::I32,
+            "uint" | "uint32" => ScalarType::U32,
+            "float" | "float32" => ScalarType::F32,
+            "double" | "float64" => ScalarType::F64,
+            _ => return parse_error(format!("unknown PLY type '{}'", name)),
+        })
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            ScalarType::I8 | ScalarType::U8 => 1,
+            ScalarType::I16 | ScalarType::U16 => 2,
+            ScalarType::I32 | ScalarType::U32 | ScalarType::F32 => 4,
+            ScalarType::F64 => 8,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Property {
+    Scalar(String, ScalarType),
+    List(String, ScalarType, ScalarType),
+}
+
+#[derive(Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+/// Reads scalar values from the body of a PLY file in any of its three encodings.
+struct ValueReader<'a> {
+    format: Format,
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ValueReader<'a> {
+    fn read(&mut self, ty: ScalarType) -> Result<f64, RusterizerError> {
+        if self.format == Format::Ascii {
+            return self.read_ascii();
+        }
+        let size = ty.size();
+        let Some(bytes) = self.data.get(self.pos..self.pos + size) else {
+            return parse_error("unexpected end of PLY data");
+        };
+        self.pos += size;
+        let mut buf = [0u8; 8];
+        buf[..size].copy_from_slice(bytes);
+        if self.format == Format::BinaryBigEndian {
+            buf[..size].reverse();
+        }
+        Ok(match ty {
+            ScalarType::I8 => buf[0] as i8 as f64,
+            ScalarType::U8 => buf[0] as f64,
+            ScalarType::I16 => i16::from_le_bytes([buf[0], buf[1]]) as f64,
+            ScalarType::U16 => u16::from_le_bytes([buf[0], buf[1]]) as f64,
+            ScalarType::I32 => i32::from_le_bytes(buf[..4].try_into().unwrap()) as f64,
+            ScalarType::U32 => u32::from_le_bytes(buf[..4].try_into().unwrap()) as f64,
+            ScalarType::F32 => f32::from_le_bytes(buf[..4].try_into().unwrap()) as f64,
+            ScalarType::F64 => f64::from_le_bytes(buf),
+        })
+    }
+
+    fn read_ascii(&mut self) -> Result<f64, RusterizerError> {
+        while self.pos < self.data.len() && self.data[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+        let start = self.pos;
+        while self.pos < self.data.len() && !self.data[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.data[start..self.pos])
+            .ok()
+            .and_then(|token| token.parse().ok())
+            .map_or_else(|| parse_error("expected a number in PLY data"), Ok)
+    }
+}
+
+#[cfg(feature = "fs")]
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Mesh, RusterizerError> {
+    let path = path.as_ref();
+    parse(&super::read(path)?).map_err(|e| super::in_file(path, e))
+}
+
+/// Parses ASCII or binary PLY, reading positions, normals and texture coordinates
+/// from the `vertex` element and triangulating the polygons of the `face` element.
+pub fn parse(data: &[u8]) -> Result<Mesh, RusterizerError> {
+    let (format, elements, body_start) = parse_header(data)?;
+    let mut reader = ValueReader {
+        format,
+        data,
+        pos: body_start,
+    };
+
+    let mut mesh = Mesh::default();
+    for element in &elements {
+        match element.name.as_str() {
+            "vertex" => read_vertices(&mut reader, element, &mut mesh)?,
+            "face" => read_faces(&mut reader, element, &mut mesh)?,
+            _ => skip_element(&mut reader, element)?,
+        }
+    }
+
+    if let Some(&index) = mesh
+        .indices
+        .iter()
+        .flatten()
+        .find(|&&i| i >= mesh.positions.len())
+    {
+        return parse_error(format!("face references missing vertex {}", index));
+    }
+    Ok(mesh)
+}
+
+/// Writes `mesh` as binary little-endian PLY with its positions, the normals, UVs and
+/// colors it has and its faces, which is smaller and faster to load than OBJ. Lines and
+/// points are left out.
+pub fn write<W: Write>(mesh: &Mesh, writer: &mut W) -> std::io::Result<()> {
+    let mut header = String::from("ply\nformat binary_little_endian 1.0\n");
+    header += "comment written by rusterizer\n";
+    header += &format!("element vertex {}\n", mesh.positions.len());
+    header += "property float x\nproperty float y\nproperty float z\n";
+    if mesh.has_normals() {
+        header += "property float nx\nproperty float ny\nproperty float nz\n";
+    }
+    if mesh.has_uvs() {
+        header += "property float u\nproperty float v\n";
+    }
+    if mesh.has_colors() {
+        header += "property uchar red\nproperty uchar green\nproperty uchar blue\n";
+    }
+    header += &format!("element face {}\n", mesh.indices.len());
+    header += "property list uchar uint vertex_indices\nend_header\n";
+
+    let mut data = header.into_bytes();
+    let push = |data: &mut Vec<u8>, values: &[f64]| {
+        for value in values {
+            data.extend_from_slice(&(*value as f32).to_le_bytes());
+        }
+    };
+    for (v, p) in mesh.positions.iter().enumerate() {
+        push(&mut data, &[p.x, p.y, p.z]);
+        if let Some(n) = mesh.normals.get(v) {
+            push(&mut data, &[n.x, n.y, n.z]);
+        }
+        if let Some(uv) = mesh.uvs.get(v) {
+            push(&mut data, uv);
+        }
+        if let Some(color) = mesh.colors.get(v) {
+            data.extend_from_slice(&[color.0, color.1, color.2]);
+        }
+    }
+    for face in &mesh.indices {
+        data.push(3);
+        for &v in face {
+            data.extend_from_slice(&(v as u32).to_le_bytes());
+        }
+    }
+    writer.write_all(&data)
+}
+
+#[cfg(feature = "fs")]
+pub fn save<P: AsRef<Path>>(mesh: &Mesh, path: P) -> Result<(), RusterizerError> {
+    let path = path.as_ref();
+    let mut data = Vec::new();
+    write(mesh, &mut data)?;
+    std::fs::write(path, data).map_err(|e| RusterizerError::in_file(path, e))
+}
+
+fn parse_header(data: &[u8]) -> Result<(Format, Vec<Element>, usize), RusterizerError> {
+    const END_HEADER: &[u8] = b"end_header";
+    let Some(end) = data.windows(END_HEADER.len()).position(|w| w == END_HEADER) else {
+        return parse_error("missing PLY end_header");
+    };
+    let body_start = match data[end..].iter().position(|&b| b == b'\n') {
+        Some(newline) => end + newline + 1,
+        None => data.len(),
+    };
+    let header =
+        std::str::from_utf8(&data[..end]).or_else(|_| parse_error("invalid PLY header"))?;
+
+    let mut lines = header.lines();
+    if lines.next().map(str::trim) != Some("ply") {
+        return parse_error("not a PLY file");
+    }
+
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    for line in lines {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.as_slice() {
+            ["format", name, _version] => {
+                format = Some(match *name {
+                    "ascii" => Format::Ascii,
+                    "binary_little_endian" => Format::BinaryLittleEndian,
+                    "binary_big_endian" => Format::BinaryBigEndian,
+                    _ => return parse_error(format!("unknown PLY format '{}'", name)),
+                });
+            }
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count
+                    .parse()
+                    .or_else(|_| parse_error("invalid PLY element count"))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count_ty, item_ty, name] => {
+                let Some(element) = elements.last_mut() else {
+                    return parse_error("PLY property outside of an element");
+                };
+                element.properties.push(Property::List(
+                    name.to_string(),
+                    ScalarType::parse(count_ty)?,
+                    ScalarType::parse(item_ty)?,
+                ));
+            }
+            ["property", ty, name] => {
+                let Some(element) = elements.last_mut() else {
+                    return parse_error("PLY property outside of an element");
+                };
+                element
+                    .properties
+                    .push(Property::Scalar(name.to_string(), ScalarType::parse(ty)?));
+            }
+            _ => {}
+        }
+    }
+
+    match format {
+        Some(format) => Ok((format, elements, body_start)),
+        None => parse_error("missing PLY format line"),
+    }
+}
+
+fn read_vertices(
+    reader: &mut ValueReader,
+    element: &Element,
+    mesh: &mut Mesh,
+) -> Result<(), RusterizerError> {
+    let names: Vec<&str> = element
+        .properties
+        .iter()
+        .map(|p| match p {
+            Property::Scalar(name, _) | Property::List(name, _, _) => name.as_str(),
+        })
+        .collect();
+    let find = |candidates: &[&str]| names.iter().position(|n| candidates.contains(n));
+    let (x, y, z) = (find(&["x"]), find(&["y"]), find(&["z"]));
+    let (nx, ny, nz) = (find(&["nx"]), find(&["ny"]), find(&["nz"]));
+    let u = find(&["u", "s", "texture_u", "texture_s"]);
+    let v = find(&["v", "t", "texture_v", "texture_t"]);
+    let red = find(&["red", "r", "diffuse_red"]);
+    let green = find(&["green", "g", "diffuse_green"]);
+    let blue = find(&["blue", "b", "diffuse_blue"]);
+    let (Some(x), Some(y), Some(z)) = (x, y, z) else {
+        return parse_error("PLY vertex element lacks x, y or z");
+    };
+    // integer channels are 0-255, floating point ones 0-1
+    let color_scale = match red.map(|i| &element.properties[i]) {
+        Some(Property::Scalar(_, ScalarType::F32 | ScalarType::F64)) => 255.0,
+        _ => 1.0,
+    };
+
+    let mut values = vec![0.0; names.len()];
+    for _ in 0..element.count {
+        for (value, property) in values.iter_mut().zip(&element.properties) {
+            match property {
+                Property::Scalar(_, ty) => *value = reader.read(*ty)?,
+                Property::List(_, count_ty, item_ty) => skip_list(reader, *count_ty, *item_ty)?,
+            }
+        }
+        mesh.positions
+            .push(Vec3f::new(values[x], values[y], values[z]));
+        if let (Some(nx), Some(ny), Some(nz)) = (nx, ny, nz) {
+            mesh.normals
+                .push(Vec3f::new(values[nx], values[ny], values[nz]));
+        }
+        if let (Some(u), Some(v)) = (u, v) {
+            mesh.uvs.push([values[u], values[v]]);
+        }
+        if let (Some(r), Some(g), Some(b)) = (red, green, blue) {
+            let to_u8 = |c: f64| (c * color_scale).round().clamp(0.0, 255.0) as u8;
+            mesh.colors
+                .push(Color(to_u8(values[r]), to_u8(values[g]), to_u8(values[b])));
+        }
+    }
+    Ok(())
+}
+
+fn read_faces(
+    reader: &mut ValueReader,
+    element: &Element,
+    mesh: &mut Mesh,
+) -> Result<(), RusterizerError> {
+    let mut polygon = Vec::new();
+    for _ in 0..element.count {
+        for property in &element.properties {
+            match property {
+                Property::List(name, count_ty, item_ty)
+                    if name == "vertex_indices" || name == "vertex_index" =>
+                {
+                    polygon.clear();
+                    let count = reader.read(*count_ty)? as usize;
+                    for _ in 0..count {
+                        polygon.push(reader.read(*item_ty)? as usize);
+                    }
+                    if polygon.iter().all(|&i| i < mesh.positions.len()) {
+                        let triangles = geometry::triangulate_polygon(&mesh.positions, &polygon);
+                        mesh.indices.extend(triangles);
+                    } else {
+                        // reported as a missing vertex once all faces are read
+                        mesh.indices.push([polygon[0], polygon[1], polygon[2]]);
+                    }
+                }
+                Property::List(_, count_ty, item_ty) => skip_list(reader, *count_ty, *item_ty)?,
+                Property::Scalar(_, ty) => {
+                    reader.read(*ty)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn skip_list(
+    reader: &mut ValueReader,
+    count_ty: ScalarType,
+    item_ty: ScalarType,
+) -> Result<(), RusterizerError> {
+    let count = reader.read(count_ty)? as usize;
+    for _ in 0..count {
+        reader.read(item_ty)?;
+    }
+    Ok(())
+}
+
+fn skip_element(reader: &mut ValueReader, element: &Element) -> Result<(), RusterizerError> {
+    for _ in 0..element.count {
+        for property in &element.properties {
+            match property {
+                Property::Scalar(_, ty) => {
+                    reader.read(*ty)?;
+                }
+                Property::List(_, count_ty, item_ty) => skip_list(reader, *count_ty, *item_ty)?,
+            }
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_parse_ascii() {
+    let data = b"ply
+format ascii 1.0
+comment a unit quad
+element vertex 4
+property float x
+property float y
+property float z
+property float nx
+property float ny
+property float nz
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0 0 0 1
+1 0 0 0 0 1
+1 1 0 0 0 1
+0 1 0 0 0 1
+4 0 1 2 3
+";
+    let mesh = parse(data).unwrap();
+    assert_eq!(mesh.positions.len(), 4);
+    assert_eq!(mesh.normals.len(), 4);
+    assert!(!mesh.has_uvs());
+    assert_eq!(mesh.indices, vec![[0, 1, 2], [0, 2, 3]]);
+    assert_eq!(mesh.positions[2], Vec3f::new(1., 1., 0.));
+}
+
+#[test]
+fn test_parse_binary() {
+    let mut data = b"ply
+format binary_big_endian 1.0
+element vertex 3
+property float x
+property float y
+property float z
+property uchar red
+element face 1
+property list uchar int vertex_indices
+end_header
+"
+    .to_vec();
+    for (i, value) in [0f32, 0., 0., 1., 0., 0., 0., 1., 0.].iter().enumerate() {
+        data.extend_from_slice(&value.to_be_bytes());
+        if i % 3 == 2 {
+            data.push(255);
+        }
+    }
+    data.push(3);
+    for index in [0i32, 1, 2] {
+        data.extend_from_slice(&index.to_be_bytes());
+    }
+    let mesh = parse(&data).unwrap();
+    assert_eq!(mesh.positions[1], Vec3f::new(1., 0., 0.));
+    // red alone does not make a vertex color
+    assert!(!mesh.has_colors());
+    assert_eq!(mesh.indices, vec![[0, 1, 2]]);
+}
+
+#[test]
+fn test_missing_vertex() {
+    let data = b"ply
+format ascii 1.0
+element vertex 1
+property float x
+property float y
+property float z
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0
+3 0 1 2
+";
+    assert!(parse(data).is_err());
+}
+
+#[test]
+fn test_vertex_colors() {
+    let data = b"ply
+format ascii 1.0
+element vertex 2
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+end_header
+0 0 0 255 128 0
+1 0 0 0 0 255
+";
+    let mesh = parse(data).unwrap();
+    assert_eq!(mesh.colors, vec![Color(255, 128, 0), Color(0, 0, 255)]);
+}
+
+#[test]
+fn test_write() {
+    let mut mesh = crate::geometry::cuboid(Vec3f::new(1., 2., 3.));
+    mesh.colors = vec![Color(10, 20, 30); mesh.positions.len()];
+    let mut data = Vec::new();
+    write(&mesh, &mut data).unwrap();
+    let read = parse(&data).unwrap();
+    assert_eq!(read.indices, mesh.indices);
+    assert_eq!(read.positions, mesh.positions);
+    assert_eq!(read.normals, mesh.normals);
+    assert_eq!(read.uvs, mesh.uvs);
+    assert_eq!(read.colors, mesh.colors);
+
+    mesh.normals.clear();
+    mesh.uvs.clear();
+    data.clear();
+    write(&mesh, &mut data).unwrap();
+    let read = parse(&data).unwrap();
+    assert!(!read.has_normals() && !read.has_uvs() && read.has_colors());
+}
@@ -0,0 +1,152 @@
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+use crate::error::RusterizerError;
+use crate::loader::parse_error;
+use crate::math::Vec3f;
+use crate::mesh::Mesh;
+
+const HEADER_SIZE: usize = 80;
+const TRIANGLE_SIZE: usize = 50;
+
+#[cfg(feature = "fs")]
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Mesh, RusterizerError> {
+    let path = path.as_ref();
+    parse(&super::read(path)?).map_err(|e| super::in_file(path, e))
+}
+
+/// Parses binary or ASCII STL. Every facet gets its own three vertices, with the
+/// facet normal (when present) copied to each of them.
+pub fn parse(data: &[u8]) -> Result<Mesh, RusterizerError> {
+    if is_binary(data) {
+        parse_binary(data)
+    } else {
+        parse_ascii(data)
+    }
+}
+
+fn is_binary(data: &[u8]) -> bool {
+    if data.len() < HEADER_SIZE + 4 {
+        return false;
+    }
+    // ASCII files start with "solid", but so do some binary headers, so trust the size
+    let count = u32::from_le_bytes(data[HEADER_SIZE..HEADER_SIZE + 4].try_into().unwrap());
+    // the count is untrusted and may overflow a 32-bit usize
+    (count as usize)
+        .checked_mul(TRIANGLE_SIZE)
+        .and_then(|n| n.checked_add(HEADER_SIZE + 4))
+        == Some(data.len())
+}
+
+fn parse_binary(data: &[u8]) -> Result<Mesh, RusterizerError> {
+    let read_vec = |bytes: &[u8]| {
+        let f = |i: usize| f32::from_le_bytes(bytes[i * 4..i * 4 + 4].try_into().unwrap()) as f64;
+        Vec3f::new(f(0), f(1), f(2))
+    };
+
+    let mut facets = Vec::new();
+    for chunk in data[HEADER_SIZE + 4..].chunks_exact(TRIANGLE_SIZE) {
+        let normal = read_vec(&chunk[0..12]);
+        let a = read_vec(&chunk[12..24]);
+        let b = read_vec(&chunk[24..36]);
+        let c = read_vec(&chunk[36..48]);
+        facets.push((normal, [a, b, c]));
+    }
+    Ok(build_mesh(facets))
+}
+
+fn parse_ascii(data: &[u8]) -> Result<Mesh, RusterizerError> {
+    let text = std::str::from_utf8(data)
+        .or_else(|_| parse_error("STL is neither valid binary nor ASCII"))?;
+    let mut tokens = text.split_whitespace();
+    if tokens.next() != Some("solid") {
+        return parse_error("ASCII STL must start with 'solid'");
+    }
+
+    let mut facets = Vec::new();
+    let mut normal = Vec3f::new(0., 0., 0.);
+    let mut vertices = Vec::new();
+    while let Some(token) = tokens.next() {
+        match token {
+            "normal" => normal = next_vec(&mut tokens)?,
+            "vertex" => vertices.push(next_vec(&mut tokens)?),
+            "endfacet" => {
+                if vertices.len() != 3 {
+                    return parse_error(format!(
+                        "facet has {} vertices, expected 3",
+                        vertices.len()
+                    ));
+                }
+                facets.push((normal, [vertices[0], vertices[1], vertices[2]]));
+                vertices.clear();
+            }
+            _ => {}
+        }
+    }
+    Ok(build_mesh(facets))
+}
+
+fn next_vec(tokens: &mut std::str::SplitWhitespace) -> Result<Vec3f, RusterizerError> {
+    let mut component = || -> Result<f64, RusterizerError> {
+        tokens
+            .next()
+            .and_then(|t| t.parse().ok())
+            .map_or_else(|| parse_error("expected a number"), Ok)
+    };
+    Ok(Vec3f::new(component()?, component()?, component()?))
+}
+
+fn build_mesh(facets: Vec<(Vec3f, [Vec3f; 3])>) -> Mesh {
+    let has_normals = facets.iter().any(|(n, _)| n.length_squared() > 0.0);
+    let mut mesh = Mesh::default();
+    for (i, (normal, vertices)) in facets.into_iter().enumerate() {
+        mesh.positions.extend_from_slice(&vertices);
+        if has_normals {
+            let normal = if normal.length_squared() > 0.0 {
+                normal.normalized()
+            } else {
+                normal
+            };
+            mesh.normals.extend_from_slice(&[normal; 3]);
+        }
+        mesh.indices.push([3 * i, 3 * i + 1, 3 * i + 2]);
+    }
+    mesh
+}
+
+#[test]
+fn test_parse_ascii() {
+    let data = b"solid test
+facet normal 0 0 1
+  outer loop
+    vertex 0 0 0
+    vertex 1 0 0
+    vertex 0 1 0
+  endloop
+endfacet
+endsolid test";
+    let mesh = parse(data).unwrap();
+    assert_eq!(mesh.positions.len(), 3);
+    assert_eq!(mesh.indices, vec![[0, 1, 2]]);
+    assert_eq!(mesh.normals[0], Vec3f::new(0., 0., 1.));
+    assert_eq!(mesh.positions[1], Vec3f::new(1., 0., 0.));
+}
+
+#[test]
+fn test_parse_binary() {
+    let mut data = vec![0u8; HEADER_SIZE];
+    data.extend_from_slice(&1u32.to_le_bytes());
+    for value in [0f32, 0., 1., 0., 0., 0., 1., 0., 0., 0., 1., 0.] {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+    data.extend_from_slice(&[0, 0]);
+    let mesh = parse(&data).unwrap();
+    assert_eq!(mesh.indices.len(), 1);
+    assert_eq!(mesh.positions[2], Vec3f::new(0., 1., 0.));
+    assert_eq!(mesh.normals[0], Vec3f::new(0., 0., 1.));
+
+    // a header claiming more triangles than the file holds is not taken as binary
+    let mut forged = vec![0u8; HEADER_SIZE];
+    forged.extend_from_slice(&u32::MAX.to_le_bytes());
+    assert!(!is_binary(&forged));
+}
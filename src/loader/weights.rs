@@ -0,0 +1,40 @@
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+use crate::error::RusterizerError;
+use crate::loader::parse_error;
+
+#[cfg(feature = "fs")]
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Vec<Vec<f64>>, RusterizerError> {
+    let path = path.as_ref();
+    parse(&super::read_to_string(path)?).map_err(|e| super::in_file(path, e))
+}
+
+/// Parses morph target weights for a sequence of frames: one line per frame holding a
+/// weight per target, separated by whitespace or commas. Blank lines and everything
+/// after a `#` are ignored.
+pub fn parse(text: &str) -> Result<Vec<Vec<f64>>, RusterizerError> {
+    let mut frames = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default();
+        let weights = line
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|value| !value.is_empty())
+            .map(|value| value.parse::<f64>())
+            .collect::<Result<Vec<_>, _>>();
+        match weights {
+            Ok(weights) if weights.is_empty() => {}
+            Ok(weights) => frames.push(weights),
+            Err(e) => return parse_error(format!("line {}: {}", number + 1, e)),
+        }
+    }
+    Ok(frames)
+}
+
+#[test]
+fn test_parse_weights() {
+    let text = "# smile, blink\n0 0\n0.5, 1\n\n1 0 # last\n";
+    let frames = parse(text).unwrap();
+    assert_eq!(frames, vec![vec![0., 0.], vec![0.5, 1.], vec![1., 0.]]);
+    assert!(parse("0 x\n").is_err());
+}
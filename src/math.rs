@@ -1,10 +1,10 @@
-use std::ops::{Add, Div, Mul, Sub};
+use core::ops::{Add, Div, Mul, Sub};
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Vec3<T> {
-    x: T,
-    y: T,
-    z: T,
+    pub x: T,
+    pub y: T,
+    pub z: T,
 }
 
 impl<T> Vec3<T> {
@@ -21,10 +21,12 @@ where
         (self.x * self.x + self.y * self.y + self.z * self.z).into()
     }
 
+    #[cfg(feature = "std")]
     pub fn length(&self) -> f64 {
         self.length_squared().sqrt()
     }
 
+    #[cfg(feature = "std")]
     pub fn normalized(&self) -> Vec3<f64> {
         let length = self.length();
         Vec3 {
@@ -90,12 +92,196 @@ where
 
 pub type Vec3f = Vec3<f64>;
 
+/// Row-major 4x4 matrix used for affine transformations of points and vectors.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Mat4 {
+    pub(crate) m: [[f64; 4]; 4],
+}
+
+impl Mat4 {
+    pub fn new(m: [[f64; 4]; 4]) -> Self {
+        Mat4 { m }
+    }
+
+    pub fn identity() -> Self {
+        Mat4::new([
+            [1., 0., 0., 0.],
+            [0., 1., 0., 0.],
+            [0., 0., 1., 0.],
+            [0., 0., 0., 1.],
+        ])
+    }
+
+    /// Rotation around the X axis by `angle` radians.
+    #[cfg(feature = "std")]
+    pub fn rotation_x(angle: f64) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Mat4::new([
+            [1., 0., 0., 0.],
+            [0., cos, -sin, 0.],
+            [0., sin, cos, 0.],
+            [0., 0., 0., 1.],
+        ])
+    }
+
+    /// Rotation around the Y axis by `angle` radians.
+    #[cfg(feature = "std")]
+    pub fn rotation_y(angle: f64) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Mat4::new([
+            [cos, 0., sin, 0.],
+            [0., 1., 0., 0.],
+            [-sin, 0., cos, 0.],
+            [0., 0., 0., 1.],
+        ])
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = self.m[j][i];
+            }
+        }
+        Mat4 { m }
+    }
+
+    /// Inverts the matrix with Gauss-Jordan elimination, returning `None` if it is singular.
+    pub fn inverse(&self) -> Option<Self> {
+        let mut a = self.m;
+        let mut inv = Mat4::identity().m;
+        for col in 0..4 {
+            let pivot = (col..4).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+            if a[pivot][col].abs() < 1e-12 {
+                return None;
+            }
+            a.swap(col, pivot);
+            inv.swap(col, pivot);
+            let factor = a[col][col];
+            for j in 0..4 {
+                a[col][j] /= factor;
+                inv[col][j] /= factor;
+            }
+            for row in 0..4 {
+                if row != col {
+                    let factor = a[row][col];
+                    for j in 0..4 {
+                        a[row][j] -= factor * a[col][j];
+                        inv[row][j] -= factor * inv[col][j];
+                    }
+                }
+            }
+        }
+        Some(Mat4 { m: inv })
+    }
+
+    pub fn transform_point(&self, p: &Vec3f) -> Vec3f {
+        let m = &self.m;
+        let x = m[0][0] * p.x + m[0][1] * p.y + m[0][2] * p.z + m[0][3];
+        let y = m[1][0] * p.x + m[1][1] * p.y + m[1][2] * p.z + m[1][3];
+        let z = m[2][0] * p.x + m[2][1] * p.y + m[2][2] * p.z + m[2][3];
+        let w = m[3][0] * p.x + m[3][1] * p.y + m[3][2] * p.z + m[3][3];
+        if w != 0.0 && w != 1.0 {
+            Vec3::new(x / w, y / w, z / w)
+        } else {
+            Vec3::new(x, y, z)
+        }
+    }
+
+    pub fn transform_vector(&self, v: &Vec3f) -> Vec3f {
+        let m = &self.m;
+        Vec3::new(
+            m[0][0] * v.x + m[0][1] * v.y + m[0][2] * v.z,
+            m[1][0] * v.x + m[1][1] * v.y + m[1][2] * v.z,
+            m[2][0] * v.x + m[2][1] * v.y + m[2][2] * v.z,
+        )
+    }
+}
+
+impl Mul for Mat4 {
+    type Output = Mat4;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.m[i][k] * rhs.m[k][j]).sum();
+            }
+        }
+        Mat4 { m }
+    }
+}
+
+/// Axis-aligned bounding box.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3f,
+    pub max: Vec3f,
+}
+
+impl Aabb {
+    /// Smallest box holding all `points`, or `None` if there are none.
+    pub fn from_points<'a, I: IntoIterator<Item = &'a Vec3f>>(points: I) -> Option<Self> {
+        let mut points = points.into_iter();
+        let first = *points.next()?;
+        Some(points.fold(
+            Aabb {
+                min: first,
+                max: first,
+            },
+            |aabb, p| Aabb {
+                min: Vec3::new(
+                    aabb.min.x.min(p.x),
+                    aabb.min.y.min(p.y),
+                    aabb.min.z.min(p.z),
+                ),
+                max: Vec3::new(
+                    aabb.max.x.max(p.x),
+                    aabb.max.y.max(p.y),
+                    aabb.max.z.max(p.z),
+                ),
+            },
+        ))
+    }
+
+    pub fn corners(&self) -> [Vec3f; 8] {
+        let (a, b) = (self.min, self.max);
+        [
+            Vec3::new(a.x, a.y, a.z),
+            Vec3::new(b.x, a.y, a.z),
+            Vec3::new(a.x, b.y, a.z),
+            Vec3::new(b.x, b.y, a.z),
+            Vec3::new(a.x, a.y, b.z),
+            Vec3::new(b.x, a.y, b.z),
+            Vec3::new(a.x, b.y, b.z),
+            Vec3::new(b.x, b.y, b.z),
+        ]
+    }
+
+    /// Box around the transformed corners, which holds everything this box held.
+    pub fn transformed(&self, transform: &Mat4) -> Self {
+        let corners = self.corners().map(|c| transform.transform_point(&c));
+        Aabb::from_points(&corners).unwrap()
+    }
+
+    pub fn overlaps(&self, other: &Aabb) -> bool {
+        self.min.x <= other.max.x
+            && other.min.x <= self.max.x
+            && self.min.y <= other.max.y
+            && other.min.y <= self.max.y
+            && self.min.z <= other.max.z
+            && other.min.z <= self.max.z
+    }
+}
+
+#[cfg(feature = "std")]
 #[test]
 fn test_length() {
     assert_eq!(Vec3::new(1, 0, 0).length_squared(), 1.0);
     assert_eq!(Vec3::new(1, 0, 0).length(), 1.0);
 }
 
+#[cfg(feature = "std")]
 #[test]
 fn test_normalized() {
     let v = Vec3::new(1, 1, 1);
@@ -131,3 +317,61 @@ fn test_cross() {
     assert_eq!(cross(&a, &b), Vec3::new(-4, 8, -4));
     assert_eq!(cross(&b, &a), Vec3::new(4, -8, 4));
 }
+
+#[cfg(feature = "std")]
+#[test]
+fn test_mat4_identity() {
+    let p = Vec3::new(1., 2., 3.);
+    assert_eq!(Mat4::identity().transform_point(&p), p);
+    assert_eq!(
+        Mat4::identity() * Mat4::rotation_y(0.5),
+        Mat4::rotation_y(0.5)
+    );
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_rotation_y() {
+    let rotation = Mat4::rotation_y(std::f64::consts::FRAC_PI_2);
+    let p = rotation.transform_point(&Vec3::new(1., 1., 0.));
+    assert!((p.x - 0.0).abs() < 1e-9);
+    assert!((p.y - 1.0).abs() < 1e-9);
+    assert!((p.z + 1.0).abs() < 1e-9);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_mat4_inverse() {
+    let m = Mat4::new([
+        [2., 0., 0., 1.],
+        [0., 0., 3., 2.],
+        [0., 1., 0., 3.],
+        [0., 0., 0., 1.],
+    ]);
+    let inverse = m.inverse().unwrap();
+    let p = Vec3::new(1., 2., 3.);
+    let round_trip = inverse.transform_point(&m.transform_point(&p));
+    assert!((round_trip - p).length() < 1e-9);
+    assert_eq!(m.transpose().transpose(), m);
+
+    let singular = Mat4::new([[0.; 4]; 4]);
+    assert!(singular.inverse().is_none());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_aabb() {
+    let points = [Vec3f::new(1., 0., 0.), Vec3f::new(-1., 2., 0.5)];
+    let aabb = Aabb::from_points(&points).unwrap();
+    assert_eq!(aabb.min, Vec3f::new(-1., 0., 0.));
+    assert_eq!(aabb.max, Vec3f::new(1., 2., 0.5));
+    assert!(Aabb::from_points(&[]).is_none());
+
+    let rotated = aabb.transformed(&Mat4::rotation_y(std::f64::consts::FRAC_PI_2));
+    assert!((rotated.min.z - -1.0).abs() < 1e-9 && (rotated.max.x - 0.5).abs() < 1e-9);
+    let far = Aabb {
+        min: Vec3f::new(5., 5., 5.),
+        max: Vec3f::new(6., 6., 6.),
+    };
+    assert!(aabb.overlaps(&aabb) && !aabb.overlaps(&far));
+}
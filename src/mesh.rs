@@ -0,0 +1,543 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use image::{GrayImage, RgbImage};
+
+use crate::color::{self, Color};
+use crate::math::{self, Aabb, Mat4, Vec3f};
+use crate::texture::Texture;
+
+/// Surface properties shared by all triangles of a mesh.
+#[derive(Clone, Debug)]
+pub struct Material {
+    pub base_color: Color,
+    /// Texture sampled with the mesh UVs, with row 0 at `v = 0`.
+    pub base_color_texture: Option<Arc<RgbImage>>,
+    /// Fraction of the environment mirrored by the surface, from 0 for a diffuse surface
+    /// to 1 for a mirror; reflections are tinted by the base color like on metals.
+    pub reflectivity: f64,
+    /// Physically based shading: 0 for a dielectric, 1 for a metal tinting its
+    /// reflections with the base color.
+    pub metallic: f64,
+    /// Physically based shading: microfacet roughness from 0 for a polished surface to 1
+    /// for a fully rough one.
+    pub roughness: f64,
+    /// Roughness in the green and metalness in the blue channel as in glTF, multiplying
+    /// `roughness` and `metallic`.
+    pub metallic_roughness_texture: Option<Texture>,
+    /// Ambient light reaching the surface in the red channel as in glTF, from 0 where it
+    /// is all blocked to 1, darkening the environment lighting. See
+    /// [`bake_ambient_occlusion`](crate::bake::bake_ambient_occlusion).
+    pub occlusion_texture: Option<Texture>,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material {
+            base_color: color::WHITE,
+            base_color_texture: None,
+            reflectivity: 0.0,
+            metallic: 0.0,
+            roughness: 1.0,
+            metallic_roughness_texture: None,
+            occlusion_texture: None,
+        }
+    }
+}
+
+/// How the normals of the faces around a vertex are weighted by
+/// [`Mesh::generate_normals`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NormalWeighting {
+    /// Larger faces count more.
+    Area,
+    /// Faces count by their angle at the vertex, so how a surface is split into triangles
+    /// does not matter.
+    Angle,
+}
+
+impl std::str::FromStr for NormalWeighting {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "area" => Ok(NormalWeighting::Area),
+            "angle" => Ok(NormalWeighting::Angle),
+            _ => Err(format!("unknown normal weighting '{}'", s)),
+        }
+    }
+}
+
+/// Indexed triangle mesh owned by the crate, optionally carrying line and point primitives.
+///
+/// `normals`, `uvs` and `colors` are either empty or hold one entry per position.
+#[derive(Clone, Debug, Default)]
+pub struct Mesh {
+    pub name: Option<String>,
+    /// Names of the groups, such as OBJ `g` groups, the mesh's faces belong to.
+    pub groups: Vec<String>,
+    pub positions: Vec<Vec3f>,
+    pub normals: Vec<Vec3f>,
+    pub uvs: Vec<[f64; 2]>,
+    pub colors: Vec<Color>,
+    pub indices: Vec<[usize; 3]>,
+    pub lines: Vec<[usize; 2]>,
+    pub points: Vec<usize>,
+    pub material: Material,
+}
+
+impl Mesh {
+    pub fn has_normals(&self) -> bool {
+        !self.normals.is_empty()
+    }
+
+    pub fn has_uvs(&self) -> bool {
+        !self.uvs.is_empty()
+    }
+
+    pub fn has_colors(&self) -> bool {
+        !self.colors.is_empty()
+    }
+
+    /// Bounds of all positions, or `None` for an empty mesh.
+    pub fn bounds(&self) -> Option<Aabb> {
+        Aabb::from_points(&self.positions)
+    }
+
+    /// Replaces the normals with weighted averages of the normals of the faces around
+    /// every position, including faces on the other side of UV seams. Faces meeting at
+    /// more than `crease_angle` radians keep a hard edge, which splits the vertices along
+    /// it; vertices used only by lines and points get zero normals.
+    pub fn generate_normals(&mut self, weighting: NormalWeighting, crease_angle: f64) {
+        let zero = Vec3f::new(0., 0., 0.);
+        let corner = |face: [usize; 3], k: usize| {
+            let p = self.positions[face[k]];
+            (
+                self.positions[face[(k + 1) % 3]] - p,
+                self.positions[face[(k + 2) % 3]] - p,
+            )
+        };
+        let face_normals: Vec<Vec3f> = self
+            .indices
+            .iter()
+            .map(|&face| {
+                let (e1, e2) = corner(face, 0);
+                math::cross(&e1, &e2)
+            })
+            .collect();
+        let weights: Vec<[f64; 3]> = self
+            .indices
+            .iter()
+            .zip(&face_normals)
+            .map(|(&face, normal)| {
+                [0, 1, 2].map(|k| match weighting {
+                    // the cross product is as long as twice the area
+                    NormalWeighting::Area => normal.length(),
+                    NormalWeighting::Angle => {
+                        let (e1, e2) = corner(face, k);
+                        let lengths = e1.length() * e2.length();
+                        if lengths > 0.0 {
+                            (math::dot(&e1, &e2) / lengths).clamp(-1.0, 1.0).acos()
+                        } else {
+                            0.0
+                        }
+                    }
+                })
+            })
+            .collect();
+        let unit = |n: Vec3f| {
+            if n.length_squared() > 0.0 {
+                n.normalized()
+            } else {
+                zero
+            }
+        };
+        let face_normals: Vec<Vec3f> = face_normals.into_iter().map(unit).collect();
+
+        let key = |p: &Vec3f| [p.x.to_bits(), p.y.to_bits(), p.z.to_bits()];
+        let mut corners_at: HashMap<[u64; 3], Vec<(usize, usize)>> = HashMap::new();
+        for (face, vertices) in self.indices.iter().enumerate() {
+            for (k, &v) in vertices.iter().enumerate() {
+                corners_at
+                    .entry(key(&self.positions[v]))
+                    .or_default()
+                    .push((face, k));
+            }
+        }
+
+        let min_cos = crease_angle.cos() - 1e-9;
+        let mut normals = vec![zero; self.positions.len()];
+        let mut assigned = vec![false; self.positions.len()];
+        let mut duplicates: HashMap<(usize, [u64; 3]), usize> = HashMap::new();
+        for face in 0..self.indices.len() {
+            let own = face_normals[face];
+            for k in 0..3 {
+                let v = self.indices[face][k];
+                let sum = corners_at[&key(&self.positions[v])]
+                    .iter()
+                    .filter(|&&(other, _)| math::dot(&own, &face_normals[other]) >= min_cos)
+                    .fold(zero, |sum, &(other, k)| {
+                        sum + face_normals[other] * weights[other][k]
+                    });
+                let normal = unit(sum);
+                if !assigned[v] {
+                    normals[v] = normal;
+                    assigned[v] = true;
+                } else if normals[v] != normal {
+                    let idx = *duplicates.entry((v, key(&normal))).or_insert_with(|| {
+                        self.positions.push(self.positions[v]);
+                        if self.has_uvs() {
+                            self.uvs.push(self.uvs[v]);
+                        }
+                        if self.has_colors() {
+                            self.colors.push(self.colors[v]);
+                        }
+                        normals.push(normal);
+                        self.positions.len() - 1
+                    });
+                    self.indices[face][k] = idx;
+                }
+            }
+        }
+        self.normals = normals;
+    }
+
+    /// Splits every triangle into four at its edge midpoints, `levels` times over, so the
+    /// mesh has the resolution to be displaced. Attributes are interpolated along the
+    /// edges; lines and points are kept as they are.
+    pub fn subdivide(&mut self, levels: u32) {
+        for _ in 0..levels {
+            self.split_triangles();
+        }
+    }
+
+    /// Subdivides like [`Mesh::subdivide`] but with Loop's rules, moving every vertex
+    /// towards its neighbours so the mesh converges to a smooth surface. Vertices are
+    /// joined by position, so UV seams and hard edges do not tear the surface apart, and
+    /// open borders are smoothed as curves of their own. Normals, if any, are regenerated
+    /// smooth.
+    pub fn subdivide_loop(&mut self, levels: u32) {
+        for _ in 0..levels {
+            let key = |p: &Vec3f| [p.x.to_bits(), p.y.to_bits(), p.z.to_bits()];
+            let mut ids: HashMap<[u64; 3], usize> = HashMap::new();
+            let mut points = Vec::new();
+            let welded: Vec<usize> = self
+                .positions
+                .iter()
+                .map(|p| {
+                    *ids.entry(key(p)).or_insert_with(|| {
+                        points.push(*p);
+                        points.len() - 1
+                    })
+                })
+                .collect();
+
+            // the corners opposite each edge, one per triangle sharing it
+            let mut opposite: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+            for face in &self.indices {
+                let [a, b, c] = face.map(|v| welded[v]);
+                for (i, j, k) in [(a, b, c), (b, c, a), (c, a, b)] {
+                    opposite.entry((i.min(j), i.max(j))).or_default().push(k);
+                }
+            }
+            let mut neighbours = vec![Vec::new(); points.len()];
+            let mut border = vec![Vec::new(); points.len()];
+            for (&(i, j), corners) in &opposite {
+                neighbours[i].push(j);
+                neighbours[j].push(i);
+                if corners.len() == 1 {
+                    border[i].push(j);
+                    border[j].push(i);
+                }
+            }
+
+            let smoothed: Vec<Vec3f> = (0..points.len())
+                .map(|i| match (border[i].as_slice(), neighbours[i].len()) {
+                    (&[a, b], _) => points[i] * 0.75 + (points[a] + points[b]) * 0.125,
+                    (&[], n) if n > 0 => {
+                        let beta = if n == 3 {
+                            3.0 / 16.0
+                        } else {
+                            3.0 / (8.0 * n as f64)
+                        };
+                        let sum = neighbours[i]
+                            .iter()
+                            .fold(Vec3f::new(0., 0., 0.), |sum, &j| sum + points[j]);
+                        points[i] * (1.0 - n as f64 * beta) + sum * beta
+                    }
+                    // isolated and non-manifold vertices stay put
+                    _ => points[i],
+                })
+                .collect();
+            let edge_point = |i: usize, j: usize| match opposite[&(i.min(j), i.max(j))].as_slice() {
+                &[c, d] => (points[i] + points[j]) * 0.375 + (points[c] + points[d]) * 0.125,
+                _ => (points[i] + points[j]) * 0.5,
+            };
+
+            let original = self.positions.len();
+            let parents = self.split_triangles();
+            for (p, &id) in self.positions.iter_mut().zip(&welded) {
+                *p = smoothed[id];
+            }
+            for (p, &(i, j)) in self.positions[original..].iter_mut().zip(&parents) {
+                *p = edge_point(welded[i], welded[j]);
+            }
+        }
+        if levels > 0 && self.has_normals() {
+            self.generate_normals(NormalWeighting::Angle, std::f64::consts::PI);
+        }
+    }
+
+    /// One level of [`Mesh::subdivide`], returning the two vertices each appended
+    /// midpoint lies between, in the order they were added.
+    fn split_triangles(&mut self) -> Vec<(usize, usize)> {
+        let mut midpoints: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut parents = Vec::new();
+        let mut indices = Vec::with_capacity(self.indices.len() * 4);
+        for [a, b, c] in std::mem::take(&mut self.indices) {
+            let mut midpoint = |i: usize, j: usize| {
+                *midpoints.entry((i.min(j), i.max(j))).or_insert_with(|| {
+                    parents.push((i, j));
+                    self.push_midpoint(i, j)
+                })
+            };
+            let (ab, bc, ca) = (midpoint(a, b), midpoint(b, c), midpoint(c, a));
+            indices.extend([[a, ab, ca], [ab, b, bc], [ca, bc, c], [ab, bc, ca]]);
+        }
+        self.indices = indices;
+        parents
+    }
+
+    /// Appends a vertex halfway between vertices `i` and `j`, returning its index.
+    fn push_midpoint(&mut self, i: usize, j: usize) -> usize {
+        self.positions
+            .push((self.positions[i] + self.positions[j]) * 0.5);
+        if self.has_normals() {
+            let normal = self.normals[i] + self.normals[j];
+            self.normals.push(if normal.length_squared() > 0.0 {
+                normal.normalized()
+            } else {
+                self.normals[i]
+            });
+        }
+        if self.has_uvs() {
+            let (a, b) = (self.uvs[i], self.uvs[j]);
+            self.uvs.push([(a[0] + b[0]) / 2.0, (a[1] + b[1]) / 2.0]);
+        }
+        if self.has_colors() {
+            let (a, b) = (self.colors[i], self.colors[j]);
+            let mix = |a: u8, b: u8| ((a as u16 + b as u16) / 2) as u8;
+            self.colors
+                .push(Color(mix(a.0, b.0), mix(a.1, b.1), mix(a.2, b.2)));
+        }
+        self.positions.len() - 1
+    }
+
+    /// Moves every vertex along its normal by `scale` times the height sampled from
+    /// `height_map` at its UV, black being 0 and white 1, then regenerates smooth
+    /// normals for the new surface. The map is addressed like material textures, with
+    /// row 0 at `v = 0`. Meshes without UVs are left alone; missing normals are generated
+    /// first.
+    pub fn displace(&mut self, height_map: &GrayImage, scale: f64) {
+        if !self.has_uvs() || height_map.width() == 0 || height_map.height() == 0 {
+            return;
+        }
+        if !self.has_normals() {
+            self.generate_normals(NormalWeighting::Angle, std::f64::consts::PI);
+        }
+        for ((p, n), uv) in self.positions.iter_mut().zip(&self.normals).zip(&self.uvs) {
+            *p = *p + *n * (sample_height(height_map, uv[0], uv[1]) * scale);
+        }
+        self.generate_normals(NormalWeighting::Angle, std::f64::consts::PI);
+    }
+
+    /// Applies `transform` to positions and its inverse transpose to normals.
+    pub fn transform(&mut self, transform: &Mat4) {
+        for p in &mut self.positions {
+            *p = transform.transform_point(p);
+        }
+        let normal_matrix = transform
+            .inverse()
+            .map_or(*transform, |inverse| inverse.transpose());
+        for n in &mut self.normals {
+            *n = normal_matrix.transform_vector(n).normalized();
+        }
+    }
+
+    /// One mesh with the vertices, faces, lines and points of all `meshes`, keeping the
+    /// normals, UVs and colors only if every mesh with vertices has them, and the name
+    /// and material of the first.
+    pub fn merged(meshes: &[Mesh]) -> Mesh {
+        let with_vertices = || meshes.iter().filter(|mesh| !mesh.positions.is_empty());
+        let keep_normals = with_vertices().all(Mesh::has_normals);
+        let keep_uvs = with_vertices().all(Mesh::has_uvs);
+        let keep_colors = with_vertices().all(Mesh::has_colors);
+        let mut merged = Mesh {
+            name: meshes.first().and_then(|mesh| mesh.name.clone()),
+            material: meshes
+                .first()
+                .map(|mesh| mesh.material.clone())
+                .unwrap_or_default(),
+            ..Default::default()
+        };
+        for mesh in meshes {
+            let offset = merged.positions.len();
+            merged.positions.extend(&mesh.positions);
+            if keep_normals {
+                merged.normals.extend(&mesh.normals);
+            }
+            if keep_uvs {
+                merged.uvs.extend(&mesh.uvs);
+            }
+            if keep_colors {
+                merged.colors.extend(&mesh.colors);
+            }
+            let shift = |v: &usize| v + offset;
+            merged
+                .indices
+                .extend(mesh.indices.iter().map(|face| face.each_ref().map(shift)));
+            merged
+                .lines
+                .extend(mesh.lines.iter().map(|line| line.each_ref().map(shift)));
+            merged.points.extend(mesh.points.iter().map(shift));
+            for group in &mesh.groups {
+                if !merged.groups.contains(group) {
+                    merged.groups.push(group.clone());
+                }
+            }
+        }
+        merged
+    }
+}
+
+/// Bilinear lookup in [0, 1] with `v = 0` at row 0, clamping at the edges.
+fn sample_height(map: &GrayImage, u: f64, v: f64) -> f64 {
+    let (width, height) = (map.width(), map.height());
+    let x = (u.clamp(0.0, 1.0) * width as f64 - 0.5).clamp(0.0, (width - 1) as f64);
+    let y = (v.clamp(0.0, 1.0) * height as f64 - 0.5).clamp(0.0, (height - 1) as f64);
+    let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+    let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+    let (fx, fy) = (x - x0 as f64, y - y0 as f64);
+    let at = |x: u32, y: u32| map.get_pixel(x, y)[0] as f64 / 255.0;
+    let top = at(x0, y0) * (1.0 - fx) + at(x1, y0) * fx;
+    let bottom = at(x0, y1) * (1.0 - fx) + at(x1, y1) * fx;
+    top * (1.0 - fy) + bottom * fy
+}
+
+#[test]
+fn test_transform() {
+    let mut mesh = Mesh {
+        positions: vec![Vec3f::new(1., 0., 0.)],
+        normals: vec![Vec3f::new(1., 0., 0.)],
+        ..Default::default()
+    };
+    mesh.transform(&Mat4::rotation_y(std::f64::consts::FRAC_PI_2));
+    assert!((mesh.positions[0] - Vec3f::new(0., 0., -1.)).length() < 1e-9);
+    assert!((mesh.normals[0] - Vec3f::new(0., 0., -1.)).length() < 1e-9);
+}
+
+#[test]
+fn test_generate_normals() {
+    let mut cube = crate::geometry::cuboid(Vec3f::new(2., 2., 2.));
+    cube.normals.clear();
+    // smooth corners point away from the center
+    cube.generate_normals(NormalWeighting::Angle, std::f64::consts::PI);
+    for (p, n) in cube.positions.iter().zip(&cube.normals) {
+        assert!((*n - p.normalized()).length() < 1e-9, "{:?} {:?}", p, n);
+    }
+
+    // a crease angle below 90 degrees keeps the faces flat
+    let mut cube = crate::geometry::cuboid(Vec3f::new(2., 2., 2.));
+    let flat = cube.normals.clone();
+    cube.normals.clear();
+    cube.generate_normals(NormalWeighting::Area, 1.0);
+    for face in &cube.indices {
+        let n = cube.normals[face[0]];
+        assert!(face.iter().all(|&v| cube.normals[v] == n));
+        assert!(flat.iter().any(|f| (*f - n).length() < 1e-9));
+    }
+}
+
+#[test]
+fn test_subdivide_and_displace() {
+    let mut plane = crate::geometry::plane(2.0, 2.0, 1);
+    let triangles = plane.indices.len();
+    plane.subdivide(2);
+    assert_eq!(plane.indices.len(), triangles * 16);
+    // shared edges share their midpoints: a 5x5 grid of vertices
+    assert_eq!(plane.positions.len(), 25);
+
+    // a map raising the half with u > 0.5
+    let map = GrayImage::from_fn(2, 1, |x, _| image::Luma([if x == 0 { 0 } else { 255 }]));
+    let up = plane.normals[0];
+    plane.displace(&map, 0.5);
+    for (p, uv) in plane.positions.iter().zip(&plane.uvs) {
+        let height = math::dot(p, &up);
+        if uv[0] <= 0.25 {
+            assert!(height.abs() < 1e-9);
+        } else if uv[0] >= 0.75 {
+            assert!((height - 0.5).abs() < 1e-9);
+        }
+    }
+}
+
+#[test]
+fn test_subdivide_loop() {
+    // smoothing cuts the corners, pulling every vertex inside the cube
+    let mut cube = crate::geometry::cuboid(Vec3f::new(2., 2., 2.));
+    cube.subdivide_loop(2);
+    for p in &cube.positions {
+        assert!(
+            p.x.abs() < 1.0 && p.y.abs() < 1.0 && p.z.abs() < 1.0,
+            "{:?}",
+            p
+        );
+    }
+    // the surface stays closed: seams between the faces are smoothed alike
+    let edges: HashMap<_, usize> = cube.indices.iter().fold(HashMap::new(), |mut edges, f| {
+        for (a, b) in [(f[0], f[1]), (f[1], f[2]), (f[2], f[0])] {
+            let (a, b) = (cube.positions[a], cube.positions[b]);
+            let key = |p: Vec3f| [p.x, p.y, p.z].map(|c| (c * 1e6).round() as i64);
+            let (a, b) = (key(a), key(b));
+            *edges.entry((a.min(b), a.max(b))).or_default() += 1;
+        }
+        edges
+    });
+    assert!(edges.values().all(|&count| count == 2));
+    assert_eq!(cube.normals.len(), cube.positions.len());
+
+    // an open plane stays flat, its border pulled in as a curve
+    let mut plane = crate::geometry::plane(2.0, 2.0, 2);
+    let up = plane.normals[0];
+    plane.subdivide_loop(1);
+    assert!(plane
+        .positions
+        .iter()
+        .all(|p| math::dot(p, &up).abs() < 1e-9));
+}
+
+#[test]
+fn test_merged() {
+    let cube = crate::geometry::cuboid(Vec3f::new(1., 1., 1.));
+    let mut sphere = crate::geometry::sphere(0.5, 8, 4);
+    sphere.lines.push([0, 1]);
+    let merged = Mesh::merged(&[cube.clone(), sphere.clone()]);
+    let offset = cube.positions.len();
+    assert_eq!(merged.positions.len(), offset + sphere.positions.len());
+    assert_eq!(
+        merged.indices.len(),
+        cube.indices.len() + sphere.indices.len()
+    );
+    assert_eq!(
+        merged.indices[cube.indices.len()],
+        sphere.indices[0].map(|v| v + offset)
+    );
+    assert_eq!(merged.lines, vec![[offset, offset + 1]]);
+    assert!(merged.has_normals() && merged.has_uvs());
+
+    // attributes one of the meshes lacks are dropped
+    sphere.uvs.clear();
+    let merged = Mesh::merged(&[cube, sphere]);
+    assert!(merged.has_normals() && !merged.has_uvs());
+    assert!(Mesh::merged(&[]).positions.is_empty());
+}
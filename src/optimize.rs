@@ -0,0 +1,288 @@
+//! Mesh preprocessing for locality: welding duplicate vertices, ordering the faces for a
+//! vertex cache after Forsyth's linear-speed algorithm and the vertices by first use.
+
+use std::collections::HashMap;
+
+use crate::mesh::Mesh;
+
+/// Vertices the face ordering assumes a cache to hold.
+const CACHE_SIZE: usize = 32;
+/// Score of the vertices of the face just drawn, lower than of the ones before them so
+/// that strips do not keep turning back.
+const LAST_FACE_SCORE: f64 = 0.75;
+const CACHE_DECAY_POWER: f64 = 1.5;
+/// Bonus of vertices with few faces left, so that they are finished off rather than left
+/// as isolated faces for the end.
+const VALENCE_BOOST_SCALE: f64 = 2.0;
+const VALENCE_BOOST_POWER: f64 = 0.5;
+
+impl Mesh {
+    /// Joins vertices with positions within `tolerance` of each other and equal normals,
+    /// UVs and colors, so that seams and hard edges stay split, and drops the faces this
+    /// collapses. Positions are compared on a grid of that size, exactly if it is 0.
+    /// Returns the number of vertices removed.
+    ///
+    /// Loaders and scanners often repeat a vertex for every face using it, which
+    /// multiplies the vertices to transform.
+    pub fn weld_vertices(&mut self, tolerance: f64) -> usize {
+        let key = |v: usize| {
+            let p = self.positions[v];
+            let position = [p.x, p.y, p.z].map(|c| {
+                if tolerance > 0.0 {
+                    (c / tolerance).round() as i64 as u64
+                } else {
+                    // joins 0 and -0
+                    (c + 0.0).to_bits()
+                }
+            });
+            let normal = self
+                .normals
+                .get(v)
+                .map(|n| [n.x, n.y, n.z].map(f64::to_bits));
+            let uv = self.uvs.get(v).map(|uv| uv.map(f64::to_bits));
+            let color = self.colors.get(v).map(|c| [c.0, c.1, c.2]);
+            (position, normal, uv, color)
+        };
+        let mut ids = HashMap::new();
+        let mut kept = Vec::new();
+        let remap: Vec<usize> = (0..self.positions.len())
+            .map(|v| {
+                *ids.entry(key(v)).or_insert_with(|| {
+                    kept.push(v);
+                    kept.len() - 1
+                })
+            })
+            .collect();
+        let removed = self.positions.len() - kept.len();
+        if removed == 0 {
+            return 0;
+        }
+        self.positions = kept.iter().map(|&v| self.positions[v]).collect();
+        gather(&mut self.normals, &kept);
+        gather(&mut self.uvs, &kept);
+        gather(&mut self.colors, &kept);
+        for v in self
+            .indices
+            .iter_mut()
+            .flatten()
+            .chain(self.lines.iter_mut().flatten())
+            .chain(&mut self.points)
+        {
+            *v = remap[*v];
+        }
+        self.indices.retain(|&[a, b, c]| a != b && b != c && c != a);
+        self.lines.retain(|&[a, b]| a != b);
+        removed
+    }
+
+    /// Orders the faces so that consecutive ones share vertices, with Forsyth's greedy
+    /// scoring of the vertices in a simulated cache of recently used ones. Lines and
+    /// points are left as they are.
+    pub fn optimize_vertex_cache(&mut self) {
+        let mut faces_at = vec![Vec::new(); self.positions.len()];
+        for (face, corners) in self.indices.iter().enumerate() {
+            for &v in corners {
+                faces_at[v].push(face);
+            }
+        }
+        let mut vertex_scores: Vec<f64> = faces_at
+            .iter()
+            .map(|faces| vertex_score(None, faces.len()))
+            .collect();
+        let face_score =
+            |corners: &[usize; 3], scores: &[f64]| corners.map(|v| scores[v]).iter().sum();
+        let mut face_scores: Vec<f64> = self
+            .indices
+            .iter()
+            .map(|corners| face_score(corners, &vertex_scores))
+            .collect();
+        let mut emitted = vec![false; self.indices.len()];
+        let mut order = Vec::with_capacity(self.indices.len());
+        let mut cache: Vec<usize> = Vec::with_capacity(CACHE_SIZE + 3);
+        // faces are started from in order whenever none in the cache is left
+        let mut next_unemitted = 0;
+        let mut best =
+            (0..self.indices.len()).max_by(|&a, &b| face_scores[a].total_cmp(&face_scores[b]));
+
+        while let Some(face) = best {
+            emitted[face] = true;
+            order.push(face);
+            let corners = self.indices[face];
+            for v in corners {
+                faces_at[v].retain(|&f| f != face);
+            }
+            // the face's vertices move to the front, the oldest ones fall out
+            let mut updated: Vec<usize> = corners.to_vec();
+            updated.extend(cache.iter().copied().filter(|v| !corners.contains(v)));
+            for &v in &updated[CACHE_SIZE.min(updated.len())..] {
+                vertex_scores[v] = vertex_score(None, faces_at[v].len());
+            }
+            cache = updated[..CACHE_SIZE.min(updated.len())].to_vec();
+            for (position, &v) in cache.iter().enumerate() {
+                vertex_scores[v] = vertex_score(Some(position), faces_at[v].len());
+            }
+
+            best = None;
+            let mut best_score = f64::NEG_INFINITY;
+            for &v in &updated {
+                for &f in &faces_at[v] {
+                    face_scores[f] = face_score(&self.indices[f], &vertex_scores);
+                    if face_scores[f] > best_score {
+                        best = Some(f);
+                        best_score = face_scores[f];
+                    }
+                }
+            }
+            if best.is_none() {
+                while next_unemitted < emitted.len() && emitted[next_unemitted] {
+                    next_unemitted += 1;
+                }
+                best = (next_unemitted < emitted.len()).then_some(next_unemitted);
+            }
+        }
+        self.indices = order.into_iter().map(|face| self.indices[face]).collect();
+    }
+
+    /// Renumbers the vertices in the order the faces, then the lines and points first
+    /// use them, so that they are read from memory front to back; unused vertices go
+    /// last.
+    pub fn optimize_vertex_fetch(&mut self) {
+        let mut remap = vec![usize::MAX; self.positions.len()];
+        let mut order = Vec::with_capacity(self.positions.len());
+        let used = self
+            .indices
+            .iter()
+            .flatten()
+            .chain(self.lines.iter().flatten())
+            .chain(&self.points)
+            .copied();
+        for v in used.chain(0..self.positions.len()) {
+            if remap[v] == usize::MAX {
+                remap[v] = order.len();
+                order.push(v);
+            }
+        }
+        self.positions = order.iter().map(|&v| self.positions[v]).collect();
+        gather(&mut self.normals, &order);
+        gather(&mut self.uvs, &order);
+        gather(&mut self.colors, &order);
+        for v in self
+            .indices
+            .iter_mut()
+            .flatten()
+            .chain(self.lines.iter_mut().flatten())
+            .chain(&mut self.points)
+        {
+            *v = remap[*v];
+        }
+    }
+
+    /// Average number of vertices per face a first-in first-out cache of `cache_size`
+    /// recently used vertices misses, between 0.5 for a large regular grid and 3 for no
+    /// reuse at all.
+    pub fn average_cache_miss_ratio(&self, cache_size: usize) -> f64 {
+        if self.indices.is_empty() {
+            return 0.0;
+        }
+        let mut cache = std::collections::VecDeque::with_capacity(cache_size + 1);
+        let mut misses = 0;
+        for &v in self.indices.iter().flatten() {
+            if !cache.contains(&v) {
+                misses += 1;
+                cache.push_back(v);
+                if cache.len() > cache_size {
+                    cache.pop_front();
+                }
+            }
+        }
+        misses as f64 / self.indices.len() as f64
+    }
+}
+
+/// Forsyth's score of a vertex at `cache_position` that `remaining` faces not yet ordered
+/// use, higher for the ones to be used sooner.
+fn vertex_score(cache_position: Option<usize>, remaining: usize) -> f64 {
+    if remaining == 0 {
+        return -1.0;
+    }
+    let cached = match cache_position {
+        None => 0.0,
+        Some(position) if position < 3 => LAST_FACE_SCORE,
+        Some(position) => {
+            let scale = 1.0 / (CACHE_SIZE - 3) as f64;
+            (1.0 - (position - 3) as f64 * scale).powf(CACHE_DECAY_POWER)
+        }
+    };
+    cached + VALENCE_BOOST_SCALE * (remaining as f64).powf(-VALENCE_BOOST_POWER)
+}
+
+/// Keeps the per-vertex `values` of the vertices in `order`; empty attributes stay empty.
+fn gather<T: Copy>(values: &mut Vec<T>, order: &[usize]) {
+    if !values.is_empty() {
+        *values = order.iter().map(|&v| values[v]).collect();
+    }
+}
+
+#[test]
+fn test_weld_vertices() {
+    // every face of a cube has its own corners, which only weld where normals match
+    let mut cube = crate::geometry::cuboid(crate::math::Vec3f::new(1., 1., 1.));
+    assert_eq!(cube.weld_vertices(0.0), 0);
+    cube.normals.clear();
+    cube.uvs.clear();
+    assert_eq!(cube.weld_vertices(0.0), 16);
+    assert_eq!(cube.positions.len(), 8);
+    assert_eq!(cube.indices.len(), 12);
+
+    // a face squashed within the tolerance goes away
+    let mut mesh = Mesh {
+        positions: vec![
+            crate::math::Vec3f::new(0., 0., 0.),
+            crate::math::Vec3f::new(1., 0., 0.),
+            crate::math::Vec3f::new(0., 1., 0.),
+            crate::math::Vec3f::new(1e-6, 1., 0.),
+        ],
+        indices: vec![[0, 1, 2], [1, 3, 2]],
+        points: vec![3],
+        ..Default::default()
+    };
+    assert_eq!(mesh.weld_vertices(1e-3), 1);
+    assert_eq!(mesh.indices, vec![[0, 1, 2]]);
+    assert_eq!(mesh.points, vec![2]);
+}
+
+#[test]
+fn test_optimize_vertex_cache() {
+    let mut torus = crate::geometry::torus(0.6, 0.25, 64, 32);
+    // shuffle the faces the way a scan might store them
+    let count = torus.indices.len();
+    torus.indices = (0..count)
+        .map(|i| torus.indices[i * 7919 % count])
+        .collect();
+    let mut faces = torus.indices.clone();
+    let shuffled = torus.average_cache_miss_ratio(16);
+    torus.optimize_vertex_cache();
+    let optimized = torus.average_cache_miss_ratio(16);
+    assert!(
+        shuffled > 2.0 && optimized < 0.8,
+        "{} {}",
+        shuffled,
+        optimized
+    );
+
+    // the same faces in another order
+    let mut reordered = torus.indices.clone();
+    faces.sort_unstable();
+    reordered.sort_unstable();
+    assert_eq!(faces, reordered);
+
+    let positions = torus.positions.clone();
+    let first = torus.indices[0];
+    torus.optimize_vertex_fetch();
+    assert_eq!(torus.indices[0], [0, 1, 2]);
+    assert_eq!(
+        first.map(|v| positions[v]),
+        [0, 1, 2].map(|v| torus.positions[v])
+    );
+    assert_eq!(torus.positions.len(), positions.len());
+}
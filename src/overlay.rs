@@ -0,0 +1,204 @@
+use std::f64::consts::TAU;
+
+use crate::color::{self, Color};
+use crate::drawable::{Drawable, Image};
+use crate::light::{Light, LightSource};
+use crate::math::{self, Mat4, Vec3f};
+
+/// Segments the circle at the end of a spot light's cone is drawn with.
+const CIRCLE_SEGMENTS: usize = 16;
+
+/// Square grid of lines on the `y = 0` plane, centered on the origin.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Grid {
+    /// Length of its sides.
+    pub size: f64,
+    /// Cells along each side.
+    pub cells: u32,
+    pub color: Color,
+}
+
+impl Default for Grid {
+    fn default() -> Self {
+        Grid {
+            size: 2.0,
+            cells: 10,
+            color: color::GRAY,
+        }
+    }
+}
+
+/// Draws the line from `a` to `b` transformed by `view`, depth tested against what is
+/// already drawn.
+fn segment(image: &mut Image, view: &Mat4, a: &Vec3f, b: &Vec3f, color: Color) {
+    let a = image.to_screen(&view.transform_point(a));
+    let b = image.to_screen(&view.transform_point(b));
+    image.line3d(&a, &b, color);
+}
+
+/// Draws the X, Y and Z axes from the origin to `length` along them in red, green and
+/// blue.
+pub fn draw_axes(image: &mut Image, view: &Mat4, length: f64) {
+    let origin = Vec3f::new(0., 0., 0.);
+    let axes = [
+        (Vec3f::new(length, 0., 0.), color::RED),
+        (Vec3f::new(0., length, 0.), color::GREEN),
+        (Vec3f::new(0., 0., length), color::BLUE),
+    ];
+    for (end, color) in axes {
+        segment(image, view, &origin, &end, color);
+    }
+}
+
+pub fn draw_grid(image: &mut Image, view: &Mat4, grid: &Grid) {
+    let half = grid.size / 2.0;
+    for i in 0..=grid.cells {
+        let offset = -half + grid.size * i as f64 / grid.cells.max(1) as f64;
+        let (a, b) = (Vec3f::new(offset, 0., -half), Vec3f::new(offset, 0., half));
+        segment(image, view, &a, &b, grid.color);
+        let (a, b) = (Vec3f::new(-half, 0., offset), Vec3f::new(half, 0., offset));
+        segment(image, view, &a, &b, grid.color);
+    }
+}
+
+/// Draws where `lights` shine from, given where shading takes them, after the model
+/// transform: an arrow towards the origin for directional lights, a star at point
+/// lights and the outline of the cone of spot lights, `size` across.
+pub fn draw_lights(image: &mut Image, lights: &[Light], size: f64, color: Color) {
+    let view = Mat4::identity();
+    for light in lights {
+        match light.source() {
+            LightSource::Directional(direction) => {
+                let tail = direction * (1.5 + size);
+                let tip = direction * 1.5;
+                segment(image, &view, &tail, &tip, color);
+                let (side, _) = perpendiculars(&direction);
+                for sign in [-1.0, 1.0] {
+                    let barb = tip + direction * (size / 4.0) + side * (sign * size / 8.0);
+                    segment(image, &view, &tip, &barb, color);
+                }
+            }
+            LightSource::Point { position, .. } => draw_star(image, &position, size, color),
+            LightSource::Spot {
+                position,
+                direction,
+                outer_angle,
+                ..
+            } => {
+                draw_star(image, &position, size / 4.0, color);
+                // the cone a unit long, or as wide for spots wider than 45 degrees
+                let length = outer_angle.cos().max(outer_angle.sin());
+                let center = position + direction * (outer_angle.cos() / length);
+                let radius = outer_angle.sin() / length;
+                let (u, v) = perpendiculars(&direction);
+                let rim = |i: usize| {
+                    let angle = TAU * i as f64 / CIRCLE_SEGMENTS as f64;
+                    center + u * (radius * angle.cos()) + v * (radius * angle.sin())
+                };
+                for i in 0..CIRCLE_SEGMENTS {
+                    segment(image, &view, &rim(i), &rim(i + 1), color);
+                    if i % (CIRCLE_SEGMENTS / 4) == 0 {
+                        segment(image, &view, &position, &rim(i), color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Three lines `size` long crossing at `center`.
+fn draw_star(image: &mut Image, center: &Vec3f, size: f64, color: Color) {
+    let half = size / 2.0;
+    for axis in [
+        Vec3f::new(half, 0., 0.),
+        Vec3f::new(0., half, 0.),
+        Vec3f::new(0., 0., half),
+    ] {
+        segment(
+            image,
+            &Mat4::identity(),
+            &(*center - axis),
+            &(*center + axis),
+            color,
+        );
+    }
+}
+
+/// Two unit vectors perpendicular to the unit `direction` and each other.
+fn perpendiculars(direction: &Vec3f) -> (Vec3f, Vec3f) {
+    let helper = if direction.y.abs() < 0.9 {
+        Vec3f::new(0., 1., 0.)
+    } else {
+        Vec3f::new(1., 0., 0.)
+    };
+    let u = math::cross(direction, &helper).normalized();
+    (u, math::cross(direction, &u))
+}
+
+/// Outlines the view volume of a camera whose `projection`, including its own view
+/// transform, maps it onto the [-1, 1] cube, as seen through `view`. Projections
+/// without an inverse outline nothing.
+pub fn draw_frustum(image: &mut Image, view: &Mat4, projection: &Mat4, color: Color) {
+    let Some(inverse) = projection.inverse() else {
+        return;
+    };
+    // corner `i` is at +1 along x, y and z where bits 0, 1 and 2 of `i` are set
+    let corners: Vec<Vec3f> = (0..8)
+        .map(|i| {
+            let sign = |bit: usize| if i & bit != 0 { 1.0 } else { -1.0 };
+            inverse.transform_point(&Vec3f::new(sign(1), sign(2), sign(4)))
+        })
+        .collect();
+    for i in 0..8 {
+        for bit in [1, 2, 4] {
+            if i & bit == 0 {
+                segment(image, view, &corners[i], &corners[i | bit], color);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_overlays() {
+    let count = |image: &Image, color: Color| {
+        image
+            .to_rgb_image()
+            .pixels()
+            .filter(|p| p.0 == [color.0, color.1, color.2])
+            .count()
+    };
+
+    // seen from the front, the X and Y axes run right and up from the middle and Z
+    // points at the viewer
+    let mut image = Image::new(32, 32);
+    draw_axes(&mut image, &Mat4::identity(), 0.5);
+    let pixels = image.to_rgb_image();
+    assert_eq!(pixels.get_pixel(22, 15).0, [255, 0, 0]);
+    assert_eq!(pixels.get_pixel(16, 10).0, [0, 255, 0]);
+    assert!(count(&image, color::BLUE) <= 1);
+
+    // the grid is a line seen edge-on and a grid from above
+    let mut image = Image::new(32, 32);
+    draw_grid(&mut image, &Mat4::identity(), &Grid::default());
+    let edge_on = count(&image, color::GRAY);
+    let mut image = Image::new(32, 32);
+    let above = Mat4::rotation_x(std::f64::consts::FRAC_PI_2);
+    draw_grid(&mut image, &above, &Grid::default());
+    assert!(count(&image, color::GRAY) > 5 * edge_on);
+
+    let mut image = Image::new(32, 32);
+    let volume = crate::camera::Orthographic::from_height(1.0, 1.0, -0.5, 0.5).projection();
+    draw_frustum(&mut image, &Mat4::identity(), &volume, color::CYAN);
+    // seen along its axis the volume outlines a square half as wide as the image
+    let pixels = image.to_rgb_image();
+    assert_eq!(pixels.get_pixel(8, 16).0, [0, 255, 255]);
+    assert_eq!(pixels.get_pixel(16, 16).0, [0, 0, 0]);
+
+    let lights = [
+        Light::directional(Vec3f::new(0., 0., 1.)).unwrap(),
+        Light::point(Vec3f::new(0.5, 0.5, 0.), crate::light::Falloff::NONE).unwrap(),
+    ];
+    let mut image = Image::new(32, 32);
+    draw_lights(&mut image, &lights, 0.2, color::YELLOW);
+    assert_eq!(image.to_rgb_image().get_pixel(24, 8).0, [255, 255, 0]);
+}
@@ -0,0 +1,564 @@
+use crate::color::{Color, HdrColor};
+use crate::deferred::GBuffer;
+use crate::drawable::Framebuffer;
+use crate::math;
+
+/// Full-screen pass run over the HDR color buffer between rasterization and tone mapping.
+pub trait PostProcess {
+    fn apply(&self, input: &Framebuffer) -> Framebuffer;
+}
+
+/// Runs its passes one after another, so a whole chain can be used as a single pass.
+#[derive(Default)]
+pub struct PassList {
+    passes: Vec<Box<dyn PostProcess>>,
+}
+
+impl PassList {
+    pub fn new() -> Self {
+        PassList::default()
+    }
+
+    pub fn with<P: PostProcess + 'static>(mut self, pass: P) -> Self {
+        self.passes.push(Box::new(pass));
+        self
+    }
+}
+
+impl PostProcess for PassList {
+    fn apply(&self, input: &Framebuffer) -> Framebuffer {
+        self.passes
+            .iter()
+            .fold(input.clone(), |framebuffer, pass| pass.apply(&framebuffer))
+    }
+}
+
+/// Relative luminance of a linear color.
+fn luminance(color: HdrColor) -> f32 {
+    0.2126 * color.0 + 0.7152 * color.1 + 0.0722 * color.2
+}
+
+fn add(a: HdrColor, b: HdrColor) -> HdrColor {
+    HdrColor(a.0 + b.0, a.1 + b.1, a.2 + b.2)
+}
+
+fn lerp(a: HdrColor, b: HdrColor, t: f32) -> HdrColor {
+    HdrColor(
+        a.0 + (b.0 - a.0) * t,
+        a.1 + (b.1 - a.1) * t,
+        a.2 + (b.2 - a.2) * t,
+    )
+}
+
+/// Separable gaussian blur with standard deviation `sigma` in pixels; edges are clamped.
+#[derive(Clone, Copy, Debug)]
+pub struct GaussianBlur {
+    pub sigma: f32,
+}
+
+impl GaussianBlur {
+    fn kernel(&self) -> Vec<f32> {
+        let sigma = self.sigma.max(1e-3);
+        let radius = (3.0 * sigma).ceil() as i64;
+        let weights: Vec<f32> = (-radius..=radius)
+            .map(|i| (-(i * i) as f32 / (2.0 * sigma * sigma)).exp())
+            .collect();
+        let sum: f32 = weights.iter().sum();
+        weights.into_iter().map(|w| w / sum).collect()
+    }
+
+    fn convolve(input: &Framebuffer, kernel: &[f32], horizontal: bool) -> Framebuffer {
+        let radius = (kernel.len() / 2) as i64;
+        let mut output = Framebuffer::new(input.width(), input.height());
+        for y in 0..input.height() {
+            for x in 0..input.width() {
+                let mut sum = HdrColor::default();
+                for (i, weight) in kernel.iter().enumerate() {
+                    let offset = i as i64 - radius;
+                    let sample = if horizontal {
+                        input.get_clamped(x as i64 + offset, y as i64)
+                    } else {
+                        input.get_clamped(x as i64, y as i64 + offset)
+                    };
+                    sum = add(sum, sample.scale(*weight as f64));
+                }
+                output.set(x, y, sum);
+            }
+        }
+        output
+    }
+}
+
+impl PostProcess for GaussianBlur {
+    fn apply(&self, input: &Framebuffer) -> Framebuffer {
+        let kernel = self.kernel();
+        let blurred = GaussianBlur::convolve(input, &kernel, true);
+        GaussianBlur::convolve(&blurred, &kernel, false)
+    }
+}
+
+/// Adds a blurred copy of everything brighter than `threshold` back onto the image.
+#[derive(Clone, Copy, Debug)]
+pub struct Bloom {
+    /// Luminance above which pixels glow.
+    pub threshold: f32,
+    /// Weight of the glow when added back.
+    pub strength: f32,
+    /// Spread of the glow in pixels.
+    pub sigma: f32,
+}
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Bloom {
+            threshold: 1.0,
+            strength: 0.5,
+            sigma: 4.0,
+        }
+    }
+}
+
+impl PostProcess for Bloom {
+    fn apply(&self, input: &Framebuffer) -> Framebuffer {
+        let mut bright = input.clone();
+        for pixel in &mut bright.pixels {
+            let luma = luminance(*pixel);
+            *pixel = if luma > self.threshold {
+                pixel.scale(((luma - self.threshold) / luma) as f64)
+            } else {
+                HdrColor::default()
+            };
+        }
+        let glow = GaussianBlur { sigma: self.sigma }.apply(&bright);
+        let mut output = input.clone();
+        for (pixel, glow) in output.pixels.iter_mut().zip(&glow.pixels) {
+            *pixel = add(*pixel, glow.scale(self.strength as f64));
+        }
+        output
+    }
+}
+
+/// Darkens the image towards its corners, by `strength` at the very corner.
+#[derive(Clone, Copy, Debug)]
+pub struct Vignette {
+    pub strength: f32,
+}
+
+impl PostProcess for Vignette {
+    fn apply(&self, input: &Framebuffer) -> Framebuffer {
+        let (cx, cy) = (input.width() as f32 / 2.0, input.height() as f32 / 2.0);
+        let max_distance_squared = cx * cx + cy * cy;
+        let mut output = input.clone();
+        for y in 0..input.height() {
+            for x in 0..input.width() {
+                let (dx, dy) = (x as f32 + 0.5 - cx, y as f32 + 0.5 - cy);
+                let falloff = (dx * dx + dy * dy) / max_distance_squared;
+                let factor = (1.0 - self.strength * falloff).max(0.0);
+                output.set(x, y, input.get(x, y).scale(factor as f64));
+            }
+        }
+        output
+    }
+}
+
+/// Simplified FXAA: finds high-contrast pixels from the luminance of their neighbours and
+/// blends them across the detected edge, without the full edge-end search.
+#[derive(Clone, Copy, Debug)]
+pub struct Fxaa {
+    /// Minimum local contrast, relative to the brightest neighbour, to count as an edge.
+    pub edge_threshold: f32,
+    /// Absolute contrast below which dark areas are left alone.
+    pub edge_threshold_min: f32,
+}
+
+impl Default for Fxaa {
+    fn default() -> Self {
+        Fxaa {
+            edge_threshold: 0.125,
+            edge_threshold_min: 0.0312,
+        }
+    }
+}
+
+impl PostProcess for Fxaa {
+    fn apply(&self, input: &Framebuffer) -> Framebuffer {
+        // perceptual luma of the displayable range
+        let luma = |x: i64, y: i64| luminance(input.get_clamped(x, y)).clamp(0.0, 1.0).sqrt();
+        let mut output = input.clone();
+        for y in 0..input.height() as i64 {
+            for x in 0..input.width() as i64 {
+                let m = luma(x, y);
+                let (n, s, e, w) = (
+                    luma(x, y + 1),
+                    luma(x, y - 1),
+                    luma(x + 1, y),
+                    luma(x - 1, y),
+                );
+                let max = m.max(n).max(s).max(e).max(w);
+                let min = m.min(n).min(s).min(e).min(w);
+                let range = max - min;
+                if range < self.edge_threshold_min.max(max * self.edge_threshold) {
+                    continue;
+                }
+
+                let (ne, nw) = (luma(x + 1, y + 1), luma(x - 1, y + 1));
+                let (se, sw) = (luma(x + 1, y - 1), luma(x - 1, y - 1));
+                let horizontal = 2.0 * (n + s - 2.0 * m).abs()
+                    + (ne + se - 2.0 * e).abs()
+                    + (nw + sw - 2.0 * w).abs();
+                let vertical = 2.0 * (e + w - 2.0 * m).abs()
+                    + (ne + nw - 2.0 * n).abs()
+                    + (se + sw - 2.0 * s).abs();
+                // blend towards the neighbour across the edge with the steeper gradient
+                let towards = |a: f32, b: f32| {
+                    if (a - m).abs() >= (b - m).abs() {
+                        1
+                    } else {
+                        -1
+                    }
+                };
+                let (dx, dy) = if horizontal >= vertical {
+                    (0, towards(n, s))
+                } else {
+                    (towards(e, w), 0)
+                };
+
+                let average = (2.0 * (n + s + e + w) + ne + nw + se + sw) / 12.0;
+                let subpixel = ((average - m).abs() / range).clamp(0.0, 1.0);
+                let subpixel = subpixel * subpixel * (3.0 - 2.0 * subpixel);
+                let blend = subpixel * subpixel * 0.75;
+                let color = lerp(
+                    input.get_clamped(x, y),
+                    input.get_clamped(x + dx, y + dy),
+                    blend,
+                );
+                output.set(x as u32, y as u32, color);
+            }
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+fn filled(width: u32, height: u32, color: HdrColor) -> Framebuffer {
+    let mut framebuffer = Framebuffer::new(width, height);
+    framebuffer.pixels.fill(color);
+    framebuffer
+}
+
+#[test]
+fn test_blur() {
+    let gray = HdrColor(0.5, 0.5, 0.5);
+    let blur = GaussianBlur { sigma: 1.5 };
+    let flat = blur.apply(&filled(8, 8, gray));
+    assert!(flat
+        .pixels
+        .iter()
+        .all(|p| (p.0 - 0.5).abs() < 1e-5 && (p.2 - 0.5).abs() < 1e-5));
+
+    let mut dot = Framebuffer::new(15, 15);
+    dot.set(7, 7, HdrColor(1.0, 1.0, 1.0));
+    let blurred = blur.apply(&dot);
+    let total: f32 = blurred.pixels.iter().map(|p| p.0).sum();
+    assert!((total - 1.0).abs() < 1e-3);
+    assert!(blurred.get(7, 7).0 < 1.0 && blurred.get(8, 7).0 > 0.0);
+}
+
+#[test]
+fn test_bloom_spreads_bright_pixels() {
+    let mut framebuffer = filled(9, 9, HdrColor(0.2, 0.2, 0.2));
+    framebuffer.set(4, 4, HdrColor(8.0, 8.0, 8.0));
+    let bloomed = Bloom::default().apply(&framebuffer);
+    assert!(bloomed.get(6, 4).0 > 0.2);
+    // pixels below the threshold do not glow on their own
+    let dim = Bloom::default().apply(&filled(4, 4, HdrColor(0.2, 0.2, 0.2)));
+    assert_eq!(dim.get(1, 1), HdrColor(0.2, 0.2, 0.2));
+}
+
+#[test]
+fn test_vignette() {
+    let white = HdrColor(1.0, 1.0, 1.0);
+    let output = Vignette { strength: 0.8 }.apply(&filled(16, 16, white));
+    assert!(output.get(8, 8).0 > 0.99);
+    assert!(output.get(0, 0).0 < 0.3);
+}
+
+#[test]
+fn test_fxaa_softens_edges_only() {
+    let white = HdrColor(1.0, 1.0, 1.0);
+    let mut staircase = Framebuffer::new(8, 8);
+    for y in 0..8 {
+        for x in 0..y {
+            staircase.set(x, y, white);
+        }
+    }
+    let output = PassList::new().with(Fxaa::default()).apply(&staircase);
+    assert!(output.pixels.iter().any(|p| p.0 > 0.05 && p.0 < 0.95));
+    // far from the edge nothing changes
+    assert_eq!(output.get(0, 7), white);
+    assert_eq!(output.get(7, 0), HdrColor::default());
+
+    let flat = filled(4, 4, white);
+    assert_eq!(Fxaa::default().apply(&flat), flat);
+}
+
+/// Camera focus for [`DepthOfField::from_depth`].
+///
+/// Distances are measured in depth units from the `z = 1` plane like [`crate::fog::Fog`].
+#[derive(Clone, Copy, Debug)]
+pub struct Focus {
+    /// Distance that stays sharp.
+    pub focal_distance: f64,
+    /// Blur radius in pixels per unit of distance away from the focal plane.
+    pub aperture: f64,
+    /// Largest blur radius in pixels, which is also what the background gets.
+    pub max_radius: u32,
+}
+
+impl Default for Focus {
+    fn default() -> Self {
+        Focus {
+            focal_distance: 1.0,
+            aperture: 8.0,
+            max_radius: 8,
+        }
+    }
+}
+
+/// Depth of field: blurs every pixel over a disc the size of its circle of confusion,
+/// worked out from the depth buffer the image was rendered with.
+pub struct DepthOfField {
+    width: u32,
+    /// Circle of confusion radius of every pixel.
+    radius: Vec<f32>,
+    /// Distance of every pixel, infinite for the background.
+    distance: Vec<f64>,
+    max_radius: u32,
+}
+
+impl DepthOfField {
+    /// Takes `depth` as returned by [`crate::drawable::Image::depth_buffer`] for an image
+    /// `width` pixels wide.
+    pub fn from_depth(width: u32, depth: &[f64], focus: &Focus) -> Self {
+        let distance: Vec<f64> = depth.iter().map(|z| 1.0 - z).collect();
+        let radius = distance
+            .iter()
+            .map(|d| {
+                let radius = focus.aperture * (d - focus.focal_distance).abs();
+                radius.min(focus.max_radius as f64) as f32
+            })
+            .collect();
+        DepthOfField {
+            width,
+            radius,
+            distance,
+            max_radius: focus.max_radius,
+        }
+    }
+
+    pub fn radius(&self, x: u32, y: u32) -> f32 {
+        self.radius[(y * self.width + x) as usize]
+    }
+}
+
+impl PostProcess for DepthOfField {
+    fn apply(&self, input: &Framebuffer) -> Framebuffer {
+        let (width, height) = (input.width(), input.height());
+        let reach = self.max_radius as i64;
+        let mut output = input.clone();
+        for y in 0..height as i64 {
+            for x in 0..width as i64 {
+                let center = (y * width as i64 + x) as usize;
+                let mut sum = HdrColor::default();
+                let mut total = 0.0;
+                // gather the neighbours whose blur discs cover this pixel, where anything
+                // behind it cannot spread further than this pixel's own blur
+                for dy in -reach..=reach {
+                    for dx in -reach..=reach {
+                        let (sx, sy) = (x + dx, y + dy);
+                        if sx < 0 || sy < 0 || sx >= width as i64 || sy >= height as i64 {
+                            continue;
+                        }
+                        let sample = (sy * width as i64 + sx) as usize;
+                        let mut radius = self.radius[sample];
+                        if self.distance[sample] > self.distance[center] {
+                            radius = radius.min(self.radius[center]);
+                        }
+                        let radius = radius.max(0.5);
+                        if (dx * dx + dy * dy) as f32 > radius * radius {
+                            continue;
+                        }
+                        // spread each sample evenly over its disc
+                        let weight = 1.0 / (radius * radius);
+                        sum = add(sum, input.pixels[sample].scale(weight as f64));
+                        total += weight;
+                    }
+                }
+                output.set(x as u32, y as u32, sum.scale(1.0 / total as f64));
+            }
+        }
+        output
+    }
+}
+
+/// Parameters for [`Outline::detect`].
+#[derive(Clone, Copy, Debug)]
+pub struct OutlineStyle {
+    pub color: Color,
+    /// Line width in pixels.
+    pub thickness: u32,
+    /// Depth difference between neighbours that counts as an edge.
+    pub depth_threshold: f64,
+    /// `1 - cos` of the angle between neighbouring normals that counts as a crease.
+    pub normal_threshold: f64,
+}
+
+impl Default for OutlineStyle {
+    fn default() -> Self {
+        OutlineStyle {
+            color: Color(0, 0, 0),
+            thickness: 1,
+            depth_threshold: 0.05,
+            normal_threshold: 0.25,
+        }
+    }
+}
+
+/// Toon-style outlines along silhouettes, depth discontinuities and normal creases found
+/// in a [`GBuffer`], painted over the image as a post-process.
+pub struct Outline {
+    width: u32,
+    mask: Vec<bool>,
+    color: HdrColor,
+}
+
+impl Outline {
+    pub fn detect(gbuffer: &GBuffer, style: &OutlineStyle) -> Self {
+        let (width, height) = (gbuffer.width(), gbuffer.height());
+        let idx = |x: u32, y: u32| (y * width + x) as usize;
+        let mut edges = vec![false; (width * height) as usize];
+        for y in 0..height {
+            for x in 0..width {
+                let neighbours = [(x + 1, y), (x, y + 1)];
+                for (nx, ny) in neighbours {
+                    if nx >= width || ny >= height {
+                        continue;
+                    }
+                    let (a, b) = (idx(x, y), idx(nx, ny));
+                    let edge = match (gbuffer.is_covered(x, y), gbuffer.is_covered(nx, ny)) {
+                        (false, false) => false,
+                        (true, true) => {
+                            let depth = (gbuffer.depth[a] - gbuffer.depth[b]).abs();
+                            let crease = 1.0 - math::dot(&gbuffer.normal[a], &gbuffer.normal[b]);
+                            depth > style.depth_threshold || crease > style.normal_threshold
+                        }
+                        _ => true,
+                    };
+                    // the line goes on the nearer surface
+                    if edge {
+                        let nearer = if gbuffer.depth[a] >= gbuffer.depth[b] {
+                            a
+                        } else {
+                            b
+                        };
+                        edges[nearer] = true;
+                    }
+                }
+            }
+        }
+
+        let reach = style.thickness.saturating_sub(1) as i64 / 2;
+        let extra = style.thickness.saturating_sub(1) as i64 % 2;
+        let mut mask = vec![false; edges.len()];
+        for y in 0..height as i64 {
+            for x in 0..width as i64 {
+                if !edges[idx(x as u32, y as u32)] {
+                    continue;
+                }
+                for dy in -reach..=reach + extra {
+                    for dx in -reach..=reach + extra {
+                        let (mx, my) = (x + dx, y + dy);
+                        if mx >= 0 && my >= 0 && mx < width as i64 && my < height as i64 {
+                            mask[idx(mx as u32, my as u32)] = true;
+                        }
+                    }
+                }
+            }
+        }
+        Outline {
+            width,
+            mask,
+            color: style.color.into(),
+        }
+    }
+
+    pub fn is_edge(&self, x: u32, y: u32) -> bool {
+        self.mask[(y * self.width + x) as usize]
+    }
+}
+
+impl PostProcess for Outline {
+    fn apply(&self, input: &Framebuffer) -> Framebuffer {
+        let mut output = input.clone();
+        for (pixel, &edge) in output.pixels.iter_mut().zip(&self.mask) {
+            if edge {
+                *pixel = self.color;
+            }
+        }
+        output
+    }
+}
+
+#[test]
+fn test_depth_of_field() {
+    // a sharp vertical edge, half in focus on the left and far away on the right
+    let mut framebuffer = Framebuffer::new(24, 8);
+    let mut depth = vec![0.0; 24 * 8];
+    for y in 0..8 {
+        for x in 0..24 {
+            if x % 2 == 0 {
+                framebuffer.set(x, y, HdrColor(1.0, 1.0, 1.0));
+            }
+            if x >= 12 {
+                depth[(y * 24 + x) as usize] = -1.0;
+            }
+        }
+    }
+    let dof = DepthOfField::from_depth(24, &depth, &Focus::default());
+    assert_eq!(dof.radius(4, 4), 0.0);
+    assert_eq!(dof.radius(18, 4), 8.0);
+
+    let output = dof.apply(&framebuffer);
+    // the stripes in focus stay sharp, the ones out of focus blur to gray
+    assert_eq!(output.get(4, 4), HdrColor(1.0, 1.0, 1.0));
+    assert_eq!(output.get(5, 4), HdrColor::default());
+    assert!((output.get(20, 4).0 - 0.5).abs() < 0.1);
+}
+
+#[test]
+fn test_outline() {
+    let mut mesh = crate::geometry::plane(1.0, 1.0, 1);
+    mesh.transform(&crate::math::Mat4::rotation_x(std::f64::consts::FRAC_PI_2));
+    let mut gbuffer = GBuffer::new(32, 32);
+    gbuffer.draw_mesh(&mesh, &crate::math::Mat4::identity(), None);
+
+    let outline = Outline::detect(&gbuffer, &OutlineStyle::default());
+    // the silhouette is outlined, the flat interior and the background are not
+    assert!(outline.is_edge(8, 16));
+    assert!(!outline.is_edge(7, 16));
+    assert!(!outline.is_edge(16, 16));
+
+    let thick = Outline::detect(
+        &gbuffer,
+        &OutlineStyle {
+            thickness: 3,
+            ..Default::default()
+        },
+    );
+    assert!(thick.is_edge(7, 16) && thick.is_edge(9, 16) && !thick.is_edge(10, 16));
+
+    let white = filled(32, 32, HdrColor(1.0, 1.0, 1.0));
+    let output = outline.apply(&white);
+    assert_eq!(output.get(8, 16), HdrColor::default());
+    assert_eq!(output.get(16, 16), HdrColor(1.0, 1.0, 1.0));
+}
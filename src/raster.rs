@@ -0,0 +1,967 @@
+//! Rasterization core that needs neither `std` nor an allocator, for driving small
+//! displays from embedded targets: barycentric triangle fill and line drawing into
+//! caller-owned pixel and depth slices.
+//!
+//! Pixel `(x, y)` is element `y * width + x`, so with the usual top-down display memory
+//! `y` grows downwards.
+
+/// Barycentric coordinates of `p` with respect to the triangle `p1`, `p2`, `p3`, or `None`
+/// for a degenerate triangle, which has none.
+pub fn barycentric(
+    p1: [f64; 2],
+    p2: [f64; 2],
+    p3: [f64; 2],
+    p: [f64; 2],
+) -> Option<(f64, f64, f64)> {
+    if is_degenerate(p1, p2, p3) {
+        return None;
+    }
+    let denom = (p1[0] - p3[0]) * (p2[1] - p3[1]) - (p1[1] - p3[1]) * (p2[0] - p3[0]);
+    let lambda1 = ((p[0] - p3[0]) * (p2[1] - p3[1]) + (p3[0] - p2[0]) * (p[1] - p3[1])) / denom;
+    let lambda2 = ((p3[0] - p[0]) * (p1[1] - p3[1]) + (p3[0] - p1[0]) * (p3[1] - p[1])) / denom;
+    Some((lambda1, lambda2, 1.0 - lambda1 - lambda2))
+}
+
+/// Smallest area in square pixels, doubled, of a triangle that is rasterized.
+const MIN_AREA: f64 = 1e-10;
+
+/// Whether the triangle `p1`, `p2`, `p3` is too thin to cover any pixel, with collinear or
+/// repeated corners, or has corners that are not finite. Interpolating across such a
+/// triangle divides by its vanishing area.
+pub fn is_degenerate(p1: [f64; 2], p2: [f64; 2], p3: [f64; 2]) -> bool {
+    let area = edge_function(p1, p2, p3);
+    !area.is_finite() || area.abs() < MIN_AREA
+}
+
+/// Twice the signed area of the triangle `a`, `b`, `p`, positive when `p` lies to the
+/// right of the edge from `a` to `b` with `y` growing downwards.
+fn edge_function(a: [f64; 2], b: [f64; 2], p: [f64; 2]) -> f64 {
+    (b[0] - a[0]) * (p[1] - a[1]) - (b[1] - a[1]) * (p[0] - a[0])
+}
+
+/// [`edge_function`] of points in fixed point, in square steps.
+fn fixed_edge_function(a: [i64; 2], b: [i64; 2], p: [i64; 2]) -> i64 {
+    (b[0] - a[0]) * (p[1] - a[1]) - (b[1] - a[1]) * (p[0] - a[0])
+}
+
+/// `value` pixels in steps of [`Precision::Fixed`], rounded to the nearest, or `None` past
+/// [`FIXED_LIMIT`].
+fn to_fixed(value: f64) -> Option<i64> {
+    if value.is_nan() || value.abs() >= FIXED_LIMIT {
+        return None;
+    }
+    let steps = value * FIXED_ONE as f64;
+    // truncating rounds towards zero, so half a step away from it rounds to the nearest
+    Some((steps + if steps < 0.0 { -0.5 } else { 0.5 }) as i64)
+}
+
+/// Whether pixels exactly on the edge from `a` to `b` of a triangle with a positive area
+/// belong to it: the top-left fill rule, under which a pixel on an edge shared by two
+/// triangles is drawn by exactly one of them.
+fn is_top_left(a: [f64; 2], b: [f64; 2]) -> bool {
+    let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+    dy < 0.0 || (dy == 0.0 && dx > 0.0)
+}
+
+/// [`is_top_left`] of an edge in fixed point.
+fn fixed_is_top_left(a: [i64; 2], b: [i64; 2]) -> bool {
+    let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+    dy < 0 || (dy == 0 && dx > 0)
+}
+
+/// How the pixels of a triangle are found by [`for_each_triangle_pixel_by`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Traversal {
+    /// Test every pixel of the bounding box, which is fastest for small triangles.
+    #[default]
+    BoundingBox,
+    /// Test only the span between the edges on each row, which skips most of the
+    /// bounding box of long thin triangles.
+    Scanline,
+}
+
+/// How the corners of a triangle are held while finding the pixels it covers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Precision {
+    /// Doubles, as given.
+    #[default]
+    Float,
+    /// 24.8 fixed point, the corners snapped to the nearest 256th of a pixel, so the
+    /// same pixels are drawn on every platform. The pixels are found with integers
+    /// alone, stepping the edge functions from one pixel to the next, so doubles are
+    /// only touched to interpolate depth, colors and UVs across the covered ones.
+    Fixed,
+}
+
+/// Steps per pixel of [`Precision::Fixed`], 8 fractional bits.
+pub const FIXED_ONE: i64 = 256;
+
+/// Corners farther than this many pixels from the origin are kept as doubles with
+/// [`Precision::Fixed`], as the edge functions of the triangle would overflow 64 bits.
+const FIXED_LIMIT: f64 = (1 << 21) as f64;
+
+/// Calls `fragment` with the position, barycentric coordinates and interpolated depth of
+/// every pixel from `min` to `max` inclusive covered by the triangle `p1`, `p2`, `p3`,
+/// given as `[x, y, z]`.
+///
+/// Vertices keep their sub-pixel positions and pixel `(x, y)` is sampled at its center
+/// `(x + 0.5, y + 0.5)`, so slowly moving edges move smoothly instead of jumping a pixel
+/// at a time. Centers on the edges follow the top-left fill rule, so triangles sharing an
+/// edge never both draw a pixel on it.
+pub fn for_each_triangle_pixel<F: FnMut(u32, u32, (f64, f64, f64), f64)>(
+    min: (u32, u32),
+    max: (u32, u32),
+    p1: [f64; 3],
+    p2: [f64; 3],
+    p3: [f64; 3],
+    fragment: F,
+) {
+    for_each_triangle_pixel_by(Traversal::BoundingBox, min, max, p1, p2, p3, fragment);
+}
+
+/// Like [`for_each_triangle_pixel`], finding the pixels with `traversal`. Both visit the
+/// same pixels in the same order.
+pub fn for_each_triangle_pixel_by<F: FnMut(u32, u32, (f64, f64, f64), f64)>(
+    traversal: Traversal,
+    min: (u32, u32),
+    max: (u32, u32),
+    p1: [f64; 3],
+    p2: [f64; 3],
+    p3: [f64; 3],
+    fragment: F,
+) {
+    if let Some(setup) = TriangleSetup::new(p1, p2, p3) {
+        if let Some((min, max)) = setup.pixels_within(min, max) {
+            setup.for_each_pixel(traversal, min, max, fragment);
+        }
+    }
+}
+
+/// A triangle given as `[x, y, z]` corners, with what all of its pixels share worked
+/// out once: its signed area, which also divides the barycentric coordinates, and the
+/// edges walked in the winding the fill rule is defined for.
+#[derive(Clone, Copy, Debug)]
+pub struct TriangleSetup {
+    corners: [[f64; 3]; 3],
+    area: f64,
+    /// Whether the first two corners are swapped in `edges` to make the area positive.
+    swapped: bool,
+    edges: [([f64; 2], [f64; 2]); 3],
+    /// Which edges own the pixels exactly on them, see [`is_top_left`].
+    owned: [bool; 3],
+    /// `edges` in steps of [`Precision::Fixed`], if the triangle is held in fixed point.
+    fixed: Option<FixedEdges>,
+}
+
+/// Edges of a triangle held in fixed point, see [`TriangleSetup::new_fixed`].
+#[derive(Clone, Copy, Debug)]
+struct FixedEdges {
+    edges: [([i64; 2], [i64; 2]); 3],
+    /// Change of each edge function from one pixel to the next along a row.
+    steps: [i64; 3],
+    /// Twice the area in square steps, made positive like the edges.
+    area: i64,
+    /// Bounding box of the corners, outside of which no pixel is covered and edge
+    /// functions could overflow.
+    min: [i64; 2],
+    max: [i64; 2],
+}
+
+impl TriangleSetup {
+    /// `None` for a degenerate triangle, see [`is_degenerate`].
+    pub fn new(p1: [f64; 3], p2: [f64; 3], p3: [f64; 3]) -> Option<Self> {
+        let (mut a, mut b, c) = ([p1[0], p1[1]], [p2[0], p2[1]], [p3[0], p3[1]]);
+        let area = edge_function(a, b, c);
+        if !area.is_finite() || area.abs() < MIN_AREA {
+            return None;
+        }
+        // the fill rule is defined for one winding, so the other one is walked in reverse
+        let swapped = area < 0.0;
+        if swapped {
+            core::mem::swap(&mut a, &mut b);
+        }
+        let edges = [(b, c), (c, a), (a, b)];
+        Some(TriangleSetup {
+            corners: [p1, p2, p3],
+            area,
+            swapped,
+            edges,
+            owned: edges.map(|(from, to)| is_top_left(from, to)),
+            fixed: None,
+        })
+    }
+
+    /// Like [`TriangleSetup::new`], with the corners snapped to [`Precision::Fixed`] and
+    /// the area, edges and fill rule worked out from them with integers. `None` also for
+    /// triangles that snapping leaves without area; triangles reaching more than 2^21
+    /// pixels from the origin are held in doubles instead.
+    pub fn new_fixed(p1: [f64; 3], p2: [f64; 3], p3: [f64; 3]) -> Option<Self> {
+        let snap = |p: [f64; 3]| Some([to_fixed(p[0])?, to_fixed(p[1])?]);
+        let (Some(f1), Some(f2), Some(f3)) = (snap(p1), snap(p2), snap(p3)) else {
+            return TriangleSetup::new(p1, p2, p3);
+        };
+        let (mut a, mut b, c) = (f1, f2, f3);
+        let area = fixed_edge_function(a, b, c);
+        if area == 0 {
+            return None;
+        }
+        let swapped = area < 0;
+        if swapped {
+            core::mem::swap(&mut a, &mut b);
+        }
+        let fixed_edges = [(b, c), (c, a), (a, b)];
+        // snapped corners are exact as doubles, which the bounding box and interpolation
+        // are worked out from
+        let to_point =
+            |[x, y]: [i64; 2]| [x as f64 / FIXED_ONE as f64, y as f64 / FIXED_ONE as f64];
+        let corners = [f1, f2, f3];
+        let min = [0, 1].map(|axis| corners.iter().map(|p| p[axis]).min().unwrap_or(0));
+        let max = [0, 1].map(|axis| corners.iter().map(|p| p[axis]).max().unwrap_or(0));
+        Some(TriangleSetup {
+            corners: [(f1, p1), (f2, p2), (f3, p3)].map(|(f, p)| {
+                let [x, y] = to_point(f);
+                [x, y, p[2]]
+            }),
+            area: area as f64 / (FIXED_ONE * FIXED_ONE) as f64,
+            swapped,
+            edges: fixed_edges.map(|(from, to)| (to_point(from), to_point(to))),
+            owned: fixed_edges.map(|(from, to)| fixed_is_top_left(from, to)),
+            fixed: Some(FixedEdges {
+                edges: fixed_edges,
+                steps: fixed_edges.map(|(from, to)| (from[1] - to[1]) * FIXED_ONE),
+                area: area.abs(),
+                min,
+                max,
+            }),
+        })
+    }
+
+    /// [`TriangleSetup::new`] or [`TriangleSetup::new_fixed`], as `precision` says.
+    pub fn with_precision(
+        precision: Precision,
+        p1: [f64; 3],
+        p2: [f64; 3],
+        p3: [f64; 3],
+    ) -> Option<Self> {
+        match precision {
+            Precision::Float => TriangleSetup::new(p1, p2, p3),
+            Precision::Fixed => TriangleSetup::new_fixed(p1, p2, p3),
+        }
+    }
+
+    /// Whether the triangle is held in fixed point, see [`TriangleSetup::new_fixed`].
+    pub fn is_fixed(&self) -> bool {
+        self.fixed.is_some()
+    }
+
+    pub fn corners(&self) -> [[f64; 3]; 3] {
+        self.corners
+    }
+
+    /// Twice the signed area, positive when the corners go clockwise with `y` growing
+    /// downwards, or counter-clockwise with `y` growing upwards.
+    pub fn signed_area(&self) -> f64 {
+        self.area
+    }
+
+    /// The pixels from `min` to `max` inclusive whose centers may be covered, or `None` if
+    /// the bounding box of the triangle misses all of them.
+    pub fn pixels_within(
+        &self,
+        min: (u32, u32),
+        max: (u32, u32),
+    ) -> Option<((u32, u32), (u32, u32))> {
+        let [p1, p2, p3] = self.corners;
+        let range = |axis: usize, min: u32, max: u32| {
+            let low = p1[axis].min(p2[axis]).min(p3[axis]) - 0.5;
+            let high = p1[axis].max(p2[axis]).max(p3[axis]) - 0.5;
+            if min > max || high < min as f64 || low > max as f64 {
+                return None;
+            }
+            // truncating the low end rounds it down, leaving a pixel of slack at most
+            Some(((low.max(min as f64) as u32), (high.min(max as f64) as u32)))
+        };
+        let (x0, x1) = range(0, min.0, max.0)?;
+        let (y0, y1) = range(1, min.1, max.1)?;
+        Some(((x0, y0), (x1, y1)))
+    }
+
+    /// Calls `fragment` like [`for_each_triangle_pixel_by`] for the covered pixels from
+    /// `min` to `max` inclusive.
+    pub fn for_each_pixel<F: FnMut(u32, u32, (f64, f64, f64), f64)>(
+        &self,
+        traversal: Traversal,
+        min: (u32, u32),
+        max: (u32, u32),
+        mut fragment: F,
+    ) {
+        if self.is_subpixel() {
+            // at most one pixel center to test, found without walking the bounding box
+            let Some((x, y)) = self.subpixel_center() else {
+                return;
+            };
+            if (min.0..=max.0).contains(&x) && (min.1..=max.1).contains(&y) {
+                if let Some((bary, z)) = self.sample(x, y) {
+                    fragment(x, y, bary, z);
+                }
+            }
+            return;
+        }
+        if let Some(fixed) = &self.fixed {
+            return self.for_each_fixed_pixel(fixed, traversal, min, max, fragment);
+        }
+        let (b, c, a) = (self.edges[0].0, self.edges[1].0, self.edges[2].0);
+        for y in min.1..=max.1 {
+            let (start, end) = match traversal {
+                Traversal::BoundingBox => (min.0, max.0),
+                Traversal::Scanline => {
+                    let Some((left, right)) = row_span([a, b, c], y as f64 + 0.5) else {
+                        continue;
+                    };
+                    // a pixel of slack either side, the edge functions having the last word
+                    let start = ((left - 0.5) as i64 - 1).max(min.0 as i64);
+                    let end = ((right - 0.5) as i64 + 1).min(max.0 as i64);
+                    if start > end {
+                        continue;
+                    }
+                    (start as u32, end as u32)
+                }
+            };
+            for x in start..=end {
+                if let Some((bary, z)) = self.sample(x, y) {
+                    fragment(x, y, bary, z);
+                }
+            }
+        }
+    }
+
+    /// [`TriangleSetup::for_each_pixel`] of a triangle held in fixed point, which works
+    /// out the edge functions at the first pixel of each row and steps them along it.
+    fn for_each_fixed_pixel<F: FnMut(u32, u32, (f64, f64, f64), f64)>(
+        &self,
+        fixed: &FixedEdges,
+        traversal: Traversal,
+        min: (u32, u32),
+        max: (u32, u32),
+        mut fragment: F,
+    ) {
+        let center = |pixel: u32| pixel as i64 * FIXED_ONE + FIXED_ONE / 2;
+        let (b, c, a) = (fixed.edges[0].0, fixed.edges[1].0, fixed.edges[2].0);
+        for y in min.1..=max.1 {
+            let (start, end) = match traversal {
+                Traversal::BoundingBox => (min.0, max.0),
+                Traversal::Scanline => {
+                    let Some((left, right)) = fixed_row_span([a, b, c], center(y)) else {
+                        continue;
+                    };
+                    // a pixel of slack either side, the edge functions having the last word
+                    let start =
+                        ((left - FIXED_ONE / 2).div_euclid(FIXED_ONE) - 1).max(min.0 as i64);
+                    let end = ((right - FIXED_ONE / 2).div_euclid(FIXED_ONE) + 1).min(max.0 as i64);
+                    if start > end {
+                        continue;
+                    }
+                    (start as u32, end as u32)
+                }
+            };
+            let p = [center(start), center(y)];
+            let mut weights = fixed
+                .edges
+                .map(|(from, to)| fixed_edge_function(from, to, p));
+            for x in start..=end {
+                if self.is_fixed_covered(weights) {
+                    let (bary, z) = self.interpolate(weights.map(|w| w as f64 / fixed.area as f64));
+                    fragment(x, y, bary, z);
+                }
+                for (weight, step) in weights.iter_mut().zip(fixed.steps) {
+                    *weight += step;
+                }
+            }
+        }
+    }
+
+    /// The barycentric coordinates and interpolated depth at the center of pixel
+    /// `(x, y)`, if the triangle covers it.
+    #[inline]
+    pub fn sample(&self, x: u32, y: u32) -> Option<((f64, f64, f64), f64)> {
+        let weights = match &self.fixed {
+            Some(fixed) => {
+                let p = [x, y].map(|c| c as i64 * FIXED_ONE + FIXED_ONE / 2);
+                if (0..2).any(|axis| p[axis] < fixed.min[axis] || p[axis] > fixed.max[axis]) {
+                    return None;
+                }
+                let weights = fixed
+                    .edges
+                    .map(|(from, to)| fixed_edge_function(from, to, p));
+                if !self.is_fixed_covered(weights) {
+                    return None;
+                }
+                weights.map(|w| w as f64 / fixed.area as f64)
+            }
+            None => {
+                let p = [x as f64 + 0.5, y as f64 + 0.5];
+                let weights = self.edges.map(|(from, to)| edge_function(from, to, p));
+                let covered = weights
+                    .iter()
+                    .zip(self.owned)
+                    .all(|(&w, owned)| w > 0.0 || (w == 0.0 && owned));
+                if !covered {
+                    return None;
+                }
+                weights.map(|w| w / self.area.abs())
+            }
+        };
+        Some(self.interpolate(weights))
+    }
+
+    /// Whether a pixel with the fixed point edge functions `weights` is covered.
+    #[inline]
+    fn is_fixed_covered(&self, weights: [i64; 3]) -> bool {
+        weights
+            .iter()
+            .zip(self.owned)
+            .all(|(&w, owned)| w > 0 || (w == 0 && owned))
+    }
+
+    /// The barycentric coordinates and depth of a pixel with the edge functions
+    /// `weights`, divided by the area.
+    #[inline]
+    fn interpolate(&self, [wa, wb, wc]: [f64; 3]) -> ((f64, f64, f64), f64) {
+        let [p1, p2, p3] = self.corners;
+        let (l1, l2) = if self.swapped { (wb, wa) } else { (wa, wb) };
+        ((l1, l2, wc), l1 * p1[2] + l2 * p2[2] + wc * p3[2])
+    }
+
+    /// Whether the bounding box of the triangle is less than a pixel across both ways,
+    /// so that it holds one pixel center at most.
+    pub fn is_subpixel(&self) -> bool {
+        let [p1, p2, p3] = self.corners;
+        let extent = |axis: usize| {
+            p1[axis].max(p2[axis]).max(p3[axis]) - p1[axis].min(p2[axis]).min(p3[axis])
+        };
+        extent(0) < 1.0 && extent(1) < 1.0
+    }
+
+    /// The pixel whose center lies in the bounding box of a triangle less than a pixel
+    /// across, if any; only it may be covered.
+    fn subpixel_center(&self) -> Option<(u32, u32)> {
+        let [p1, p2, p3] = self.corners;
+        let center = |axis: usize| {
+            let low = p1[axis].min(p2[axis]).min(p3[axis]);
+            let high = p1[axis].max(p2[axis]).max(p3[axis]);
+            // the first center at or past the low end, rounding up without `f64::ceil`,
+            // which needs `std`
+            let first = (low - 0.5).max(0.0);
+            if first.is_nan() || first > u32::MAX as f64 {
+                return None;
+            }
+            let pixel = first as u32 + ((first as u32 as f64) < first) as u32;
+            (pixel as f64 + 0.5 <= high).then_some(pixel)
+        };
+        Some((center(0)?, center(1)?))
+    }
+}
+
+/// Leftmost and rightmost points where the edges of a triangle cross the row at `y`, or
+/// `None` if none does.
+fn row_span(corners: [[f64; 2]; 3], y: f64) -> Option<(f64, f64)> {
+    let mut span: Option<(f64, f64)> = None;
+    for k in 0..3 {
+        let (from, to) = (corners[k], corners[(k + 1) % 3]);
+        if (from[1] <= y) == (to[1] <= y) {
+            continue;
+        }
+        let x = from[0] + (y - from[1]) * (to[0] - from[0]) / (to[1] - from[1]);
+        span = Some(span.map_or((x, x), |(left, right)| (left.min(x), right.max(x))));
+    }
+    span
+}
+
+/// [`row_span`] of a triangle in fixed point, the crossings truncated to whole steps.
+fn fixed_row_span(corners: [[i64; 2]; 3], y: i64) -> Option<(i64, i64)> {
+    let mut span: Option<(i64, i64)> = None;
+    for k in 0..3 {
+        let (from, to) = (corners[k], corners[(k + 1) % 3]);
+        if (from[1] <= y) == (to[1] <= y) {
+            continue;
+        }
+        let x = from[0] + (y - from[1]) * (to[0] - from[0]) / (to[1] - from[1]);
+        span = Some(span.map_or((x, x), |(left, right)| (left.min(x), right.max(x))));
+    }
+    span
+}
+
+/// Walks the pixels of the line from `(x0, y0)` to `(x1, y1)` with Bresenham's algorithm,
+/// passing each pixel along with its parameter `t` in [0, 1] measured from the start point.
+pub fn for_each_line_pixel<F: FnMut(u32, u32, f64)>(
+    mut x0: u32,
+    mut y0: u32,
+    mut x1: u32,
+    mut y1: u32,
+    mut plot: F,
+) {
+    let steep;
+    if x0.abs_diff(x1) < y0.abs_diff(y1) {
+        steep = true;
+        core::mem::swap(&mut x0, &mut y0);
+        core::mem::swap(&mut x1, &mut y1);
+    } else {
+        steep = false;
+    }
+
+    let swapped = x0 > x1;
+    if swapped {
+        core::mem::swap(&mut x0, &mut x1);
+        core::mem::swap(&mut y0, &mut y1);
+    }
+
+    let dx = (x1 - x0) as i32;
+    let dy = y1 as i32 - y0 as i32;
+
+    let derror2 = dy.abs() * 2;
+    let mut error2 = 0;
+    let mut y = y0 as i32;
+    for x in x0..=x1 {
+        let t = if dx == 0 {
+            0.0
+        } else {
+            (x - x0) as f64 / dx as f64
+        };
+        let t = if swapped { 1.0 - t } else { t };
+        if steep {
+            plot(y as u32, x, t);
+        } else {
+            plot(x, y as u32, t);
+        }
+        error2 += derror2;
+        if error2 > dx {
+            y += dy.signum();
+            error2 -= dx * 2;
+        }
+    }
+}
+
+/// Framebuffer over borrowed pixels of any type, such as RGB565 words for an LCD, with an
+/// optional borrowed depth buffer where larger values are nearer.
+pub struct SliceBuffer<'a, P> {
+    pixels: &'a mut [P],
+    depth: Option<&'a mut [f32]>,
+    width: u32,
+    height: u32,
+    precision: Precision,
+}
+
+impl<'a, P: Copy> SliceBuffer<'a, P> {
+    /// Returns `None` if `pixels` holds fewer than `width * height` elements.
+    pub fn new(pixels: &'a mut [P], width: u32, height: u32) -> Option<Self> {
+        (pixels.len() >= (width * height) as usize).then_some(SliceBuffer {
+            pixels,
+            depth: None,
+            width,
+            height,
+            precision: Precision::Float,
+        })
+    }
+
+    /// Depth-tests triangles and lines against `depth`, which is cleared to the farthest
+    /// value. Returns `None` if it is too small.
+    pub fn with_depth(mut self, depth: &'a mut [f32]) -> Option<Self> {
+        if depth.len() < (self.width * self.height) as usize {
+            return None;
+        }
+        depth.fill(f32::NEG_INFINITY);
+        self.depth = Some(depth);
+        Some(self)
+    }
+
+    /// Finds the pixels of triangles with corners held in `precision`, see [`Precision`].
+    pub fn with_precision(mut self, precision: Precision) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Fills every pixel with `color` and resets the depth buffer.
+    pub fn clear(&mut self, color: P) {
+        self.pixels.fill(color);
+        if let Some(depth) = self.depth.as_deref_mut() {
+            depth.fill(f32::NEG_INFINITY);
+        }
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> P {
+        self.pixels[(y * self.width + x) as usize]
+    }
+
+    /// Sets a pixel, ignoring coordinates outside the buffer.
+    pub fn set(&mut self, x: u32, y: u32, color: P) {
+        if x < self.width && y < self.height {
+            self.pixels[(y * self.width + x) as usize] = color;
+        }
+    }
+
+    fn depth_test(&mut self, x: u32, y: u32, z: f64) -> bool {
+        match self.depth.as_deref_mut() {
+            Some(depth) => {
+                let stored = &mut depth[(y * self.width + x) as usize];
+                let z = z as f32;
+                if *stored < z {
+                    *stored = z;
+                    true
+                } else {
+                    false
+                }
+            }
+            None => true,
+        }
+    }
+
+    pub fn line(&mut self, x0: u32, y0: u32, x1: u32, y1: u32, color: P) {
+        for_each_line_pixel(x0, y0, x1, y1, |x, y, _| self.set(x, y, color));
+    }
+
+    /// Fills the triangle with corners given as `[x, y, z]` in pixels.
+    pub fn triangle(&mut self, p1: [f64; 3], p2: [f64; 3], p3: [f64; 3], color: P) {
+        self.triangle_shaded(p1, p2, p3, |_| color);
+    }
+
+    /// Fills the triangle, asking `shade` for the color of every covered pixel given its
+    /// barycentric coordinates.
+    pub fn triangle_shaded<F: FnMut((f64, f64, f64)) -> P>(
+        &mut self,
+        p1: [f64; 3],
+        p2: [f64; 3],
+        p3: [f64; 3],
+        mut shade: F,
+    ) {
+        if self.width == 0 || self.height == 0 {
+            return;
+        }
+        let Some(setup) = TriangleSetup::with_precision(self.precision, p1, p2, p3) else {
+            return;
+        };
+        let Some((min, max)) = setup.pixels_within((0, 0), (self.width - 1, self.height - 1))
+        else {
+            return;
+        };
+        setup.for_each_pixel(Traversal::BoundingBox, min, max, |x, y, bary, z| {
+            if self.depth_test(x, y, z) {
+                let color = shade(bary);
+                self.pixels[(y * self.width + x) as usize] = color;
+            }
+        });
+    }
+}
+
+#[test]
+fn test_barycentric() {
+    let (p1, p2, p3) = ([5., 5.], [10., 5.], [10., 7.]);
+
+    fn close_enough(res: (f64, f64, f64), ref_vals: (f64, f64, f64)) -> bool {
+        const EPS: f64 = 1e-6;
+        (res.0 - ref_vals.0).abs() <= EPS
+            && (res.1 - ref_vals.1).abs() <= EPS
+            && (res.2 - ref_vals.2).abs() <= EPS
+    }
+    let bary = |p| barycentric(p1, p2, p3, p).unwrap();
+    assert!(close_enough(bary(p1), (1.0, 0.0, 0.0)));
+    assert!(close_enough(bary(p2), (0.0, 1.0, 0.0)));
+    assert!(close_enough(bary(p3), (0.0, 0.0, 1.0)));
+
+    let (a, b, c) = bary([100., 100.]);
+    assert!([a, b, c].iter().any(|&x| x < 0.0));
+
+    // collinear and repeated corners have no barycentric coordinates
+    assert_eq!(barycentric(p1, p2, [15., 5.], p1), None);
+    assert_eq!(barycentric(p1, p1, p3, p1), None);
+    assert_eq!(barycentric(p1, p2, [f64::NAN, 0.], p1), None);
+}
+
+#[test]
+fn test_degenerate_triangles() {
+    let triangles = [
+        // collinear
+        [[0., 0., 0.], [4., 4., 1.], [8., 8., 2.]],
+        // a repeated corner
+        [[0., 0., 0.], [8., 0., 0.], [8., 0., 1.]],
+        // all corners the same
+        [[3., 3., 0.]; 3],
+        // thinner than can be told apart from a line
+        [[0., 0., 0.], [8., 0., 0.], [0., 1e-12, 0.]],
+    ];
+    for [p1, p2, p3] in triangles {
+        assert!(is_degenerate(
+            [p1[0], p1[1]],
+            [p2[0], p2[1]],
+            [p3[0], p3[1]]
+        ));
+        for_each_triangle_pixel((0, 0), (7, 7), p1, p2, p3, |x, y, bary, z| {
+            panic!("{} {} covered at {:?} {}", x, y, bary, z)
+        });
+    }
+    assert!(!is_degenerate([0., 0.], [8., 0.], [0., 0.01]));
+}
+
+/// Repeatable numbers in `[0, 1)` from a linear congruential generator.
+#[cfg(test)]
+fn random_numbers(mut seed: u32) -> impl FnMut() -> f64 {
+    move || {
+        seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+        (seed >> 8) as f64 / (1u32 << 24) as f64
+    }
+}
+
+#[cfg(test)]
+type Coverage = [[Option<((f64, f64, f64), f64)>; 64]; 64];
+
+/// Weights and depth of every pixel of a 64x64 target `draw` passes to its fragment
+/// callback, asserting none is passed twice.
+#[cfg(test)]
+fn coverage(draw: impl FnOnce(&mut dyn FnMut(u32, u32, (f64, f64, f64), f64))) -> Coverage {
+    let mut grid = [[None; 64]; 64];
+    draw(&mut |x, y, bary, z| {
+        let pixel = &mut grid[y as usize][x as usize];
+        assert!(pixel.replace((bary, z)).is_none(), "{} {}", x, y);
+    });
+    grid
+}
+
+#[test]
+fn test_subpixel_triangles() {
+    // tiny triangles scattered over a few pixels cover the same pixels with the fast
+    // path as when testing every pixel of the area
+    let mut random = random_numbers(12345);
+    let mut sampled = 0;
+    for _ in 0..2000 {
+        let origin = [random() * 6.0, random() * 6.0];
+        let mut corner = || {
+            [
+                origin[0] + random() * 0.9,
+                origin[1] + random() * 0.9,
+                random(),
+            ]
+        };
+        let Some(setup) = TriangleSetup::new(corner(), corner(), corner()) else {
+            continue;
+        };
+        assert!(setup.is_subpixel());
+        let mut fast = None;
+        setup.for_each_pixel(Traversal::BoundingBox, (0, 0), (7, 7), |x, y, bary, z| {
+            assert!(fast.replace((x, y, bary, z)).is_none());
+        });
+        let slow = (0..8)
+            .flat_map(|y| (0..8).map(move |x| (x, y)))
+            .find_map(|(x, y)| setup.sample(x, y).map(|(bary, z)| (x, y, bary, z)));
+        assert_eq!(fast, slow);
+        sampled += fast.is_some() as u32;
+    }
+    assert!(sampled > 100);
+    let setup = TriangleSetup::new([2.2, 2.2, 0.], [2.4, 2.2, 0.], [2.2, 2.4, 0.]).unwrap();
+    assert_eq!(setup.subpixel_center(), None);
+}
+
+#[test]
+fn test_fixed_point() {
+    let mut random = random_numbers(54321);
+    let pixels = |setup: &TriangleSetup, traversal: Traversal| {
+        coverage(|fragment| setup.for_each_pixel(traversal, (0, 0), (63, 63), fragment))
+    };
+    // with corners anywhere, fixed point covers the pixels doubles cover at the snapped
+    // corners, with the same weights and depths
+    let mut drawn = 0;
+    for _ in 0..500 {
+        let mut corner = || [random() * 70.0 - 3.0, random() * 70.0 - 3.0, random()];
+        let (p1, p2, p3) = (corner(), corner(), corner());
+        let Some(fixed) = TriangleSetup::new_fixed(p1, p2, p3) else {
+            continue;
+        };
+        assert!(fixed.is_fixed());
+        let [s1, s2, s3] = fixed.corners();
+        for (p, snapped) in [(p1, s1), (p2, s2), (p3, s3)] {
+            for axis in 0..2 {
+                assert!((p[axis] - snapped[axis]).abs() <= 0.5 / FIXED_ONE as f64);
+                assert_eq!(snapped[axis] * FIXED_ONE as f64 % 1.0, 0.0);
+            }
+            assert_eq!(p[2], snapped[2]);
+        }
+        let float = TriangleSetup::new(s1, s2, s3).unwrap();
+        for traversal in [Traversal::BoundingBox, Traversal::Scanline] {
+            let expected = pixels(&float, traversal);
+            assert_eq!(pixels(&fixed, traversal), expected);
+            drawn += expected.iter().flatten().any(Option::is_some) as u32;
+        }
+    }
+    assert!(drawn > 500);
+
+    // moving corners by less than half a step draws the same
+    let corners = [[10.25, 3.5, 0.], [50.75, 20.125, 1.], [4.0, 40.5, 0.5]];
+    let nudged = corners.map(|[x, y, z]| [x + 1e-4, y - 1e-4, z]);
+    let [p1, p2, p3] = corners;
+    let [n1, n2, n3] = nudged;
+    let setup = TriangleSetup::new_fixed(p1, p2, p3).unwrap();
+    assert_eq!(
+        pixels(&setup, Traversal::BoundingBox),
+        pixels(
+            &TriangleSetup::new_fixed(n1, n2, n3).unwrap(),
+            Traversal::BoundingBox
+        )
+    );
+
+    // snapping can leave a triangle without area, and ones too large for fixed point
+    // fall back to doubles
+    assert!(TriangleSetup::new([0., 0., 0.], [8., 0., 0.], [0., 1e-3, 0.]).is_some());
+    assert!(TriangleSetup::new_fixed([0., 0., 0.], [8., 0., 0.], [0., 1e-3, 0.]).is_none());
+    let huge = TriangleSetup::new_fixed([-1e7, 0., 0.], [1e7, 0., 0.], [0., 1e7, 0.]).unwrap();
+    assert!(!huge.is_fixed());
+    let covered = pixels(&huge, Traversal::BoundingBox)
+        .iter()
+        .flatten()
+        .flatten()
+        .count();
+    assert_eq!(covered, 64 * 64);
+
+    // a square split along its diagonal, corners between steps, is covered exactly once
+    let [a, b, c, d] = [
+        [0.3, 0.3, 0.],
+        [6.3, 0.3, 0.],
+        [6.3, 6.3, 0.],
+        [0.3, 6.3, 0.],
+    ];
+    let mut counts = [[0; 8]; 8];
+    for [p1, p2, p3] in [[a, b, c], [a, c, d]] {
+        let setup = TriangleSetup::with_precision(Precision::Fixed, p1, p2, p3).unwrap();
+        setup.for_each_pixel(Traversal::BoundingBox, (0, 0), (7, 7), |x, y, _, _| {
+            counts[y as usize][x as usize] += 1
+        });
+    }
+    for (y, row) in counts.iter().enumerate() {
+        for (x, &count) in row.iter().enumerate() {
+            assert_eq!(count, (x < 6 && y < 6) as i32, "{} {}", x, y);
+        }
+    }
+
+    let mut float_pixels = [0u8; 16 * 16];
+    let mut fixed_pixels = [0u8; 16 * 16];
+    let triangle = ([0.5, 0.5, 0.], [15.5, 4., 0.], [3., 15.5, 0.]);
+    for (pixels, precision) in [
+        (&mut float_pixels, Precision::Float),
+        (&mut fixed_pixels, Precision::Fixed),
+    ] {
+        let mut buffer = SliceBuffer::new(pixels, 16, 16)
+            .unwrap()
+            .with_precision(precision);
+        buffer.triangle(triangle.0, triangle.1, triangle.2, 1);
+    }
+    assert_eq!(float_pixels, fixed_pixels);
+}
+
+#[test]
+fn test_fill_rule() {
+    // two triangles splitting a square along its diagonal, in either winding, and a
+    // fan around its center cover every pixel inside exactly once
+    let corners = [[0., 0., 0.], [6., 0., 0.], [6., 6., 0.], [0., 6., 0.]];
+    let center = [3., 3., 0.];
+    let [a, b, c, d] = corners;
+    let fan: [_; 4] = core::array::from_fn(|i| [corners[i], corners[(i + 1) % 4], center]);
+    let meshes: [&[[[f64; 3]; 3]]; 3] = [&[[a, b, c], [a, c, d]], &[[a, c, b], [a, d, c]], &fan];
+    for triangles in meshes {
+        let mut counts = [[0; 7]; 7];
+        for &[p1, p2, p3] in triangles {
+            for_each_triangle_pixel((0, 0), (6, 6), p1, p2, p3, |x, y, _, _| {
+                counts[y as usize][x as usize] += 1;
+            });
+        }
+        // the square's own top and left edges are drawn, the bottom and right ones not
+        for (y, row) in counts.iter().enumerate() {
+            for (x, &count) in row.iter().enumerate() {
+                assert_eq!(count, (x < 6 && y < 6) as i32, "{} {}", x, y);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_pixel_centers() {
+    // an edge moving by a fraction of a pixel covers a column once it passes its center
+    let covered = |right: f64| {
+        let mut columns = 0;
+        for_each_triangle_pixel(
+            (0, 0),
+            (7, 7),
+            [0., 0., 0.],
+            [right, 0., 0.],
+            [right, 8., 0.],
+            |_, y, _, _| columns += (y == 0) as u32,
+        );
+        columns
+    };
+    assert_eq!(covered(7.4), 7);
+    assert_eq!(covered(7.6), 8);
+}
+
+#[test]
+fn test_triangle_setup() {
+    let setup = TriangleSetup::new([2., 2., 0.], [6., 2., 0.], [2., 5., 0.]).unwrap();
+    assert_eq!(setup.signed_area(), 12.0);
+    let flipped = TriangleSetup::new([2., 2., 0.], [2., 5., 0.], [6., 2., 0.]).unwrap();
+    assert_eq!(flipped.signed_area(), -12.0);
+
+    // only pixels whose centers the bounding box reaches are visited
+    assert_eq!(setup.pixels_within((0, 0), (7, 7)), Some(((1, 1), (5, 4))));
+    assert_eq!(setup.pixels_within((3, 0), (4, 7)), Some(((3, 1), (4, 4))));
+    // a bounding box entirely outside the target has nothing to visit
+    assert_eq!(setup.pixels_within((6, 0), (7, 7)), None);
+    assert_eq!(setup.pixels_within((0, 5), (7, 7)), None);
+    let outside = TriangleSetup::new([-9., 1., 0.], [-1., 1., 0.], [-1., 6., 0.]).unwrap();
+    assert_eq!(outside.pixels_within((0, 0), (7, 7)), None);
+}
+
+#[test]
+fn test_scanline_traversal() {
+    // both traversals cover the same pixels with the same weights, long thin triangles
+    // and ones with corners on pixel centers or past the edges of the target included
+    let triangles = [
+        [[0.5, 0.5, 0.], [63.5, 2., 1.], [0., 1.5, 0.5]],
+        [[1., 0., 0.], [3., 63., 0.], [2.2, 0., 0.]],
+        [[-10., 4., 0.], [70., 40., 1.], [30., 80., 0.]],
+        [[8.5, 8.5, 0.], [40.5, 8.5, 0.], [8.5, 40.5, 0.]],
+        [[40.5, 8.5, 0.], [8.5, 8.5, 0.], [8.5, 40.5, 0.]],
+    ];
+    for [p1, p2, p3] in triangles {
+        let by_box =
+            coverage(|fragment| for_each_triangle_pixel((0, 0), (63, 63), p1, p2, p3, fragment));
+        let by_scanline = coverage(|fragment| {
+            let traversal = Traversal::Scanline;
+            for_each_triangle_pixel_by(traversal, (0, 0), (63, 63), p1, p2, p3, fragment)
+        });
+        assert!(by_box.iter().flatten().any(Option::is_some));
+        assert_eq!(by_box, by_scanline);
+    }
+}
+
+#[test]
+fn test_slice_buffer() {
+    // RGB565 words as on a small LCD
+    let mut pixels = [0u16; 8 * 8];
+    let mut depth = [0.0f32; 8 * 8];
+    let mut buffer = SliceBuffer::new(&mut pixels, 8, 8)
+        .unwrap()
+        .with_depth(&mut depth)
+        .unwrap();
+    buffer.triangle([0., 0., 0.], [7., 0., 0.], [0., 7., 0.], 0xf800);
+    // a farther triangle over it is hidden
+    buffer.triangle([0., 0., -1.], [7., 0., -1.], [0., 7., -1.], 0x07e0);
+    buffer.line(0, 7, 7, 7, 0x001f);
+    assert_eq!(buffer.get(1, 1), 0xf800);
+    assert_eq!(buffer.get(7, 6), 0);
+    assert_eq!(buffer.get(4, 7), 0x001f);
+
+    assert!(SliceBuffer::new(&mut pixels, 9, 8).is_none());
+}
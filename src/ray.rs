@@ -0,0 +1,174 @@
+use crate::math::{self, Mat4, Vec3f};
+use crate::mesh::Mesh;
+
+/// Line of sight: the points `origin + direction * t` for `t` in `near..=far`, with a
+/// unit `direction`, so that `t` is a distance.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: Vec3f,
+    pub direction: Vec3f,
+    pub near: f64,
+    pub far: f64,
+    /// Whether triangles wound clockwise as seen along the ray are passed through, as
+    /// the rasterizer culls them.
+    pub cull_back_faces: bool,
+}
+
+/// Where a ray first meets a mesh.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hit {
+    /// Distance along the ray.
+    pub distance: f64,
+    pub point: Vec3f,
+    /// Index of the face within its mesh.
+    pub face: usize,
+    /// Weights of the corners of the face at the hit point.
+    pub barycentric: (f64, f64, f64),
+}
+
+impl Ray {
+    /// Half-line starting at `origin`.
+    pub fn new(origin: Vec3f, direction: Vec3f) -> Self {
+        Ray {
+            origin,
+            direction: direction.normalized(),
+            near: 0.0,
+            far: f64::INFINITY,
+            cull_back_faces: false,
+        }
+    }
+
+    pub fn at(&self, t: f64) -> Vec3f {
+        self.origin + self.direction * t
+    }
+
+    /// Distance to the triangle `a`, `b`, `c` and the weights of its corners where the
+    /// ray meets it, using the Möller-Trumbore test.
+    pub fn intersect_triangle(
+        &self,
+        a: &Vec3f,
+        b: &Vec3f,
+        c: &Vec3f,
+    ) -> Option<(f64, (f64, f64, f64))> {
+        let (ab, ac) = (*b - *a, *c - *a);
+        let p = math::cross(&self.direction, &ac);
+        let det = math::dot(&ab, &p);
+        // parallel to the plane of the triangle, or its back seen when culled
+        if det < 1e-12 && (self.cull_back_faces || det > -1e-12) {
+            return None;
+        }
+        let to_origin = self.origin - *a;
+        let u = math::dot(&to_origin, &p) / det;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q = math::cross(&to_origin, &ab);
+        let v = math::dot(&self.direction, &q) / det;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        let t = math::dot(&ac, &q) / det;
+        (self.near..=self.far)
+            .contains(&t)
+            .then_some((t, (1.0 - u - v, u, v)))
+    }
+
+    /// Nearest face of `mesh` transformed by `model` on the ray, testing every face; a
+    /// [`Bvh`](crate::spatial::Bvh) answers many rays sooner.
+    pub fn intersect_mesh(&self, mesh: &Mesh, model: &Mat4) -> Option<Hit> {
+        let positions: Vec<Vec3f> = mesh
+            .positions
+            .iter()
+            .map(|p| model.transform_point(p))
+            .collect();
+        let mut nearest: Option<Hit> = None;
+        for (face, &[i1, i2, i3]) in mesh.indices.iter().enumerate() {
+            let Some((distance, barycentric)) =
+                self.intersect_triangle(&positions[i1], &positions[i2], &positions[i3])
+            else {
+                continue;
+            };
+            if nearest.is_none_or(|hit| distance < hit.distance) {
+                nearest = Some(Hit {
+                    distance,
+                    point: self.at(distance),
+                    face,
+                    barycentric,
+                });
+            }
+        }
+        nearest
+    }
+
+    /// Nearest face among all `meshes` transformed by `model` on the ray, with the index
+    /// of its mesh.
+    pub fn intersect_scene(&self, meshes: &[Mesh], model: &Mat4) -> Option<(usize, Hit)> {
+        meshes
+            .iter()
+            .enumerate()
+            .filter_map(|(index, mesh)| Some((index, self.intersect_mesh(mesh, model)?)))
+            .min_by(|(_, a), (_, b)| a.distance.total_cmp(&b.distance))
+    }
+}
+
+#[test]
+fn test_intersect_triangle() {
+    let (a, b, c) = (
+        Vec3f::new(0., 0., 0.),
+        Vec3f::new(2., 0., 0.),
+        Vec3f::new(0., 2., 0.),
+    );
+    let ray = Ray::new(Vec3f::new(0.5, 0.5, 3.), Vec3f::new(0., 0., -2.));
+    let (t, (wa, wb, wc)) = ray.intersect_triangle(&a, &b, &c).unwrap();
+    assert!((t - 3.0).abs() < 1e-12);
+    assert!((wa - 0.5).abs() < 1e-12 && (wb - 0.25).abs() < 1e-12 && (wc - 0.25).abs() < 1e-12);
+    // either side is hit, but not behind the origin or beside the triangle
+    let below = Ray::new(Vec3f::new(0.5, 0.5, -1.), Vec3f::new(0., 0., 1.));
+    assert!(below.intersect_triangle(&a, &b, &c).is_some());
+    let culled = Ray {
+        cull_back_faces: true,
+        ..below
+    };
+    assert!(culled.intersect_triangle(&a, &b, &c).is_none());
+    let away = Ray::new(Vec3f::new(0.5, 0.5, 3.), Vec3f::new(0., 0., 1.));
+    assert!(away.intersect_triangle(&a, &b, &c).is_none());
+    let beside = Ray::new(Vec3f::new(1.5, 1.5, 3.), Vec3f::new(0., 0., -1.));
+    assert!(beside.intersect_triangle(&a, &b, &c).is_none());
+}
+
+#[test]
+fn test_pixel_rays_match_rasterizer() {
+    use crate::camera::Orthographic;
+    use crate::color::Color;
+    use crate::drawable::Image;
+    use crate::render::draw_mesh;
+    use crate::DrawStyle;
+
+    let sphere = crate::geometry::sphere(0.8, 16, 8);
+    let style = DrawStyle::Filled(Color(255, 255, 255));
+    let model = Mat4::rotation_y(0.3);
+    for projection in [None, Some(Orthographic::from_height(2.0, 1.0, -2.0, 2.0))] {
+        let mut image = Image::new(24, 24);
+        image.set_projection(projection.map(|volume| volume.projection()));
+        image.enable_id_buffer();
+        draw_mesh(&mut image, &sphere, &style, &model);
+
+        let mut hits = 0;
+        for y in 0..24 {
+            for x in 0..24 {
+                let ray = image.pixel_ray(x, y).unwrap();
+                let hit = ray.intersect_scene(std::slice::from_ref(&sphere), &model);
+                let picked = image.pick(x, y);
+                assert_eq!(hit.is_some(), picked.is_some(), "{} {}", x, y);
+                if let (Some((_, hit)), Some(picked)) = (hit, picked) {
+                    assert_eq!(hit.face as u32, picked.triangle);
+                    let depth = image.depth_buffer()[((23 - y) * 24 + x) as usize];
+                    assert!((image.to_screen(&hit.point).z - depth).abs() < 1e-9);
+                    hits += 1;
+                }
+            }
+        }
+        assert!(hits > 100);
+    }
+    assert!(Image::new(4, 4).pixel_ray(4, 0).is_none());
+}
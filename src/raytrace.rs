@@ -0,0 +1,77 @@
+use crate::color::HdrColor;
+use crate::drawable::{Drawable, Image};
+use crate::light::HEADLIGHT_DIRECTION;
+use crate::math::{self, Mat4};
+use crate::mesh::Mesh;
+use crate::spatial::Bvh;
+
+/// Renders `meshes` transformed by `model` by casting a ray through the center of every
+/// pixel, as a reference for the rasterizer to be compared with. Every mesh gets a
+/// [`Bvh`] to keep that tractable.
+///
+/// Faces are flat shaded in their material color by the same light from the viewer as
+/// [`draw_mesh`](crate::render::draw_mesh) with [`DrawStyle::Filled`](crate::DrawStyle),
+/// back faces are passed through as they are culled there, and depths and IDs are
+/// written as the rasterizer would. Lines and points are left out.
+pub fn raytrace(image: &mut Image, meshes: &[Mesh], model: &Mat4) {
+    let hierarchies: Vec<Bvh> = meshes.iter().map(|mesh| Bvh::build(mesh, model)).collect();
+    for y in 0..image.height() {
+        for x in 0..image.width() {
+            let Some(mut ray) = image.pixel_ray(x, y) else {
+                return;
+            };
+            ray.cull_back_faces = true;
+            let mut nearest = None;
+            for (object, bvh) in hierarchies.iter().enumerate() {
+                if let Some(hit) = bvh.intersect(&ray) {
+                    // farther meshes only need to be searched up to this hit
+                    ray.far = hit.distance;
+                    nearest = Some((object, hit));
+                }
+            }
+            let Some((object, hit)) = nearest else {
+                continue;
+            };
+            let mesh = &meshes[object];
+            let [a, b, c] =
+                mesh.indices[hit.face].map(|i| model.transform_point(&mesh.positions[i]));
+            let normal = math::cross(&(b - a), &(c - a)).normalized();
+            let intensity = math::dot(&normal, &HEADLIGHT_DIRECTION);
+            // rows of the image count from the bottom, those of the ray from the top
+            let row = image.height() - 1 - y;
+            let depth = image.to_screen(&hit.point).z;
+            image.set_object_id(object as u32);
+            image.set_triangle_id(hit.face as u32);
+            if image.check_and_set_zbuf(x, row, depth) {
+                let color = HdrColor::from(mesh.material.base_color).scale(intensity);
+                image.point_hdr(x, row, color);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_raytrace_matches_rasterizer() {
+    use crate::render::draw_mesh;
+    use crate::DrawStyle;
+
+    let torus = crate::geometry::torus(0.6, 0.25, 24, 12);
+    let model = Mat4::rotation_x(0.8) * Mat4::rotation_y(0.4);
+    let style = DrawStyle::Filled(torus.material.base_color);
+    let mut rasterized = Image::new(48, 48);
+    draw_mesh(&mut rasterized, &torus, &style, &model);
+    let mut traced = Image::new(48, 48);
+    raytrace(&mut traced, std::slice::from_ref(&torus), &model);
+
+    // sampled at the same pixel centers, the two only differ where edges pass within
+    // rounding of a center
+    let (rasterized, traced) = (rasterized.to_rgb_image(), traced.to_rgb_image());
+    let differing = rasterized
+        .pixels()
+        .zip(traced.pixels())
+        .filter(|(a, b)| a != b)
+        .count();
+    let covered = traced.pixels().filter(|p| p.0 != [0, 0, 0]).count();
+    assert!(covered > 500);
+    assert!(differing <= 2, "{} pixels differ", differing);
+}
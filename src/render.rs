@@ -0,0 +1,952 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::color::{Color, HdrColor};
+use crate::colormap::{Colormap, Scale};
+use crate::drawable::{triangle_shaded, Drawable, Image, Point3f, ScreenPoint};
+use crate::light::{HemisphereLight, Light, HEADLIGHT_DIRECTION};
+use crate::math::{self, Aabb, Mat4, Vec3f};
+use crate::mesh::Mesh;
+use crate::raster::TriangleSetup;
+use crate::DrawStyle;
+
+/// Returns `true` and counts the object if its model-space `bounds` fall entirely
+/// outside the image.
+fn cull_object(image: &mut Image, bounds: Option<Aabb>, model: &Mat4) -> bool {
+    match bounds {
+        Some(bounds) if !image.view_bounds().overlaps(&bounds.transformed(model)) => {
+            image.record_object_culled();
+            true
+        }
+        _ => false,
+    }
+}
+
+fn calculate_intensity(v1: &Vec3f, v2: &Vec3f, v3: &Vec3f, light_dir: &Vec3f) -> f64 {
+    let normal = math::cross(&(*v2 - *v1), &(*v3 - *v1)).normalized();
+    math::dot(&normal, light_dir)
+}
+
+/// Draws every triangle, line and point of `mesh` transformed by `model`.
+///
+/// For [`DrawStyle::Textured`] and [`DrawStyle::VertexColors`] the texture coordinates and
+/// colors are taken from the mesh; meshes without them fall back to their material color.
+/// [`DrawStyle::Matcap`] takes the mesh normals, or face normals if it has none. Lines and
+/// points are unlit and use the style's color where it has one. [`DrawStyle::FilledRandom`]
+/// and [`DrawStyle::PerFace`] colors are keyed by face index.
+pub fn draw_mesh(image: &mut Image, mesh: &Mesh, draw_style: &DrawStyle, model: &Mat4) {
+    draw_mesh_instanced(image, mesh, draw_style, std::slice::from_ref(model), 1);
+}
+
+/// Draws `mesh` once for every model transform in `instances`, as [`draw_mesh`] would,
+/// sharing the vertex data among them. The instances go through one geometry stage
+/// together on up to `threads` threads, those outside the image culled one by one, and
+/// are then rasterized in order.
+pub fn draw_mesh_instanced(
+    image: &mut Image,
+    mesh: &Mesh,
+    draw_style: &DrawStyle,
+    instances: &[Mat4],
+    threads: usize,
+) {
+    let objects: Vec<(&Mesh, &Mat4)> = instances.iter().map(|model| (mesh, model)).collect();
+    let geometries = process_geometry(image, &objects, threads);
+    for (geometry, model) in geometries.iter().zip(instances) {
+        let Some(geometry) = geometry else {
+            continue;
+        };
+        if image.is_cancelled() {
+            break;
+        }
+        draw_geometry(image, mesh, geometry, draw_style, model);
+    }
+}
+
+/// Face of a mesh after the geometry stage, lit and ready to be rasterized.
+#[derive(Clone, Copy, Debug)]
+pub struct ScreenTriangle {
+    /// Index of the face within its mesh.
+    pub face: usize,
+    /// `None` for a face without an area.
+    pub setup: Option<TriangleSetup>,
+    /// Light from the viewer falling on the face, plus the image's ambient light, as
+    /// [`draw_mesh`] shades with.
+    pub light: HdrColor,
+    /// Normal of the face after the model transform, not normalized.
+    pub normal: Vec3f,
+}
+
+/// A mesh transformed into the pixels of an image by [`process_geometry`].
+#[derive(Clone, Debug, Default)]
+pub struct Geometry {
+    /// Every vertex of the mesh in screen space.
+    pub vertices: Vec<Point3f>,
+    /// The faces turned towards the viewer, in the order of the mesh.
+    pub triangles: Vec<ScreenTriangle>,
+    /// Number of faces left out for facing away.
+    pub culled: u64,
+}
+
+/// Vertices or faces transformed together by one thread of [`process_geometry`].
+const GEOMETRY_CHUNK: usize = 4096;
+
+/// Geometry stage of [`draw_mesh`] for several meshes at once: transforms the vertices of
+/// every `(mesh, model)` object into the pixels of `image` and sets up and lights its
+/// faces, on up to `threads` threads. Objects outside the image are culled and get `None`;
+/// [`draw_geometry`] rasterizes the others.
+pub fn process_geometry(
+    image: &mut Image,
+    objects: &[(&Mesh, &Mat4)],
+    threads: usize,
+) -> Vec<Option<Geometry>> {
+    let visible: Vec<bool> = objects
+        .iter()
+        .map(|&(mesh, model)| !cull_object(image, mesh.bounds(), model))
+        .collect();
+    let mapping = image.screen_mapping();
+    let lighting = FaceLighting {
+        lights: image.lights().to_vec(),
+        ambient: image.ambient_light(),
+    };
+    let chunks = |len: usize| {
+        (0..len)
+            .step_by(GEOMETRY_CHUNK)
+            .map(move |start| start..(start + GEOMETRY_CHUNK).min(len))
+    };
+
+    // vertices first, as the faces share them
+    let vertex_work: Vec<(usize, std::ops::Range<usize>)> = objects
+        .iter()
+        .enumerate()
+        .filter(|&(object, _)| visible[object])
+        .flat_map(|(object, (mesh, _))| {
+            chunks(mesh.positions.len()).map(move |range| (object, range))
+        })
+        .collect();
+    let transformed = parallel_map(&vertex_work, threads, |(object, range)| {
+        let (mesh, model) = objects[*object];
+        let view: Vec<Vec3f> = mesh.positions[range.clone()]
+            .iter()
+            .map(|p| model.transform_point(p))
+            .collect();
+        let screen = view
+            .iter()
+            .map(|v| mapping.to_screen(v))
+            .collect::<Vec<_>>();
+        (view, screen)
+    });
+    let mut views = vec![Vec::new(); objects.len()];
+    let mut geometries: Vec<Option<Geometry>> = visible
+        .iter()
+        .map(|&visible| visible.then(Geometry::default))
+        .collect();
+    for ((object, _), (view, screen)) in vertex_work.iter().zip(transformed) {
+        views[*object].extend(view);
+        if let Some(geometry) = &mut geometries[*object] {
+            geometry.vertices.extend(screen);
+        }
+    }
+
+    let face_work: Vec<(usize, std::ops::Range<usize>)> = objects
+        .iter()
+        .enumerate()
+        .filter(|&(object, _)| visible[object])
+        .flat_map(|(object, (mesh, _))| {
+            chunks(mesh.indices.len()).map(move |range| (object, range))
+        })
+        .collect();
+    let set_up = parallel_map(&face_work, threads, |(object, range)| {
+        let (mesh, _) = objects[*object];
+        let geometry = geometries[*object]
+            .as_ref()
+            .expect("only visible objects have work");
+        set_up_faces(
+            mesh,
+            &views[*object],
+            &geometry.vertices,
+            range.clone(),
+            &lighting,
+        )
+    });
+    for ((object, _), (triangles, culled)) in face_work.iter().zip(set_up) {
+        if let Some(geometry) = &mut geometries[*object] {
+            geometry.triangles.extend(triangles);
+            geometry.culled += culled;
+        }
+    }
+    geometries
+}
+
+/// What the geometry stage lights faces with, taken from the image.
+struct FaceLighting {
+    /// Lights replacing the one from the viewer.
+    lights: Vec<Light>,
+    ambient: Option<HemisphereLight>,
+}
+
+/// Sets up and lights the faces of `mesh` in `range` from its vertices after the model
+/// transform and in screen space, returning those facing the viewer and the number of the
+/// others.
+fn set_up_faces(
+    mesh: &Mesh,
+    view: &[Vec3f],
+    screen: &[Point3f],
+    range: std::ops::Range<usize>,
+    lighting: &FaceLighting,
+) -> (Vec<ScreenTriangle>, u64) {
+    let light_dir = HEADLIGHT_DIRECTION;
+    let diffuse = |n_dot_l: f64| match &lighting.ambient {
+        Some(ambient) => ambient.wrapped_diffuse(n_dot_l),
+        None => n_dot_l.max(0.0),
+    };
+    let mut triangles = Vec::with_capacity(range.len());
+    let mut culled = 0;
+    for face in range {
+        let [idx1, idx2, idx3] = mesh.indices[face];
+        let (v1, v2, v3) = (&view[idx1], &view[idx2], &view[idx3]);
+        let intensity = calculate_intensity(v1, v2, v3, &light_dir);
+        if intensity < 0.0 {
+            // not visible
+            culled += 1;
+            continue;
+        }
+        let normal = math::cross(&(*v2 - *v1), &(*v3 - *v1));
+        let unit_normal = normal.normalized();
+        let direct = if lighting.lights.is_empty() {
+            diffuse(intensity)
+        } else {
+            // lit at the centroid, flat like the light from the viewer
+            let centroid = (*v1 + *v2 + *v3) * (1.0 / 3.0);
+            lighting
+                .lights
+                .iter()
+                .filter_map(|light| light.incident(&centroid))
+                .map(|incident| {
+                    diffuse(math::dot(&unit_normal, &incident.direction)) * incident.attenuation
+                })
+                .sum()
+        };
+        let light = match &lighting.ambient {
+            Some(ambient) => {
+                let fill = ambient.irradiance(&unit_normal);
+                let direct = direct as f32;
+                HdrColor(fill.0 + direct, fill.1 + direct, fill.2 + direct)
+            }
+            None => HdrColor(1.0, 1.0, 1.0).scale(direct),
+        };
+        let corner = |idx: usize| [screen[idx].x, screen[idx].y, screen[idx].z];
+        triangles.push(ScreenTriangle {
+            face,
+            setup: TriangleSetup::new(corner(idx1), corner(idx2), corner(idx3)),
+            light,
+            normal,
+        });
+    }
+    (triangles, culled)
+}
+
+/// Calls `f` on every item on up to `threads` threads taking the next item as they
+/// finish one, and returns the results in the order of the items.
+fn parallel_map<T: Sync, R: Send, F: Fn(&T) -> R + Sync>(
+    items: &[T],
+    threads: usize,
+    f: F,
+) -> Vec<R> {
+    if threads <= 1 || items.len() <= 1 {
+        return items.iter().map(f).collect();
+    }
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<(usize, R)> = std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads.min(items.len()))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(item) = items.get(index) else {
+                            return done;
+                        };
+                        done.push((index, f(item)));
+                    }
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("geometry worker panicked"))
+            .collect()
+    });
+    results.sort_by_key(|&(index, _)| index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Raster stage of [`draw_mesh`]: draws the triangles of `geometry` made from `mesh`
+/// transformed by `model`, followed by its lines and points.
+pub fn draw_geometry(
+    image: &mut Image,
+    mesh: &Mesh,
+    geometry: &Geometry,
+    draw_style: &DrawStyle,
+    model: &Mat4,
+) {
+    let normal_matrix = model
+        .inverse()
+        .map_or(*model, |inverse| inverse.transpose());
+    for _ in 0..geometry.culled {
+        image.record_culled();
+    }
+    let vertices = &geometry.vertices;
+    for triangle in &geometry.triangles {
+        if image.is_cancelled() {
+            return;
+        }
+        let face = triangle.face;
+        let [idx1, idx2, idx3] = mesh.indices[face];
+        image.set_triangle_id(face as u32);
+        let (p1, p2, p3) = (&vertices[idx1], &vertices[idx2], &vertices[idx3]);
+        let mut draw = |style: &DrawStyle| {
+            image.triangle_with_setup(p1, p2, p3, triangle.setup, style, triangle.light)
+        };
+
+        match draw_style {
+            DrawStyle::Textured(tex, _) if mesh.has_uvs() => {
+                let to_tex_point =
+                    |idx: usize| Point3f::new(mesh.uvs[idx][0], mesh.uvs[idx][1], 0.);
+                let tx1 = to_tex_point(idx1);
+                let tx2 = to_tex_point(idx2);
+                let tx3 = to_tex_point(idx3);
+                draw(&DrawStyle::Textured(tex, (&tx1, &tx2, &tx3)));
+            }
+            DrawStyle::Textured(..) => draw(&DrawStyle::Filled(mesh.material.base_color)),
+            &DrawStyle::FilledRandom(seed) => {
+                // keyed by face index so colors stay put while the mesh moves
+                let key = seed ^ (face as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+                draw(&DrawStyle::Filled(Color::from_seed(key)))
+            }
+            &DrawStyle::VertexColors { lit, .. } if mesh.has_colors() => {
+                let colors = (mesh.colors[idx1], mesh.colors[idx2], mesh.colors[idx3]);
+                draw(&DrawStyle::VertexColors { colors, lit })
+            }
+            DrawStyle::VertexColors { .. } => draw(&DrawStyle::Filled(mesh.material.base_color)),
+            DrawStyle::PerFace(face_color) => draw(&DrawStyle::Filled(face_color(face))),
+            &DrawStyle::Matcap(tex, _) => {
+                let normal = |idx: usize| {
+                    if mesh.has_normals() {
+                        normal_matrix.transform_vector(&mesh.normals[idx])
+                    } else {
+                        triangle.normal
+                    }
+                };
+                draw(&DrawStyle::Matcap(
+                    tex,
+                    (normal(idx1), normal(idx2), normal(idx3)),
+                ))
+            }
+            _ => draw(draw_style),
+        }
+    }
+    let line_color = match draw_style {
+        DrawStyle::Wireframe(color) | DrawStyle::Filled(color) => *color,
+        _ => mesh.material.base_color,
+    };
+    // lines and points are numbered on from the faces
+    let mut element = mesh.indices.len() as u32;
+    for &[idx1, idx2] in &mesh.lines {
+        image.set_triangle_id(element);
+        element += 1;
+        image.line3d(&vertices[idx1], &vertices[idx2], line_color);
+    }
+    for &idx in &mesh.points {
+        image.set_triangle_id(element);
+        element += 1;
+        let p = vertices[idx];
+        if p.x >= 0.0 && p.y >= 0.0 {
+            let screen = ScreenPoint::from(&p);
+            depth_tested_point(image, screen.x, screen.y, p.z, line_color);
+        }
+    }
+}
+
+/// Mesh attributes [`draw_mesh_debug`] can visualize instead of shading.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DebugView {
+    /// World-space normals remapped from [-1, 1] to RGB.
+    Normal,
+    /// Depth as grayscale over the depth range of the mesh, white being nearest.
+    Depth,
+    /// Texture coordinates as red and green; meshes without UVs are black.
+    Uv,
+}
+
+impl DebugView {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DebugView::Normal => "normal",
+            DebugView::Depth => "depth",
+            DebugView::Uv => "uv",
+        }
+    }
+}
+
+impl std::str::FromStr for DebugView {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "normal" => Ok(DebugView::Normal),
+            "depth" => Ok(DebugView::Depth),
+            "uv" => Ok(DebugView::Uv),
+            _ => Err(format!("unknown debug view '{}'", s)),
+        }
+    }
+}
+
+/// Draws the front-facing triangles of `mesh` with `view` written unlit into the image,
+/// so that with the default tone mapping a value `v` in [0, 1] is saved as `v * 255`.
+///
+/// Meshes without normals get flat face normals.
+pub fn draw_mesh_debug(image: &mut Image, mesh: &Mesh, model: &Mat4, view: DebugView) {
+    if cull_object(image, mesh.bounds(), model) {
+        return;
+    }
+    let light_dir = HEADLIGHT_DIRECTION;
+    let normal_matrix = model
+        .inverse()
+        .map_or(*model, |inverse| inverse.transpose());
+    let positions: Vec<Vec3f> = mesh
+        .positions
+        .iter()
+        .map(|p| model.transform_point(p))
+        .collect();
+    let (min_z, max_z) = positions
+        .iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), p| {
+            (min.min(p.z), max.max(p.z))
+        });
+    let to_u8 = |c: f64| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+    let encode = |r: f64, g: f64, b: f64| HdrColor::from(Color(to_u8(r), to_u8(g), to_u8(b)));
+
+    for (face, &[idx1, idx2, idx3]) in mesh.indices.iter().enumerate() {
+        if image.is_cancelled() {
+            return;
+        }
+        image.set_triangle_id(face as u32);
+        let (v1, v2, v3) = (&positions[idx1], &positions[idx2], &positions[idx3]);
+        if calculate_intensity(v1, v2, v3, &light_dir) < 0.0 {
+            image.record_culled();
+            continue;
+        }
+        let (p1, p2, p3) = (
+            image.to_screen(v1),
+            image.to_screen(v2),
+            image.to_screen(v3),
+        );
+        image.record_triangle(&p1, &p2, &p3);
+        let corner = |p: &Point3f| [p.x, p.y, p.z];
+        let Some(setup) = TriangleSetup::new(corner(&p1), corner(&p2), corner(&p3)) else {
+            continue;
+        };
+        let face_normal = math::cross(&(*v2 - *v1), &(*v3 - *v1)).normalized();
+        let normals = [idx1, idx2, idx3].map(|idx| {
+            if mesh.has_normals() {
+                normal_matrix.transform_vector(&mesh.normals[idx])
+            } else {
+                face_normal
+            }
+        });
+        let interpolate = |(a, b, c): (f64, f64, f64), x: [f64; 3]| a * x[0] + b * x[1] + c * x[2];
+
+        triangle_shaded(image, &setup, |bary, _| {
+            Some(match view {
+                DebugView::Normal => {
+                    let n = Vec3f::new(
+                        interpolate(bary, normals.map(|n| n.x)),
+                        interpolate(bary, normals.map(|n| n.y)),
+                        interpolate(bary, normals.map(|n| n.z)),
+                    )
+                    .normalized();
+                    encode(n.x * 0.5 + 0.5, n.y * 0.5 + 0.5, n.z * 0.5 + 0.5)
+                }
+                DebugView::Depth => {
+                    let z = interpolate(bary, [v1.z, v2.z, v3.z]);
+                    let t = if max_z - min_z > 1e-9 {
+                        (z - min_z) / (max_z - min_z)
+                    } else {
+                        1.0
+                    };
+                    HdrColor::from(Colormap::Grayscale.color(t))
+                }
+                DebugView::Uv if mesh.has_uvs() => {
+                    let uvs = [mesh.uvs[idx1], mesh.uvs[idx2], mesh.uvs[idx3]];
+                    encode(
+                        interpolate(bary, uvs.map(|uv| uv[0])),
+                        interpolate(bary, uvs.map(|uv| uv[1])),
+                        0.0,
+                    )
+                }
+                DebugView::Uv => HdrColor::default(),
+            })
+        });
+    }
+}
+
+/// Draws the front-facing triangles of `mesh` unlit, in the color `colormap` gives the
+/// `values` of its vertices interpolated across the faces and placed in `range` by
+/// `scale`. Vertices without a value are black.
+pub fn draw_scalar_field(
+    image: &mut Image,
+    mesh: &Mesh,
+    model: &Mat4,
+    values: &[f64],
+    range: (f64, f64),
+    colormap: Colormap,
+    scale: Scale,
+) {
+    if cull_object(image, mesh.bounds(), model) {
+        return;
+    }
+    let positions: Vec<Vec3f> = mesh
+        .positions
+        .iter()
+        .map(|p| model.transform_point(p))
+        .collect();
+    for (face, &[idx1, idx2, idx3]) in mesh.indices.iter().enumerate() {
+        if image.is_cancelled() {
+            return;
+        }
+        image.set_triangle_id(face as u32);
+        let (v1, v2, v3) = (&positions[idx1], &positions[idx2], &positions[idx3]);
+        if calculate_intensity(v1, v2, v3, &HEADLIGHT_DIRECTION) < 0.0 {
+            image.record_culled();
+            continue;
+        }
+        let (p1, p2, p3) = (
+            image.to_screen(v1),
+            image.to_screen(v2),
+            image.to_screen(v3),
+        );
+        image.record_triangle(&p1, &p2, &p3);
+        let corner = |p: &Point3f| [p.x, p.y, p.z];
+        let Some(setup) = TriangleSetup::new(corner(&p1), corner(&p2), corner(&p3)) else {
+            continue;
+        };
+        let [s1, s2, s3] =
+            [idx1, idx2, idx3].map(|idx| values.get(idx).copied().unwrap_or(f64::NAN));
+        triangle_shaded(image, &setup, |(a, b, c), _| {
+            let value = a * s1 + b * s2 + c * s3;
+            Some(HdrColor::from(colormap.map(value, range, scale)))
+        });
+    }
+}
+
+fn depth_tested_point(image: &mut Image, x: u32, y: u32, z: f64, color: Color) {
+    if x < image.width() && y < image.height() && image.check_and_set_zbuf(x, y, z) {
+        image.point(x, y, color);
+    }
+}
+
+/// How [`draw_point_cloud`] colors its splats.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SplatColoring {
+    Solid(Color),
+    /// Normal components remapped from [-1, 1] to RGB; meshes without normals use their
+    /// material color.
+    Normal,
+    /// Depth through the colormap over the depth range of the mesh, the nearest at its
+    /// high end.
+    Depth(Colormap),
+}
+
+impl std::str::FromStr for SplatColoring {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "normal" => Ok(SplatColoring::Normal),
+            "depth" => Ok(SplatColoring::Depth(Colormap::Turbo)),
+            _ => Err(format!("unknown splat coloring '{}'", s)),
+        }
+    }
+}
+
+/// Draws every vertex of `mesh` as a depth-tested disc of `radius` pixels, ignoring its faces.
+pub fn draw_point_cloud(
+    image: &mut Image,
+    mesh: &Mesh,
+    model: &Mat4,
+    radius: f64,
+    coloring: SplatColoring,
+) {
+    let positions: Vec<Vec3f> = mesh
+        .positions
+        .iter()
+        .map(|p| model.transform_point(p))
+        .collect();
+    let (min_z, max_z) = positions
+        .iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), p| {
+            (min.min(p.z), max.max(p.z))
+        });
+
+    let radius = radius.max(0.0);
+    let extent = radius.ceil() as i64;
+    for (idx, v) in positions.iter().enumerate() {
+        image.set_triangle_id(idx as u32);
+        let color = match coloring {
+            SplatColoring::Solid(color) => color,
+            SplatColoring::Normal if mesh.has_normals() => {
+                let n = model.transform_vector(&mesh.normals[idx]).normalized();
+                let to_u8 = |c: f64| ((c * 0.5 + 0.5).clamp(0.0, 1.0) * 255.0) as u8;
+                Color(to_u8(n.x), to_u8(n.y), to_u8(n.z))
+            }
+            SplatColoring::Normal => mesh.material.base_color,
+            SplatColoring::Depth(colormap) => {
+                let t = if max_z > min_z {
+                    (v.z - min_z) / (max_z - min_z)
+                } else {
+                    1.0
+                };
+                colormap.color(t)
+            }
+        };
+
+        let center = image.to_screen(v);
+        let (cx, cy) = (center.x.floor() as i64, center.y.floor() as i64);
+        for dy in -extent..=extent {
+            for dx in -extent..=extent {
+                if (dx * dx + dy * dy) as f64 > radius * radius {
+                    continue;
+                }
+                let (x, y) = (cx + dx, cy + dy);
+                if x >= 0 && y >= 0 {
+                    depth_tested_point(image, x as u32, y as u32, v.z, color);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_point_cloud_splat_size() {
+    let mesh = Mesh {
+        positions: vec![Vec3f::new(0., 0., 0.)],
+        ..Default::default()
+    };
+    let mut image = Image::new(32, 32);
+    let white = Color(255, 255, 255);
+    draw_point_cloud(
+        &mut image,
+        &mesh,
+        &Mat4::identity(),
+        2.0,
+        SplatColoring::Solid(white),
+    );
+    let lit = image
+        .to_rgb_image()
+        .pixels()
+        .filter(|p| p[0] == 255)
+        .count();
+    // lattice points within a radius-2 disc
+    assert_eq!(lit, 13);
+}
+
+#[test]
+fn test_filled_random_is_deterministic() {
+    let mesh = crate::geometry::sphere(0.8, 12, 6);
+    let render = |seed| {
+        let mut image = Image::new(32, 32);
+        draw_mesh(
+            &mut image,
+            &mesh,
+            &DrawStyle::FilledRandom(seed),
+            &Mat4::identity(),
+        );
+        image.to_rgb_image()
+    };
+    assert_eq!(render(7), render(7));
+    assert_ne!(render(7), render(8));
+}
+
+#[test]
+fn test_per_face_colors() {
+    let mut mesh = crate::geometry::plane(1.5, 1.5, 1);
+    mesh.transform(&Mat4::rotation_x(std::f64::consts::FRAC_PI_2));
+    let colors = [Color(255, 0, 0), Color(0, 0, 255)];
+    let face_color = |face: usize| colors[face % 2];
+    let mut image = Image::new(32, 32);
+    draw_mesh(
+        &mut image,
+        &mesh,
+        &DrawStyle::PerFace(&face_color),
+        &Mat4::identity(),
+    );
+    let pixels = image.to_rgb_image();
+    let red = pixels.pixels().filter(|p| p[0] > 0 && p[2] == 0).count();
+    let blue = pixels.pixels().filter(|p| p[2] > 0 && p[0] == 0).count();
+    assert!(red > 100 && blue > 100, "red {} blue {}", red, blue);
+}
+
+#[test]
+fn test_objects_outside_the_view_are_culled() {
+    let mut mesh = crate::geometry::sphere(0.3, 8, 4);
+    let style = DrawStyle::Filled(Color(255, 255, 255));
+    let mut image = Image::new(16, 16);
+    draw_mesh(&mut image, &mesh, &style, &Mat4::identity());
+    assert_eq!(image.stats().objects_culled, 0);
+
+    mesh.transform(&Mat4::new([
+        [1., 0., 0., 1.5],
+        [0., 1., 0., 0.],
+        [0., 0., 1., 0.],
+        [0., 0., 0., 1.],
+    ]));
+    let before = image.stats();
+    draw_mesh(&mut image, &mesh, &style, &Mat4::identity());
+    let after = image.stats();
+    assert_eq!(after.objects_culled, 1);
+    assert_eq!(after.triangles_submitted, before.triangles_submitted);
+
+    // a band only sees its own rows
+    let band = Image::band(16, 16, 12, 4);
+    assert_eq!(band.view_bounds().min.y, 0.5);
+}
+
+#[test]
+fn test_ambient_light() {
+    let sphere = crate::geometry::sphere(0.8, 32, 16);
+    let style = DrawStyle::Filled(Color(255, 255, 255));
+    let render = |ambient_light: Option<HemisphereLight>| {
+        let mut image = Image::new(32, 32);
+        image.set_ambient_light(ambient_light);
+        draw_mesh(&mut image, &sphere, &style, &Mat4::identity());
+        image.framebuffer().clone()
+    };
+    let unlit = render(None);
+    let lit = render(Some(HemisphereLight {
+        sky: HdrColor(0.0, 0.0, 0.5),
+        ground: HdrColor(0.5, 0.0, 0.0),
+        wrap: 0.0,
+    }));
+    // the rims facing up and down turn blue and red, the middle stays as bright
+    let (top, bottom, middle) = ((16, 27), (16, 4), (16, 16));
+    let at = |buffer: &crate::drawable::Framebuffer, (x, y): (u32, u32)| buffer.get(x, y);
+    assert!(at(&lit, top).2 > at(&unlit, top).2 + 0.3);
+    assert!(at(&lit, bottom).0 > at(&unlit, bottom).0 + 0.3);
+    assert_eq!(at(&lit, middle).1, at(&unlit, middle).1);
+}
+
+#[test]
+fn test_spot_light_forward_and_deferred() {
+    use crate::deferred::{GBuffer, Lighting};
+    use crate::light::Falloff;
+
+    let mut plane = crate::geometry::plane(2.0, 2.0, 32);
+    plane.transform(&Mat4::rotation_x(std::f64::consts::FRAC_PI_2));
+    let spot = Light::spot(
+        Vec3f::new(0., 0., 1.),
+        Vec3f::new(0., 0., -1.),
+        10f64.to_radians(),
+        25f64.to_radians(),
+        Falloff::NONE,
+    )
+    .unwrap();
+
+    let mut forward = Image::new(32, 32);
+    forward.set_lights(vec![spot]);
+    let style = DrawStyle::Filled(Color(255, 255, 255));
+    draw_mesh(&mut forward, &plane, &style, &Mat4::identity());
+    let mut gbuffer = GBuffer::new(32, 32);
+    gbuffer.draw_mesh(&plane, &Mat4::identity(), None);
+    let mut deferred = Image::new(32, 32);
+    let lighting = Lighting {
+        lights: &[spot],
+        ..Default::default()
+    };
+    gbuffer.resolve(&mut deferred, &lighting);
+
+    // lit in the middle, dark past the cone some 7 pixels out
+    for image in [forward, deferred] {
+        let pixels = image.to_rgb_image();
+        assert!(pixels.get_pixel(16, 16)[0] > 240);
+        assert!(pixels.get_pixel(10, 16)[0] > 0);
+        assert_eq!(pixels.get_pixel(16, 6)[0], 0);
+        assert_eq!(pixels.get_pixel(3, 3)[0], 0);
+    }
+}
+
+#[test]
+fn test_instances_match_separate_draws() {
+    let mesh = crate::geometry::sphere(0.2, 8, 4);
+    let style = DrawStyle::Filled(Color(255, 255, 255));
+    let translation = |x: f64| {
+        Mat4::new([
+            [1., 0., 0., x],
+            [0., 1., 0., 0.],
+            [0., 0., 1., 0.],
+            [0., 0., 0., 1.],
+        ])
+    };
+    let instances = [translation(-0.5), translation(0.5), translation(3.0)];
+
+    let mut separate = Image::new(32, 32);
+    for model in &instances {
+        draw_mesh(&mut separate, &mesh, &style, model);
+    }
+    for threads in [1, 4] {
+        let mut instanced = Image::new(32, 32);
+        draw_mesh_instanced(&mut instanced, &mesh, &style, &instances, threads);
+        assert_eq!(instanced.stats(), separate.stats());
+        assert_eq!(instanced.stats().objects_culled, 1);
+        assert_eq!(instanced.to_rgb_image(), separate.to_rgb_image());
+    }
+}
+
+#[test]
+fn test_vertex_colors() {
+    let mesh = Mesh {
+        positions: vec![
+            Vec3f::new(-1., -1., 0.),
+            Vec3f::new(1., -1., 0.),
+            Vec3f::new(-1., 1., 0.),
+        ],
+        colors: vec![Color(255, 0, 0), Color(0, 255, 0), Color(0, 0, 255)],
+        indices: vec![[0, 1, 2]],
+        ..Default::default()
+    };
+    let mut image = Image::new(32, 32);
+    let style = DrawStyle::VertexColors {
+        colors: (Color(0, 0, 0), Color(0, 0, 0), Color(0, 0, 0)),
+        lit: false,
+    };
+    draw_mesh(&mut image, &mesh, &style, &Mat4::identity());
+    let pixels = image.to_rgb_image();
+    // pixels in the corners take on about their vertex color, rows are flipped on export
+    assert!(pixels.get_pixel(0, 31)[0] > 240);
+    let top_left = pixels.get_pixel(0, 1);
+    assert!(top_left[2] > 240 && top_left[0] < 60);
+    let mixed = pixels.get_pixel(10, 21);
+    assert!(mixed[0] > 0 && mixed[1] > 0 && mixed[2] > 0);
+}
+
+#[test]
+fn test_debug_views() {
+    let mut mesh = crate::geometry::plane(1.5, 1.5, 1);
+    mesh.transform(&Mat4::rotation_x(std::f64::consts::FRAC_PI_2));
+    let center = |view| {
+        let mut image = Image::new(32, 32);
+        draw_mesh_debug(&mut image, &mesh, &Mat4::identity(), view);
+        *image.to_rgb_image().get_pixel(16, 16)
+    };
+    // the plane faces the viewer, so its normal is (0, 0, 1)
+    assert_eq!(center(DebugView::Normal).0, [128, 128, 255]);
+    assert_eq!(center(DebugView::Depth).0, [255, 255, 255]);
+    let uv = center(DebugView::Uv);
+    // the plane's UVs span [0, 1], so its center is near 0.5
+    assert!((100..156).contains(&uv[0]) && (100..156).contains(&uv[1]));
+    assert_eq!(uv[2], 0);
+}
+
+#[test]
+fn test_matcap() {
+    // red on the left half of the capture, green on the right, dark in the upper half at
+    // rows past v = 0.5
+    let matcap = image::RgbImage::from_fn(8, 8, |x, y| match (x < 4, y < 4) {
+        (_, false) => image::Rgb([0, 0, 0]),
+        (true, true) => image::Rgb([255, 0, 0]),
+        (false, true) => image::Rgb([0, 255, 0]),
+    });
+    let mut sphere = crate::geometry::sphere(0.8, 32, 16);
+    let zero = Vec3f::new(0., 0., 0.);
+    let mut image = Image::new(32, 32);
+    let style = DrawStyle::Matcap(&matcap, (zero, zero, zero));
+    draw_mesh(&mut image, &sphere, &style, &Mat4::identity());
+    let pixels = image.to_rgb_image();
+    // facing left and right, and up at the top, which is row 0 on export
+    assert_eq!(pixels.get_pixel(8, 20).0, [255, 0, 0]);
+    assert_eq!(pixels.get_pixel(23, 20).0, [0, 255, 0]);
+    assert_eq!(pixels.get_pixel(16, 6).0, [0, 0, 0]);
+
+    // the capture turns with the view, not with the model
+    sphere.normals.clear();
+    let mut turned = Image::new(32, 32);
+    draw_mesh(&mut turned, &sphere, &style, &Mat4::rotation_y(1.0));
+    assert_eq!(turned.to_rgb_image().get_pixel(23, 20).0, [0, 255, 0]);
+}
+
+#[test]
+fn test_pick() {
+    use crate::drawable::PixelId;
+
+    let style = DrawStyle::Filled(Color(255, 255, 255));
+    let quad = Mesh {
+        positions: vec![
+            Vec3f::new(-1., -1., 0.),
+            Vec3f::new(1., -1., 0.),
+            Vec3f::new(1., 1., 0.),
+            Vec3f::new(-1., 1., 0.),
+        ],
+        indices: vec![[0, 1, 2], [0, 2, 3]],
+        ..Mesh::default()
+    };
+    let mut image = Image::new(16, 16);
+    assert_eq!(image.pick(8, 8), None);
+    image.enable_id_buffer();
+    image.set_object_id(4);
+    draw_mesh(&mut image, &quad, &style, &Mat4::identity());
+    // nearer, over the right half of the image
+    let nearer = Mat4::new([
+        [0.5, 0., 0., 0.5],
+        [0., 1., 0., 0.],
+        [0., 0., 1., 0.5],
+        [0., 0., 0., 1.],
+    ]);
+    image.set_object_id(9);
+    draw_mesh(&mut image, &quad, &style, &nearer);
+
+    // rows count from the top, where the upper left triangle of the quad is
+    let id = |object, triangle| Some(PixelId { object, triangle });
+    assert_eq!(image.pick(1, 1), id(4, 1));
+    assert_eq!(image.pick(6, 14), id(4, 0));
+    assert_eq!(image.pick(14, 8), id(9, 0));
+    assert_eq!(image.pick(16, 0), None);
+}
+
+#[test]
+fn test_parallel_geometry() {
+    let sphere = crate::geometry::sphere(0.5, 96, 48);
+    let torus = crate::geometry::torus(0.6, 0.25, 32, 16);
+    let model = Mat4::rotation_x(0.5);
+    let away = Mat4::new([
+        [1., 0., 0., 5.],
+        [0., 1., 0., 0.],
+        [0., 0., 1., 0.],
+        [0., 0., 0., 1.],
+    ]);
+    let style = DrawStyle::FilledRandom(7);
+    let mut serial = Image::new(64, 64);
+    draw_mesh(&mut serial, &sphere, &style, &model);
+    draw_mesh(&mut serial, &torus, &style, &model);
+
+    let mut parallel = Image::new(64, 64);
+    let objects = [(&sphere, &model), (&torus, &away), (&torus, &model)];
+    let geometries = process_geometry(&mut parallel, &objects, 4);
+    assert!(geometries[1].is_none());
+    for ((mesh, model), geometry) in objects.iter().zip(&geometries) {
+        if let Some(geometry) = geometry {
+            draw_geometry(&mut parallel, mesh, geometry, &style, model);
+        }
+    }
+    // the sphere spans several chunks of work, whose faces keep their order
+    let faces: Vec<usize> = geometries[0]
+        .as_ref()
+        .unwrap()
+        .triangles
+        .iter()
+        .map(|t| t.face)
+        .collect();
+    assert!(sphere.indices.len() > GEOMETRY_CHUNK && faces.is_sorted());
+    assert_eq!(serial.to_rgb_image(), parallel.to_rgb_image());
+    assert_eq!(
+        serial.stats().triangles_culled,
+        parallel.stats().triangles_culled
+    );
+}
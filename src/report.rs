@@ -0,0 +1,259 @@
+use std::fmt::Write;
+
+#[cfg(feature = "fs")]
+use crate::error::RusterizerError;
+use crate::stats::{RenderStats, StageTimings};
+
+/// Round constants of SHA-256, the first 32 bits of the fractional parts of the cube
+/// roots of the first 64 primes.
+const SHA256_K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// A file written by a run, as listed in a [`Report`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OutputFile {
+    pub path: String,
+    pub bytes: u64,
+    /// SHA-256 of the contents, which `sha256sum` prints in hex.
+    pub sha256: [u8; 32],
+}
+
+impl OutputFile {
+    /// Reads back the file at `path` to hash it.
+    #[cfg(feature = "fs")]
+    pub fn read(path: &str) -> Result<Self, RusterizerError> {
+        let contents = std::fs::read(path)
+            .map_err(|e| RusterizerError::in_file(std::path::Path::new(path), e))?;
+        Ok(OutputFile {
+            path: path.to_string(),
+            bytes: contents.len() as u64,
+            sha256: sha256(&contents),
+        })
+    }
+}
+
+/// Summary of a run for pipelines checking the renders they make, see
+/// [`Report::to_json`].
+#[derive(Clone, Debug, Default)]
+pub struct Report {
+    /// Exit code of the run, 0 if everything was rendered.
+    pub exit_code: i32,
+    pub error: Option<String>,
+    pub stats: RenderStats,
+    pub timings: StageTimings,
+    pub outputs: Vec<OutputFile>,
+}
+
+impl Report {
+    /// The report as a JSON object with the exit code and error, the triangle counts,
+    /// the duration of every stage in milliseconds and the outputs with their SHA-256
+    /// in hex.
+    pub fn to_json(&self) -> String {
+        let stats = &self.stats;
+        let drawn = stats.triangles_submitted
+            - stats.triangles_culled
+            - stats.triangles_occluded
+            - stats.triangles_degenerate;
+        let mut json = String::from("{\n");
+        let _ = writeln!(json, "  \"exit_code\": {},", self.exit_code);
+        let error = self
+            .error
+            .as_deref()
+            .map_or("null".to_string(), json_string);
+        let _ = writeln!(json, "  \"error\": {},", error);
+        let _ = writeln!(json, "  \"triangles\": {{");
+        let counts = [
+            ("submitted", stats.triangles_submitted),
+            ("drawn", drawn),
+            ("culled", stats.triangles_culled),
+            ("clipped", stats.triangles_clipped),
+            ("occluded", stats.triangles_occluded),
+            ("degenerate", stats.triangles_degenerate),
+            ("subpixel", stats.triangles_subpixel),
+        ];
+        let counts: Vec<String> = counts
+            .iter()
+            .map(|(name, count)| format!("    \"{}\": {}", name, count))
+            .collect();
+        let _ = writeln!(json, "{}\n  }},", counts.join(",\n"));
+        let _ = writeln!(json, "  \"objects_culled\": {},", stats.objects_culled);
+        let _ = writeln!(json, "  \"pixels_shaded\": {},", stats.pixels_shaded);
+        let stages: Vec<String> = self
+            .timings
+            .stages()
+            .iter()
+            .map(|(name, duration)| format!("    {}: {:.3}", json_string(name), ms(duration)))
+            .collect();
+        match stages.is_empty() {
+            true => json.push_str("  \"stages_ms\": {},\n"),
+            false => {
+                let _ = writeln!(json, "  \"stages_ms\": {{\n{}\n  }},", stages.join(",\n"));
+            }
+        }
+        let _ = writeln!(json, "  \"total_ms\": {:.3},", ms(&self.timings.total()));
+        let outputs: Vec<String> = self
+            .outputs
+            .iter()
+            .map(|output| {
+                let hash: String = output.sha256.iter().map(|b| format!("{:02x}", b)).collect();
+                format!(
+                    "    {{\"path\": {}, \"bytes\": {}, \"sha256\": \"{}\"}}",
+                    json_string(&output.path),
+                    output.bytes,
+                    hash
+                )
+            })
+            .collect();
+        match outputs.is_empty() {
+            true => json.push_str("  \"outputs\": []\n"),
+            false => {
+                let _ = writeln!(json, "  \"outputs\": [\n{}\n  ]", outputs.join(",\n"));
+            }
+        }
+        json.push('}');
+        json
+    }
+}
+
+fn ms(duration: &std::time::Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// `s` as a quoted JSON string.
+fn json_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(quoted, "\\u{:04x}", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// SHA-256 digest of `data`, as in FIPS 180-4.
+pub fn sha256(data: &[u8]) -> [u8; 32] {
+    let mut state: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+        0x5be0cd19,
+    ];
+    // a one bit, zeros up to 8 bytes short of a whole block and the length in bits
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+
+    for block in message.chunks_exact(64) {
+        let mut w = [0u32; 64];
+        for (i, word) in block.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let choice = (e & f) ^ (!e & g);
+            let t1 = h
+                .wrapping_add(s1)
+                .wrapping_add(choice)
+                .wrapping_add(SHA256_K[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let majority = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(majority);
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (value, added) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *value = value.wrapping_add(added);
+        }
+    }
+
+    let mut digest = [0u8; 32];
+    for (bytes, value) in digest.chunks_exact_mut(4).zip(state) {
+        bytes.copy_from_slice(&value.to_be_bytes());
+    }
+    digest
+}
+
+#[test]
+fn test_sha256() {
+    let hex =
+        |digest: [u8; 32]| -> String { digest.iter().map(|b| format!("{:02x}", b)).collect() };
+    assert_eq!(
+        hex(sha256(b"")),
+        "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+    );
+    assert_eq!(
+        hex(sha256(b"abc")),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+    // two blocks, the padding spilling into the second
+    assert_eq!(
+        hex(sha256(
+            b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"
+        )),
+        "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+    );
+}
+
+#[test]
+fn test_report_json() {
+    let mut timings = StageTimings::new();
+    timings.time("render", || ());
+    let report = Report {
+        error: Some("mesh \"a\"\nfailed".to_string()),
+        exit_code: 1,
+        stats: RenderStats {
+            triangles_submitted: 10,
+            triangles_culled: 4,
+            ..Default::default()
+        },
+        timings,
+        outputs: vec![OutputFile {
+            path: "out.png".to_string(),
+            bytes: 3,
+            sha256: sha256(b"abc"),
+        }],
+    };
+    let json = report.to_json();
+    assert!(json.contains("\"exit_code\": 1,"));
+    assert!(json.contains(r#""error": "mesh \"a\"\nfailed","#));
+    assert!(json.contains("\"drawn\": 6,"));
+    assert!(json.contains("\"render\": "));
+    assert!(json.contains(r#"{"path": "out.png", "bytes": 3, "sha256": "ba7816bf"#));
+    assert!(json.starts_with('{') && json.ends_with('}'));
+    assert!(Report::default().to_json().contains("\"outputs\": []"));
+}
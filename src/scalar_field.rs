@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+
+use crate::error::RusterizerError;
+use crate::math::{self, Vec3f};
+use crate::mesh::Mesh;
+
+/// Fraction of the diagonal of a mesh's bounds within which
+/// [`Mesh::mean_curvature`] takes vertices to be the same, such as the two ends of a
+/// seam computed in different ways.
+pub const WELD_DISTANCE: f64 = 1e-9;
+
+impl Mesh {
+    /// Mean curvature at every vertex from the cotangent Laplacian, positive where the
+    /// surface bulges out like a sphere seen from outside and the inverse of the radius
+    /// there. Vertices at the same position, up to [`WELD_DISTANCE`] times the size of the
+    /// mesh, are matched so that seams do not break up the surface; at its boundary the
+    /// estimate is meaningless.
+    pub fn mean_curvature(&self) -> Vec<f64> {
+        let count = self.positions.len();
+        let cell = self.bounds().map_or(0.0, |bounds| {
+            (bounds.max - bounds.min).length() * WELD_DISTANCE
+        });
+        let cell = if cell > 0.0 { cell } else { 1.0 };
+        let mut welded: HashMap<[i64; 3], usize> = HashMap::new();
+        let ids: Vec<usize> = self
+            .positions
+            .iter()
+            .map(|p| {
+                let next = welded.len();
+                *welded
+                    .entry([p.x, p.y, p.z].map(|c| (c / cell).round() as i64))
+                    .or_insert(next)
+            })
+            .collect();
+        let zero = Vec3f::new(0., 0., 0.);
+        let mut laplacian = vec![zero; welded.len()];
+        let mut normals = vec![zero; welded.len()];
+        let mut areas = vec![0.0; welded.len()];
+        for face in self
+            .indices
+            .iter()
+            .filter(|face| face.iter().all(|&v| v < count))
+        {
+            let p = face.map(|v| self.positions[v]);
+            let corners = face.map(|v| ids[v]);
+            let normal = math::cross(&(p[1] - p[0]), &(p[2] - p[0]));
+            let area = normal.length() / 2.0;
+            if area == 0.0 {
+                continue;
+            }
+            for k in 0..3 {
+                // the edge from i to j and the corner o opposite of it
+                let (i, j, o) = (k, (k + 1) % 3, (k + 2) % 3);
+                let (a, b) = (p[i] - p[o], p[j] - p[o]);
+                let cot = math::dot(&a, &b) / (2.0 * area);
+                laplacian[corners[i]] = laplacian[corners[i]] + (p[j] - p[i]) * cot;
+                laplacian[corners[j]] = laplacian[corners[j]] + (p[i] - p[j]) * cot;
+                normals[corners[k]] = normals[corners[k]] + normal;
+                areas[corners[k]] += area / 3.0;
+            }
+        }
+        // the Laplacian of the positions is -2H times the normal
+        let curvature: Vec<f64> = (0..welded.len())
+            .map(|v| match areas[v] > 0.0 {
+                true => -math::dot(&laplacian[v], &normals[v].normalized()) / (4.0 * areas[v]),
+                false => 0.0,
+            })
+            .collect();
+        ids.iter().map(|&id| curvature[id]).collect()
+    }
+}
+
+/// Parses values from a CSV file, one per line, taking the last column of lines with
+/// several. Empty lines and a header line are skipped.
+pub fn parse_scalars(text: &str) -> Result<Vec<f64>, RusterizerError> {
+    let mut values = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let field = line.rsplit(',').next().unwrap_or_default().trim();
+        if field.is_empty() {
+            continue;
+        }
+        match field.parse::<f64>() {
+            Ok(value) => values.push(value),
+            Err(_) if number == 0 => (),
+            Err(e) => {
+                return Err(RusterizerError::Parse(format!(
+                    "line {}: invalid value '{}': {}",
+                    number + 1,
+                    field,
+                    e
+                )))
+            }
+        }
+    }
+    Ok(values)
+}
+
+#[cfg(feature = "fs")]
+pub fn load_scalars<P: AsRef<std::path::Path>>(path: P) -> Result<Vec<f64>, RusterizerError> {
+    let path = path.as_ref();
+    let text = std::fs::read_to_string(path).map_err(|e| RusterizerError::in_file(path, e))?;
+    parse_scalars(&text).map_err(|e| match e {
+        RusterizerError::Parse(msg) => {
+            RusterizerError::Parse(format!("{}: {}", path.display(), msg))
+        }
+        e => e,
+    })
+}
+
+#[test]
+fn test_mean_curvature() {
+    let sphere = crate::geometry::sphere(0.8, 48, 24);
+    let curvature = sphere.mean_curvature();
+    assert_eq!(curvature.len(), sphere.positions.len());
+    // away from the poles, where the faces are thin; the two ends of the seam are
+    // rounded to slightly different positions
+    for (p, h) in sphere.positions.iter().zip(&curvature) {
+        if p.y.abs() < 0.5 {
+            assert!((h - 1.25).abs() < 0.05, "{} at {:?}", h, p);
+        }
+    }
+    // a plane is flat inside
+    let plane = crate::geometry::plane(1.0, 1.0, 4);
+    let center = plane
+        .positions
+        .iter()
+        .position(|p| p.length() < 1e-9)
+        .unwrap();
+    assert!(plane.mean_curvature()[center].abs() < 1e-9);
+}
+
+#[test]
+fn test_parse_scalars() {
+    let values = parse_scalars("vertex,value\n0,1.5\n1, -2\n\n2,3e2\n").unwrap();
+    assert_eq!(values, vec![1.5, -2.0, 300.0]);
+    assert_eq!(parse_scalars("0.5\n1").unwrap(), vec![0.5, 1.0]);
+    assert!(matches!(
+        parse_scalars("1\nx"),
+        Err(RusterizerError::Parse(msg)) if msg.starts_with("line 2")
+    ));
+}
@@ -0,0 +1,375 @@
+//! Mesh decimation by quadric error edge collapse, after Garland and Heckbert.
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BinaryHeap, HashMap};
+use std::ops::Add;
+
+use crate::math::{self, Aabb, Vec3f};
+use crate::mesh::Mesh;
+
+/// Distance, relative to the size of the mesh, within which positions are joined.
+const WELD_TOLERANCE: f64 = 1e-9;
+
+/// How much more moving off an open border costs than moving off a face.
+const BORDER_WEIGHT: f64 = 100.0;
+
+/// Sum of squared distances to a set of planes, kept as the upper triangle of a
+/// symmetric 4x4 matrix.
+#[derive(Clone, Copy, Debug, Default)]
+struct Quadric([f64; 10]);
+
+impl Quadric {
+    /// The plane through `point` with the unit `normal`, counted `weight` times.
+    fn plane(normal: Vec3f, point: Vec3f, weight: f64) -> Self {
+        let (a, b, c) = (normal.x, normal.y, normal.z);
+        let d = -math::dot(&normal, &point);
+        Quadric(
+            [
+                a * a,
+                a * b,
+                a * c,
+                a * d,
+                b * b,
+                b * c,
+                b * d,
+                c * c,
+                c * d,
+                d * d,
+            ]
+            .map(|q| q * weight),
+        )
+    }
+
+    fn error(&self, p: &Vec3f) -> f64 {
+        let [aa, ab, ac, ad, bb, bc, bd, cc, cd, dd] = self.0;
+        let (x, y, z) = (p.x, p.y, p.z);
+        aa * x * x
+            + bb * y * y
+            + cc * z * z
+            + dd
+            + 2.0 * (ab * x * y + ac * x * z + bc * y * z + ad * x + bd * y + cd * z)
+    }
+}
+
+impl Add for Quadric {
+    type Output = Quadric;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        let mut sum = self.0;
+        for (a, b) in sum.iter_mut().zip(rhs.0) {
+            *a += b;
+        }
+        Quadric(sum)
+    }
+}
+
+/// Merging vertex `remove` into `keep` at `target`, valid while neither has changed since.
+#[derive(Debug)]
+struct Collapse {
+    cost: f64,
+    keep: usize,
+    remove: usize,
+    target: Vec3f,
+    versions: (u32, u32),
+}
+
+impl Collapse {
+    /// The cheapest of merging at either end or halfway between them.
+    fn new(
+        keep: usize,
+        remove: usize,
+        points: &[Vec3f],
+        quadrics: &[Quadric],
+        versions: &[u32],
+    ) -> Self {
+        let quadric = quadrics[keep] + quadrics[remove];
+        let (a, b) = (points[keep], points[remove]);
+        let (cost, target) = [a, b, (a + b) * 0.5]
+            .map(|p| (quadric.error(&p), p))
+            .into_iter()
+            .min_by(|x, y| x.0.total_cmp(&y.0))
+            .unwrap();
+        Collapse {
+            cost,
+            keep,
+            remove,
+            target,
+            versions: (versions[keep], versions[remove]),
+        }
+    }
+}
+
+// ordered so that the binary heap pops the cheapest collapse first, equal costs by
+// vertex so every run decimates the same way
+impl Ord for Collapse {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .cost
+            .total_cmp(&self.cost)
+            .then_with(|| (other.keep, other.remove).cmp(&(self.keep, self.remove)))
+    }
+}
+
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Collapse {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Collapse {}
+
+fn face_normal([a, b, c]: [Vec3f; 3]) -> Vec3f {
+    math::cross(&(b - a), &(c - a))
+}
+
+impl Mesh {
+    /// Collapses edges, the ones changing the surface least first, until at most
+    /// `target_triangles` triangles are left, for quick previews of dense scans.
+    ///
+    /// Vertices are joined by position so UV seams and hard edges do not open up. Open
+    /// borders are kept in place where possible, and collapses that would turn a face
+    /// over are skipped, so more triangles than asked for remain when no further collapse
+    /// is safe. Merged vertices keep their own normals, UVs and colors; lines and points
+    /// are kept as they are.
+    pub fn decimate(&mut self, target_triangles: usize) {
+        if self.indices.len() <= target_triangles {
+            return;
+        }
+        // generated seams rarely meet exactly, so positions are joined on a fine grid
+        let size =
+            Aabb::from_points(&self.positions).map_or(0.0, |aabb| (aabb.max - aabb.min).length());
+        let cell = if size > 0.0 {
+            size * WELD_TOLERANCE
+        } else {
+            1.0
+        };
+        let key = |p: &Vec3f| [p.x, p.y, p.z].map(|c| (c / cell).round() as i64);
+        let mut ids: HashMap<[i64; 3], usize> = HashMap::new();
+        let mut points = Vec::new();
+        let welded: Vec<usize> = self
+            .positions
+            .iter()
+            .map(|p| {
+                *ids.entry(key(p)).or_insert_with(|| {
+                    points.push(*p);
+                    points.len() - 1
+                })
+            })
+            .collect();
+        let mut faces: Vec<[usize; 3]> =
+            self.indices.iter().map(|f| f.map(|v| welded[v])).collect();
+        let mut live: Vec<bool> = faces
+            .iter()
+            .map(|&[a, b, c]| a != b && b != c && c != a)
+            .collect();
+
+        let mut quadrics = vec![Quadric::default(); points.len()];
+        let mut faces_at = vec![Vec::new(); points.len()];
+        let mut edge_faces: BTreeMap<(usize, usize), usize> = BTreeMap::new();
+        for (f, &face) in faces.iter().enumerate().filter(|&(f, _)| live[f]) {
+            let normal = face_normal(face.map(|v| points[v]));
+            let length = normal.length();
+            // the cross product is as long as twice the area
+            let quadric = if length > 0.0 {
+                Quadric::plane(normal * (1.0 / length), points[face[0]], length / 2.0)
+            } else {
+                Quadric::default()
+            };
+            for (k, &v) in face.iter().enumerate() {
+                quadrics[v] = quadrics[v] + quadric;
+                faces_at[v].push(f);
+                let w = face[(k + 1) % 3];
+                *edge_faces.entry((v.min(w), v.max(w))).or_default() += 1;
+            }
+        }
+        // planes standing on the open borders hold them in place
+        for (&face, _) in faces.iter().zip(&live).filter(|(_, &live)| live) {
+            let normal = face_normal(face.map(|v| points[v]));
+            for k in 0..3 {
+                let (v, w) = (face[k], face[(k + 1) % 3]);
+                if edge_faces[&(v.min(w), v.max(w))] != 1 {
+                    continue;
+                }
+                let edge = points[w] - points[v];
+                let side = math::cross(&edge, &normal);
+                if side.length_squared() > 0.0 {
+                    let quadric = Quadric::plane(
+                        side.normalized(),
+                        points[v],
+                        BORDER_WEIGHT * edge.length_squared(),
+                    );
+                    quadrics[v] = quadrics[v] + quadric;
+                    quadrics[w] = quadrics[w] + quadric;
+                }
+            }
+        }
+
+        let mut versions = vec![0; points.len()];
+        let mut heap: BinaryHeap<Collapse> = edge_faces
+            .keys()
+            .map(|&(v, w)| Collapse::new(v, w, &points, &quadrics, &versions))
+            .collect();
+        let mut merged_into: Vec<usize> = (0..points.len()).collect();
+        let mut remaining = live.iter().filter(|&&live| live).count();
+        while remaining > target_triangles {
+            let Some(collapse) = heap.pop() else {
+                break;
+            };
+            let (keep, remove) = (collapse.keep, collapse.remove);
+            if merged_into[keep] != keep
+                || merged_into[remove] != remove
+                || collapse.versions != (versions[keep], versions[remove])
+            {
+                continue;
+            }
+
+            // faces left around the merged vertex must not turn over
+            let moved = |v: usize| {
+                if v == keep || v == remove {
+                    collapse.target
+                } else {
+                    points[v]
+                }
+            };
+            let flips = faces_at[keep]
+                .iter()
+                .chain(&faces_at[remove])
+                .filter(|&&f| live[f] && !(faces[f].contains(&keep) && faces[f].contains(&remove)))
+                .any(|&f| {
+                    let before = face_normal(faces[f].map(|v| points[v]));
+                    let after = face_normal(faces[f].map(moved));
+                    math::dot(&before, &after) <= 0.0
+                });
+            if flips {
+                continue;
+            }
+
+            points[keep] = collapse.target;
+            quadrics[keep] = quadrics[keep] + quadrics[remove];
+            merged_into[remove] = keep;
+            versions[keep] += 1;
+            for f in std::mem::take(&mut faces_at[remove]) {
+                if !live[f] {
+                    continue;
+                }
+                faces[f] = faces[f].map(|v| if v == remove { keep } else { v });
+                if faces[f].iter().filter(|&&v| v == keep).count() > 1 {
+                    live[f] = false;
+                    remaining -= 1;
+                } else {
+                    faces_at[keep].push(f);
+                }
+            }
+            faces_at[keep].retain(|&f| live[f]);
+
+            let mut neighbours: Vec<usize> = faces_at[keep]
+                .iter()
+                .flat_map(|&f| faces[f])
+                .filter(|&v| v != keep)
+                .collect();
+            neighbours.sort_unstable();
+            neighbours.dedup();
+            for v in neighbours {
+                heap.push(Collapse::new(keep, v, &points, &quadrics, &versions));
+            }
+        }
+
+        let root = |mut v: usize| {
+            while merged_into[v] != v {
+                v = merged_into[v];
+            }
+            v
+        };
+        for (p, &v) in self.positions.iter_mut().zip(&welded) {
+            *p = points[root(v)];
+        }
+        self.indices = self
+            .indices
+            .iter()
+            .zip(&live)
+            .filter(|(_, &live)| live)
+            .map(|(&face, _)| face)
+            .collect();
+        self.remove_unused_vertices();
+    }
+
+    /// Drops vertices no triangle, line or point refers to.
+    fn remove_unused_vertices(&mut self) {
+        let mut used = vec![false; self.positions.len()];
+        let referenced = self
+            .indices
+            .iter()
+            .flatten()
+            .chain(self.lines.iter().flatten())
+            .chain(&self.points);
+        for &v in referenced {
+            used[v] = true;
+        }
+        let mut remap = vec![0; self.positions.len()];
+        let mut count = 0;
+        for (v, &used) in used.iter().enumerate() {
+            remap[v] = count;
+            count += used as usize;
+        }
+        retain_used(&mut self.positions, &used);
+        retain_used(&mut self.normals, &used);
+        retain_used(&mut self.uvs, &used);
+        retain_used(&mut self.colors, &used);
+        for v in self
+            .indices
+            .iter_mut()
+            .flatten()
+            .chain(self.lines.iter_mut().flatten())
+            .chain(&mut self.points)
+        {
+            *v = remap[*v];
+        }
+    }
+}
+
+/// Keeps the per-vertex `values` of the used vertices; empty attributes stay empty.
+fn retain_used<T>(values: &mut Vec<T>, used: &[bool]) {
+    if !values.is_empty() {
+        let mut used = used.iter();
+        values.retain(|_| *used.next().unwrap());
+    }
+}
+
+#[test]
+fn test_decimate() {
+    let mut sphere = crate::geometry::sphere(1.0, 32, 16);
+    let target = sphere.indices.len() / 4;
+    let mut again = sphere.clone();
+    sphere.decimate(target);
+    again.decimate(target);
+    assert_eq!(again.indices, sphere.indices);
+    assert_eq!(again.positions, sphere.positions);
+    assert!(sphere.indices.len() <= target && sphere.indices.len() > target / 2);
+    assert!(sphere
+        .indices
+        .iter()
+        .flatten()
+        .all(|&v| v < sphere.positions.len()));
+    assert_eq!(sphere.normals.len(), sphere.positions.len());
+    for p in &sphere.positions {
+        assert!((p.length() - 1.0).abs() < 0.1, "{:?}", p);
+    }
+
+    // a flat grid collapses to two triangles between its corners without shrinking
+    let mut plane = crate::geometry::plane(2.0, 2.0, 4);
+    plane.decimate(2);
+    assert_eq!(plane.indices.len(), 2);
+    for p in &plane.positions {
+        assert!(
+            (p.x.abs() - 1.0).abs() < 1e-9 && (p.z.abs() - 1.0).abs() < 1e-9,
+            "{:?}",
+            p
+        );
+    }
+}
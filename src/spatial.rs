@@ -0,0 +1,317 @@
+use crate::math::{Aabb, Mat4, Vec3f};
+use crate::mesh::Mesh;
+use crate::ray::{Hit, Ray};
+
+/// Most faces kept in a leaf of a [`Bvh`].
+const LEAF_FACES: usize = 4;
+
+/// Bounding volume hierarchy over the faces of a mesh, answering ray and frustum
+/// queries without testing every face.
+#[derive(Clone, Debug)]
+pub struct Bvh {
+    positions: Vec<Vec3f>,
+    indices: Vec<[usize; 3]>,
+    /// Nodes with the root first, each parent before its children.
+    nodes: Vec<Node>,
+    /// Face indices, grouped so that every leaf refers to a range of them.
+    faces: Vec<usize>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Node {
+    bounds: Aabb,
+    kind: NodeKind,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum NodeKind {
+    Leaf { start: usize, end: usize },
+    Inner { left: usize, right: usize },
+}
+
+impl Bvh {
+    /// Builds the hierarchy over the faces of `mesh` transformed by `model`, splitting
+    /// every node at the median of its faces along its longest side.
+    pub fn build(mesh: &Mesh, model: &Mat4) -> Self {
+        let positions: Vec<Vec3f> = mesh
+            .positions
+            .iter()
+            .map(|p| model.transform_point(p))
+            .collect();
+        let mut bvh = Bvh {
+            indices: mesh.indices.clone(),
+            nodes: Vec::new(),
+            faces: (0..mesh.indices.len()).collect(),
+            positions,
+        };
+        if !bvh.faces.is_empty() {
+            let centroids: Vec<Vec3f> = bvh
+                .indices
+                .iter()
+                .map(|&[a, b, c]| {
+                    let p = &bvh.positions;
+                    (p[a] + p[b] + p[c]) * (1.0 / 3.0)
+                })
+                .collect();
+            bvh.build_node(0, bvh.faces.len(), &centroids);
+        }
+        bvh
+    }
+
+    /// Appends the node over `faces[start..end]` and its descendants, returning its index.
+    fn build_node(&mut self, start: usize, end: usize, centroids: &[Vec3f]) -> usize {
+        let faces = &self.faces[start..end];
+        let bounds = Aabb::from_points(
+            faces
+                .iter()
+                .flat_map(|&face| self.indices[face].map(|i| &self.positions[i])),
+        )
+        .expect("nodes are never empty");
+        let index = self.nodes.len();
+        self.nodes.push(Node {
+            bounds,
+            kind: NodeKind::Leaf { start, end },
+        });
+        if end - start <= LEAF_FACES {
+            return index;
+        }
+
+        let extent = bounds.max - bounds.min;
+        let axis = |p: &Vec3f| {
+            if extent.x >= extent.y && extent.x >= extent.z {
+                p.x
+            } else if extent.y >= extent.z {
+                p.y
+            } else {
+                p.z
+            }
+        };
+        let middle = (end - start) / 2;
+        self.faces[start..end].select_nth_unstable_by(middle, |&a, &b| {
+            axis(&centroids[a]).total_cmp(&axis(&centroids[b]))
+        });
+        let left = self.build_node(start, start + middle, centroids);
+        let right = self.build_node(start + middle, end, centroids);
+        self.nodes[index].kind = NodeKind::Inner { left, right };
+        index
+    }
+
+    /// Box around every face, `None` for a mesh without any.
+    pub fn bounds(&self) -> Option<Aabb> {
+        self.nodes.first().map(|root| root.bounds)
+    }
+
+    /// Nearest face on `ray`, as [`Ray::intersect_mesh`] would find it.
+    pub fn intersect(&self, ray: &Ray) -> Option<Hit> {
+        let mut ray = *ray;
+        let mut nearest: Option<Hit> = None;
+        let mut stack = Vec::new();
+        if !self.nodes.is_empty() {
+            stack.push(0);
+        }
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if slab_distance(&ray, &node.bounds).is_none() {
+                continue;
+            }
+            match node.kind {
+                NodeKind::Leaf { start, end } => {
+                    for &face in &self.faces[start..end] {
+                        let [a, b, c] = self.indices[face].map(|i| &self.positions[i]);
+                        if let Some((distance, barycentric)) = ray.intersect_triangle(a, b, c) {
+                            // later hits have to be nearer
+                            ray.far = distance;
+                            nearest = Some(Hit {
+                                distance,
+                                point: ray.at(distance),
+                                face,
+                                barycentric,
+                            });
+                        }
+                    }
+                }
+                NodeKind::Inner { left, right } => {
+                    // visit the nearer child first so the farther one is more often
+                    // skipped
+                    let entry = |child: usize| {
+                        slab_distance(&ray, &self.nodes[child].bounds).unwrap_or(f64::INFINITY)
+                    };
+                    if entry(left) <= entry(right) {
+                        stack.extend([right, left]);
+                    } else {
+                        stack.extend([left, right]);
+                    }
+                }
+            }
+        }
+        nearest
+    }
+
+    /// Whether any face lies on `ray`, stopping at the first one found; enough for
+    /// shadow rays, which need not know the nearest.
+    pub fn occludes(&self, ray: &Ray) -> bool {
+        let mut stack = Vec::new();
+        if !self.nodes.is_empty() {
+            stack.push(0);
+        }
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if slab_distance(ray, &node.bounds).is_none() {
+                continue;
+            }
+            match node.kind {
+                NodeKind::Leaf { start, end } => {
+                    let blocked = self.faces[start..end].iter().any(|&face| {
+                        let [a, b, c] = self.indices[face].map(|i| &self.positions[i]);
+                        ray.intersect_triangle(a, b, c).is_some()
+                    });
+                    if blocked {
+                        return true;
+                    }
+                }
+                NodeKind::Inner { left, right } => stack.extend([right, left]),
+            }
+        }
+        false
+    }
+
+    /// Faces whose bounding boxes are at least partly inside `frustum`, which includes
+    /// every face that is.
+    pub fn query_frustum(&self, frustum: &Frustum) -> Vec<usize> {
+        let mut found = Vec::new();
+        let mut stack = Vec::new();
+        if !self.nodes.is_empty() {
+            stack.push(0);
+        }
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if !frustum.may_contain(&node.bounds) {
+                continue;
+            }
+            match node.kind {
+                NodeKind::Leaf { start, end } => {
+                    found.extend(self.faces[start..end].iter().copied().filter(|&face| {
+                        let corners = self.indices[face].map(|i| &self.positions[i]);
+                        Aabb::from_points(corners).is_some_and(|b| frustum.may_contain(&b))
+                    }));
+                }
+                NodeKind::Inner { left, right } => stack.extend([right, left]),
+            }
+        }
+        found.sort_unstable();
+        found
+    }
+}
+
+/// Distance along `ray` at which it enters `bounds`, if it does within its range.
+fn slab_distance(ray: &Ray, bounds: &Aabb) -> Option<f64> {
+    let (mut near, mut far) = (ray.near, ray.far);
+    let axes = [
+        (ray.origin.x, ray.direction.x, bounds.min.x, bounds.max.x),
+        (ray.origin.y, ray.direction.y, bounds.min.y, bounds.max.y),
+        (ray.origin.z, ray.direction.z, bounds.min.z, bounds.max.z),
+    ];
+    for (origin, direction, min, max) in axes {
+        if direction == 0.0 {
+            if origin < min || origin > max {
+                return None;
+            }
+            continue;
+        }
+        let (t0, t1) = ((min - origin) / direction, (max - origin) / direction);
+        near = near.max(t0.min(t1));
+        far = far.min(t0.max(t1));
+        if near > far {
+            return None;
+        }
+    }
+    Some(near)
+}
+
+/// Region of space a projection maps into the [-1, 1] cube of normalized device
+/// coordinates, bounded by six planes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frustum {
+    /// Planes `[a, b, c, d]` with `a * x + b * y + c * z + d >= 0` on the inside.
+    pub planes: [[f64; 4]; 6],
+}
+
+impl Frustum {
+    /// The frustum of `projection`, or with the model transform of a mesh folded in, the
+    /// frustum in the mesh's own coordinates.
+    pub fn from_projection(projection: &Mat4) -> Self {
+        let m = &projection.m;
+        // clip space keeps -w <= x, y, z <= w
+        let combine = |row: usize, sign: f64| std::array::from_fn(|i| m[3][i] + sign * m[row][i]);
+        Frustum {
+            planes: [
+                combine(0, 1.0),
+                combine(0, -1.0),
+                combine(1, 1.0),
+                combine(1, -1.0),
+                combine(2, 1.0),
+                combine(2, -1.0),
+            ],
+        }
+    }
+
+    /// Whether any of `bounds` may be inside, `false` only if it lies entirely outside
+    /// one of the planes.
+    pub fn may_contain(&self, bounds: &Aabb) -> bool {
+        self.planes.iter().all(|&[a, b, c, d]| {
+            // the corner farthest along the inward normal
+            let x = if a >= 0.0 { bounds.max.x } else { bounds.min.x };
+            let y = if b >= 0.0 { bounds.max.y } else { bounds.min.y };
+            let z = if c >= 0.0 { bounds.max.z } else { bounds.min.z };
+            a * x + b * y + c * z + d >= 0.0
+        })
+    }
+}
+
+#[test]
+fn test_bvh_matches_brute_force() {
+    let torus = crate::geometry::torus(0.6, 0.25, 32, 16);
+    let model = Mat4::rotation_x(0.7);
+    let bvh = Bvh::build(&torus, &model);
+    for i in 0..200 {
+        // rays from all around towards points near the middle
+        let angle = i as f64 * 0.37;
+        let origin = Vec3f::new(
+            2.0 * angle.cos(),
+            (i as f64 * 0.13).sin(),
+            2.0 * angle.sin(),
+        );
+        let target = Vec3f::new((i as f64 * 0.71).sin() * 0.8, 0.1, 0.);
+        let ray = Ray::new(origin, target - origin);
+        let expected = ray.intersect_mesh(&torus, &model);
+        let found = bvh.intersect(&ray);
+        assert_eq!(expected.map(|hit| hit.face), found.map(|hit| hit.face));
+        assert_eq!(expected.is_some(), bvh.occludes(&ray));
+    }
+    assert!(Bvh::build(&Mesh::default(), &model)
+        .intersect(&Ray::new(Vec3f::new(0., 0., 1.), Vec3f::new(0., 0., -1.)))
+        .is_none());
+}
+
+#[test]
+fn test_query_frustum() {
+    use crate::camera::Orthographic;
+
+    let plane = crate::geometry::plane(2.0, 2.0, 8);
+    let bvh = Bvh::build(&plane, &Mat4::identity());
+    let all = bvh.query_frustum(&Frustum::from_projection(&Mat4::identity()));
+    assert_eq!(all.len(), plane.indices.len());
+
+    // a narrow box over the middle of the plane only catches the faces there
+    let volume = Orthographic {
+        left: -0.1,
+        right: 0.1,
+        ..Orthographic::default()
+    };
+    let found = bvh.query_frustum(&Frustum::from_projection(&volume.projection()));
+    assert!(!found.is_empty() && found.len() < all.len() / 2);
+    for face in found {
+        let xs = plane.indices[face].map(|i| plane.positions[i].x);
+        assert!(xs.iter().any(|&x| x >= -0.1) && xs.iter().any(|&x| x <= 0.1));
+    }
+}
@@ -0,0 +1,170 @@
+use std::fmt;
+use std::ops::AddAssign;
+use std::time::{Duration, Instant};
+
+use image::RgbImage;
+
+use crate::colormap::{Colormap, Scale};
+
+/// Counters gathered by an [`Image`](crate::drawable::Image) while drawing.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RenderStats {
+    /// Meshes skipped entirely because their bounds lie outside the view.
+    pub objects_culled: u64,
+    /// Triangles handed to the renderer, including the culled ones.
+    pub triangles_submitted: u64,
+    /// Back-facing triangles dropped before rasterization.
+    pub triangles_culled: u64,
+    /// Rasterized triangles reaching past the edges of the image.
+    pub triangles_clipped: u64,
+    /// Triangles found hidden by the hierarchical z-buffer without shading any pixel.
+    pub triangles_occluded: u64,
+    /// Triangles skipped for having no area on screen, such as ones seen edge-on.
+    pub triangles_degenerate: u64,
+    /// Rasterized triangles less than a pixel across, which test their one pixel center
+    /// at most instead of walking their bounding box.
+    pub triangles_subpixel: u64,
+    /// Pixels written after passing the depth test.
+    pub pixels_shaded: u64,
+}
+
+impl AddAssign for RenderStats {
+    fn add_assign(&mut self, other: RenderStats) {
+        self.objects_culled += other.objects_culled;
+        self.triangles_submitted += other.triangles_submitted;
+        self.triangles_culled += other.triangles_culled;
+        self.triangles_clipped += other.triangles_clipped;
+        self.triangles_occluded += other.triangles_occluded;
+        self.triangles_degenerate += other.triangles_degenerate;
+        self.triangles_subpixel += other.triangles_subpixel;
+        self.pixels_shaded += other.pixels_shaded;
+    }
+}
+
+impl fmt::Display for RenderStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "objects culled:       {}", self.objects_culled)?;
+        writeln!(f, "triangles submitted:  {}", self.triangles_submitted)?;
+        writeln!(f, "triangles culled:     {}", self.triangles_culled)?;
+        writeln!(f, "triangles clipped:    {}", self.triangles_clipped)?;
+        writeln!(f, "triangles occluded:   {}", self.triangles_occluded)?;
+        writeln!(f, "triangles degenerate: {}", self.triangles_degenerate)?;
+        writeln!(f, "triangles subpixel:   {}", self.triangles_subpixel)?;
+        write!(f, "pixels shaded:        {}", self.pixels_shaded)
+    }
+}
+
+/// Fragments drawn into every pixel of an [`Image`](crate::drawable::Image), counted
+/// once enabled with [`Image::count_overdraw`](crate::drawable::Image::count_overdraw).
+/// Rows are stored bottom-up like the depth buffer.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Overdraw {
+    pub width: u32,
+    pub height: u32,
+    /// Fragments covering every pixel, before the depth test, including those of tiles
+    /// the hierarchical z-buffer skipped.
+    pub covered: Vec<u32>,
+    /// Fragments that passed the depth test and were written.
+    pub written: Vec<u32>,
+}
+
+impl Overdraw {
+    pub fn new(width: u32, height: u32) -> Self {
+        let pixels = (width * height) as usize;
+        Overdraw {
+            width,
+            height,
+            covered: vec![0; pixels],
+            written: vec![0; pixels],
+        }
+    }
+
+    /// Counts a fragment about to be depth tested at `(x, y)`, ignoring pixels outside.
+    pub fn count_covered(&mut self, x: u32, y: u32) {
+        if x < self.width && y < self.height {
+            self.covered[(y * self.width + x) as usize] += 1;
+        }
+    }
+
+    /// Average fragments covering and written to the pixels covered at all, which early
+    /// depth testing and front-to-back sorting would bring down towards 1.
+    pub fn averages(&self) -> (f64, f64) {
+        let pixels = self.covered.iter().filter(|&&count| count > 0).count();
+        if pixels == 0 {
+            return (0.0, 0.0);
+        }
+        let sum = |counts: &[u32]| counts.iter().map(|&count| count as u64).sum::<u64>();
+        (
+            sum(&self.covered) as f64 / pixels as f64,
+            sum(&self.written) as f64 / pixels as f64,
+        )
+    }
+
+    /// `counts`, one of the buffers, as an image from no fragments at the low end of
+    /// `colormap` to `max` or more at the high end, with rows top-down as saved.
+    pub fn heatmap(&self, counts: &[u32], max: u32, colormap: Colormap) -> RgbImage {
+        RgbImage::from_fn(self.width, self.height, |x, y| {
+            let row = self.height - 1 - y;
+            let count = counts[(row * self.width + x) as usize];
+            colormap
+                .map(count as f64, (0.0, max as f64), Scale::Linear)
+                .into()
+        })
+    }
+}
+
+/// Wall-clock time spent in named stages, in the order they first ran.
+#[derive(Clone, Debug, Default)]
+pub struct StageTimings {
+    stages: Vec<(&'static str, Duration)>,
+}
+
+impl StageTimings {
+    pub fn new() -> Self {
+        StageTimings::default()
+    }
+
+    /// Runs `stage`, adding its duration to any earlier runs under the same name.
+    pub fn time<T, F: FnOnce() -> T>(&mut self, name: &'static str, stage: F) -> T {
+        let start = Instant::now();
+        let result = stage();
+        let elapsed = start.elapsed();
+        match self.stages.iter_mut().find(|(stage, _)| *stage == name) {
+            Some((_, total)) => *total += elapsed,
+            None => self.stages.push((name, elapsed)),
+        }
+        result
+    }
+
+    pub fn stages(&self) -> &[(&'static str, Duration)] {
+        &self.stages
+    }
+
+    pub fn total(&self) -> Duration {
+        self.stages.iter().map(|(_, duration)| *duration).sum()
+    }
+}
+
+impl fmt::Display for StageTimings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, duration) in &self.stages {
+            writeln!(f, "{:<20} {:>10.3} ms", format!("{}:", name), ms(duration))?;
+        }
+        write!(f, "{:<20} {:>10.3} ms", "total:", ms(&self.total()))
+    }
+}
+
+fn ms(duration: &Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+#[test]
+fn test_stage_timings() {
+    let mut timings = StageTimings::new();
+    assert_eq!(timings.time("load", || 1 + 1), 2);
+    timings.time("render", || ());
+    timings.time("load", || ());
+    let names: Vec<_> = timings.stages().iter().map(|(name, _)| *name).collect();
+    assert_eq!(names, ["load", "render"]);
+    assert!(timings.to_string().ends_with("ms"));
+}
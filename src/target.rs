@@ -0,0 +1,273 @@
+//! Buffers other than an [`Image`](crate::drawable::Image) that triangles and lines can be drawn into, such as a
+//! window surface, with [`triangle`] and [`line`].
+
+use image::GrayImage;
+
+use crate::color::{Color, HdrColor};
+use crate::drawable::{line, Framebuffer, Point3f};
+use crate::raster::{Precision, Traversal};
+use crate::stats::{Overdraw, RenderStats};
+
+/// Pixel storage with a depth buffer, addressed with row 0 at the bottom like an
+/// [`Image`](crate::drawable::Image), which is one. Besides storing pixels and depths, a target may restrict where is drawn,
+/// choose how triangles are rasterized and count what is drawn; the defaults leave all
+/// of that out.
+pub trait RenderTarget {
+    fn width(&self) -> u32;
+    fn height(&self) -> u32;
+    /// Stores a linear color, converted to whatever the target holds.
+    fn put_pixel(&mut self, x: u32, y: u32, color: HdrColor);
+    /// The depth to store for a fragment at `z` if it is nearer, that is larger, than the
+    /// stored depth, without storing it.
+    fn depth_test(&self, x: u32, y: u32, z: f64) -> Option<f64>;
+    fn set_depth(&mut self, x: u32, y: u32, z: f64);
+
+    /// First and last pixel that may be drawn, `None` if there are none.
+    fn drawable_area(&self) -> Option<((u32, u32), (u32, u32))> {
+        let (width, height) = (self.width(), self.height());
+        (width > 0 && height > 0).then(|| ((0, 0), (width - 1, height - 1)))
+    }
+
+    fn traversal(&self) -> Traversal {
+        Traversal::default()
+    }
+
+    fn precision(&self) -> Precision {
+        Precision::default()
+    }
+
+    /// Whether every pixel from `min` to `max` inclusive is known to be nearer than `z`,
+    /// so that a triangle no nearer than that can skip them.
+    fn is_hidden(&mut self, _min: (u32, u32), _max: (u32, u32), _z: f64) -> bool {
+        false
+    }
+
+    fn stats_mut(&mut self) -> Option<&mut RenderStats> {
+        None
+    }
+
+    fn overdraw_mut(&mut self) -> Option<&mut Overdraw> {
+        None
+    }
+
+    /// Draws an edge of a [`DrawStyle::Wireframe`](crate::DrawStyle::Wireframe) triangle.
+    fn wireframe_edge(&mut self, a: &Point3f, b: &Point3f, color: Color) {
+        line(self, a, b, color);
+    }
+}
+
+fn depth_test(depth: &[f64], idx: usize, z: f64) -> Option<f64> {
+    (depth[idx] < z).then_some(z)
+}
+
+/// Linear floating point colors without tone mapping, rows stored bottom-up.
+pub struct HdrTarget {
+    pub color: Framebuffer,
+    depth: Vec<f64>,
+}
+
+impl HdrTarget {
+    pub fn new(width: u32, height: u32) -> Self {
+        HdrTarget {
+            color: Framebuffer::new(width, height),
+            depth: vec![f64::NEG_INFINITY; (width * height) as usize],
+        }
+    }
+}
+
+impl RenderTarget for HdrTarget {
+    fn width(&self) -> u32 {
+        self.color.width()
+    }
+
+    fn height(&self) -> u32 {
+        self.color.height()
+    }
+
+    fn put_pixel(&mut self, x: u32, y: u32, color: HdrColor) {
+        self.color.set(x, y, color);
+    }
+
+    fn depth_test(&self, x: u32, y: u32, z: f64) -> Option<f64> {
+        depth_test(&self.depth, (y * self.color.width() + x) as usize, z)
+    }
+
+    fn set_depth(&mut self, x: u32, y: u32, z: f64) {
+        self.depth[(y * self.color.width() + x) as usize] = z;
+    }
+}
+
+/// 8-bit sRGB luminance, rows stored top-down so it converts straight into an image.
+pub struct GrayTarget {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+    depth: Vec<f64>,
+}
+
+impl GrayTarget {
+    pub fn new(width: u32, height: u32) -> Self {
+        let size = (width * height) as usize;
+        GrayTarget {
+            width,
+            height,
+            pixels: vec![0; size],
+            depth: vec![f64::NEG_INFINITY; size],
+        }
+    }
+
+    pub fn into_gray_image(self) -> GrayImage {
+        GrayImage::from_raw(self.width, self.height, self.pixels).unwrap()
+    }
+}
+
+impl RenderTarget for GrayTarget {
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn put_pixel(&mut self, x: u32, y: u32, color: HdrColor) {
+        let luminance = 0.2126 * color.0 + 0.7152 * color.1 + 0.0722 * color.2;
+        let row = self.height - 1 - y;
+        self.pixels[(row * self.width + x) as usize] =
+            HdrColor(luminance, luminance, luminance).to_srgb().0;
+    }
+
+    fn depth_test(&self, x: u32, y: u32, z: f64) -> Option<f64> {
+        depth_test(&self.depth, (y * self.width + x) as usize, z)
+    }
+
+    fn set_depth(&mut self, x: u32, y: u32, z: f64) {
+        self.depth[(y * self.width + x) as usize] = z;
+    }
+}
+
+/// Byte layout of the pixels of a [`SliceTarget`].
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PixelLayout {
+    Rgb,
+    /// RGB followed by an alpha byte, set to opaque for every drawn pixel.
+    Rgba,
+    /// Blue, green, red and an opaque alpha byte, as used by many window surfaces.
+    Bgra,
+}
+
+impl PixelLayout {
+    pub fn bytes_per_pixel(&self) -> usize {
+        match self {
+            PixelLayout::Rgb => 3,
+            PixelLayout::Rgba | PixelLayout::Bgra => 4,
+        }
+    }
+}
+
+/// Draws into caller-owned bytes holding top-down rows of `stride` bytes, such as a
+/// window surface, keeping its own depth buffer.
+pub struct SliceTarget<'a> {
+    pixels: &'a mut [u8],
+    width: u32,
+    height: u32,
+    stride: usize,
+    layout: PixelLayout,
+    depth: Vec<f64>,
+}
+
+impl<'a> SliceTarget<'a> {
+    /// Returns `None` if `pixels` is too small for `height` rows of `stride` bytes or a
+    /// row is too short for `width` pixels.
+    pub fn new(
+        pixels: &'a mut [u8],
+        width: u32,
+        height: u32,
+        stride: usize,
+        layout: PixelLayout,
+    ) -> Option<Self> {
+        let fits = stride >= width as usize * layout.bytes_per_pixel()
+            && pixels.len() >= stride * height as usize;
+        fits.then(|| SliceTarget {
+            pixels,
+            width,
+            height,
+            stride,
+            layout,
+            depth: vec![f64::NEG_INFINITY; (width * height) as usize],
+        })
+    }
+}
+
+impl RenderTarget for SliceTarget<'_> {
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn put_pixel(&mut self, x: u32, y: u32, color: HdrColor) {
+        let Color(r, g, b) = color.to_srgb();
+        let row = (self.height - 1 - y) as usize;
+        let start = row * self.stride + x as usize * self.layout.bytes_per_pixel();
+        let bytes = &mut self.pixels[start..start + self.layout.bytes_per_pixel()];
+        match self.layout {
+            PixelLayout::Rgb => bytes.copy_from_slice(&[r, g, b]),
+            PixelLayout::Rgba => bytes.copy_from_slice(&[r, g, b, 255]),
+            PixelLayout::Bgra => bytes.copy_from_slice(&[b, g, r, 255]),
+        }
+    }
+
+    fn depth_test(&self, x: u32, y: u32, z: f64) -> Option<f64> {
+        depth_test(&self.depth, (y * self.width + x) as usize, z)
+    }
+
+    fn set_depth(&mut self, x: u32, y: u32, z: f64) {
+        self.depth[(y * self.width + x) as usize] = z;
+    }
+}
+
+#[test]
+fn test_targets_match_image() {
+    use crate::drawable::{triangle, Drawable, Image};
+    use crate::DrawStyle;
+
+    let (a, b, c) = (
+        Point3f::new(1., 1., 0.),
+        Point3f::new(14., 3., 0.),
+        Point3f::new(6., 13., 0.),
+    );
+    let style = DrawStyle::Filled(Color(200, 100, 50));
+    let mut image = Image::new(16, 16);
+    image.triangle(&a, &b, &c, &style, 1.0);
+    let expected = image.into_rgb_buffer();
+    // an image drawn into as a target draws the same
+    let mut target = Image::new(16, 16);
+    triangle(&mut target, &a, &b, &c, &style, 1.0);
+    assert_eq!(target.into_rgb_buffer(), expected);
+
+    let mut hdr = HdrTarget::new(16, 16);
+    triangle(&mut hdr, &a, &b, &c, &style, 1.0);
+    assert_eq!(hdr.color.get(6, 6).to_srgb(), Color(200, 100, 50));
+
+    let mut gray = GrayTarget::new(16, 16);
+    triangle(&mut gray, &a, &b, &c, &style, 1.0);
+    let gray = gray.into_gray_image();
+
+    // a window surface with padded rows
+    let mut surface = vec![0u8; 16 * 72];
+    let mut target = SliceTarget::new(&mut surface, 16, 16, 72, PixelLayout::Bgra).unwrap();
+    triangle(&mut target, &a, &b, &c, &style, 1.0);
+    for (x, y, pixel) in expected.enumerate_pixels() {
+        let start = y as usize * 72 + x as usize * 4;
+        let [r, g, b] = pixel.0;
+        let covered = (r, g, b) != (0, 0, 0);
+        assert_eq!(surface[start..start + 3], [b, g, r]);
+        assert_eq!(surface[start + 3] == 255, covered);
+        assert_eq!(gray.get_pixel(x, y)[0] > 0, covered);
+    }
+    assert!(SliceTarget::new(&mut surface, 16, 16, 60, PixelLayout::Bgra).is_none());
+}
@@ -0,0 +1,257 @@
+use std::sync::Arc;
+
+use image::{DynamicImage, GrayImage, RgbImage, RgbaImage};
+
+use crate::color::{Color, HdrColor};
+
+/// How the values of a texture are to be read: color maps are usually sRGB encoded, while
+/// normal, roughness and height maps hold data to be used as it is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ColorSpace {
+    Srgb,
+    Linear,
+}
+
+impl std::str::FromStr for ColorSpace {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "srgb" => Ok(ColorSpace::Srgb),
+            "linear" => Ok(ColorSpace::Linear),
+            _ => Err(format!("unknown color space '{}'", s)),
+        }
+    }
+}
+
+/// Channels a texture is decoded to, whatever the file stores.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Channels {
+    Gray,
+    Rgb,
+    Rgba,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TextureFormat {
+    pub channels: Channels,
+    pub color_space: ColorSpace,
+}
+
+impl TextureFormat {
+    /// Base color maps.
+    pub const COLOR: TextureFormat = TextureFormat {
+        channels: Channels::Rgb,
+        color_space: ColorSpace::Srgb,
+    };
+    /// Normal maps and other vectors stored as colors.
+    pub const VECTOR: TextureFormat = TextureFormat {
+        channels: Channels::Rgb,
+        color_space: ColorSpace::Linear,
+    };
+    /// Height, roughness and other single values.
+    pub const SCALAR: TextureFormat = TextureFormat {
+        channels: Channels::Gray,
+        color_space: ColorSpace::Linear,
+    };
+}
+
+/// Texel storage for each of the [`Channels`], shared between the meshes using it.
+#[derive(Clone, Debug)]
+pub enum Texels {
+    Gray(Arc<GrayImage>),
+    Rgb(Arc<RgbImage>),
+    Rgba(Arc<RgbaImage>),
+    /// Squares along each side of a [`Texture::checker`], computed where it is sampled.
+    Checker(u32),
+}
+
+/// Texels per side a checkerboard is given when it has to be turned into an image,
+/// keeping up to [`CHECKER_CELL`] texels per square.
+const CHECKER_MAX_SIZE: u32 = 4096;
+const CHECKER_CELL: u32 = 8;
+
+/// Image sampled by UV, with row 0 at `v = 0`.
+#[derive(Clone, Debug)]
+pub struct Texture {
+    pub texels: Texels,
+    pub color_space: ColorSpace,
+}
+
+impl Texture {
+    /// Converts `image` to the channels of `format`, flipping it so that the bottom row of
+    /// the stored image is at `v = 0`. Channels are reduced to 8 bits as they are.
+    pub fn from_image(image: &DynamicImage, format: TextureFormat) -> Self {
+        let image = image.flipv();
+        let texels = match format.channels {
+            Channels::Gray => Texels::Gray(Arc::new(image.to_luma8())),
+            Channels::Rgb => Texels::Rgb(Arc::new(image.to_rgb8())),
+            Channels::Rgba => Texels::Rgba(Arc::new(image.to_rgba8())),
+        };
+        Texture {
+            texels,
+            color_space: format.color_space,
+        }
+    }
+
+    /// Procedural checkerboard of `squares` by `squares` cells, for inspecting UV seams,
+    /// stretching and the perspective correction of the interpolation. Light cells are
+    /// tinted red along `u` and green along `v` so that flipped UVs show. Nothing is
+    /// stored; the cells are worked out from the UVs each lookup.
+    pub fn checker(squares: u32) -> Self {
+        Texture {
+            texels: Texels::Checker(squares.max(1)),
+            color_space: ColorSpace::Srgb,
+        }
+    }
+
+    pub fn format(&self) -> TextureFormat {
+        let channels = match self.texels {
+            Texels::Gray(_) => Channels::Gray,
+            Texels::Rgb(_) => Channels::Rgb,
+            Texels::Rgba(_) => Channels::Rgba,
+            Texels::Checker(_) => Channels::Rgb,
+        };
+        TextureFormat {
+            channels,
+            color_space: self.color_space,
+        }
+    }
+
+    pub fn dimensions(&self) -> (u32, u32) {
+        match &self.texels {
+            Texels::Gray(texels) => texels.dimensions(),
+            Texels::Rgb(texels) => texels.dimensions(),
+            Texels::Rgba(texels) => texels.dimensions(),
+            &Texels::Checker(squares) => {
+                let size = (squares as u64 * CHECKER_CELL as u64).min(CHECKER_MAX_SIZE as u64);
+                (size as u32, size as u32)
+            }
+        }
+    }
+
+    /// Nearest-neighbour lookup clamping coordinates past 1, decoded to linear RGBA. Gray
+    /// values are repeated in all three color channels and alpha is 1 if there is none.
+    pub fn sample(&self, u: f64, v: f64) -> [f32; 4] {
+        if let Texels::Checker(squares) = self.texels {
+            return checker_sample(squares, u, v);
+        }
+        let (width, height) = self.dimensions();
+        let x = ((u * width as f64) as u32).min(width - 1);
+        let y = ((v * height as f64) as u32).min(height - 1);
+        let ([r, g, b], alpha) = match &self.texels {
+            Texels::Gray(texels) => {
+                let value = texels.get_pixel(x, y)[0];
+                ([value; 3], 255)
+            }
+            Texels::Rgb(texels) => (texels.get_pixel(x, y).0, 255),
+            Texels::Rgba(texels) => {
+                let [r, g, b, a] = texels.get_pixel(x, y).0;
+                ([r, g, b], a)
+            }
+            Texels::Checker(_) => unreachable!(),
+        };
+        let HdrColor(r, g, b) = match self.color_space {
+            ColorSpace::Srgb => HdrColor::from(Color(r, g, b)),
+            ColorSpace::Linear => HdrColor(r as f32, g as f32, b as f32).scale(1.0 / 255.0),
+        };
+        [r, g, b, alpha as f32 / 255.0]
+    }
+
+    /// The texels as an image with the bottom row at `v = 0`, the way
+    /// [`Texture::from_image`] takes it.
+    pub fn to_image(&self) -> DynamicImage {
+        let image = match &self.texels {
+            Texels::Gray(texels) => DynamicImage::from((**texels).clone()),
+            Texels::Rgb(texels) => DynamicImage::from((**texels).clone()),
+            Texels::Rgba(texels) => DynamicImage::from((**texels).clone()),
+            Texels::Checker(_) => DynamicImage::from((*self.to_srgb_rgb()).clone()),
+        };
+        image.flipv()
+    }
+
+    /// The texture as the sRGB colors [`DrawStyle::Textured`](crate::DrawStyle) draws,
+    /// shared with the texture if it is already stored that way.
+    pub fn to_srgb_rgb(&self) -> Arc<RgbImage> {
+        if let (Texels::Rgb(texels), ColorSpace::Srgb) = (&self.texels, self.color_space) {
+            return texels.clone();
+        }
+        let (width, height) = self.dimensions();
+        RgbImage::from_fn(width, height, |x, y| {
+            let [r, g, b, _] = self.sample(
+                (x as f64 + 0.5) / width as f64,
+                (y as f64 + 0.5) / height as f64,
+            );
+            HdrColor(r, g, b).to_srgb().into()
+        })
+        .into()
+    }
+
+    /// One value per texel, shared with the texture if it is gray; colors are reduced to
+    /// their luma.
+    pub fn to_gray(&self) -> Arc<GrayImage> {
+        match &self.texels {
+            Texels::Gray(texels) => texels.clone(),
+            Texels::Rgb(texels) => Arc::new(DynamicImage::from((**texels).clone()).to_luma8()),
+            Texels::Rgba(texels) => Arc::new(DynamicImage::from((**texels).clone()).to_luma8()),
+            Texels::Checker(_) => {
+                Arc::new(DynamicImage::from((*self.to_srgb_rgb()).clone()).to_luma8())
+            }
+        }
+    }
+}
+
+/// Color of the checkerboard cell holding `(u, v)`, clamped to the board like the
+/// lookup of stored texels.
+fn checker_sample(squares: u32, u: f64, v: f64) -> [f32; 4] {
+    let cell = |t: f64| ((t * squares as f64).floor() as i64).clamp(0, squares as i64 - 1);
+    let (column, row) = (cell(u), cell(v));
+    let Color(r, g, b) = if (column + row) & 1 == 0 {
+        Color(40, 40, 40)
+    } else {
+        let tint = |cell: i64| (128 + cell * 127 / squares as i64) as u8;
+        Color(tint(column), tint(row), 160)
+    };
+    let HdrColor(r, g, b) = HdrColor::from(Color(r, g, b));
+    [r, g, b, 1.0]
+}
+
+#[test]
+fn test_color_spaces() {
+    let mut image = RgbImage::new(2, 1);
+    image.put_pixel(1, 0, image::Rgb([128, 128, 128]));
+    let image = DynamicImage::from(image);
+
+    let color = Texture::from_image(&image, TextureFormat::COLOR);
+    let data = Texture::from_image(&image, TextureFormat::VECTOR);
+    // sRGB mid gray is about a fifth of the light, as data it stays a half
+    assert!((color.sample(0.75, 0.5)[0] - 0.216).abs() < 0.001);
+    assert!((data.sample(0.75, 0.5)[0] - 0.502).abs() < 0.001);
+    assert_eq!(data.sample(0.75, 0.5)[3], 1.0);
+
+    // linear data is encoded to draw as the value it holds
+    assert_eq!(color.to_srgb_rgb().get_pixel(1, 0).0, [128, 128, 128]);
+    assert_eq!(data.to_srgb_rgb().get_pixel(1, 0).0, [188, 188, 188]);
+
+    let scalar = Texture::from_image(&image, TextureFormat::SCALAR);
+    assert_eq!(scalar.format(), TextureFormat::SCALAR);
+    assert_eq!(scalar.to_gray().get_pixel(1, 0)[0], 128);
+    assert_eq!(scalar.to_image().to_luma8(), image.to_luma8());
+}
+
+#[test]
+fn test_checker() {
+    let checker = Texture::checker(4);
+    let cell = |u: f64, v: f64| checker.sample(u, v);
+    // neighbouring cells alternate, light ones getting redder along u
+    assert!(cell(0.125, 0.125)[0] < cell(0.375, 0.125)[0]);
+    assert!(cell(0.375, 0.125)[0] > cell(0.625, 0.125)[0]);
+    assert!(cell(0.375, 0.125)[0] < cell(0.875, 0.125)[0]);
+    assert_eq!(cell(0.125, 0.125), cell(0.375, 0.375));
+
+    // the board is not stored, so any number of squares can be asked for
+    let fine = Texture::checker(u32::MAX);
+    assert_eq!(fine.dimensions(), (CHECKER_MAX_SIZE, CHECKER_MAX_SIZE));
+    let next = 0.5 + 1.0 / u32::MAX as f64;
+    assert_ne!(fine.sample(0.5, 0.5)[1], fine.sample(next, 0.5)[1]);
+}
@@ -0,0 +1,81 @@
+#[cfg(feature = "fs")]
+use std::fs::File;
+#[cfg(feature = "fs")]
+use std::io::BufWriter;
+use std::io::Write;
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+use image::error::EncodingError;
+use image::{ImageError, ImageFormat, ImageResult};
+
+use crate::drawable::Image;
+
+/// Renders a `width` x `height` image as horizontal bands of at most `band_height` rows
+/// and streams them to a PNG, so only one band's buffers are ever in memory.
+///
+/// `draw` is called once per band, top band first, with an image made by
+/// [`Image::band`]; it has to submit the whole scene every time. Passes that look at
+/// neighbouring pixels, such as post-processing, see only the band they run on.
+pub fn render_png<W: Write, F: FnMut(&mut Image)>(
+    writer: W,
+    width: u32,
+    height: u32,
+    band_height: u32,
+    mut draw: F,
+) -> ImageResult<()> {
+    let to_image_error = |e: png::EncodingError| {
+        ImageError::Encoding(EncodingError::new(ImageFormat::Png.into(), e))
+    };
+
+    let mut encoder = png::Encoder::new(writer, width, height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(to_image_error)?;
+    let mut stream = writer.stream_writer().map_err(to_image_error)?;
+
+    // PNG rows go top to bottom while image rows are counted from the bottom
+    let band_height = band_height.max(1);
+    let mut top = height;
+    while top > 0 {
+        let rows = band_height.min(top);
+        let mut band = Image::band(width, height, top - rows, rows);
+        draw(&mut band);
+        stream.write_all(band.into_rgb_buffer().as_raw())?;
+        top -= rows;
+    }
+    stream.finish().map_err(to_image_error)
+}
+
+/// Like [`render_png`], writing to the file at `path`.
+#[cfg(feature = "fs")]
+pub fn save_png<Q: AsRef<Path>, F: FnMut(&mut Image)>(
+    path: Q,
+    width: u32,
+    height: u32,
+    band_height: u32,
+    draw: F,
+) -> ImageResult<()> {
+    let file = BufWriter::new(File::create(path)?);
+    render_png(file, width, height, band_height, draw)
+}
+
+#[test]
+fn test_bands_match_full_render() {
+    use crate::color::Color;
+    use crate::math::Mat4;
+    use crate::render::draw_mesh;
+    use crate::DrawStyle;
+
+    let sphere = crate::geometry::sphere(0.8, 16, 8);
+    let style = DrawStyle::Filled(Color(200, 150, 100));
+    let draw = |image: &mut Image| draw_mesh(image, &sphere, &style, &Mat4::rotation_x(0.3));
+
+    let mut full = Image::new(40, 30);
+    draw(&mut full);
+    let mut png = Vec::new();
+    render_png(&mut png, 40, 30, 7, draw).unwrap();
+
+    let banded = image::load_from_memory(&png).unwrap().to_rgb8();
+    assert_eq!(banded, full.into_rgb_buffer());
+}
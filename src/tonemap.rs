@@ -0,0 +1,75 @@
+use crate::color::{Color, HdrColor};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ToneMapOperator {
+    /// Values above 1.0 are clipped.
+    Clamp,
+    Reinhard,
+    /// Narkowicz's fit of the ACES filmic curve.
+    Aces,
+}
+
+impl std::str::FromStr for ToneMapOperator {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" | "clamp" => Ok(ToneMapOperator::Clamp),
+            "reinhard" => Ok(ToneMapOperator::Reinhard),
+            "aces" => Ok(ToneMapOperator::Aces),
+            _ => Err(format!("unknown tone mapping operator '{}'", s)),
+        }
+    }
+}
+
+/// Converts linear HDR values to displayable 8-bit sRGB colors.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ToneMapping {
+    pub exposure: f32,
+    pub operator: ToneMapOperator,
+}
+
+impl Default for ToneMapping {
+    fn default() -> Self {
+        ToneMapping {
+            exposure: 1.0,
+            operator: ToneMapOperator::Clamp,
+        }
+    }
+}
+
+impl ToneMapping {
+    pub fn apply(&self, color: HdrColor) -> Color {
+        let exposed = color.map(|c| c.max(0.0) * self.exposure);
+        let mapped = match self.operator {
+            ToneMapOperator::Clamp => exposed,
+            ToneMapOperator::Reinhard => exposed.map(|c| c / (1.0 + c)),
+            ToneMapOperator::Aces => {
+                exposed.map(|c| (c * (2.51 * c + 0.03)) / (c * (2.43 * c + 0.59) + 0.14))
+            }
+        };
+        mapped.to_srgb()
+    }
+}
+
+#[test]
+fn test_tone_mapping_operators() {
+    let bright = HdrColor(4.0, 1.0, 0.0);
+    let clamp = ToneMapping::default().apply(bright);
+    assert_eq!((clamp.0, clamp.1, clamp.2), (255, 255, 0));
+
+    let reinhard = ToneMapping {
+        exposure: 1.0,
+        operator: ToneMapOperator::Reinhard,
+    }
+    .apply(bright);
+    assert!(reinhard.0 < 255 && reinhard.0 > reinhard.1);
+
+    let aces = ToneMapping {
+        exposure: 1.0,
+        operator: ToneMapOperator::Aces,
+    }
+    .apply(HdrColor(100.0, 0.0, 0.0));
+    assert_eq!(aces.0, 255);
+    assert_eq!(aces.2, 0);
+}
@@ -0,0 +1,186 @@
+use std::collections::HashMap;
+
+use image::RgbImage;
+
+use crate::color::{self, Color};
+use crate::drawable::{sample_texture, Drawable, Image};
+use crate::mesh::Mesh;
+
+/// Colors of the edges drawn by [`draw_uv_layout`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UvLayoutStyle {
+    pub edge: Color,
+    /// Edges where the surface continues with other UVs, cutting it into islands.
+    pub seam: Color,
+}
+
+impl Default for UvLayoutStyle {
+    fn default() -> Self {
+        UvLayoutStyle {
+            edge: color::WHITE,
+            seam: color::MAGENTA,
+        }
+    }
+}
+
+/// Edge of a face in UV space, and whether it lies on a seam.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct UvEdge {
+    a: [f64; 2],
+    b: [f64; 2],
+    seam: bool,
+}
+
+/// Draws the triangles of `meshes` where their UVs put them over the 0..1 square
+/// stretched across `image`, over `texture` if given so that texels line up with the
+/// faces sampling them. Seams are drawn over the other edges in their own color; parts
+/// of faces outside of 0..1 are left out.
+pub fn draw_uv_layout(
+    image: &mut Image,
+    meshes: &[Mesh],
+    texture: Option<&RgbImage>,
+    style: &UvLayoutStyle,
+) {
+    let (width, height) = (image.width(), image.height());
+    if let Some(texture) = texture {
+        for y in 0..height {
+            for x in 0..width {
+                let u = (x as f64 + 0.5) / width as f64;
+                let v = (y as f64 + 0.5) / height as f64;
+                image.point(x, y, sample_texture(texture, u, v));
+            }
+        }
+    }
+    let to_pixel = |[u, v]: [f64; 2]| {
+        (
+            (u * (width - 1) as f64).round() as u32,
+            (v * (height - 1) as f64).round() as u32,
+        )
+    };
+    let edges: Vec<UvEdge> = meshes.iter().flat_map(uv_edges).collect();
+    for seams in [false, true] {
+        let color = if seams { style.seam } else { style.edge };
+        for edge in edges.iter().filter(|edge| edge.seam == seams) {
+            let Some((a, b)) = clip_to_unit_square(edge.a, edge.b) else {
+                continue;
+            };
+            let ((x0, y0), (x1, y1)) = (to_pixel(a), to_pixel(b));
+            image.line(x0, y0, x1, y1, color);
+        }
+    }
+}
+
+/// Every edge of the faces of `mesh` in UV space, once. Edges are matched by the
+/// positions and UVs of their ends, so an edge shared by faces with different UVs on
+/// either side is a seam even where the vertices are split.
+fn uv_edges(mesh: &Mesh) -> Vec<UvEdge> {
+    let count = mesh.positions.len().min(mesh.uvs.len());
+    let key = |v: usize| {
+        let p = mesh.positions[v];
+        let [u, w] = mesh.uvs[v];
+        // joins 0 and -0
+        [p.x, p.y, p.z, u, w].map(|c| (c + 0.0).to_bits())
+    };
+    let sorted = |a: [u64; 5], b: [u64; 5]| (a.min(b), a.max(b));
+    let mut uv_faces: HashMap<_, usize> = HashMap::new();
+    let mut position_faces: HashMap<_, usize> = HashMap::new();
+    let mut edges = Vec::new();
+    for face in mesh
+        .indices
+        .iter()
+        .filter(|face| face.iter().all(|&v| v < count))
+    {
+        for (i, j) in [(0, 1), (1, 2), (2, 0)] {
+            let (a, b) = (face[i], face[j]);
+            let (key_a, key_b) = (key(a), key(b));
+            let uv_key = sorted(key_a, key_b);
+            let position = |[x, y, z, ..]: [u64; 5]| [x, y, z, 0, 0];
+            let position_key = sorted(position(key_a), position(key_b));
+            *position_faces.entry(position_key).or_default() += 1;
+            let faces = uv_faces.entry(uv_key).or_default();
+            if *faces == 0 {
+                edges.push((uv_key, position_key, mesh.uvs[a], mesh.uvs[b]));
+            }
+            *faces += 1;
+        }
+    }
+    edges
+        .into_iter()
+        .map(|(uv_key, position_key, a, b)| UvEdge {
+            a,
+            b,
+            seam: uv_faces[&uv_key] < position_faces[&position_key],
+        })
+        .collect()
+}
+
+/// The part of the segment from `a` to `b` inside of the 0..1 square, if any.
+fn clip_to_unit_square(a: [f64; 2], b: [f64; 2]) -> Option<([f64; 2], [f64; 2])> {
+    let (mut t0, mut t1) = (0.0f64, 1.0f64);
+    for axis in 0..2 {
+        let (start, delta) = (a[axis], b[axis] - a[axis]);
+        // inside of the bound where `p * t <= q`, entering it where `p < 0`
+        for (p, q) in [(-delta, start), (delta, 1.0 - start)] {
+            if p == 0.0 {
+                if q < 0.0 {
+                    return None;
+                }
+            } else if p < 0.0 {
+                t0 = t0.max(q / p);
+            } else {
+                t1 = t1.min(q / p);
+            }
+        }
+    }
+    if t0 > t1 {
+        return None;
+    }
+    let at = |t: f64| [a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t];
+    Some((at(t0), at(t1)))
+}
+
+#[test]
+fn test_uv_edges() {
+    // the plane is a single island, its diagonal is no seam
+    let plane = crate::geometry::plane(1.0, 1.0, 1);
+    let edges = uv_edges(&plane);
+    assert_eq!(edges.len(), 5);
+    assert!(edges.iter().all(|edge| !edge.seam));
+
+    // every face of the cube is an island of its own
+    let cube = crate::geometry::cuboid(crate::math::Vec3f::new(1., 1., 1.));
+    let edges = uv_edges(&cube);
+    assert_eq!(edges.len(), 30);
+    assert_eq!(edges.iter().filter(|edge| edge.seam).count(), 24);
+
+    assert_eq!(
+        clip_to_unit_square([-1.0, 0.5], [2.0, 0.5]),
+        Some(([0.0, 0.5], [1.0, 0.5]))
+    );
+    assert_eq!(clip_to_unit_square([-1.0, 2.0], [2.0, 2.0]), None);
+}
+
+#[test]
+fn test_draw_uv_layout() {
+    let cube = crate::geometry::cuboid(crate::math::Vec3f::new(1., 1., 1.));
+    let texture = RgbImage::from_pixel(4, 4, image::Rgb([0, 0, 255]));
+    let mut image = Image::new(16, 16);
+    draw_uv_layout(
+        &mut image,
+        &[cube],
+        Some(&texture),
+        &UvLayoutStyle::default(),
+    );
+    let pixels = image.to_rgb_image();
+    // seams around the border, the texture inside and a diagonal across it
+    assert_eq!(pixels.get_pixel(0, 8).0, [255, 0, 255]);
+    assert_eq!(pixels.get_pixel(15, 8).0, [255, 0, 255]);
+    assert_eq!(pixels.get_pixel(4, 8).0, [0, 0, 255]);
+    let diagonal = (1..15)
+        .filter(|&x| pixels.get_pixel(x, x).0 == [255, 255, 255])
+        .count()
+        + (1..15)
+            .filter(|&x| pixels.get_pixel(x, 15 - x).0 == [255, 255, 255])
+            .count();
+    assert!(diagonal >= 10);
+}
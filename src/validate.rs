@@ -0,0 +1,177 @@
+//! Checks for the defects in loaded meshes that make drawing panic or quietly come out
+//! wrong, counted by [`Mesh::validate`].
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::math::{self, Vec3f};
+use crate::mesh::Mesh;
+
+/// Faces whose area is below this fraction of the squared diagonal of the mesh's bounds
+/// count as degenerate.
+const DEGENERATE_AREA: f64 = 1e-12;
+
+/// What [`Mesh::validate`] found, as counts of the offending elements.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MeshIssues {
+    /// Face, line and point indices past the last vertex, which make drawing panic.
+    pub out_of_range_indices: usize,
+    /// Attributes holding neither nothing nor one value per position, which do too.
+    pub mismatched_attributes: Vec<&'static str>,
+    /// Vertices with an infinite or NaN position, normal or UV.
+    pub non_finite_vertices: usize,
+    /// Faces with repeated corners or no area, which draw nothing.
+    pub degenerate_faces: usize,
+    /// Edges shared by more than two faces, which smooth normals and outlines get wrong.
+    pub non_manifold_edges: usize,
+    /// Edges of a single face, where a closed surface has a hole.
+    pub boundary_edges: usize,
+    /// UVs outside of 0..1, which sample a repeated texture.
+    pub uvs_out_of_range: usize,
+}
+
+impl MeshIssues {
+    /// Whether any issue was found.
+    pub fn is_empty(&self) -> bool {
+        *self == MeshIssues::default()
+    }
+
+    /// Whether the mesh cannot be drawn as it is, rather than only drawn poorly.
+    pub fn is_broken(&self) -> bool {
+        self.out_of_range_indices > 0
+            || !self.mismatched_attributes.is_empty()
+            || self.non_finite_vertices > 0
+    }
+}
+
+/// One issue per line.
+impl fmt::Display for MeshIssues {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for attribute in &self.mismatched_attributes {
+            writeln!(f, "{} do not match the positions", attribute)?;
+        }
+        let counts = [
+            (self.out_of_range_indices, "out-of-range indices"),
+            (
+                self.non_finite_vertices,
+                "vertices with infinite or NaN values",
+            ),
+            (self.degenerate_faces, "degenerate faces"),
+            (self.non_manifold_edges, "non-manifold edges"),
+            (self.boundary_edges, "boundary edges"),
+            (self.uvs_out_of_range, "UVs outside of 0..1"),
+        ];
+        for (count, what) in counts {
+            if count > 0 {
+                writeln!(f, "{} {}", count, what)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Mesh {
+    /// Counts the defects of the mesh listed in [`MeshIssues`]. Edges are matched by
+    /// position, so that vertices split along UV seams and hard edges do not open up
+    /// the surface; faces with out-of-range indices are left out of the other checks.
+    pub fn validate(&self) -> MeshIssues {
+        let count = self.positions.len();
+        let mut issues = MeshIssues::default();
+        for (attribute, len) in [
+            ("normals", self.normals.len()),
+            ("UVs", self.uvs.len()),
+            ("colors", self.colors.len()),
+        ] {
+            if len != 0 && len != count {
+                issues.mismatched_attributes.push(attribute);
+            }
+        }
+        issues.out_of_range_indices = self
+            .indices
+            .iter()
+            .flatten()
+            .chain(self.lines.iter().flatten())
+            .chain(&self.points)
+            .filter(|&&v| v >= count)
+            .count();
+        let finite = |v: &Vec3f| v.x.is_finite() && v.y.is_finite() && v.z.is_finite();
+        issues.non_finite_vertices = (0..count)
+            .filter(|&v| {
+                !finite(&self.positions[v])
+                    || self.normals.get(v).is_some_and(|n| !finite(n))
+                    || self
+                        .uvs
+                        .get(v)
+                        .is_some_and(|uv| !uv.iter().all(|c| c.is_finite()))
+            })
+            .count();
+        issues.uvs_out_of_range = self
+            .uvs
+            .iter()
+            .filter(|uv| uv.iter().any(|c| !(0.0..=1.0).contains(c)))
+            .count();
+
+        let min_area = self.bounds().map_or(0.0, |bounds| {
+            (bounds.max - bounds.min).length_squared() * DEGENERATE_AREA
+        });
+        let key = |v: usize| {
+            let p = self.positions[v];
+            // joins 0 and -0
+            [p.x, p.y, p.z].map(|c| (c + 0.0).to_bits())
+        };
+        let mut edges: HashMap<_, usize> = HashMap::new();
+        for face in self
+            .indices
+            .iter()
+            .filter(|face| face.iter().all(|&v| v < count))
+        {
+            let [a, b, c] = face.map(|v| self.positions[v]);
+            let area = math::cross(&(b - a), &(c - a)).length() / 2.0;
+            let keys = face.map(key);
+            if keys[0] == keys[1] || keys[1] == keys[2] || keys[2] == keys[0] || area <= min_area {
+                issues.degenerate_faces += 1;
+                continue;
+            }
+            for (k1, k2) in [(0, 1), (1, 2), (2, 0)] {
+                let (a, b) = (keys[k1], keys[k2]);
+                *edges.entry((a.min(b), a.max(b))).or_default() += 1;
+            }
+        }
+        issues.non_manifold_edges = edges.values().filter(|&&faces| faces > 2).count();
+        issues.boundary_edges = edges.values().filter(|&&faces| faces == 1).count();
+        issues
+    }
+}
+
+#[test]
+fn test_validate() {
+    // a cube is closed even though its faces have their own vertices
+    let cube = crate::geometry::cuboid(Vec3f::new(1., 1., 1.));
+    assert!(cube.validate().is_empty());
+
+    let mut plane = crate::geometry::plane(1.0, 1.0, 1);
+    assert_eq!(plane.validate().boundary_edges, 4);
+    // a face folded back onto the diagonal and one without area
+    let [a, c, _] = plane.indices[0];
+    let far = plane.positions.len();
+    plane.positions.push(Vec3f::new(0., 1., 0.));
+    plane.normals.push(Vec3f::new(0., 0., 1.));
+    plane.uvs.push([2.0, 0.5]);
+    plane.indices.push([a, c, far]);
+    plane.indices.push([a, a, c]);
+    let issues = plane.validate();
+    assert_eq!(issues.non_manifold_edges, 1);
+    assert_eq!(issues.degenerate_faces, 1);
+    assert_eq!(issues.uvs_out_of_range, 1);
+    assert!(!issues.is_broken());
+
+    plane.indices.push([0, 1, 99]);
+    plane.colors.push(crate::color::WHITE);
+    plane.positions[0].x = f64::NAN;
+    let issues = plane.validate();
+    assert_eq!(issues.out_of_range_indices, 1);
+    assert_eq!(issues.mismatched_attributes, vec!["colors"]);
+    assert_eq!(issues.non_finite_vertices, 1);
+    assert!(issues.is_broken());
+    assert!(issues.to_string().contains("1 out-of-range indices\n"));
+}
@@ -0,0 +1,127 @@
+//! Renders a bundled mesh with every [`DrawStyle`] and compares the result against the
+//! reference images in `tests/golden`.
+//!
+//! After an intended change to the output, regenerate the references with
+//! `UPDATE_GOLDEN=1 cargo test --test golden` and review the new images.
+
+use std::path::PathBuf;
+
+use image::{Rgb, RgbImage};
+use rusterizer::color::Color;
+use rusterizer::drawable::{Image, Point3f};
+use rusterizer::loader::obj;
+use rusterizer::math::Mat4;
+use rusterizer::mesh::Mesh;
+use rusterizer::render::draw_mesh;
+use rusterizer::DrawStyle;
+
+const SIZE: u32 = 64;
+/// Largest per-channel difference still counted as matching.
+const CHANNEL_TOLERANCE: u8 = 2;
+/// Share of pixels allowed to differ by more than the channel tolerance.
+const PIXEL_TOLERANCE: f64 = 0.005;
+
+fn pyramid() -> Mesh {
+    let content = include_str!("data/pyramid.obj");
+    obj::parse(content).unwrap().remove(0)
+}
+
+fn checker() -> RgbImage {
+    RgbImage::from_fn(8, 8, |x, y| {
+        if (x + y) % 2 == 0 {
+            Rgb([230, 230, 230])
+        } else {
+            Rgb([40, 90, 160])
+        }
+    })
+}
+
+fn render(mesh: &Mesh, style: &DrawStyle) -> RgbImage {
+    let model = Mat4::rotation_x(0.4) * Mat4::rotation_y(0.6);
+    let mut image = Image::new(SIZE, SIZE);
+    draw_mesh(&mut image, mesh, style, &model);
+    image.into_rgb_buffer()
+}
+
+fn reference_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{}.png", name))
+}
+
+fn assert_golden(name: &str, actual: &RgbImage) {
+    let path = reference_path(name);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        actual.save(&path).unwrap();
+        return;
+    }
+    let expected = image::open(&path)
+        .unwrap_or_else(|e| panic!("missing reference {}: {}", path.display(), e))
+        .to_rgb8();
+    assert_eq!(expected.dimensions(), actual.dimensions(), "{}", name);
+
+    let mismatched = expected
+        .pixels()
+        .zip(actual.pixels())
+        .filter(|(e, a)| {
+            e.0.iter()
+                .zip(a.0.iter())
+                .any(|(e, a)| e.abs_diff(*a) > CHANNEL_TOLERANCE)
+        })
+        .count();
+    let allowed = (PIXEL_TOLERANCE * (SIZE * SIZE) as f64) as usize;
+    if mismatched > allowed {
+        let failed = path.with_file_name(format!("{}.actual.png", name));
+        actual.save(&failed).unwrap();
+        panic!(
+            "{} differs from its reference in {} pixels, output written to {}",
+            name,
+            mismatched,
+            failed.display()
+        );
+    }
+}
+
+#[test]
+fn golden_wireframe() {
+    let actual = render(&pyramid(), &DrawStyle::Wireframe(Color(255, 255, 255)));
+    assert_golden("wireframe", &actual);
+}
+
+#[test]
+fn golden_filled() {
+    let actual = render(&pyramid(), &DrawStyle::Filled(Color(220, 160, 60)));
+    assert_golden("filled", &actual);
+}
+
+#[test]
+fn golden_filled_random() {
+    let actual = render(&pyramid(), &DrawStyle::FilledRandom(7));
+    assert_golden("filled_random", &actual);
+}
+
+#[test]
+fn golden_per_face() {
+    let palette = [Color(255, 0, 0), Color(0, 255, 0), Color(0, 0, 255)];
+    let face_color = |face: usize| palette[face % palette.len()];
+    let actual = render(&pyramid(), &DrawStyle::PerFace(&face_color));
+    assert_golden("per_face", &actual);
+}
+
+#[test]
+fn golden_vertex_colors() {
+    let white = Color(255, 255, 255);
+    let style = DrawStyle::VertexColors {
+        colors: (white, white, white),
+        lit: true,
+    };
+    assert_golden("vertex_colors", &render(&pyramid(), &style));
+}
+
+#[test]
+fn golden_textured() {
+    let texture = checker();
+    let p = Point3f::new(0., 0., 0.);
+    let style = DrawStyle::Textured(&texture, (&p, &p, &p));
+    assert_golden("textured", &render(&pyramid(), &style));
+}